@@ -0,0 +1,154 @@
+//! カメラと、キー・マウスでの動かし方（CPU版・GPU版で共有）
+//!
+//! 自由飛行では W/A/S/D・Space/LShift で動き、矢印キーと Q/E でカメラ自身の軸のまわりに回ります。
+//! オービットモード（`orbit.rs`）では同じキーで注視点のまわりを回り、近づきます。
+//! ウィンドウのライブラリ（CPU版は minifb、GPU版は winit）ごとにキーの型が違うため、各版は押されているキーを
+//! `Control` に読み替えて渡すだけにし、動かす量や回す向きはここで揃えます。
+
+use crate::orbit::{self, Orbit};
+use glam::{Mat3, Quat, Vec3};
+
+/// 1 フレームで動かす距離
+pub const MOVE_SPEED: f32 = 0.05;
+
+/// 1 フレームで回す角度（ラジアン）
+pub const ROTATE_SPEED: f32 = 0.05;
+
+/// カメラの操作（押している間だけ続ける）
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Control {
+    Forward,   // W
+    Backward,  // S
+    Left,      // A
+    Right,     // D
+    Up,        // Space
+    Down,      // LShift
+    YawLeft,   // ←
+    YawRight,  // →
+    PitchUp,   // ↑
+    PitchDown, // ↓
+    RollLeft,  // Q
+    RollRight, // E
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub pos: Vec3,
+    pub orientation: Quat, // カメラ座標系（右: +X、上: +Y、前: +Z）からワールド座標系への回転
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self {
+            pos: Vec3::new(0.0, 0.0, -2.5),
+            orientation: Quat::IDENTITY,
+        }
+    }
+
+    /// `pos` から原点を向いたカメラ（画面の上がなるべくワールドの +Y になる向き）
+    pub fn looking_at_origin(pos: Vec3) -> Self {
+        let forward = (-pos).normalize();
+        let right = Vec3::Y.cross(forward).normalize();
+        let up = forward.cross(right);
+        Self {
+            pos,
+            orientation: Quat::from_mat3(&Mat3::from_cols(right, up, forward)),
+        }
+    }
+
+    /// 画面上の点 `uv`（縦の中央が 0、上下の端が ±1）を通るレイの向き（CPU版の描画で使う）
+    pub fn get_ray_dir(&self, uv: (f32, f32)) -> Vec3 {
+        self.orientation * Vec3::new(uv.0, uv.1, 1.0).normalize()
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.orientation * Vec3::Z
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.orientation * Vec3::X
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.orientation * Vec3::Y
+    }
+
+    /// カメラ自身の上・右・前の軸まわりに回転（ヨー・ピッチ・ロール、ラジアン）
+    ///
+    /// 回転をローカル座標系で右から掛けるため、どの向きからでも同じように回転が合成されます。
+    /// 誤差が積もって長さが 1 からずれないよう毎回正規化します。
+    pub fn rotate(&mut self, yaw: f32, pitch: f32, roll: f32) {
+        let local =
+            Quat::from_rotation_y(yaw) * Quat::from_rotation_x(pitch) * Quat::from_rotation_z(roll);
+        self.orientation = (self.orientation * local).normalize();
+    }
+
+    /// 自由飛行で 1 フレーム動かす（`held` は操作のキーが押されているか）
+    ///
+    /// 操作がなければ向きを正規化し直さず、カメラをそのままにします（止まっている間の累積描画を続けるため）。
+    pub fn fly(&mut self, held: impl Fn(Control) -> bool) {
+        let axis = |dec: Control, inc: Control| held(inc) as i32 as f32 - held(dec) as i32 as f32;
+        self.pos += (self.forward() * axis(Control::Backward, Control::Forward)
+            + self.right() * axis(Control::Left, Control::Right)
+            + self.up() * axis(Control::Down, Control::Up))
+            * MOVE_SPEED;
+        let (yaw, pitch, roll) = (
+            axis(Control::YawLeft, Control::YawRight),
+            axis(Control::PitchUp, Control::PitchDown),
+            axis(Control::RollRight, Control::RollLeft),
+        );
+        if (yaw, pitch, roll) != (0.0, 0.0, 0.0) {
+            self.rotate(
+                yaw * ROTATE_SPEED,
+                pitch * ROTATE_SPEED,
+                roll * ROTATE_SPEED,
+            );
+        }
+    }
+
+    /// オービットモードで 1 フレーム動かす
+    ///
+    /// `drag` はこのフレームの左ドラッグの量（ピクセル）、`wheel` はホイールを上に回した段数で、
+    /// 矢印キーで注視点 `target` のまわりを回り、W/S で近づく・離れます。
+    /// 操作がなければカメラはそのままにします（位置から求め直した誤差で向きが揺れないように）。
+    pub fn orbit(
+        &mut self,
+        target: Vec3,
+        drag: (f32, f32),
+        wheel: f32,
+        held: impl Fn(Control) -> bool,
+    ) {
+        let axis = |dec: Control, inc: Control| held(inc) as i32 as f32 - held(dec) as i32 as f32;
+        let mut orbit = Orbit::around(target, self.pos);
+        let before = orbit;
+        orbit.rotate(
+            -drag.0 * orbit::DRAG_ROTATE_SPEED,
+            drag.1 * orbit::DRAG_ROTATE_SPEED,
+        );
+        orbit.zoom(orbit::WHEEL_ZOOM_FACTOR.powf(wheel));
+        orbit.rotate(
+            axis(Control::YawLeft, Control::YawRight) * orbit::KEY_ROTATE_SPEED,
+            axis(Control::PitchDown, Control::PitchUp) * orbit::KEY_ROTATE_SPEED,
+        );
+        orbit.zoom(1.0 + axis(Control::Forward, Control::Backward) * orbit::KEY_ZOOM_RATE);
+        if orbit != before {
+            self.follow(&orbit);
+        }
+    }
+
+    /// オービットモードに切り替えたときに、今の位置のまま注視点 `target` を向く
+    pub fn face(&mut self, target: Vec3) {
+        self.follow(&Orbit::around(target, self.pos));
+    }
+
+    fn follow(&mut self, orbit: &Orbit) {
+        self.pos = orbit.position();
+        self.orientation = orbit.orientation();
+    }
+}
@@ -0,0 +1,144 @@
+//! CPU版・GPU版で共通のコマンドラインオプション
+//!
+//! clap で読むため、`--help` でオプションの一覧を表示し、知らないオプションや値のないオプションはエラーにします
+//! （`--fog-color --glow 1` のように次のオプションを値として読むこともありません）。
+//! 各版だけのオプションは、それぞれの `Args` にこの構造体を `#[command(flatten)]` で含めて足します。
+//! 値の形（数値・`<幅>x<高さ>`・`RRGGBB`）はここで確かめ、版ごとに違う範囲は各設定の `from_args` で確かめます。
+//! 負の値から始まる値は `--orbit-target=-1,0,0` のように `=` でつなぎます。
+
+use glam::Vec3;
+use std::path::PathBuf;
+
+/// CPU版・GPU版で共通のオプション
+#[derive(clap::Args)]
+pub struct Common {
+    /// ウィンドウの大きさ（CPU版は論理ピクセル、GPU版は物理ピクセル。既定: 640x480）
+    #[arg(long, value_name = "WxH", value_parser = parse_size)]
+    pub size: Option<(usize, usize)>,
+    /// マンデルバルブのパワーの初期値（1〜16、既定: 2。R キーでもこの値に戻る）
+    #[arg(long)]
+    pub power: Option<f32>,
+    /// レイマーチングの最大ステップ数の初期値
+    #[arg(long, value_name = "STEPS")]
+    pub max_steps: Option<u32>,
+    /// マンデルバルブ・四元数ジュリア集合の反復回数の初期値
+    #[arg(long)]
+    pub iterations: Option<u32>,
+    /// マンデルバルブの脱出半径の初期値
+    #[arg(long, value_name = "RADIUS")]
+    pub bailout: Option<f32>,
+    /// スクリーンショットなどの保存先（既定: CPU版は assets、GPU版は ../assets）
+    #[arg(long, value_name = "DIR")]
+    pub screenshot_dir: Option<PathBuf>,
+    /// 霧の濃さ（既定: 0.2、0 で霧なし）
+    #[arg(long, value_name = "DENSITY")]
+    pub fog_density: Option<f32>,
+    /// 霧の色（既定: 背景に近い紺色）
+    #[arg(long, value_name = "RRGGBB", value_parser = parse_color)]
+    pub fog_color: Option<u32>,
+    /// 輪郭の光の強さ（既定: 0 で光らない）
+    #[arg(long, value_name = "STRENGTH")]
+    pub glow: Option<f32>,
+    /// 輪郭の光の色（既定: 橙色）
+    #[arg(long, value_name = "RRGGBB", value_parser = parse_color)]
+    pub glow_color: Option<u32>,
+    /// キーフレームのファイル（既定: camera_keyframes.json）
+    #[arg(long, value_name = "FILE")]
+    pub keyframes: Option<PathBuf>,
+    /// アニメーションの連番画像の出力先（既定: animation_frames）
+    #[arg(long, value_name = "DIR")]
+    pub frames_dir: Option<PathBuf>,
+    /// アニメーションのフレームレート（既定: 30）
+    #[arg(long)]
+    pub fps: Option<u32>,
+    /// アニメーションの大きさ（既定: ウィンドウの大きさ）
+    #[arg(long, value_name = "WxH", value_parser = parse_size)]
+    pub animation_size: Option<(usize, usize)>,
+    /// モーフィングの最初のパワー（既定: 1）
+    #[arg(long, value_name = "POWER")]
+    pub morph_from: Option<f32>,
+    /// モーフィングの最後のパワー（既定: 9）
+    #[arg(long, value_name = "POWER")]
+    pub morph_to: Option<f32>,
+    /// モーフィングのフレーム数（既定: 240）
+    #[arg(long, value_name = "FRAMES")]
+    pub morph_frames: Option<usize>,
+    /// モーフィングの連番画像の出力先（既定: morph_frames）
+    #[arg(long, value_name = "DIR")]
+    pub morph_dir: Option<PathBuf>,
+    /// カメラのプリセットのファイル（既定: camera_presets.json）
+    #[arg(long, value_name = "FILE")]
+    pub presets: Option<PathBuf>,
+    /// オービットモードの注視点（既定: 原点。負の値から始めるときは --orbit-target=-1,0,0）
+    #[arg(long, value_name = "X,Y,Z", value_parser = parse_vec3)]
+    pub orbit_target: Option<Vec3>,
+}
+
+/// `<幅>x<高さ>`（各 1 以上）の大きさ
+pub fn parse_size(value: &str) -> Result<(usize, usize), String> {
+    value
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+        .filter(|&(w, h)| w > 0 && h > 0)
+        .ok_or_else(|| "<幅>x<高さ>（各 1 以上）で指定してください".to_string())
+}
+
+/// `RRGGBB`（先頭の # は省略可）の色
+pub fn parse_color(value: &str) -> Result<u32, String> {
+    Some(value.trim_start_matches('#'))
+        .filter(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .ok_or_else(|| "RRGGBB（先頭の # は省略可）で指定してください".to_string())
+}
+
+/// `<x>,<y>,<z>` の座標
+pub fn parse_vec3(value: &str) -> Result<Vec3, String> {
+    value
+        .split(',')
+        .map(|v| v.trim().parse::<f32>().ok().filter(|v| v.is_finite()))
+        .collect::<Option<Vec<f32>>>()
+        .filter(|values| values.len() == 3)
+        .map(|values| Vec3::from_slice(&values))
+        .ok_or_else(|| "<x>,<y>,<z> で指定してください".to_string())
+}
+
+/// `RRGGBB` の色を 0〜1 の RGB にする
+pub fn rgb(color: u32) -> Vec3 {
+    Vec3::new(
+        ((color >> 16) & 0xFF) as f32 / 255.0,
+        ((color >> 8) & 0xFF) as f32 / 255.0,
+        (color & 0xFF) as f32 / 255.0,
+    )
+}
+
+/// 指定した値（省略時は `default`）。`range` の外ならエラー（`name` はエラーに出す名前）
+pub fn in_range<T>(value: Option<T>, default: T, range: (T, T), name: &str) -> Result<T, String>
+where
+    T: PartialOrd + std::fmt::Display + Copy,
+{
+    match value {
+        Some(v) if !(range.0..=range.1).contains(&v) => Err(format!(
+            "{}は {}〜{} にしてください: {}",
+            name, range.0, range.1, v
+        )),
+        Some(v) => Ok(v),
+        None => Ok(default),
+    }
+}
+
+/// 指定した大きさ（省略時は `default`）。幅か高さが `max` を超えればエラー（`name` はエラーに出す名前）
+pub fn size_within(
+    value: Option<(usize, usize)>,
+    default: (usize, usize),
+    max: usize,
+    name: &str,
+) -> Result<(usize, usize), String> {
+    match value {
+        Some((w, h)) if w > max || h > max => Err(format!(
+            "{}の幅・高さは {} 以下にしてください: {}x{}",
+            name, max, w, h
+        )),
+        Some(size) => Ok(size),
+        None => Ok(default),
+    }
+}
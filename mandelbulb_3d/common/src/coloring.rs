@@ -0,0 +1,78 @@
+//! 表面の色の付け方（CPU版・GPU版で共有）
+//!
+//! `/` キーで切り替えます。既定の「混合」は反復回数・法線・オービットトラップ・位置から求めた 4 つの色相を
+//! 重み付けして混ぜますが、混ざった色ではそれぞれの特徴が見分けにくいため、1 つだけで色を付けたり、
+//! 色を付けずに粘土のような単色で形と陰影だけを見せたりできます。
+//!   - 混合: 4 つの色相を混ぜる（CPU版は質感の `hue_*` の重み、GPU版は 0.4 / 0.2 / 0.2 / 0.2）
+//!   - オービットトラップ: 反復中の点が原点に最も近づいた距離
+//!   - 反復回数: 脱出までの反復回数の虹色
+//!   - 法線: 面の向き
+//!   - 位置: 表面の点の座標
+//!   - 粘土: 色相を使わない単色（形と陰影の確認用）
+
+/// 色の付け方（値はシェーダーに渡す番号）
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorMode {
+    Blend = 0,
+    OrbitTrap = 1,
+    Iteration = 2,
+    Normal = 3,
+    Position = 4,
+    Clay = 5,
+}
+
+impl ColorMode {
+    pub const ALL: [ColorMode; 6] = [
+        ColorMode::Blend,
+        ColorMode::OrbitTrap,
+        ColorMode::Iteration,
+        ColorMode::Normal,
+        ColorMode::Position,
+        ColorMode::Clay,
+    ];
+
+    /// タイトルバーに表示する名前
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorMode::Blend => "blend",
+            ColorMode::OrbitTrap => "orbit trap",
+            ColorMode::Iteration => "iteration",
+            ColorMode::Normal => "normal",
+            ColorMode::Position => "position",
+            ColorMode::Clay => "clay",
+        }
+    }
+
+    /// プリセットのファイルに書く名前
+    pub fn key(self) -> &'static str {
+        match self {
+            ColorMode::Blend => "blend",
+            ColorMode::OrbitTrap => "orbit_trap",
+            ColorMode::Iteration => "iteration",
+            ColorMode::Normal => "normal",
+            ColorMode::Position => "position",
+            ColorMode::Clay => "clay",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&m| m == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+impl std::str::FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.key() == s)
+            .ok_or_else(|| {
+                format!(
+                    "色の付け方の指定が不正です（blend / orbit_trap / iteration / normal / position / clay）: {}",
+                    s
+                )
+            })
+    }
+}
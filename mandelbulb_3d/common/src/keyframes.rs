@@ -0,0 +1,331 @@
+//! カメラのキーフレームアニメーションと、パワーのモーフィング（CPU版・GPU版で共有）
+//!
+//! 操作中に N キーで今のカメラの位置・向きとフラクタルの種類・パワーをキーフレームとして JSON ファイルに追加し、
+//! O キーでキーフレームの間を補間したフレームを連番画像に書き出します。
+//! 位置は Catmull-Rom スプライン、向きは球面線形補間 (slerp)、パワーは線形補間でつなぎます。
+//!
+//! ```json
+//! {
+//!   "keyframes": [
+//!     { "fractal": "mandelbulb", "position": [0.0, 0.0, -2.5],
+//!       "orientation": [0.0, 0.0, 0.0, 1.0], "power": 8.0, "duration": 2.0 },
+//!     { "fractal": "mandelbulb", "position": [0.8, 0.3, -1.2],
+//!       "orientation": [0.0, -0.26, 0.0, 0.97], "power": 8.0 }
+//!   ]
+//! }
+//! ```
+//!
+//! `orientation` はクォータニオン (x, y, z, w)、`duration` は次のキーフレームまでの秒数（省略時は 2 秒）です。
+//! 区間のフラクタルの種類は始点のキーフレームのものを使います。
+//!
+//! F9 キーでは、今のカメラのままマンデルバルブのパワーを少しずつ変えたフレーム（古典的なマンデルバルブのモーフィング）を
+//! 書き出します（`--morph-from <パワー>`、既定: 1。`--morph-to <パワー>`、既定: 9。`--morph-frames <枚数>`、既定: 240。
+//! `--morph-dir <ディレクトリ>`、既定: `morph_frames`）。色相はフレームの時刻に合わせて少しずつずらします。
+
+use crate::cli::Common;
+use glam::{Quat, Vec3};
+use std::path::{Path, PathBuf};
+
+/// キーフレームファイルの既定のパス
+pub const DEFAULT_KEYFRAMES_FILE: &str = "camera_keyframes.json";
+
+/// 連番画像の既定の出力ディレクトリ
+pub const DEFAULT_FRAMES_DIR: &str = "animation_frames";
+
+/// 既定のフレームレート
+pub const DEFAULT_FPS: u32 = 30;
+
+/// 次のキーフレームまでの既定の秒数
+pub const DEFAULT_DURATION: f32 = 2.0;
+
+/// モーフィングの連番画像の既定の出力ディレクトリ
+pub const DEFAULT_MORPH_DIR: &str = "morph_frames";
+
+/// モーフィングの既定のパワーの範囲（開始、終了）
+pub const DEFAULT_MORPH_POWERS: (f32, f32) = (1.0, 9.0);
+
+/// モーフィングの既定のフレーム数
+pub const DEFAULT_MORPH_FRAMES: usize = 240;
+
+/// キーフレーム
+pub struct CameraKeyframe {
+    /// フラクタルの種類（`mandelbulb` など）
+    pub fractal: String,
+    pub position: Vec3,
+    pub orientation: Quat,
+    pub power: f32,
+    /// 次のキーフレームまでの秒数
+    pub duration: f32,
+}
+
+/// 補間した 1 フレーム
+pub struct CameraFrame {
+    pub position: Vec3,
+    pub orientation: Quat,
+    pub power: f32,
+    /// 属する区間の始点のキーフレーム（フラクタルの種類の選択に使う）
+    pub keyframe: usize,
+}
+
+/// キーフレームを順にたどるカメラパス
+#[derive(Default)]
+pub struct CameraPath {
+    pub keyframes: Vec<CameraKeyframe>,
+}
+
+impl CameraPath {
+    /// JSON ファイルから読み込む
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let value: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        let entries = value["keyframes"]
+            .as_array()
+            .ok_or_else(|| "\"keyframes\" の配列が必要です".to_string())?;
+
+        let keyframes = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let numbers = |name: &str, len: usize| {
+                    entry[name]
+                        .as_array()
+                        .filter(|values| values.len() == len)
+                        .and_then(|values| {
+                            values
+                                .iter()
+                                .map(|v| v.as_f64().map(|v| v as f32))
+                                .collect::<Option<Vec<f32>>>()
+                        })
+                        .ok_or_else(|| {
+                            format!("{}番目: \"{}\" に {} 個の数値が必要です", i + 1, name, len)
+                        })
+                };
+                let fractal = entry["fractal"]
+                    .as_str()
+                    .ok_or_else(|| format!("{}番目: \"fractal\" に文字列が必要です", i + 1))?
+                    .to_string();
+                let position = Vec3::from_slice(&numbers("position", 3)?);
+                let orientation = Quat::from_slice(&numbers("orientation", 4)?);
+                if orientation.length_squared() == 0.0 {
+                    return Err(format!("{}番目: \"orientation\" が 0 です", i + 1));
+                }
+                let power = entry["power"]
+                    .as_f64()
+                    .ok_or_else(|| format!("{}番目: \"power\" に数値が必要です", i + 1))?
+                    as f32;
+                let duration =
+                    match &entry["duration"] {
+                        serde_json::Value::Null => DEFAULT_DURATION,
+                        v => v.as_f64().filter(|&d| d > 0.0).ok_or_else(|| {
+                            format!("{}番目: \"duration\" が不正です: {}", i + 1, v)
+                        })? as f32,
+                    };
+
+                Ok(CameraKeyframe {
+                    fractal,
+                    position,
+                    orientation: orientation.normalize(),
+                    power,
+                    duration,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        if keyframes.is_empty() {
+            return Err("キーフレームが1つもありません".to_string());
+        }
+        Ok(Self { keyframes })
+    }
+
+    /// キーフレームファイルに1つ追加して保存（ファイルがなければ新規作成）
+    ///
+    /// 追加後のキーフレーム数を返します。
+    pub fn append(path: &Path, keyframe: CameraKeyframe) -> Result<usize, String> {
+        let mut keyframes = if path.exists() {
+            Self::load(path)?
+        } else {
+            Self::default()
+        };
+        keyframes.keyframes.push(keyframe);
+        keyframes.save(path)?;
+        Ok(keyframes.keyframes.len())
+    }
+
+    /// JSON ファイルに保存
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let keyframes: Vec<serde_json::Value> = self
+            .keyframes
+            .iter()
+            .map(|k| {
+                // f32 を f64 に広げたときの誤差（1.7999999523162842 など）を丸める
+                let round = |values: &[f32]| {
+                    values
+                        .iter()
+                        .map(|&v| (v as f64 * 1e6).round() / 1e6)
+                        .collect::<Vec<f64>>()
+                };
+                serde_json::json!({
+                    "fractal": k.fractal,
+                    "position": round(&k.position.to_array()),
+                    "orientation": round(&k.orientation.to_array()),
+                    "power": round(&[k.power])[0],
+                    "duration": round(&[k.duration])[0],
+                })
+            })
+            .collect();
+
+        let text = serde_json::to_string_pretty(&serde_json::json!({ "keyframes": keyframes }))
+            .map_err(|e| e.to_string())?;
+        std::fs::write(path, text + "\n").map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// キーフレーム間を `fps` で補間した全フレーム
+    ///
+    /// 各区間のフレーム数は `duration` × `fps` です。位置は前後のキーフレームも使う Catmull-Rom スプライン
+    /// （両端は端のキーフレームを繰り返す）で、キーフレームを通る滑らかな曲線になります。
+    pub fn frames(&self, fps: u32) -> Vec<CameraFrame> {
+        let keyframes = &self.keyframes;
+        let mut frames = Vec::new();
+        for index in 0..keyframes.len().saturating_sub(1) {
+            let (from, to) = (&keyframes[index], &keyframes[index + 1]);
+            let before = &keyframes[index.saturating_sub(1)];
+            let after = &keyframes[(index + 2).min(keyframes.len() - 1)];
+            let count = ((from.duration * fps as f32).round() as usize).max(1);
+
+            // 区間の終点は次の区間の始点と重なるため含めない
+            for j in 0..count {
+                let t = j as f32 / count as f32;
+                frames.push(CameraFrame {
+                    position: catmull_rom(
+                        before.position,
+                        from.position,
+                        to.position,
+                        after.position,
+                        t,
+                    ),
+                    orientation: from.orientation.slerp(to.orientation, t),
+                    power: from.power + (to.power - from.power) * t,
+                    keyframe: index,
+                });
+            }
+        }
+
+        if let Some(last) = keyframes.last() {
+            frames.push(CameraFrame {
+                position: last.position,
+                orientation: last.orientation,
+                power: last.power,
+                keyframe: keyframes.len() - 1,
+            });
+        }
+        frames
+    }
+}
+
+/// `p1` から `p2` への区間の一様 Catmull-Rom スプライン（`p0`・`p3` は前後の制御点）
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+/// アニメーション出力の設定（コマンドライン引数から作成）
+pub struct AnimationOptions {
+    /// キーフレームファイル
+    pub keyframes: PathBuf,
+    /// 連番画像の出力先
+    pub frames_dir: PathBuf,
+    pub fps: u32,
+    /// 出力する画像の幅と高さ
+    pub size: (usize, usize),
+}
+
+impl AnimationOptions {
+    /// `--keyframes` / `--frames-dir` / `--fps` / `--animation-size` を読み込む（大きさの省略時は `default_size`）
+    pub fn from_args(args: &Common, default_size: (usize, usize)) -> Result<Self, String> {
+        let fps = match args.fps {
+            Some(0) => return Err("フレームレートは 1 以上にしてください: 0".to_string()),
+            Some(fps) => fps,
+            None => DEFAULT_FPS,
+        };
+        Ok(Self {
+            keyframes: args
+                .keyframes
+                .clone()
+                .unwrap_or_else(|| DEFAULT_KEYFRAMES_FILE.into()),
+            frames_dir: args
+                .frames_dir
+                .clone()
+                .unwrap_or_else(|| DEFAULT_FRAMES_DIR.into()),
+            fps,
+            size: args.animation_size.unwrap_or(default_size),
+        })
+    }
+
+    /// 連番画像のファイル名
+    pub fn frame_path(&self, index: usize) -> PathBuf {
+        self.frames_dir.join(format!("frame_{:05}.png", index))
+    }
+}
+
+/// パワーのモーフィングの設定（コマンドライン引数から作成）
+pub struct PowerMorph {
+    /// 最初と最後のフレームのパワー
+    pub powers: (f32, f32),
+    pub frames: usize,
+    /// 連番画像の出力先
+    pub frames_dir: PathBuf,
+}
+
+impl PowerMorph {
+    /// `--morph-from` / `--morph-to` / `--morph-frames` / `--morph-dir` を読み込む
+    pub fn from_args(args: &Common) -> Result<Self, String> {
+        let power = |value: Option<f32>, default: f32| match value {
+            Some(p) if !(p.is_finite() && p >= 1.0) => Err(format!(
+                "モーフィングのパワーは 1 以上にしてください: {}",
+                p
+            )),
+            Some(p) => Ok(p),
+            None => Ok(default),
+        };
+        let frames = match args.morph_frames {
+            Some(0) => return Err("モーフィングのフレーム数は 1 以上にしてください: 0".to_string()),
+            Some(n) => n,
+            None => DEFAULT_MORPH_FRAMES,
+        };
+
+        Ok(Self {
+            powers: (
+                power(args.morph_from, DEFAULT_MORPH_POWERS.0)?,
+                power(args.morph_to, DEFAULT_MORPH_POWERS.1)?,
+            ),
+            frames,
+            frames_dir: args
+                .morph_dir
+                .clone()
+                .unwrap_or_else(|| DEFAULT_MORPH_DIR.into()),
+        })
+    }
+
+    /// `index` 番目のフレームのパワー（最初と最後のフレームは範囲の両端）
+    pub fn power(&self, index: usize) -> f32 {
+        let (from, to) = self.powers;
+        if self.frames < 2 {
+            return from;
+        }
+        from + (to - from) * index as f32 / (self.frames - 1) as f32
+    }
+
+    /// 連番画像のファイル名
+    pub fn frame_path(&self, index: usize) -> PathBuf {
+        self.frames_dir.join(format!("frame_{:05}.png", index))
+    }
+}
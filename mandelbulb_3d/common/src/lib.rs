@@ -0,0 +1,12 @@
+//! CPU版・GPU版で共有するカメラ・操作・設定のファイル
+//!
+//! 描画に依存しない部分（カメラとそのキー操作、共通のコマンドラインオプション、キーフレーム・プリセットの保存、
+//! 色の付け方の種類、画面表示用のフォント）をまとめ、両方の版がパスで依存します。
+
+pub mod camera;
+pub mod cli;
+pub mod coloring;
+pub mod font;
+pub mod keyframes;
+pub mod orbit;
+pub mod presets;
@@ -0,0 +1,99 @@
+//! 注視点のまわりを回るカメラ（オービットモード。CPU版・GPU版で共有）
+//!
+//! Enter キーで自由飛行と切り替えます。オービットモードではカメラを注視点からの方位角・仰角・距離で動かし、
+//! 常に注視点を画面の中央に捉えるため、細部を見回すときに位置を手で合わせ直す必要がありません。
+//!   - 左ドラッグ / 矢印キー: 注視点のまわりを回る（方位角・仰角）
+//!   - ホイール / W・S: 注視点に近づく・離れる
+//!
+//! 注視点は `--orbit-target <x>,<y>,<z>`（既定: 原点）で指定します。
+//! 切り替えたときは今のカメラの位置から方位角・仰角・距離を求めるため、カメラは飛ばずに向きだけが注視点に向きます。
+
+use crate::cli::Common;
+use glam::{Mat3, Quat, Vec3};
+
+/// 仰角の上限（度。真上・真下の手前で止め、画面の上の向きが定まるようにする）
+pub const MAX_ELEVATION: f32 = 89.0;
+
+/// 注視点までの距離の下限
+pub const MIN_DISTANCE: f32 = 0.05;
+
+/// 注視点までの距離の上限
+pub const MAX_DISTANCE: f32 = 20.0;
+
+/// 矢印キーで 1 フレームに回す角度（度）
+pub const KEY_ROTATE_SPEED: f32 = 2.0;
+
+/// W・S キーで 1 フレームに距離を変える割合
+pub const KEY_ZOOM_RATE: f32 = 0.02;
+
+/// ドラッグで 1 ピクセルあたりに回す角度（度）
+pub const DRAG_ROTATE_SPEED: f32 = 0.3;
+
+/// ホイール 1 段で距離を変える倍率
+pub const WHEEL_ZOOM_FACTOR: f32 = 0.9;
+
+/// `--orbit-target <x>,<y>,<z>` で指定した注視点（省略時は原点）
+pub fn target_from_args(args: &Common) -> Vec3 {
+    args.orbit_target.unwrap_or(Vec3::ZERO)
+}
+
+/// 注視点から見たカメラの位置（方位角・仰角は度。方位角 0・仰角 0 は注視点の -Z 側）
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Orbit {
+    pub target: Vec3,
+    pub azimuth: f32,
+    pub elevation: f32,
+    pub distance: f32,
+}
+
+impl Orbit {
+    /// `target` のまわりを、今 `pos` にあるカメラが回るときの方位角・仰角・距離
+    pub fn around(target: Vec3, pos: Vec3) -> Self {
+        let offset = pos - target;
+        let distance = offset.length();
+        let (azimuth, elevation) = if distance > 0.0 {
+            (
+                offset.x.atan2(-offset.z).to_degrees(),
+                (offset.y / distance).clamp(-1.0, 1.0).asin().to_degrees(),
+            )
+        } else {
+            (0.0, 0.0)
+        };
+        Self {
+            target,
+            azimuth,
+            elevation: elevation.clamp(-MAX_ELEVATION, MAX_ELEVATION),
+            distance: distance.clamp(MIN_DISTANCE, MAX_DISTANCE),
+        }
+    }
+
+    /// 方位角・仰角を変える（度）
+    pub fn rotate(&mut self, azimuth: f32, elevation: f32) {
+        self.azimuth = (self.azimuth + azimuth).rem_euclid(360.0);
+        self.elevation = (self.elevation + elevation).clamp(-MAX_ELEVATION, MAX_ELEVATION);
+    }
+
+    /// 距離を `factor` 倍にする
+    pub fn zoom(&mut self, factor: f32) {
+        self.distance = (self.distance * factor).clamp(MIN_DISTANCE, MAX_DISTANCE);
+    }
+
+    /// カメラの位置
+    pub fn position(&self) -> Vec3 {
+        let (azimuth, elevation) = (self.azimuth.to_radians(), self.elevation.to_radians());
+        self.target
+            + Vec3::new(
+                elevation.cos() * azimuth.sin(),
+                elevation.sin(),
+                -elevation.cos() * azimuth.cos(),
+            ) * self.distance
+    }
+
+    /// 注視点を向いたカメラの向き（画面の上がなるべくワールドの +Y になる向き）
+    pub fn orientation(&self) -> Quat {
+        let forward = (self.target - self.position()).normalize();
+        let right = Vec3::Y.cross(forward).normalize();
+        let up = forward.cross(right);
+        Quat::from_mat3(&Mat3::from_cols(right, up, forward))
+    }
+}
@@ -0,0 +1,245 @@
+//! カメラのプリセット（CPU版・GPU版で共有）
+//!
+//! Ctrl+1〜9 で今のカメラの位置・向きと形・霧・描画の設定をその番号に保存し、Alt+1〜9 で呼び出します。
+//! 保存するたびに JSON ファイル（`--presets <ファイル>`、既定: `camera_presets.json`）に書き出し、
+//! 起動時にそのファイルがあれば読み込むため、見つけた視点を次回も呼び出せます。
+//!
+//! 描画の設定（`render`）は両方の版にある画質・色の付け方と、版ごとの設定（`viewer`。CPU版は照明・質感・反射の回数、
+//! GPU版は主光源・当たりの判定の距離など）です。もう一方の版で保存した `viewer` の値や、`render` のない古いファイルの
+//! プリセットでは、その分の設定を今のまま変えません。
+//!
+//! ```json
+//! {
+//!   "presets": {
+//!     "1": { "fractal": "mandelbulb", "position": [0.0, 0.0, -2.5],
+//!            "orientation": [0.0, 0.0, 0.0, 1.0], "power": 8.0,
+//!            "julia_c": [-0.291, -0.399, 0.339, 0.437], "depth": 4,
+//!            "fog_density": 0.2, "fog_color": [0.051, 0.071, 0.102],
+//!            "render": { "max_steps": 100, "iterations": 12, "bailout": 2.0, "coloring": "blend",
+//!                        "viewer": { "reflections": 0 } } }
+//!   }
+//! }
+//! ```
+
+use crate::cli::Common;
+use crate::coloring::ColorMode;
+use glam::{Quat, Vec3, Vec4};
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+
+/// プリセットの数（1〜9 キー）
+pub const PRESET_SLOTS: usize = 9;
+
+/// プリセットファイルの既定のパス
+pub const DEFAULT_PRESETS_FILE: &str = "camera_presets.json";
+
+/// 1 つのプリセット
+pub struct CameraPreset {
+    /// フラクタルの種類（`mandelbulb` など。キーフレームファイルと同じ名前）
+    pub fractal: String,
+    pub position: Vec3,
+    pub orientation: Quat,
+    pub power: f32,
+    /// 四元数ジュリア集合の c (実部, i, j, k)
+    pub julia_c: Vec4,
+    /// メンガーのスポンジ・シェルピンスキーの四面体の再帰の深さ
+    pub depth: u32,
+    pub fog_density: f32,
+    pub fog_color: Vec3,
+    /// 描画の設定（`render` のない古いファイルでは `None`）
+    pub render: Option<RenderSettings>,
+}
+
+/// プリセットに保存する描画の設定
+pub struct RenderSettings {
+    /// レイマーチングの最大ステップ数
+    pub max_steps: u32,
+    /// マンデルバルブ・四元数ジュリア集合の反復回数
+    pub iterations: u32,
+    /// マンデルバルブの脱出半径
+    pub bailout: f32,
+    pub coloring: ColorMode,
+    /// 版ごとの設定（名前と値。呼び出すときはその版が使う名前だけを読む）
+    pub viewer: Map<String, Value>,
+}
+
+/// 番号ごとのプリセットと、その保存先
+pub struct CameraPresets {
+    pub path: PathBuf,
+    slots: [Option<CameraPreset>; PRESET_SLOTS],
+}
+
+impl CameraPresets {
+    /// `--presets <ファイル>`（省略時は `camera_presets.json`）のプリセット（ファイルがなければ空）
+    pub fn from_args(args: &Common) -> Result<Self, String> {
+        let path = args
+            .presets
+            .clone()
+            .unwrap_or_else(|| DEFAULT_PRESETS_FILE.into());
+        let mut presets = Self {
+            path,
+            slots: Default::default(),
+        };
+        if presets.path.exists() {
+            let text = std::fs::read_to_string(&presets.path)
+                .map_err(|e| format!("{}: {}", presets.path.display(), e))?;
+            presets
+                .parse(&text)
+                .map_err(|e| format!("{}: {}", presets.path.display(), e))?;
+        }
+        Ok(presets)
+    }
+
+    fn parse(&mut self, text: &str) -> Result<(), String> {
+        let value: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        let entries = value["presets"]
+            .as_object()
+            .ok_or_else(|| "\"presets\" のオブジェクトが必要です".to_string())?;
+
+        for (key, entry) in entries {
+            let slot = key
+                .parse::<usize>()
+                .ok()
+                .filter(|n| (1..=PRESET_SLOTS).contains(n))
+                .ok_or_else(|| {
+                    format!(
+                        "プリセットの番号は 1〜{} にしてください: {}",
+                        PRESET_SLOTS, key
+                    )
+                })?;
+            let numbers = |name: &str, len: usize| {
+                entry[name]
+                    .as_array()
+                    .filter(|values| values.len() == len)
+                    .and_then(|values| {
+                        values
+                            .iter()
+                            .map(|v| v.as_f64().map(|v| v as f32))
+                            .collect::<Option<Vec<f32>>>()
+                    })
+                    .ok_or_else(|| format!("{}: \"{}\" に {} 個の数値が必要です", key, name, len))
+            };
+            let number = |name: &str| {
+                entry[name]
+                    .as_f64()
+                    .map(|v| v as f32)
+                    .ok_or_else(|| format!("{}: \"{}\" に数値が必要です", key, name))
+            };
+            let orientation = Quat::from_slice(&numbers("orientation", 4)?);
+            if orientation.length_squared() == 0.0 {
+                return Err(format!("{}: \"orientation\" が 0 です", key));
+            }
+
+            let render = match &entry["render"] {
+                Value::Null => None,
+                render => Some(parse_render(render).map_err(|e| format!("{}: {}", key, e))?),
+            };
+
+            self.slots[slot - 1] = Some(CameraPreset {
+                fractal: entry["fractal"]
+                    .as_str()
+                    .ok_or_else(|| format!("{}: \"fractal\" に文字列が必要です", key))?
+                    .to_string(),
+                position: Vec3::from_slice(&numbers("position", 3)?),
+                orientation: orientation.normalize(),
+                power: number("power")?,
+                julia_c: Vec4::from_slice(&numbers("julia_c", 4)?),
+                depth: entry["depth"]
+                    .as_u64()
+                    .ok_or_else(|| format!("{}: \"depth\" に整数が必要です", key))?
+                    as u32,
+                fog_density: number("fog_density")?,
+                fog_color: Vec3::from_slice(&numbers("fog_color", 3)?),
+                render,
+            });
+        }
+        Ok(())
+    }
+
+    /// `slot` 番（1〜9）のプリセット
+    pub fn get(&self, slot: usize) -> Option<&CameraPreset> {
+        self.slots.get(slot.wrapping_sub(1))?.as_ref()
+    }
+
+    /// `slot` 番（1〜9）にプリセットを入れ、ファイルに保存する
+    pub fn store(&mut self, slot: usize, preset: CameraPreset) -> Result<(), String> {
+        let entry = self
+            .slots
+            .get_mut(slot.wrapping_sub(1))
+            .ok_or_else(|| format!("プリセットの番号は 1〜{} です: {}", PRESET_SLOTS, slot))?;
+        *entry = Some(preset);
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        // f32 を f64 に広げたときの誤差（1.7999999523162842 など）を丸める
+        let round = |values: &[f32]| {
+            values
+                .iter()
+                .map(|&v| (v as f64 * 1e6).round() / 1e6)
+                .collect::<Vec<f64>>()
+        };
+        let presets: serde_json::Map<String, serde_json::Value> = self
+            .slots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, preset)| Some((i + 1, preset.as_ref()?)))
+            .map(|(slot, p)| {
+                let mut entry = serde_json::json!({
+                    "fractal": p.fractal,
+                    "position": round(&p.position.to_array()),
+                    "orientation": round(&p.orientation.to_array()),
+                    "power": round(&[p.power])[0],
+                    "julia_c": round(&p.julia_c.to_array()),
+                    "depth": p.depth,
+                    "fog_density": round(&[p.fog_density])[0],
+                    "fog_color": round(&p.fog_color.to_array()),
+                });
+                if let Some(render) = &p.render {
+                    entry["render"] = serde_json::json!({
+                        "max_steps": render.max_steps,
+                        "iterations": render.iterations,
+                        "bailout": round(&[render.bailout])[0],
+                        "coloring": render.coloring.key(),
+                        "viewer": render.viewer,
+                    });
+                }
+                (slot.to_string(), entry)
+            })
+            .collect();
+
+        let text = serde_json::to_string_pretty(&serde_json::json!({ "presets": presets }))
+            .map_err(|e| e.to_string())?;
+        std::fs::write(&self.path, text + "\n")
+            .map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+}
+
+/// プリセットの `render` の値
+fn parse_render(render: &Value) -> Result<RenderSettings, String> {
+    let count = |name: &str| {
+        render[name]
+            .as_u64()
+            .map(|v| v as u32)
+            .ok_or_else(|| format!("\"render\" の \"{}\" に整数が必要です", name))
+    };
+    Ok(RenderSettings {
+        max_steps: count("max_steps")?,
+        iterations: count("iterations")?,
+        bailout: render["bailout"]
+            .as_f64()
+            .ok_or_else(|| "\"render\" の \"bailout\" に数値が必要です".to_string())?
+            as f32,
+        coloring: render["coloring"]
+            .as_str()
+            .ok_or_else(|| "\"render\" の \"coloring\" に文字列が必要です".to_string())?
+            .parse()?,
+        viewer: match &render["viewer"] {
+            Value::Null => Map::new(),
+            viewer => viewer
+                .as_object()
+                .cloned()
+                .ok_or_else(|| "\"render\" の \"viewer\" にオブジェクトが必要です".to_string())?,
+        },
+    })
+}
@@ -21,17 +21,39 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
 // Uniform バッファ
 struct Params {
     camera_pos_power: vec4<f32>, // xyz: pos, w: power
-    rotation: vec2<f32>,         // x: rot_x, y: rot_y
+    orientation: vec4<f32>,      // カメラの向きのクォータニオン (xyz: ベクトル部, w: スカラー部)
+    julia_c: vec4<f32>,          // 四元数ジュリア集合の c (x: 実部, y/z/w: i/j/k)
+    fog: vec4<f32>,              // xyz: 霧の色, w: 霧の濃さ
+    light_dir: vec4<f32>,        // xyz: 表面から主光源へ向かう単位ベクトル
+    light_color: vec4<f32>,      // xyz: 主光源の色
+    clip_plane: vec4<f32>,       // xyz: 切断面の法線 (取り除く側), w: 原点からの距離
+    glow: vec4<f32>,             // xyz: 輪郭の光の色, w: 強さ
+    mandelbox: vec4<f32>,        // マンデルボックスの x: 倍率, y: 最小半径², z: 固定半径², w: 形の大きさ
     time: f32,
     aspect: f32,
+    fractal: u32,                // 0: マンデルバルブ, 1: 四元数ジュリア集合, 2: メンガーのスポンジ, 3: シェルピンスキーの四面体, 4: マンデルボックス
+    depth: u32,                  // メンガーのスポンジ・シェルピンスキーの四面体の再帰の深さ
+    label: array<vec4<u32>, 12>, // 画面に表示する名前のグリフ (1 文字 2 ワード: 0〜5 行目 / 6 行目)
+    label_len: u32,              // 名前の文字数
+    clip_enabled: u32,           // 断面表示 (0: なし, 1: あり)
+    max_steps: u32,              // レイマーチングの最大ステップ数
+    iterations: u32,             // マンデルバルブ・四元数ジュリア集合の反復回数
+    bailout: f32,                // マンデルバルブの脱出半径
+    color_mode: u32,             // 0: 混合, 1: オービットトラップ, 2: 反復回数, 3: 法線, 4: 位置, 5: 粘土
+    hit_cone: f32,               // 表面に当たったとみなす距離 (その距離での 1 画素の大きさに対する割合)
+    max_distance: f32,           // レイを打ち切る距離 (これより遠くは背景)
 }
 
 @group(0) @binding(0) var<uniform> params: Params;
 
-const MAX_STEPS: u32 = 100u;
-const MAX_ITER: u32 = 10u;
-const BAILOUT: f32 = 2.0;
-const EPSILON: f32 = 0.001;
+const JULIA_BAILOUT: f32 = 4.0;
+const MANDELBOX_BAILOUT: f32 = 100.0;
+const EPSILON: f32 = 0.001;           // 法線の差分で使う微小距離
+const MIN_HIT_EPSILON: f32 = 0.00005; // 表面に当たったとみなす距離の下限 (カメラのすぐ近くの形)
+const GLOW_RADIUS: f32 = 0.05;        // 輪郭の光の広がり (形からこの距離で強さが 1/e になる)
+const CLAY_COLOR: vec3<f32> = vec3<f32>(0.9, 0.87, 0.83); // 粘土の色付けで使う表面の色 (わずかに暖かい灰色)
+const LABEL_MARGIN: f32 = 8.0; // 名前の表示位置 (画面左上からのピクセル)
+const LABEL_DOT: f32 = 2.0;    // 文字の 1 ドットの大きさ (ピクセル)
 
 // HSVからRGBへの変換
 fn hsv_to_rgb(h: f32, s: f32, v: f32) -> vec3<f32> {
@@ -53,17 +75,34 @@ fn hsv_to_rgb(h: f32, s: f32, v: f32) -> vec3<f32> {
     return vec3<f32>(v, p, q);
 }
 
-// マンデルバルブ距離関数
+// 距離関数 (x: 距離, y: 反復回数, z: オービットトラップ)
 fn map_with_iter(pos: vec3<f32>, power: f32) -> vec3<f32> {
+    var result: vec3<f32>;
+    switch (params.fractal) {
+        case 1u: { result = quaternion_julia_with_iter(pos, params.julia_c); }
+        case 2u: { result = menger_sponge_with_iter(pos, params.depth); }
+        case 3u: { result = sierpinski_tetrahedron_with_iter(pos, params.depth); }
+        case 4u: { result = mandelbox_with_iter(pos, params.mandelbox); }
+        default: { result = mandelbulb_with_iter(pos, power); }
+    }
+    // 断面表示: 切断面より法線の側を取り除く (平面までの距離との max)
+    if (params.clip_enabled != 0u) {
+        result.x = max(result.x, dot(pos, params.clip_plane.xyz) - params.clip_plane.w);
+    }
+    return result;
+}
+
+// マンデルバルブ距離関数
+fn mandelbulb_with_iter(pos: vec3<f32>, power: f32) -> vec3<f32> {
     var z = pos;
     var dr = 1.0;
     var r = 0.0;
     var trap = 1e10;
     var iterations = 0u;
 
-    for (var iter = 0u; iter < MAX_ITER; iter = iter + 1u) {
+    for (var iter = 0u; iter < params.iterations; iter = iter + 1u) {
         r = length(z);
-        if (r > BAILOUT) {
+        if (r > params.bailout) {
             iterations = iter;
             break;
         }
@@ -92,6 +131,118 @@ fn map_with_iter(pos: vec3<f32>, power: f32) -> vec3<f32> {
     return vec3<f32>(dist, f32(iterations), trap);
 }
 
+// 四元数の 2 乗 (x を実部、y/z/w を i/j/k の係数とする)
+fn quat_square(q: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(
+        q.x * q.x - dot(q.yzw, q.yzw),
+        2.0 * q.x * q.yzw
+    );
+}
+
+// 四元数ジュリア集合距離関数 (k 成分が 0 の断面)
+// 導関数は |z'| だけを追えばよく、|2 z z'| = 2 |z| |z'| で更新する
+fn quaternion_julia_with_iter(pos: vec3<f32>, c: vec4<f32>) -> vec3<f32> {
+    var z = vec4<f32>(pos, 0.0);
+    var dr = 1.0;
+    var trap = 1e10;
+    var iterations = 0u;
+
+    for (var iter = 0u; iter < params.iterations; iter = iter + 1u) {
+        iterations = iter;
+        let r = length(z);
+        if (r > JULIA_BAILOUT) {
+            break;
+        }
+
+        trap = min(trap, r);
+        dr = dr * 2.0 * r;
+        z = quat_square(z) + c;
+    }
+
+    let r = length(z);
+    let dist = 0.5 * log(r) * r / dr;
+    return vec3<f32>(dist, f32(iterations), trap);
+}
+
+// メンガーのスポンジ距離関数 (一辺 2 の立方体から、各段で 3 分割した十字の穴をくり抜く)
+fn menger_sponge_with_iter(pos: vec3<f32>, depth: u32) -> vec3<f32> {
+    let q = abs(pos) - vec3<f32>(1.0);
+    var dist = length(max(q, vec3<f32>(0.0))) + min(max(q.x, max(q.y, q.z)), 0.0);
+    var trap = 1e10;
+
+    var s = 1.0;
+    for (var i = 0u; i < depth; i = i + 1u) {
+        // 各段のセルを [-1, 1]³ に写し、中心の十字形の穴までの距離を求める
+        let ps = pos * s;
+        let a = ps - 2.0 * floor(ps * 0.5) - vec3<f32>(1.0);
+        s = s * 3.0;
+        let r = abs(vec3<f32>(1.0) - 3.0 * abs(a));
+        let da = max(r.x, r.y);
+        let db = max(r.y, r.z);
+        let dc = max(r.z, r.x);
+        let hole = (min(da, min(db, dc)) - 1.0) / s;
+        dist = max(dist, hole);
+        trap = min(trap, length(a));
+    }
+
+    return vec3<f32>(dist, f32(depth), trap);
+}
+
+// シェルピンスキーの四面体距離関数 (頂点 (1,1,1), (-1,-1,1), (1,-1,-1), (-1,1,-1))
+// 対称面で折り返して頂点 (1,1,1) 側に寄せ、2 倍に拡大する操作を depth 回繰り返す
+fn sierpinski_tetrahedron_with_iter(pos: vec3<f32>, depth: u32) -> vec3<f32> {
+    var z = pos;
+    var trap = 1e10;
+
+    var s = 1.0;
+    for (var i = 0u; i < depth; i = i + 1u) {
+        if (z.x + z.y < 0.0) { z = vec3<f32>(-z.y, -z.x, z.z); }
+        if (z.x + z.z < 0.0) { z = vec3<f32>(-z.z, z.y, -z.x); }
+        if (z.y + z.z < 0.0) { z = vec3<f32>(z.x, -z.z, -z.y); }
+        z = z * 2.0 - vec3<f32>(1.0);
+        s = s * 2.0;
+        trap = min(trap, length(z) / s);
+    }
+
+    let planes = max(max(-z.x - z.y - z.z, z.x + z.y - z.z), max(-z.x + z.y + z.z, z.x - z.y + z.z));
+    let dist = (planes - 1.0) / sqrt(3.0) / s;
+    return vec3<f32>(dist, f32(depth), trap);
+}
+
+// マンデルボックス距離関数 (shape: x: 倍率, y: 最小半径², z: 固定半径², w: 形の大きさ)
+// 形の大きさで割った座標で描き、ほかの形と同じく一辺 2 ほどに収める
+fn mandelbox_with_iter(pos: vec3<f32>, shape: vec4<f32>) -> vec3<f32> {
+    let c = pos * shape.w;
+    var z = c;
+    var dr = 1.0;
+    var trap = 1e10;
+    var iterations = 0u;
+
+    for (var iter = 0u; iter < params.iterations; iter = iter + 1u) {
+        iterations = iter;
+        // 箱の折り返し: 各座標を ±1 の面で折り返す
+        z = clamp(z, vec3<f32>(-1.0), vec3<f32>(1.0)) * 2.0 - z;
+        // 球面の折り返し: 最小半径の内側は一定の倍率で、固定半径までは反転で広げる
+        let r2 = dot(z, z);
+        if (r2 < shape.y) {
+            z = z * (shape.z / shape.y);
+            dr = dr * (shape.z / shape.y);
+        } else if (r2 < shape.z) {
+            z = z * (shape.z / r2);
+            dr = dr * (shape.z / r2);
+        }
+        z = z * shape.x + c;
+        dr = dr * abs(shape.x) + 1.0;
+
+        trap = min(trap, length(z));
+        if (length(z) > MANDELBOX_BAILOUT) {
+            break;
+        }
+    }
+
+    return vec3<f32>(length(z) / abs(dr) / shape.w, f32(iterations), trap / shape.w);
+}
+
 fn map(pos: vec3<f32>, power: f32) -> f32 {
     return map_with_iter(pos, power).x;
 }
@@ -107,17 +258,35 @@ fn calc_normal(p: vec3<f32>, power: f32) -> vec3<f32> {
     return normalize(n);
 }
 
-// ベクトル回転
-fn rotate_x(v: vec3<f32>, angle: f32) -> vec3<f32> {
-    let c = cos(angle);
-    let s = sin(angle);
-    return vec3<f32>(v.x, v.y * c - v.z * s, v.y * s + v.z * c);
+// 単位クォータニオン q でベクトルを回転 (q v q* を展開した形)
+fn rotate_by_quat(v: vec3<f32>, q: vec4<f32>) -> vec3<f32> {
+    let t = 2.0 * cross(q.xyz, v);
+    return v + q.w * t + cross(q.xyz, t);
 }
 
-fn rotate_y(v: vec3<f32>, angle: f32) -> vec3<f32> {
-    let c = cos(angle);
-    let s = sin(angle);
-    return vec3<f32>(v.x * c - v.z * s, v.y, v.x * s + v.z * c);
+// 画面左上にフラクタルの種類の名前を描画 (周囲 2 ドットの背景を暗くし、文字を白で描く)
+fn draw_label(frag: vec2<f32>, rgb: vec3<f32>) -> vec3<f32> {
+    // 文字の左上を原点としたドット単位の位置
+    let p = (frag - vec2<f32>(LABEL_MARGIN)) / LABEL_DOT;
+    let text_width = f32(params.label_len * 6u) - 1.0;
+    if (params.label_len == 0u || p.x < -2.0 || p.y < -2.0 || p.x >= text_width + 2.0 || p.y >= 9.0) {
+        return rgb;
+    }
+
+    if (p.x >= 0.0 && p.y >= 0.0 && p.y < 7.0) {
+        let ch = u32(p.x) / 6u;
+        let col = u32(p.x) % 6u;
+        let row = u32(p.y);
+        if (ch < params.label_len && col < 5u) {
+            let index = ch * 2u + select(0u, 1u, row == 6u);
+            let word = params.label[index / 4u][index % 4u];
+            let bit = select(row * 5u, 0u, row == 6u) + 4u - col;
+            if (((word >> bit) & 1u) == 1u) {
+                return vec3<f32>(1.0);
+            }
+        }
+    }
+    return rgb * 0.5;
 }
 
 // フラグメントシェーダー
@@ -126,9 +295,9 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     let u = (in.uv.x * 2.0 - 1.0) * params.aspect;
     let v = -(in.uv.y * 2.0 - 1.0);
     
-    var dir = normalize(vec3<f32>(u, v, 1.0));
-    dir = rotate_x(dir, params.rotation.x);
-    dir = rotate_y(dir, params.rotation.y);
+    let dir = rotate_by_quat(normalize(vec3<f32>(u, v, 1.0)), params.orientation);
+    // 1 画素の見込む角度 (コーントレーシング。画面の大きさが変わっても画素の大きさに合わせる)
+    let pixel_angle = abs(dpdy(v));
     
     let camera_pos = params.camera_pos_power.xyz;
     let power = params.camera_pos_power.w;
@@ -139,22 +308,25 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     var steps = 0u;
     var total_iter = 0u;
     var min_trap = 1e10;
+    var min_dist = 1e10; // 形に最も近づいたときの距離 (外れたレイの輪郭の光)
     
-    for (var i = 0u; i < MAX_STEPS; i = i + 1u) {
+    for (var i = 0u; i < params.max_steps; i = i + 1u) {
         let p = camera_pos + dir * t;
         let result = map_with_iter(p, power);
         let d = result.x;
         total_iter = u32(result.y);
         min_trap = min(min_trap, result.z);
+        min_dist = min(min_dist, d);
         
-        if (d < EPSILON) {
+        // 1 画素より細かい形は見分けられないため、遠くほど大きな距離で当たりとして無駄なステップを省く
+        if (d < max(t * pixel_angle * params.hit_cone, MIN_HIT_EPSILON)) {
             hit = true;
             steps = i;
             break;
         }
         
         t = t + d * 0.8;
-        if (t > 6.0) {
+        if (t > params.max_distance) {
             break;
         }
     }
@@ -163,7 +335,8 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         let p = camera_pos + dir * t;
         let normal = calc_normal(p, power);
         
-        let light1 = normalize(vec3<f32>(0.577, 0.577, -0.577));
+        let light1 = params.light_dir.xyz;
+        let light1_color = params.light_color.xyz;
         let light2 = normalize(vec3<f32>(-0.5, 0.8, 0.3));
         
         let diff1 = max(dot(normal, light1), 0.0);
@@ -173,26 +346,44 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         let reflect_dir = 2.0 * dot(normal, light1) * normal - light1;
         let spec = pow(max(dot(view_dir, reflect_dir), 0.0), 32.0);
         
-        let ao = 1.0 - pow(f32(steps) / f32(MAX_STEPS), 0.4);
+        let ao = 1.0 - pow(f32(steps) / f32(params.max_steps), 0.4);
         
-        let hue1 = f32(total_iter) / f32(MAX_ITER) + params.time * 0.1;
+        let hue1 = f32(total_iter) / f32(params.iterations) + params.time * 0.1;
         let hue2 = (normal.x + normal.y * 0.5 + 1.0) * 0.5;
         let hue3 = min_trap * 2.0;
         let hue4 = (p.x + p.y + p.z) * 0.3;
         
-        let final_hue = fract(hue1 * 0.4 + hue2 * 0.2 + hue3 * 0.2 + hue4 * 0.2);
+        // 色の付け方に応じて色相を選ぶ (粘土では色相を使わない)
+        var final_hue = hue1 * 0.4 + hue2 * 0.2 + hue3 * 0.2 + hue4 * 0.2;
+        switch params.color_mode {
+            case 1u: { final_hue = hue3; }
+            case 2u: { final_hue = hue1; }
+            case 3u: { final_hue = hue2; }
+            case 4u: { final_hue = hue4; }
+            default: {}
+        }
         let saturation = 0.8 + (1.0 - ao) * 0.2;
-        let value = min((diff1 + diff2 + 0.15) * ao, 1.0);
+        // 明るさは光源の色ごとに求める (白色光なら明度 (diff1 + diff2 + 0.15) * ao と同じ)
+        let lighting = min((light1_color * diff1 + vec3<f32>(diff2 + 0.15)) * ao, vec3<f32>(1.0));
         
-        var rgb = hsv_to_rgb(final_hue, saturation, value);
-        rgb = rgb + vec3<f32>(spec * 0.5);
+        var base = hsv_to_rgb(fract(final_hue), saturation, 1.0);
+        if (params.color_mode == 5u) {
+            base = CLAY_COLOR;
+        }
+        var rgb = base * lighting;
+        rgb = rgb + light1_color * (spec * 0.5);
         rgb = min(rgb, vec3<f32>(1.0));
+
+        // 距離に応じた霧
+        rgb = mix(rgb, params.fog.xyz, 1.0 - exp(-params.fog.w * t));
         
-        return vec4<f32>(rgb, 1.0);
+        return vec4<f32>(draw_label(in.position.xy, rgb), 1.0);
     } else {
         let gradient = (dir.y + 1.0) * 0.5;
         let bg_hue = 0.6 + params.time * 0.02;
-        let rgb = hsv_to_rgb(bg_hue, 0.5, gradient * 0.15 + 0.02);
-        return vec4<f32>(rgb, 1.0);
+        // 形をかすめたレイほど輪郭の光を足す
+        let glow = params.glow.xyz * (params.glow.w * exp(-max(min_dist, 0.0) / GLOW_RADIUS));
+        let rgb = min(hsv_to_rgb(bg_hue, 0.5, gradient * 0.15 + 0.02) + glow, vec3<f32>(1.0));
+        return vec4<f32>(draw_label(in.position.xy, rgb), 1.0);
     }
 }
@@ -1,82 +1,1252 @@
 //! マンデルバルブ (Mandelbulb) 3Dフラクタルビューア - GPU版
 //! wgpu + WGSLフラグメントシェーダーによるGPUレンダリング
 //!
+//! Tab キーでマンデルバルブ、四元数ジュリア集合（z → z² + c を四元数で反復し、3 次元の断面を描画）、
+//! メンガーのスポンジ、シェルピンスキーの四面体、マンデルボックスを切り替えます。メンガーのスポンジと
+//! シェルピンスキーの四面体は反復関数系 (IFS) の距離関数で、再帰の深さを [ / ] キーで変えられます。
+//! マンデルボックスは箱と球面での折り返しと倍率の操作を繰り返す式で、倍率を PageUp/PageDown キー、
+//! 球面の折り返しの最小半径を [ / ] キーで変えます（どちらもユニフォームでシェーダーに渡す）。
+//! F キーの断面表示では、距離関数を切断面の距離との max にして平面の片側を取り除き、形の内部の構造を見せます
+//! （切断面はユニフォームでシェーダーに渡す）。
+//! 表示中のフラクタルの種類は画面の左上に表示し（文字のグリフは CPU版と共有する `common/src/font.rs` のもの）、
+//! 切り替えるとカメラをその形に合った初期位置に戻します。
+//!
 //! 操作方法:
 //!   - W/A/S/D: カメラ移動 (前後左右)
 //!   - Space/LShift: カメラ移動 (上昇/下降)
-//!   - 矢印キー: カメラ回転
-//!   - 1-9: パワー変更 (形状が変化)
-//!   - R: リセット
-//!   - Esc/Q: 終了
+//!   - 矢印キー: カメラ回転 (ヨー/ピッチ)
+//!   - Q/E: カメラを左/右に傾ける (ロール)
+//!   - M: マウスで見回す操作の切り替え (カーソルを隠して閉じ込め、マウスの移動でヨー/ピッチ。`--mouse-sensitivity`)
+//!   - Enter: オービットモードの切り替え (注視点のまわりを回る。左ドラッグ・矢印キーで回転、ホイール・W/S で距離)
+//!   - PageDown/PageUp: パワーを 0.1 ずつ減らす/増やす (形状が変化、マンデルバルブ。3.5 のような半端な値でも描ける。マンデルボックスでは倍率)
+//!   - Tab: フラクタルの種類を切り替え (マンデルバルブ / 四元数ジュリア集合 / メンガーのスポンジ / シェルピンスキーの四面体 / マンデルボックス)
+//!   - `[` / `]`: 再帰の深さを減らす/増やす (メンガーのスポンジ、シェルピンスキーの四面体。マンデルボックスでは最小半径)
+//!   - T/G, Y/H, U/J, I/K: 四元数ジュリア集合の c の各成分 (実部, i, j, k) を増減
+//!   - Z/X: 主光源を左/右に回す, C/V: 主光源を下げる/上げる
+//!   - B: 主光源の色を切り替え (白 / 暖色 / 寒色 / マゼンタ / 緑)
+//!   - /: 色の付け方を切り替え (混合 / オービットトラップ / 反復回数 / 法線 / 位置 / 粘土)
+//!   - F1/F2: レイマーチングの最大ステップ数を減らす/増やす, F3/F4: 反復回数を減らす/増やす, F6/F7: 脱出半径を減らす/増やす
+//!   - F5: 画質の設定の組を切り替え (低 / 中 / 高)
+//!   - F: 断面表示の切り替え, `,` / `.`: 切断面を動かす, L: 切断面をカメラに向ける
+//!   - F10: パラメータの画面の表示を切り替え (パワー・ジュリア集合の c・画質・主光源・色のスライダー、リセット・スクリーンショットのボタン)
+//!   - N: キーフレームを記録, O: キーフレームアニメーションを書き出し, F9: パワーのモーフィングを書き出し
+//!   - Ctrl+1-9: カメラのプリセットを保存, Alt+1-9: プリセットを呼び出し
+//!   - R: リセット (カメラ・パワー・c・再帰の深さ・切断面・霧・光源。画質の設定と色の付け方はそのまま)
+//!   - Esc: 終了
+//!
+//! カメラの向きはクォータニオンで持ち、シェーダーにもそのまま渡してレイの向きを回転させます。
+//! カメラと、その移動・回転のキー操作は CPU版と共有し（`common/src/camera.rs`）、操作の感覚を揃えます。
+//!
+//! レイマーチングの最大ステップ数・マンデルバルブと四元数ジュリア集合の反復回数・マンデルバルブの脱出半径・
+//! 表面に当たったとみなす距離・レイを打ち切る距離はユニフォームでシェーダーに渡し、再コンパイルせずにキーや
+//! パラメータの画面で変えられます（現在の値はタイトルバーに表示）。F5 キーで低・中・高の組に切り替えられます。
+//!
+//! レイが表面に当たったとみなす距離は、その距離での 1 画素の大きさの半分にします（コーントレーシング）。
+//! 遠くの形は見分けられない細かさまで追わずに済み、近くの形は固定の閾値より細かく捉えます。
+//!
+//! 遠くの形ほど霧の色に溶け込ませ、奥行きを分かりやすくします
+//! （`--fog-density <濃さ>`、既定: 0.2、0 で霧なし。`--fog-color <RRGGBB>`、既定: 背景に近い紺色）。
+//! 形に当たらなかったレイには、途中で形に最も近づいた距離に応じた光を足し、シルエットに光の縁を付けられます
+//! （`--glow <強さ>`、既定: 0 で光らない。`--glow-color <RRGGBB>`、既定: 橙色。CPU版と同じ）。
+//!
+//! 起動時の設定は CPU版と同じオプションで指定できます（`--size <幅>x<高さ>`、既定: 640x480。
+//! `--fractal <種類>`・`--power <パワー>`・`--max-steps <ステップ数>`・`--iterations <回数>`・`--bailout <半径>`・
+//! `--screenshot-dir <ディレクトリ>`、既定: `../assets`）。GPU版の大きさは物理ピクセルで、画質の既定値は 100・10・2 です。
+//! ウィンドウは起動後にドラッグや最大化で大きさを変えられ、描画先と縦横比をそれに合わせます（形は引き伸ばされません）。
+//!
+//! 主光源の向きと色はユニフォームでシェーダーに渡すため、再コンパイルせずにライティングを変えられます。
+//! 色の付け方（CPU版と共有する `common/src/coloring.rs` の種類）も番号をユニフォームで渡し、シェーダーの中で色相の求め方を切り替えます。
+//!
+//! N キーで今のカメラとパワーをキーフレームとして記録し、O キーでその間をスプライン補間したアニメーションを
+//! オフスクリーンに描画して連番画像に書き出します（`common/src/keyframes.rs` を CPU版と共有。オプションも CPU版と同じ
+//! `--keyframes` / `--fps` / `--animation-size` / `--frames-dir`）。
+//! F9 キーでは今のカメラのままパワーを 1 から 9 へ少しずつ変えたモーフィングを書き出します
+//! （`--morph-from` / `--morph-to` / `--morph-frames` / `--morph-dir`）。
+//!
+//! Enter キーのオービットモードでは、カメラが注視点（`--orbit-target <x>,<y>,<z>`、既定: 原点）のまわりを
+//! 方位角・仰角・距離で回り、常に注視点を画面の中央に捉えます（`common/src/orbit.rs` を CPU版と共有）。
+//!
+//! P キーのスクリーンショットは、画面をバッファにコピーするところまでを描画と一緒に行い、読み出しと保存は
+//! 別のスレッドに任せて描画を止めません（保存が終わるまでは次を受け付けない）。
+//!
+//! Ctrl+数字キーで今のカメラと形・霧・描画（画質・色の付け方・主光源）の設定をプリセットとして保存し、
+//! Alt+数字キーで呼び出します（`common/src/presets.rs` を CPU版と共有。`--presets <ファイル>`、既定: `camera_presets.json`）。
+//!
+//! シェーダーは実行時にファイル（`--shader <ファイル>`、既定: `shaders/mandelbulb.wgsl`）から読み、書き換えられたら
+//! パイプラインを作り直します（`shader.rs`。コンパイルエラーはコンソールに出し、前のシェーダーのまま描き続ける）。
+
+mod panel;
+mod shader;
 
 use bytemuck::{Pod, Zeroable};
-use glam::{Mat3, Vec2, Vec3, Vec4};
+use clap::Parser;
+use glam::{Quat, UVec4, Vec3, Vec4};
+use mandelbulb_3d_common::camera::{Camera, Control};
+use mandelbulb_3d_common::coloring::ColorMode;
+use mandelbulb_3d_common::keyframes::{
+    AnimationOptions, CameraKeyframe, CameraPath, PowerMorph, DEFAULT_DURATION,
+};
+use mandelbulb_3d_common::presets::{CameraPreset, CameraPresets, RenderSettings};
+use mandelbulb_3d_common::{cli, font, orbit};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 use wgpu::util::DeviceExt;
 use winit::{
-    dpi::PhysicalSize,
-    event::{ElementState, Event, KeyEvent, WindowEvent},
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{
+        DeviceEvent, ElementState, Event, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent,
+    },
     event_loop::EventLoop,
     keyboard::{KeyCode, PhysicalKey},
-    window::WindowBuilder,
+    window::{CursorGrabMode, Window, WindowBuilder},
 };
 
-const WIDTH: u32 = 640;
+const WIDTH: u32 = 640; // ウィンドウの大きさの既定値（物理ピクセル）
 const HEIGHT: u32 = 480;
+const MAX_WINDOW_SIZE: u32 = 4096; // ウィンドウの幅・高さの上限
+const SCREENSHOT_DIR: &str = "../assets"; // スクリーンショットの保存先の既定値
+const JULIA_C: Vec4 = Vec4::new(-0.291, -0.399, 0.339, 0.437); // 四元数ジュリア集合の c の初期値 (実部, i, j, k)
+const JULIA_C_STEP: f32 = 0.005; // c を 1 フレームで変える量
+const JULIA_C_RANGE: (f32, f32) = (-1.5, 1.5); // パラメータの画面で c の各成分を変えられる範囲
+const IFS_DEPTH: u32 = 4; // メンガーのスポンジ・シェルピンスキーの四面体の再帰の深さの初期値
+const MAX_IFS_DEPTH: u32 = 8; // 再帰の深さの上限
+const MANDELBOX_SCALE: f32 = 2.0; // マンデルボックスの倍率の初期値
+const MANDELBOX_SCALE_RANGE: (f32, f32) = (1.2, 3.0); // 倍率の絶対値の範囲（1 に近いと形ができないため、正負を飛び越す）
+const MANDELBOX_SCALE_STEP: f32 = 0.1; // PageUp/PageDown で倍率を変える量
+const MANDELBOX_MIN_RADIUS: f32 = 0.5; // 球面の折り返しの最小半径の初期値（固定半径は 1）
+const MANDELBOX_MIN_RADIUS_RANGE: (f32, f32) = (0.05, 1.0); // 最小半径の範囲
+const MANDELBOX_MIN_RADIUS_STEP: f32 = 0.05; // [ / ] で最小半径を変える量
+const LABEL_CHARS: usize = 24; // 画面に表示する名前の最大文字数
+const FOG_DENSITY: f32 = 0.2; // 霧の濃さの既定値（距離 t で exp(-濃さ·t) だけ元の色が残る）
+const FOG_COLOR: u32 = 0x0D121A; // 霧の色の既定値（背景のグラデーションの中ほどの色）
+const GLOW_STRENGTH: f32 = 0.0; // 輪郭の光の強さの既定値（0 で光らない）
+const GLOW_COLOR: u32 = 0xFF9A40; // 輪郭の光の色の既定値（橙色）
+const LIGHT_AZIMUTH: f32 = 45.0; // 主光源の方位角の初期値（度、+Z 方向から +X 方向へ）
+const LIGHT_ELEVATION: f32 = 35.26; // 主光源の仰角の初期値（度）
+const LIGHT_ROT_SPEED: f32 = 2.0; // 主光源を 1 フレームで回す角度（度）
+const POWER_STEP: f32 = 0.1; // PageUp/PageDown でパワーを変える量
+const MIN_POWER: f32 = 1.0; // パワーの下限
+const MAX_POWER: f32 = 16.0; // パワーの上限
+const MAX_STEPS: u32 = 100; // レイマーチングの最大ステップ数の初期値
+const MAX_ITER: u32 = 10; // フラクタル計算の反復回数の初期値
+const BAILOUT: f32 = 2.0; // マンデルバルブの脱出半径の初期値
+const STEPS_STEP: u32 = 10; // F1/F2 で最大ステップ数を変える量
+const STEPS_RANGE: (u32, u32) = (10, 1000); // 最大ステップ数の範囲
+const ITER_RANGE: (u32, u32) = (1, 64); // 反復回数の範囲
+const BAILOUT_STEP: f32 = 0.5; // F6/F7 で脱出半径を変える量
+const BAILOUT_RANGE: (f32, f32) = (1.5, 16.0); // 脱出半径の範囲
+const HIT_CONE: f32 = 0.5; // 表面に当たったとみなす距離の初期値（その距離での 1 画素の大きさに対する割合）
+const HIT_CONE_RANGE: (f32, f32) = (0.1, 4.0); // 表面に当たったとみなす距離の範囲（パラメータの画面で変更）
+const MAX_DISTANCE: f32 = 6.0; // レイを打ち切る距離の初期値（これより遠くは背景）
+const MAX_DISTANCE_RANGE: (f32, f32) = (2.0, 20.0); // レイを打ち切る距離の範囲（パラメータの画面で変更）
+const CLIP_STEP: f32 = 0.01; // 切断面を 1 フレームで動かす距離
+const MAX_CLIP_OFFSET: f32 = 4.0; // 切断面の原点からの距離の上限
+const MAX_LIGHT_ELEVATION: f32 = 89.0; // 主光源の仰角の上限（度）
+const MOUSE_SENSITIVITY: f32 = 0.003; // マウスで見回すときの、マウスの移動量 1 あたりの回転角の既定値（ラジアン）
+
+// 主光源の色の候補（白、暖色、寒色、マゼンタ、緑）
+const LIGHT_COLORS: [Vec3; 5] = [
+    Vec3::new(1.0, 1.0, 1.0),
+    Vec3::new(1.0, 0.8, 0.6),
+    Vec3::new(0.6, 0.8, 1.0),
+    Vec3::new(1.0, 0.5, 0.9),
+    Vec3::new(0.6, 1.0, 0.6),
+];
 
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
 struct Params {
     camera_pos_power: Vec4, // xyz: camera_pos, w: power
-    rotation: Vec2,         // x: rot_x, y: rot_y
+    orientation: Quat,      // カメラの向き (xyz: ベクトル部, w: スカラー部)
+    julia_c: Vec4,          // 四元数ジュリア集合の c (x: 実部, y/z/w: i/j/k)
+    fog: Vec4,              // xyz: 霧の色, w: 霧の濃さ
+    light_dir: Vec4,        // xyz: 表面から主光源へ向かう単位ベクトル
+    light_color: Vec4,      // xyz: 主光源の色
+    clip_plane: Vec4,       // xyz: 切断面の法線（取り除く側）, w: 原点からの距離
+    glow: Vec4,             // xyz: 輪郭の光の色, w: 強さ
+    mandelbox: Vec4,        // マンデルボックスの x: 倍率, y: 最小半径², z: 固定半径², w: 形の大きさ
     time: f32,
     aspect: f32,
+    fractal: u32,                    // FractalType の値
+    depth: u32,                      // メンガーのスポンジ・シェルピンスキーの四面体の再帰の深さ
+    label: [UVec4; LABEL_CHARS / 2], // 画面に表示する名前のグリフ（pack_label）
+    label_len: u32,
+    clip_enabled: u32, // 断面表示（0: なし, 1: あり）
+    max_steps: u32,    // レイマーチングの最大ステップ数
+    iterations: u32,   // マンデルバルブ・四元数ジュリア集合の反復回数
+    bailout: f32,      // マンデルバルブの脱出半径
+    color_mode: u32,   // 表面の色の付け方（ColorMode の値）
+    hit_cone: f32,     // 表面に当たったとみなす距離（1 画素の大きさに対する割合）
+    max_distance: f32, // レイを打ち切る距離
+}
+
+impl Params {
+    fn new(camera: &Camera, fractal: &Fractal, shading: &Shading) -> Self {
+        let Shading { fog, light, glow } = *shading;
+        Self {
+            camera_pos_power: camera.pos.extend(fractal.power),
+            orientation: camera.orientation,
+            julia_c: fractal.julia_c,
+            fog: fog.color.extend(fog.density),
+            light_dir: light.direction().extend(0.0),
+            light_color: light.color().extend(1.0),
+            clip_plane: fractal.clip.normal.extend(fractal.clip.offset),
+            glow: glow.color.extend(glow.strength),
+            mandelbox: Vec4::new(
+                fractal.mandelbox.scale,
+                fractal.mandelbox.min_radius * fractal.mandelbox.min_radius,
+                1.0,
+                fractal.mandelbox.extent(),
+            ),
+            time: 0.0, // アニメーション停止
+            aspect: WIDTH as f32 / HEIGHT as f32,
+            fractal: fractal.kind as u32,
+            depth: fractal.depth,
+            label: pack_label(fractal.kind.name()),
+            label_len: fractal.kind.name().chars().count().min(LABEL_CHARS) as u32,
+            clip_enabled: fractal.clip.enabled as u32,
+            max_steps: fractal.quality.max_steps,
+            iterations: fractal.quality.iterations,
+            bailout: fractal.quality.bailout,
+            color_mode: fractal.coloring as u32,
+            hit_cone: fractal.quality.hit_cone,
+            max_distance: fractal.quality.max_distance,
+        }
+    }
+}
+
+/// 文字列のグリフをシェーダーに渡す形に詰める
+///
+/// 1 文字を 2 ワードで表し、1 ワード目に 0〜5 行目、2 ワード目に 6 行目を 5 ビットずつ
+/// （行 r の左端の列がビット 5r + 4）入れます。フォントにない文字（空白など）は 0 です。
+fn pack_label(text: &str) -> [UVec4; LABEL_CHARS / 2] {
+    let mut words = [0u32; LABEL_CHARS * 2];
+    for (i, c) in text.chars().take(LABEL_CHARS).enumerate() {
+        if let Some(glyph) = font::glyph(c) {
+            for (row, &bits) in glyph.iter().enumerate() {
+                let (word, row) = if row < 6 {
+                    (2 * i, row)
+                } else {
+                    (2 * i + 1, 0)
+                };
+                words[word] |= (bits as u32) << (row * 5);
+            }
+        }
+    }
+    std::array::from_fn(|i| UVec4::from_slice(&words[i * 4..i * 4 + 4]))
+}
+
+// フラクタルの種類（値はシェーダーの params.fractal と対応）
+#[derive(Clone, Copy, PartialEq)]
+enum FractalType {
+    Mandelbulb = 0,
+    QuaternionJulia = 1,
+    MengerSponge = 2,
+    SierpinskiTetrahedron = 3,
+    Mandelbox = 4,
+}
+
+impl FractalType {
+    fn next(self) -> Self {
+        match self {
+            FractalType::Mandelbulb => FractalType::QuaternionJulia,
+            FractalType::QuaternionJulia => FractalType::MengerSponge,
+            FractalType::MengerSponge => FractalType::SierpinskiTetrahedron,
+            FractalType::SierpinskiTetrahedron => FractalType::Mandelbox,
+            FractalType::Mandelbox => FractalType::Mandelbulb,
+        }
+    }
+
+    /// 画面に表示する名前
+    fn name(self) -> &'static str {
+        match self {
+            FractalType::Mandelbulb => "MANDELBULB",
+            FractalType::QuaternionJulia => "QUATERNION JULIA",
+            FractalType::MengerSponge => "MENGER SPONGE",
+            FractalType::SierpinskiTetrahedron => "SIERPINSKI TETRAHEDRON",
+            FractalType::Mandelbox => "MANDELBOX",
+        }
+    }
+
+    /// キーフレームファイルで使う名前（CPU版と共通）
+    fn key(self) -> &'static str {
+        match self {
+            FractalType::Mandelbulb => "mandelbulb",
+            FractalType::QuaternionJulia => "quaternion_julia",
+            FractalType::MengerSponge => "menger_sponge",
+            FractalType::SierpinskiTetrahedron => "sierpinski_tetrahedron",
+            FractalType::Mandelbox => "mandelbox",
+        }
+    }
+
+    /// 形全体が見える初期のカメラ（原点を向く）
+    ///
+    /// 立方体・四面体は面が正面を向くと平板に見えるため、斜め上から見下ろします。
+    fn camera(self) -> Camera {
+        match self {
+            FractalType::Mandelbulb => Camera::new(),
+            FractalType::QuaternionJulia => Camera::looking_at_origin(Vec3::new(0.0, 0.0, -2.0)),
+            FractalType::MengerSponge => Camera::looking_at_origin(Vec3::new(1.6, 1.4, -2.4)),
+            FractalType::SierpinskiTetrahedron => {
+                Camera::looking_at_origin(Vec3::new(1.2, 1.0, -2.6))
+            }
+            FractalType::Mandelbox => Camera::looking_at_origin(Vec3::new(1.4, 1.2, -2.2)),
+        }
+    }
+}
+
+impl std::str::FromStr for FractalType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "mandelbulb" => Ok(FractalType::Mandelbulb),
+            "quaternion_julia" => Ok(FractalType::QuaternionJulia),
+            "menger_sponge" => Ok(FractalType::MengerSponge),
+            "sierpinski_tetrahedron" => Ok(FractalType::SierpinskiTetrahedron),
+            "mandelbox" => Ok(FractalType::Mandelbox),
+            _ => Err(format!(
+                "フラクタルの種類の指定が不正です（mandelbulb / quaternion_julia / menger_sponge / sierpinski_tetrahedron / mandelbox）: {}",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Fractal {
+    kind: FractalType,
+    power: f32,           // マンデルバルブのパワー
+    julia_c: Vec4,        // 四元数ジュリア集合の c
+    depth: u32,           // メンガーのスポンジ・シェルピンスキーの四面体の再帰の深さ
+    mandelbox: Mandelbox, // マンデルボックスの倍率と最小半径
+    clip: ClipPlane,      // 断面表示の切断面
+    quality: Quality,     // 画質（最大ステップ数・反復回数・脱出半径・表面に当たったとみなす距離）
+    coloring: ColorMode,  // 表面の色の付け方
+}
+
+impl Fractal {
+    fn new() -> Self {
+        Self {
+            kind: FractalType::Mandelbulb,
+            power: 2.0,
+            julia_c: JULIA_C,
+            depth: IFS_DEPTH,
+            mandelbox: Mandelbox::new(),
+            clip: ClipPlane::new(),
+            quality: Quality::new(),
+            coloring: ColorMode::Blend,
+        }
+    }
+
+    /// R キーのリセット後の形（種類・画質の設定・色の付け方はそのまま、パワーは `power`）
+    fn reset(&self, power: f32) -> Self {
+        Self {
+            kind: self.kind,
+            power,
+            quality: self.quality,
+            coloring: self.coloring,
+            ..Self::new()
+        }
+    }
+
+    /// パワーを `amount` だけ変える（範囲内に収め、0.1 刻みの誤差を丸める）
+    fn shift_power(&mut self, amount: f32) {
+        self.power = ((self.power + amount) * 10.0).round() / 10.0;
+        self.power = self.power.clamp(MIN_POWER, MAX_POWER);
+    }
+}
+
+/// マンデルボックスの式の値（箱と球面で折り返し、倍率を掛けて元の点を足す操作を繰り返す）
+///
+/// 倍率が正なら大きさは 2(倍率 + 1) / (倍率 - 1)、負なら 2 ほどになるため、シェーダーではその大きさで割って
+/// ほかの形と同じくらいの大きさ（一辺 2 ほど）で描きます。
+#[derive(Clone, Copy)]
+struct Mandelbox {
+    scale: f32,      // 倍率（絶対値は MANDELBOX_SCALE_RANGE の範囲）
+    min_radius: f32, // 球面の折り返しで、これより内側の点を一定の倍率で広げる半径
+}
+
+impl Mandelbox {
+    fn new() -> Self {
+        Self {
+            scale: MANDELBOX_SCALE,
+            min_radius: MANDELBOX_MIN_RADIUS,
+        }
+    }
+
+    /// 倍率を `amount` だけ変える（0.1 刻みの誤差を丸め、絶対値が範囲の下限を切るときは反対の符号へ飛び越す）
+    fn shift_scale(&mut self, amount: f32) {
+        let scale = ((self.scale + amount) * 10.0).round() / 10.0;
+        self.set_scale(if scale.abs() < MANDELBOX_SCALE_RANGE.0 {
+            MANDELBOX_SCALE_RANGE.0.copysign(amount)
+        } else {
+            scale
+        });
+    }
+
+    /// 倍率を `scale` にする（絶対値を範囲内に収める）
+    fn set_scale(&mut self, scale: f32) {
+        self.scale = scale
+            .abs()
+            .clamp(MANDELBOX_SCALE_RANGE.0, MANDELBOX_SCALE_RANGE.1)
+            * scale.signum();
+    }
+
+    /// 最小半径を `amount` だけ変える（範囲内に収める）
+    fn shift_min_radius(&mut self, amount: f32) {
+        self.min_radius = (self.min_radius + amount)
+            .clamp(MANDELBOX_MIN_RADIUS_RANGE.0, MANDELBOX_MIN_RADIUS_RANGE.1);
+    }
+
+    /// 形の大きさ（各座標の絶対値の最大）
+    fn extent(&self) -> f32 {
+        if self.scale > 0.0 {
+            2.0 * (self.scale + 1.0) / (self.scale - 1.0)
+        } else {
+            2.0
+        }
+    }
+}
+
+/// 画質とフレームレートの釣り合い（ユニフォームでシェーダーに渡し、実行中にキーで変える）
+#[derive(Clone, Copy, PartialEq)]
+struct Quality {
+    max_steps: u32,
+    iterations: u32,
+    bailout: f32,
+    hit_cone: f32, // 表面に当たったとみなす距離（1 画素の大きさに対する割合。パラメータの画面で変更）
+    max_distance: f32, // レイを打ち切る距離（パラメータの画面で変更）
+}
+
+impl Quality {
+    fn new() -> Self {
+        Self {
+            max_steps: MAX_STEPS,
+            iterations: MAX_ITER,
+            bailout: BAILOUT,
+            hit_cone: HIT_CONE,
+            max_distance: MAX_DISTANCE,
+        }
+    }
+
+    /// 最大ステップ数を `steps` 段階だけ変える（範囲内に収める）
+    fn adjust_steps(&mut self, steps: i32) {
+        let value = self.max_steps as i32 + steps * STEPS_STEP as i32;
+        self.max_steps = (value.max(0) as u32).clamp(STEPS_RANGE.0, STEPS_RANGE.1);
+    }
+
+    /// 反復回数を `steps` 回だけ変える（範囲内に収める）
+    fn adjust_iterations(&mut self, steps: i32) {
+        let value = self.iterations as i32 + steps;
+        self.iterations = (value.max(0) as u32).clamp(ITER_RANGE.0, ITER_RANGE.1);
+    }
+
+    /// 脱出半径を `steps` 段階だけ変える（範囲内に収める）
+    fn adjust_bailout(&mut self, steps: i32) {
+        self.bailout =
+            (self.bailout + steps as f32 * BAILOUT_STEP).clamp(BAILOUT_RANGE.0, BAILOUT_RANGE.1);
+    }
+}
+
+/// 画質の設定の組（F5 キー・パラメータの画面で切り替え）
+///
+/// 「中」は起動時の既定値です。「低」は当たりの判定を粗くして近くだけを描き、ノート PC の内蔵 GPU などでも
+/// 動かしやすくします。「高」はステップ数と反復回数を増やして遠くの細部まで描き、スクリーンショット向けです。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum QualityPreset {
+    Low,
+    Medium,
+    High,
+}
+
+impl QualityPreset {
+    const ALL: [QualityPreset; 3] = [
+        QualityPreset::Low,
+        QualityPreset::Medium,
+        QualityPreset::High,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            QualityPreset::Low => "low",
+            QualityPreset::Medium => "medium",
+            QualityPreset::High => "high",
+        }
+    }
+
+    fn quality(self) -> Quality {
+        match self {
+            QualityPreset::Low => Quality {
+                max_steps: 50,
+                iterations: 6,
+                bailout: 2.0,
+                hit_cone: 1.5,
+                max_distance: 4.0,
+            },
+            QualityPreset::Medium => Quality::new(),
+            QualityPreset::High => Quality {
+                max_steps: 300,
+                iterations: 16,
+                bailout: 4.0,
+                hit_cone: 0.25,
+                max_distance: 10.0,
+            },
+        }
+    }
+
+    /// `quality` と同じ設定の組（キーやスライダーで値を変えた後は `None`）
+    fn of(quality: &Quality) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.quality() == *quality)
+    }
+
+    /// 低 → 中 → 高 → 低 の順に次の組（今の設定がどの組とも違うときは低）
+    fn after(quality: &Quality) -> Self {
+        match Self::of(quality) {
+            Some(QualityPreset::Low) => QualityPreset::Medium,
+            Some(QualityPreset::Medium) => QualityPreset::High,
+            Some(QualityPreset::High) | None => QualityPreset::Low,
+        }
+    }
+}
+
+// コマンドラインのオプション（CPU版と共通の `cli::Common` に GPU版だけのものを足す。ドキュメントコメントは --help に出る）
+
+/// マンデルバルブ (Mandelbulb) 3Dフラクタルビューア - GPU版
+#[derive(Parser)]
+struct Args {
+    #[command(flatten)]
+    common: cli::Common,
+    /// 最初に表示する形（mandelbulb / quaternion_julia / menger_sponge / sierpinski_tetrahedron / mandelbox）
+    #[arg(long, value_name = "KIND")]
+    fractal: Option<FractalType>,
+    /// シェーダーのファイル（既定: shaders/mandelbulb.wgsl。書き換えると読み込み直す）
+    #[arg(long, value_name = "FILE")]
+    shader: Option<PathBuf>,
+    /// マウスで見回すときの感度（マウスの移動量 1 あたりのラジアン、既定: 0.003）
+    #[arg(long, value_name = "RADIANS")]
+    mouse_sensitivity: Option<f32>,
+}
+
+/// コマンドラインで指定する起動時の設定（ウィンドウの大きさ・形・画質・保存先。CPU版と同じオプション）
+struct StartOptions {
+    size: (u32, u32),        // ウィンドウの大きさ（物理ピクセル）
+    kind: FractalType,       // 最初に表示するフラクタルの種類
+    power: f32,              // マンデルバルブのパワーの初期値（R キーのリセットでもこの値に戻す）
+    quality: Quality,        // 画質の初期値
+    screenshot_dir: PathBuf, // スクリーンショットの保存先
+}
+
+impl StartOptions {
+    /// `--size <幅>x<高さ>`・`--fractal <種類>`・`--power <パワー>`・`--max-steps <ステップ数>`・
+    /// `--iterations <回数>`・`--bailout <半径>`・`--screenshot-dir <ディレクトリ>` で指定した設定（省略時は既定値）
+    fn from_args(args: &Args) -> Result<Self, String> {
+        let common = &args.common;
+        let mut quality = Quality::new();
+        quality.max_steps = cli::in_range(
+            common.max_steps,
+            quality.max_steps,
+            STEPS_RANGE,
+            "最大ステップ数",
+        )?;
+        quality.iterations = cli::in_range(
+            common.iterations,
+            quality.iterations,
+            ITER_RANGE,
+            "反復回数",
+        )?;
+        quality.bailout =
+            cli::in_range(common.bailout, quality.bailout, BAILOUT_RANGE, "脱出半径")?;
+        let (width, height) = cli::size_within(
+            common.size,
+            (WIDTH as usize, HEIGHT as usize),
+            MAX_WINDOW_SIZE as usize,
+            "ウィンドウ",
+        )?;
+        Ok(Self {
+            size: (width as u32, height as u32),
+            kind: args.fractal.unwrap_or(FractalType::Mandelbulb),
+            power: cli::in_range(
+                common.power,
+                Fractal::new().power,
+                (MIN_POWER, MAX_POWER),
+                "パワー",
+            )?,
+            quality,
+            screenshot_dir: common
+                .screenshot_dir
+                .clone()
+                .unwrap_or_else(|| SCREENSHOT_DIR.into()),
+        })
+    }
+}
+
+/// 断面表示の切断面（法線の側を取り除く。距離の計算はシェーダーで行う）
+#[derive(Clone, Copy)]
+struct ClipPlane {
+    enabled: bool,
+    normal: Vec3, // 取り除く側を向いた単位ベクトル
+    offset: f32,  // 原点から平面までの、法線の向きに沿った距離
+}
+
+impl ClipPlane {
+    /// 原点を通り、初期のカメラ側（-Z 側）の半分を取り除く平面（無効）
+    fn new() -> Self {
+        Self {
+            enabled: false,
+            normal: Vec3::NEG_Z,
+            offset: 0.0,
+        }
+    }
+
+    /// 法線をカメラの方へ向ける（カメラの正面から断面が見える向き。原点からの距離は保つ）
+    fn face(&mut self, camera: &Camera) {
+        self.normal = -camera.forward();
+    }
+
+    /// 法線の向きに `amount` だけ動かす
+    fn shift(&mut self, amount: f32) {
+        self.offset = (self.offset + amount).clamp(-MAX_CLIP_OFFSET, MAX_CLIP_OFFSET);
+    }
+}
+
+/// 距離に応じて色を霧の色に近づける指数関数的な霧
+#[derive(Clone, Copy)]
+struct Fog {
+    density: f32,
+    color: Vec3,
+}
+
+impl Fog {
+    /// `--fog-density <濃さ>`・`--fog-color <RRGGBB>` で指定した霧（省略時は既定値）
+    fn from_args(args: &cli::Common) -> Result<Self, String> {
+        let density = match args.fog_density {
+            Some(d) if !(d.is_finite() && d >= 0.0) => {
+                return Err(format!("霧の濃さには 0 以上の数値が必要です: {}", d))
+            }
+            Some(d) => d,
+            None => FOG_DENSITY,
+        };
+        Ok(Self {
+            density,
+            color: cli::rgb(args.fog_color.unwrap_or(FOG_COLOR)),
+        })
+    }
+}
+
+/// 形の輪郭のまわりの光（形をかすめて外れたレイほど強く光る。CPU版と同じ）
+#[derive(Clone, Copy)]
+struct Glow {
+    strength: f32,
+    color: Vec3,
+}
+
+impl Glow {
+    /// `--glow <強さ>`・`--glow-color <RRGGBB>` で指定した光（省略時は光らない）
+    fn from_args(args: &cli::Common) -> Result<Self, String> {
+        let strength = match args.glow {
+            Some(s) if !(s.is_finite() && s >= 0.0) => {
+                return Err(format!("輪郭の光の強さには 0 以上の数値が必要です: {}", s))
+            }
+            Some(s) => s,
+            None => GLOW_STRENGTH,
+        };
+        Ok(Self {
+            strength,
+            color: cli::rgb(args.glow_color.unwrap_or(GLOW_COLOR)),
+        })
+    }
+}
+
+/// シェーダーに渡す陰影付けの設定（霧・主光源・輪郭の光）
+#[derive(Clone, Copy)]
+struct Shading<'a> {
+    fog: &'a Fog,
+    light: &'a Light,
+    glow: &'a Glow,
+}
+
+/// マウスで見回す操作（M キーで切り替え。有効な間はカーソルを隠してウィンドウに閉じ込める）
+///
+/// カーソルの位置ではなくマウスそのものの移動量（`DeviceEvent::MouseMotion`）を使うため、
+/// カーソルが画面の端に届いても回し続けられます。矢印キーでの回転もそのまま使えます。
+struct MouseLook {
+    enabled: bool,
+    sensitivity: f32,   // マウスの移動量 1 あたりの回転角（ラジアン）
+    motion: (f32, f32), // 次のフレームまでに溜めたマウスの移動量（右、下が正）
+}
+
+impl MouseLook {
+    /// `--mouse-sensitivity <ラジアン>` で感度を指定した操作（無効の状態から始める）
+    fn from_args(args: &Args) -> Result<Self, String> {
+        let sensitivity = match args.mouse_sensitivity {
+            Some(s) if !(s.is_finite() && s > 0.0) => {
+                return Err(format!("マウスの感度には正の数値が必要です: {}", s))
+            }
+            Some(s) => s,
+            None => MOUSE_SENSITIVITY,
+        };
+        Ok(Self {
+            enabled: false,
+            sensitivity,
+            motion: (0.0, 0.0),
+        })
+    }
+
+    /// 有効・無効を切り替え、カーソルを閉じ込める・解放する
+    ///
+    /// カーソルをその場に固定できない環境（Windows）ではウィンドウの中に閉じ込め、
+    /// どちらもできないときは無効のままにします。
+    fn set(&mut self, window: &Window, enabled: bool) -> Result<(), String> {
+        self.motion = (0.0, 0.0);
+        if enabled {
+            window
+                .set_cursor_grab(CursorGrabMode::Locked)
+                .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))
+                .map_err(|e| format!("マウスカーソルを閉じ込められません: {}", e))?;
+        } else {
+            let _ = window.set_cursor_grab(CursorGrabMode::None);
+        }
+        window.set_cursor_visible(!enabled);
+        self.enabled = enabled;
+        Ok(())
+    }
+
+    /// マウスの移動量を溜める（無効な間は捨てる）
+    fn add(&mut self, delta: (f64, f64)) {
+        if self.enabled {
+            self.motion.0 += delta.0 as f32;
+            self.motion.1 += delta.1 as f32;
+        }
+    }
+
+    /// 溜めた移動量を取り出す
+    fn take(&mut self) -> (f32, f32) {
+        std::mem::take(&mut self.motion)
+    }
 }
 
-struct Camera {
-    pos: Vec3,
-    rot_x: f32,
-    rot_y: f32,
+/// 主光源（方向と色をキーで変えられる。補助光源はシェーダー内で固定）
+#[derive(Clone, Copy)]
+struct Light {
+    azimuth: f32,   // 方位角（度）
+    elevation: f32, // 仰角（度）
+    color: usize,   // LIGHT_COLORS の番号
 }
 
-impl Camera {
+impl Light {
     fn new() -> Self {
         Self {
-            pos: Vec3::new(0.0, 0.0, -2.5),
-            rot_x: 0.0,
-            rot_y: 0.0,
+            azimuth: LIGHT_AZIMUTH,
+            elevation: LIGHT_ELEVATION,
+            color: 0,
         }
     }
 
-    fn forward(&self) -> Vec3 {
-        let rot = Mat3::from_rotation_y(self.rot_y) * Mat3::from_rotation_x(self.rot_x);
-        rot * Vec3::new(0.0, 0.0, 1.0)
+    /// 表面から光源へ向かう単位ベクトル
+    fn direction(&self) -> Vec3 {
+        let (azimuth, elevation) = (self.azimuth.to_radians(), self.elevation.to_radians());
+        Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos(),
+        )
     }
 
-    fn right(&self) -> Vec3 {
-        let rot = Mat3::from_rotation_y(self.rot_y);
-        rot * Vec3::new(1.0, 0.0, 0.0)
+    fn color(&self) -> Vec3 {
+        LIGHT_COLORS[self.color]
     }
 
-    fn move_forward(&mut self, amount: f32) {
-        self.pos += self.forward() * amount;
+    /// 方位角・仰角を変える（仰角は真上・真下の手前で止める）
+    fn rotate(&mut self, azimuth: f32, elevation: f32) {
+        self.azimuth = (self.azimuth + azimuth).rem_euclid(360.0);
+        self.elevation =
+            (self.elevation + elevation).clamp(-MAX_LIGHT_ELEVATION, MAX_LIGHT_ELEVATION);
+    }
+
+    fn next_color(&mut self) {
+        self.color = (self.color + 1) % LIGHT_COLORS.len();
+    }
+}
+
+/// 今のカメラ・形・霧・描画の設定のプリセット
+fn camera_preset(camera: &Camera, fractal: &Fractal, fog: &Fog, light: &Light) -> CameraPreset {
+    let viewer = [
+        ("hit_cone", serde_json::json!(fractal.quality.hit_cone)),
+        (
+            "max_distance",
+            serde_json::json!(fractal.quality.max_distance),
+        ),
+        ("light_azimuth", serde_json::json!(light.azimuth)),
+        ("light_elevation", serde_json::json!(light.elevation)),
+        ("light_color", serde_json::json!(light.color)),
+    ];
+    CameraPreset {
+        fractal: fractal.kind.key().to_string(),
+        position: camera.pos,
+        orientation: camera.orientation,
+        power: fractal.power,
+        julia_c: fractal.julia_c,
+        depth: fractal.depth,
+        fog_density: fog.density,
+        fog_color: fog.color,
+        render: Some(RenderSettings {
+            max_steps: fractal.quality.max_steps,
+            iterations: fractal.quality.iterations,
+            bailout: fractal.quality.bailout,
+            coloring: fractal.coloring,
+            viewer: viewer
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        }),
     }
+}
 
-    fn move_right(&mut self, amount: f32) {
-        self.pos += self.right() * amount;
+/// プリセットのカメラ・形・霧・描画の設定に切り替える（プリセットにない描画の設定は今のまま）
+fn apply_preset(
+    preset: &CameraPreset,
+    camera: &mut Camera,
+    fractal: &mut Fractal,
+    fog: &mut Fog,
+    light: &mut Light,
+) -> Result<(), String> {
+    *fractal = Fractal {
+        kind: preset.fractal.parse()?,
+        power: preset.power.clamp(MIN_POWER, MAX_POWER),
+        julia_c: preset.julia_c,
+        depth: preset.depth.clamp(1, MAX_IFS_DEPTH),
+        mandelbox: fractal.mandelbox,
+        clip: fractal.clip,
+        quality: fractal.quality,
+        coloring: fractal.coloring,
+    };
+    *camera = Camera {
+        pos: preset.position,
+        orientation: preset.orientation,
+    };
+    *fog = Fog {
+        density: preset.fog_density.max(0.0),
+        color: preset.fog_color,
+    };
+    if let Some(render) = &preset.render {
+        let number = |name: &str| render.viewer.get(name).and_then(|v| v.as_f64());
+        let quality = &mut fractal.quality;
+        quality.max_steps = render.max_steps.clamp(STEPS_RANGE.0, STEPS_RANGE.1);
+        quality.iterations = render.iterations.clamp(ITER_RANGE.0, ITER_RANGE.1);
+        quality.bailout = render.bailout.clamp(BAILOUT_RANGE.0, BAILOUT_RANGE.1);
+        if let Some(hit_cone) = number("hit_cone") {
+            quality.hit_cone = (hit_cone as f32).clamp(HIT_CONE_RANGE.0, HIT_CONE_RANGE.1);
+        }
+        if let Some(max_distance) = number("max_distance") {
+            quality.max_distance =
+                (max_distance as f32).clamp(MAX_DISTANCE_RANGE.0, MAX_DISTANCE_RANGE.1);
+        }
+        fractal.coloring = render.coloring;
+        if let (Some(azimuth), Some(elevation)) =
+            (number("light_azimuth"), number("light_elevation"))
+        {
+            // rotate で方位角を 0〜360 度に、仰角を上限の内側に収める
+            light.azimuth = 0.0;
+            light.elevation = 0.0;
+            light.rotate(azimuth as f32, elevation as f32);
+        }
+        if let Some(color) = render.viewer.get("light_color").and_then(|v| v.as_u64()) {
+            light.color = color as usize % LIGHT_COLORS.len();
+        }
+    }
+    Ok(())
+}
+
+/// オフスクリーン描画に使う GPU の資源（ウィンドウと同じパイプライン・パラメータバッファを使う）
+struct Offscreen<'a> {
+    device: &'a wgpu::Device,
+    queue: &'a wgpu::Queue,
+    pipeline: &'a wgpu::RenderPipeline,
+    bind_group: &'a wgpu::BindGroup,
+    param_buffer: &'a wgpu::Buffer,
+    format: wgpu::TextureFormat,
+}
+
+impl Offscreen<'_> {
+    /// `count` 枚のフレームを描画して連番画像に保存する（進み具合をコンソールに表示）
+    ///
+    /// `params(i)` が i 番目のフレームのパラメータ（縦横比は `size` に合わせ、画面左上の名前は描かない）、
+    /// `path(i)` が保存先です。
+    fn render_frames(
+        &self,
+        size: (usize, usize),
+        count: usize,
+        params: impl Fn(usize) -> Params,
+        path: impl Fn(usize) -> PathBuf,
+    ) -> Result<(), String> {
+        let (width, height) = (size.0 as u32, size.1 as u32);
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Animation Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bytes_per_row = 4 * width;
+        let padded_bytes_per_row = (bytes_per_row + 255) & !255;
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Animation Buffer"),
+            size: (padded_bytes_per_row * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let bgra = matches!(
+            self.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+
+        let start = Instant::now();
+        for index in 0..count {
+            print!("\rRendering frame {}/{}", index + 1, count);
+            let _ = std::io::Write::flush(&mut std::io::stdout());
+
+            let params = Params {
+                aspect: width as f32 / height as f32,
+                label_len: 0,
+                ..params(index)
+            };
+            self.queue
+                .write_buffer(self.param_buffer, 0, bytemuck::cast_slice(&[params]));
+
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Animation Encoder"),
+                });
+            {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Animation Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                render_pass.set_pipeline(self.pipeline);
+                render_pass.set_bind_group(0, self.bind_group, &[]);
+                render_pass.draw(0..3, 0..1);
+            }
+            encoder.copy_texture_to_buffer(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(padded_bytes_per_row),
+                        rows_per_image: Some(height),
+                    },
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+            self.queue.submit(std::iter::once(encoder.finish()));
+
+            let slice = buffer.slice(..);
+            slice.map_async(wgpu::MapMode::Read, |_| {});
+            self.device.poll(wgpu::Maintain::Wait);
+            let mut pixels = Vec::with_capacity((bytes_per_row * height) as usize);
+            for chunk in slice
+                .get_mapped_range()
+                .chunks(padded_bytes_per_row as usize)
+            {
+                pixels.extend_from_slice(&chunk[..bytes_per_row as usize]);
+            }
+            buffer.unmap();
+            if bgra {
+                for pixel in pixels.chunks_exact_mut(4) {
+                    pixel.swap(0, 2);
+                }
+            }
+
+            let frame_path = path(index);
+            image::save_buffer_with_format(
+                &frame_path,
+                &pixels,
+                width,
+                height,
+                image::ColorType::Rgba8,
+                image::ImageFormat::Png,
+            )
+            .map_err(|e| format!("{}: {}", frame_path.display(), e))?;
+        }
+        println!(" ({:.1} s)", start.elapsed().as_secs_f32());
+        Ok(())
+    }
+
+    /// キーフレームの間を補間したフレームを描画し、連番画像に書き出す（書き出したフレーム数を返す）
+    ///
+    /// カメラの位置・向き、フラクタルの種類、パワー以外（c・再帰の深さ・切断面・霧・主光源・輪郭の光）は今の設定を使います。
+    fn render_animation(
+        &self,
+        options: &AnimationOptions,
+        fractal: &Fractal,
+        shading: &Shading,
+    ) -> Result<usize, String> {
+        let path = CameraPath::load(&options.keyframes)?;
+        let kinds = path
+            .keyframes
+            .iter()
+            .enumerate()
+            .map(|(i, k)| {
+                k.fractal
+                    .parse::<FractalType>()
+                    .map_err(|e| format!("{}: {}番目: {}", options.keyframes.display(), i + 1, e))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let frames = path.frames(options.fps);
+        std::fs::create_dir_all(&options.frames_dir)
+            .map_err(|e| format!("{}: {}", options.frames_dir.display(), e))?;
+
+        self.render_frames(
+            options.size,
+            frames.len(),
+            |index| {
+                let frame = &frames[index];
+                let camera = Camera {
+                    pos: frame.position,
+                    orientation: frame.orientation,
+                };
+                let fractal = Fractal {
+                    kind: kinds[frame.keyframe],
+                    power: frame.power,
+                    ..*fractal
+                };
+                Params::new(&camera, &fractal, shading)
+            },
+            |index| options.frame_path(index),
+        )?;
+        Ok(frames.len())
+    }
+
+    /// 今のカメラのまま、マンデルバルブのパワーを少しずつ変えたフレームを連番画像に書き出す（書き出したフレーム数を返す）
+    ///
+    /// 大きさとフレームレート（色相をずらす時刻の刻み）はキーフレームアニメーションと同じ設定を使います。
+    fn render_morph(
+        &self,
+        morph: &PowerMorph,
+        options: &AnimationOptions,
+        camera: &Camera,
+        fractal: &Fractal,
+        shading: &Shading,
+    ) -> Result<usize, String> {
+        std::fs::create_dir_all(&morph.frames_dir)
+            .map_err(|e| format!("{}: {}", morph.frames_dir.display(), e))?;
+
+        self.render_frames(
+            options.size,
+            morph.frames,
+            |index| {
+                let fractal = Fractal {
+                    kind: FractalType::Mandelbulb,
+                    power: morph.power(index),
+                    ..*fractal
+                };
+                Params {
+                    time: index as f32 / options.fps as f32,
+                    ..Params::new(camera, &fractal, shading)
+                }
+            },
+            |index| morph.frame_path(index),
+        )?;
+        Ok(morph.frames)
+    }
+}
+
+/// 画面のスクリーンショット（P キー）
+///
+/// 描画したフレームをバッファにコピーするところまでを描画と同じコマンドに積み、読み出し・PNG への変換・保存は
+/// 別のスレッドで行います（読み出しの完了はチャネルで受け取る）。描画のループは読み出しを待たないため、
+/// 保存している間もカメラが引っかからずに動きます。
+struct Screenshot {
+    buffer: wgpu::Buffer,
+    size: (u32, u32),
+    padded_bytes_per_row: u32, // バッファの 1 行のバイト数（256 バイト境界に揃える）
+    bgra: bool,                // 画面の色の並びが BGRA か
+    path: PathBuf,
+}
+
+impl Screenshot {
+    /// `texture` の今の内容をバッファにコピーするコマンドを `encoder` に積む
+    fn record(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        path: PathBuf,
+    ) -> Self {
+        let (width, height) = (texture.width(), texture.height());
+        let padded_bytes_per_row = (4 * width + 255) & !255;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Screenshot Buffer"),
+            size: (padded_bytes_per_row * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        Self {
+            buffer,
+            size: (width, height),
+            padded_bytes_per_row,
+            bgra: matches!(
+                texture.format(),
+                wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+            ),
+            path,
+        }
+    }
+
+    /// コマンドを送った後に呼び、読み出せるようになったら別のスレッドで保存する（終わると `busy` を下ろす）
+    ///
+    /// 読み出しの完了は、描画のループで毎フレーム呼ぶ `device.poll` の中で通知されます。
+    fn save_in_background(self, busy: Arc<AtomicBool>) {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        std::thread::spawn(move || {
+            let result = receiver
+                .recv()
+                .map_err(|e| e.to_string())
+                .and_then(|mapped| mapped.map_err(|e| e.to_string()))
+                .and_then(|()| self.save());
+            match result {
+                Ok(()) => println!("Screenshot saved to {}", self.path.display()),
+                Err(e) => eprintln!("Failed to save screenshot: {}", e),
+            }
+            busy.store(false, Ordering::Release);
+        });
+    }
+
+    /// 読み出せるようになったバッファを PNG に保存する
+    fn save(&self) -> Result<(), String> {
+        let (width, height) = self.size;
+        let bytes_per_row = 4 * width;
+        let mut pixels = Vec::with_capacity((bytes_per_row * height) as usize);
+        for chunk in self
+            .buffer
+            .slice(..)
+            .get_mapped_range()
+            .chunks(self.padded_bytes_per_row as usize)
+        {
+            pixels.extend_from_slice(&chunk[..bytes_per_row as usize]);
+        }
+        self.buffer.unmap();
+        if self.bgra {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        if let Some(dir) = self.path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        image::save_buffer_with_format(
+            &self.path,
+            &pixels,
+            width,
+            height,
+            image::ColorType::Rgba8,
+            image::ImageFormat::Png,
+        )
+        .map_err(|e| e.to_string())
     }
 }
 
 fn main() {
     env_logger::init();
 
+    let args = Args::parse();
+    let start = StartOptions::from_args(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let default_fog = Fog::from_args(&args.common).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let mut glow = Glow::from_args(&args.common).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let animation =
+        AnimationOptions::from_args(&args.common, (start.size.0 as usize, start.size.1 as usize))
+            .unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            });
+    let morph = PowerMorph::from_args(&args.common).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let mut presets = CameraPresets::from_args(&args.common).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let mut shader_file = shader::ShaderFile::from_args(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let orbit_target = orbit::target_from_args(&args.common);
+    let mut mouse_look = MouseLook::from_args(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
     let event_loop = EventLoop::new().unwrap();
     let window = Arc::new(
         WindowBuilder::new()
             .with_title("Mandelbulb 3D GPU Explorer")
-            .with_inner_size(PhysicalSize::new(WIDTH, HEIGHT))
-            .with_resizable(false)
+            .with_inner_size(PhysicalSize::new(start.size.0, start.size.1))
             .build(&event_loop)
             .unwrap(),
     );
@@ -108,35 +1278,44 @@ fn main() {
     let surface_caps = surface.get_capabilities(&adapter);
     let surface_format = surface_caps.formats[0];
 
-    let config = wgpu::SurfaceConfiguration {
+    let mut config = wgpu::SurfaceConfiguration {
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
         format: surface_format,
-        width: WIDTH,
-        height: HEIGHT,
+        width: start.size.0,
+        height: start.size.1,
         present_mode: wgpu::PresentMode::AutoVsync,
         alpha_mode: surface_caps.alpha_modes[0],
         view_formats: vec![],
         desired_maximum_frame_latency: 2,
     };
     surface.configure(&device, &config);
-
-    // シェーダー読み込み
-    let shader_source = include_str!("../shaders/mandelbulb.wgsl");
-    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-        label: Some("Shader"),
-        source: wgpu::ShaderSource::Wgsl(shader_source.into()),
-    });
+    let mut panel = panel::Panel::new(&window, &device, surface_format);
 
     // パラメータバッファ
-    let mut camera = Camera::new();
-    let mut power = 2.0f32;
+    let mut camera = start.kind.camera();
+    let mut fractal = Fractal {
+        kind: start.kind,
+        power: start.power,
+        quality: start.quality,
+        ..Fractal::new()
+    };
+    // 画面の縦横比（ウィンドウの大きさを変えると合わせて変える）
+    let mut aspect = start.size.0 as f32 / start.size.1 as f32;
+    let mut light = Light::new();
+    let mut fog = default_fog;
     // let start_time = Instant::now(); // 不要
 
     let params = Params {
-        camera_pos_power: Vec4::new(camera.pos.x, camera.pos.y, camera.pos.z, power),
-        rotation: Vec2::new(camera.rot_x, camera.rot_y),
-        time: 0.0, // アニメーション停止
-        aspect: WIDTH as f32 / HEIGHT as f32,
+        aspect,
+        ..Params::new(
+            &camera,
+            &fractal,
+            &Shading {
+                fog: &fog,
+                light: &light,
+                glow: &glow,
+            },
+        )
     };
 
     let param_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -169,268 +1348,574 @@ fn main() {
         }],
     });
 
-    // レンダーパイプライン
     let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("Pipeline Layout"),
         bind_group_layouts: &[&bind_group_layout],
         push_constant_ranges: &[],
     });
 
-    let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some("Render Pipeline"),
-        layout: Some(&pipeline_layout),
-        vertex: wgpu::VertexState {
-            module: &shader,
-            entry_point: "vs_main",
-            buffers: &[],
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-        },
-        fragment: Some(wgpu::FragmentState {
-            module: &shader,
-            entry_point: "fs_main",
-            targets: &[Some(wgpu::ColorTargetState {
-                format: surface_format,
-                blend: Some(wgpu::BlendState::REPLACE),
-                write_mask: wgpu::ColorWrites::ALL,
-            })],
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-        }),
-        primitive: wgpu::PrimitiveState {
-            topology: wgpu::PrimitiveTopology::TriangleList,
-            strip_index_format: None,
-            front_face: wgpu::FrontFace::Ccw,
-            cull_mode: None,
-            polygon_mode: wgpu::PolygonMode::Fill,
-            unclipped_depth: false,
-            conservative: false,
-        },
-        depth_stencil: None,
-        multisample: wgpu::MultisampleState::default(),
-        multiview: None,
-    });
+    // レンダーパイプライン（シェーダーのファイルが書き換えられたら作り直す）
+    let mut render_pipeline =
+        shader::initial_pipeline(&device, &pipeline_layout, surface_format, &mut shader_file);
 
     // キー状態
     let mut keys_pressed = std::collections::HashSet::new();
+    // オービットモードと、次のフレームまでに溜めたマウスの操作（ドラッグしたピクセル数、ホイールの段数）
+    let mut orbit_mode = false;
+    let mut dragging = false;
+    let mut cursor: Option<PhysicalPosition<f64>> = None;
+    let mut drag = (0.0f32, 0.0f32);
+    let mut wheel = 0.0f32;
+    // スクリーンショット（P キーを押したフレームで 1 枚。保存中は次を受け付けない）
+    let mut screenshot_requested = false;
+    let screenshot_busy = Arc::new(AtomicBool::new(false));
 
     println!("=== Mandelbulb 3D GPU Explorer ===");
     println!("  Move: W/A/S/D + Space/Shift");
-    println!("  Look: Arrow Keys");
-    println!("  Power: 1-9 keys");
-    println!("  Screenshot: P");
+    println!("  Look: Arrow Keys (yaw/pitch), Q/E (roll)");
+    println!(
+        "  Mouse look: M (grab the cursor, sensitivity {} rad)",
+        mouse_look.sensitivity
+    );
+    println!(
+        "  Orbit mode: Enter (drag / arrows to orbit, wheel / W/S to zoom, target {})",
+        orbit_target
+    );
+    println!("  Power: PageDown/PageUp (-/+ {})", POWER_STEP);
+    println!(
+        "  Fractal: Tab (Mandelbulb / Quaternion Julia / Menger Sponge / Sierpinski Tetrahedron / Mandelbox)"
+    );
+    println!("  Julia c: T/G, Y/H, U/J, I/K (real, i, j, k)");
+    println!("  Depth: [ / ] (Menger Sponge, Sierpinski Tetrahedron)");
+    println!("  Mandelbox: PageDown/PageUp (scale), [ / ] (min radius)");
+    println!("  Screenshot: P (to {})", start.screenshot_dir.display());
+    println!("  Light: Z/X (azimuth), C/V (elevation), B (color)");
+    println!("  Coloring: / (blend / orbit trap / iteration / normal / position / clay)");
+    println!(
+        "  Quality: F1/F2 (ray-march steps), F3/F4 (iterations), F6/F7 (bailout), F5 (low / medium / high)"
+    );
+    println!("  Cross-section: F (toggle), , / . (move plane), L (face camera)");
+    println!(
+        "  Animation: N (record keyframe), O (render {}x{} at {} fps to {})",
+        animation.size.0,
+        animation.size.1,
+        animation.fps,
+        animation.frames_dir.display()
+    );
+    println!(
+        "  Power morph: F9 (power {} to {}, {} frames to {})",
+        morph.powers.0,
+        morph.powers.1,
+        morph.frames,
+        morph.frames_dir.display()
+    );
+    println!(
+        "  Camera presets: Ctrl+1-9 (save), Alt+1-9 (recall) ({})",
+        presets.path.display()
+    );
+    println!("  Parameter panel: F10 (sliders for power, quality, light, colors)");
     println!("  Reset: R");
+    println!("  Quit: Esc");
 
     let _ = event_loop.run(move |event, elwt| match event {
-        Event::WindowEvent { event, .. } => match event {
-            WindowEvent::CloseRequested => elwt.exit(),
-            WindowEvent::Focused(false) => {
-                keys_pressed.clear();
-            }
-            WindowEvent::KeyboardInput {
-                event:
-                    KeyEvent {
-                        physical_key: PhysicalKey::Code(key),
-                        state,
-                        ..
-                    },
-                ..
-            } => match state {
-                ElementState::Pressed => {
-                    keys_pressed.insert(key);
-
-                    match key {
-                        KeyCode::Escape | KeyCode::KeyQ => elwt.exit(),
-                        KeyCode::KeyR => {
-                            camera = Camera::new();
-                            power = 2.0;
-                        }
-                        KeyCode::Digit1 => power = 2.0,
-                        KeyCode::Digit2 => power = 3.0,
-                        KeyCode::Digit3 => power = 4.0,
-                        KeyCode::Digit4 => power = 5.0,
-                        KeyCode::Digit5 => power = 6.0,
-                        KeyCode::Digit6 => power = 7.0,
-                        KeyCode::Digit7 => power = 8.0,
-                        KeyCode::Digit8 => power = 9.0,
-                        KeyCode::Digit9 => power = 12.0,
-                        _ => {}
-                    }
-                }
-                ElementState::Released => {
-                    keys_pressed.remove(&key);
-                }
-            },
-            WindowEvent::RedrawRequested => {
-                let frame_start = Instant::now();
-
-                // 入力処理
-                let move_speed = 0.05;
-                let rot_speed = 0.05;
-
-                if keys_pressed.contains(&KeyCode::KeyW) {
-                    camera.move_forward(move_speed);
-                }
-                if keys_pressed.contains(&KeyCode::KeyS) {
-                    camera.move_forward(-move_speed);
-                }
-                if keys_pressed.contains(&KeyCode::KeyA) {
-                    camera.move_right(-move_speed);
-                }
-                if keys_pressed.contains(&KeyCode::KeyD) {
-                    camera.move_right(move_speed);
-                }
-                if keys_pressed.contains(&KeyCode::Space) {
-                    camera.pos.y += move_speed;
+        Event::WindowEvent { event, .. } => {
+            // パラメータの画面の上での操作はカメラの操作に渡さない
+            let on_panel = panel.on_window_event(&window, &event);
+            match event {
+                WindowEvent::CloseRequested => elwt.exit(),
+                // 描画先の大きさと縦横比をウィンドウに合わせる（最小化して大きさが 0 になったときはそのまま）
+                WindowEvent::Resized(size) if size.width > 0 && size.height > 0 => {
+                    config.width = size.width;
+                    config.height = size.height;
+                    surface.configure(&device, &config);
+                    aspect = size.width as f32 / size.height as f32;
+                    window.request_redraw();
                 }
-                if keys_pressed.contains(&KeyCode::ShiftLeft) {
-                    camera.pos.y -= move_speed;
+                WindowEvent::Focused(false) => {
+                    keys_pressed.clear();
+                    dragging = false;
+                    // 他のウィンドウに切り替えたらカーソルを解放する
+                    let _ = mouse_look.set(&window, false);
                 }
-                if keys_pressed.contains(&KeyCode::ArrowLeft) {
-                    camera.rot_y -= rot_speed;
-                }
-                if keys_pressed.contains(&KeyCode::ArrowRight) {
-                    camera.rot_y += rot_speed;
-                }
-                if keys_pressed.contains(&KeyCode::ArrowUp) {
-                    camera.rot_x -= rot_speed;
+                WindowEvent::MouseInput {
+                    state,
+                    button: MouseButton::Left,
+                    ..
+                } => dragging = state == ElementState::Pressed && !on_panel,
+                WindowEvent::CursorMoved { position, .. } => {
+                    if let (true, Some(from)) = (dragging, cursor) {
+                        drag.0 += (position.x - from.x) as f32;
+                        drag.1 += (position.y - from.y) as f32;
+                    }
+                    cursor = Some(position);
                 }
-                if keys_pressed.contains(&KeyCode::ArrowDown) {
-                    camera.rot_x += rot_speed;
+                WindowEvent::MouseWheel { delta, .. } if !on_panel => {
+                    // 上に回すと近づく（トラックパッドの大きな量は 1 段に丸める）
+                    wheel += match delta {
+                        MouseScrollDelta::LineDelta(_, y) => y,
+                        MouseScrollDelta::PixelDelta(p) => p.y as f32,
+                    }
+                    .clamp(-1.0, 1.0);
                 }
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            physical_key: PhysicalKey::Code(key),
+                            state,
+                            repeat,
+                            ..
+                        },
+                    ..
+                } => match state {
+                    ElementState::Pressed if !on_panel => {
+                        keys_pressed.insert(key);
 
-                // パラメータ更新
-                let params = Params {
-                    camera_pos_power: Vec4::new(camera.pos.x, camera.pos.y, camera.pos.z, power),
-                    rotation: Vec2::new(camera.rot_x, camera.rot_y),
-                    time: 0.0,
-                    aspect: WIDTH as f32 / HEIGHT as f32,
-                };
-                queue.write_buffer(&param_buffer, 0, bytemuck::cast_slice(&[params]));
-
-                // レンダリング
-                let output = match surface.get_current_texture() {
-                    Ok(t) => t,
-                    Err(_) => {
-                        surface.configure(&device, &config);
-                        return;
-                    }
-                };
-                let view = output
-                    .texture
-                    .create_view(&wgpu::TextureViewDescriptor::default());
+                        // カメラのプリセット（Ctrl+数字キーで保存、Alt+数字キーで呼び出し）
+                        let digit_keys = [
+                            KeyCode::Digit1,
+                            KeyCode::Digit2,
+                            KeyCode::Digit3,
+                            KeyCode::Digit4,
+                            KeyCode::Digit5,
+                            KeyCode::Digit6,
+                            KeyCode::Digit7,
+                            KeyCode::Digit8,
+                            KeyCode::Digit9,
+                        ];
+                        if let Some(index) = digit_keys.iter().position(|&k| k == key) {
+                            let slot = index + 1;
+                            let held =
+                                |keys: [KeyCode; 2]| keys.iter().any(|k| keys_pressed.contains(k));
+                            if held([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+                                match presets.store(slot, camera_preset(&camera, &fractal, &fog, &light)) {
+                                    Ok(()) => {
+                                        println!(
+                                            "Preset {} saved to {}",
+                                            slot,
+                                            presets.path.display()
+                                        )
+                                    }
+                                    Err(e) => eprintln!("{}", e),
+                                }
+                            } else if held([KeyCode::AltLeft, KeyCode::AltRight]) {
+                                match presets.get(slot) {
+                                    Some(preset) => {
+                                        match apply_preset(
+                                            preset,
+                                            &mut camera,
+                                            &mut fractal,
+                                            &mut fog,
+                                            &mut light,
+                                        ) {
+                                            Ok(()) => println!("Preset {} recalled", slot),
+                                            Err(e) => eprintln!("Preset {}: {}", slot, e),
+                                        }
+                                    }
+                                    None => println!("Preset {} is empty", slot),
+                                }
+                            }
+                        }
 
-                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                    label: Some("Render Encoder"),
-                });
+                        match key {
+                            KeyCode::Escape => elwt.exit(),
+                            KeyCode::Enter => {
+                                orbit_mode = !orbit_mode;
+                                println!(
+                                    "Camera: {}",
+                                    if orbit_mode { "orbit" } else { "free flight" }
+                                );
+                                if orbit_mode {
+                                    camera.face(orbit_target);
+                                }
+                            }
+                            KeyCode::KeyP if !repeat => {
+                                if screenshot_busy.load(Ordering::Acquire) {
+                                    println!("Screenshot is still being saved");
+                                } else {
+                                    screenshot_requested = true;
+                                }
+                            }
+                            KeyCode::KeyM if !repeat => {
+                                match mouse_look.set(&window, !mouse_look.enabled) {
+                                    Ok(()) => println!(
+                                        "Mouse look: {}",
+                                        if mouse_look.enabled { "on" } else { "off" }
+                                    ),
+                                    Err(e) => eprintln!("{}", e),
+                                }
+                            }
+                            KeyCode::KeyR => {
+                                camera = fractal.kind.camera();
+                                light = Light::new();
+                                fog = default_fog;
+                                fractal = fractal.reset(start.power);
+                            }
+                            KeyCode::F10 => panel.visible = !panel.visible,
+                            // パワー変更（押し続けるとキーリピートで繰り返す）
+                            // マンデルボックスでは倍率を変える
+                            KeyCode::PageDown if fractal.kind == FractalType::Mandelbox => {
+                                fractal.mandelbox.shift_scale(-MANDELBOX_SCALE_STEP)
+                            }
+                            KeyCode::PageUp if fractal.kind == FractalType::Mandelbox => {
+                                fractal.mandelbox.shift_scale(MANDELBOX_SCALE_STEP)
+                            }
+                            KeyCode::PageDown => fractal.shift_power(-POWER_STEP),
+                            KeyCode::PageUp => fractal.shift_power(POWER_STEP),
+                            // 画質（押し続けるとキーリピートで繰り返す）
+                            KeyCode::F1 => fractal.quality.adjust_steps(-1),
+                            KeyCode::F2 => fractal.quality.adjust_steps(1),
+                            KeyCode::F3 => fractal.quality.adjust_iterations(-1),
+                            KeyCode::F4 => fractal.quality.adjust_iterations(1),
+                            KeyCode::F6 => fractal.quality.adjust_bailout(-1),
+                            KeyCode::F7 => fractal.quality.adjust_bailout(1),
+                            KeyCode::F5 => {
+                                let preset = QualityPreset::after(&fractal.quality);
+                                fractal.quality = preset.quality();
+                                println!("Quality: {}", preset.name());
+                            }
+                            KeyCode::Tab => {
+                                fractal.kind = fractal.kind.next();
+                                camera = fractal.kind.camera();
+                            }
+                            // マンデルボックスでは最小半径を変える
+                            KeyCode::BracketLeft if fractal.kind == FractalType::Mandelbox => {
+                                fractal.mandelbox.shift_min_radius(-MANDELBOX_MIN_RADIUS_STEP)
+                            }
+                            KeyCode::BracketRight if fractal.kind == FractalType::Mandelbox => {
+                                fractal.mandelbox.shift_min_radius(MANDELBOX_MIN_RADIUS_STEP)
+                            }
+                            KeyCode::BracketLeft => fractal.depth = (fractal.depth - 1).max(1),
+                            KeyCode::BracketRight => {
+                                fractal.depth = (fractal.depth + 1).min(MAX_IFS_DEPTH)
+                            }
+                            KeyCode::KeyB => light.next_color(),
+                            KeyCode::Slash => fractal.coloring = fractal.coloring.next(),
+                            KeyCode::KeyF => fractal.clip.enabled = !fractal.clip.enabled,
+                            KeyCode::KeyL => fractal.clip.face(&camera),
+                            KeyCode::KeyN => {
+                                let keyframe = CameraKeyframe {
+                                    fractal: fractal.kind.key().to_string(),
+                                    position: camera.pos,
+                                    orientation: camera.orientation,
+                                    power: fractal.power,
+                                    duration: DEFAULT_DURATION,
+                                };
+                                match CameraPath::append(&animation.keyframes, keyframe) {
+                                    Ok(count) => println!(
+                                        "Keyframe {} saved to {}",
+                                        count,
+                                        animation.keyframes.display()
+                                    ),
+                                    Err(e) => eprintln!("{}", e),
+                                }
+                            }
+                            // アニメーション・モーフィングの書き出し（描画が終わるまでウィンドウは止まる）
+                            KeyCode::KeyO => {
+                                let offscreen = Offscreen {
+                                    device: &device,
+                                    queue: &queue,
+                                    pipeline: &render_pipeline,
+                                    bind_group: &bind_group,
+                                    param_buffer: &param_buffer,
+                                    format: surface_format,
+                                };
+                                match offscreen.render_animation(
+                                    &animation,
+                                    &fractal,
+                                    &Shading {
+                                        fog: &fog,
+                                        light: &light,
+                                        glow: &glow,
+                                    },
+                                ) {
+                                    Ok(count) => println!(
+                                        "{} frames saved to {}",
+                                        count,
+                                        animation.frames_dir.display()
+                                    ),
+                                    Err(e) => eprintln!("{}", e),
+                                }
+                            }
+                            KeyCode::F9 => {
+                                let offscreen = Offscreen {
+                                    device: &device,
+                                    queue: &queue,
+                                    pipeline: &render_pipeline,
+                                    bind_group: &bind_group,
+                                    param_buffer: &param_buffer,
+                                    format: surface_format,
+                                };
+                                match offscreen.render_morph(
+                                    &morph,
+                                    &animation,
+                                    &camera,
+                                    &fractal,
+                                    &Shading {
+                                        fog: &fog,
+                                        light: &light,
+                                        glow: &glow,
+                                    },
+                                ) {
+                                    Ok(count) => println!(
+                                        "{} frames saved to {}",
+                                        count,
+                                        morph.frames_dir.display()
+                                    ),
+                                    Err(e) => eprintln!("{}", e),
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    ElementState::Pressed => {}
+                    ElementState::Released => {
+                        keys_pressed.remove(&key);
+                    }
+                },
+                WindowEvent::RedrawRequested => {
+                    let frame_start = Instant::now();
 
-                {
-                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                        label: Some("Render Pass"),
-                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                            view: &view,
-                            resolve_target: None,
-                            ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                                store: wgpu::StoreOp::Store,
-                            },
-                        })],
-                        depth_stencil_attachment: None,
-                        timestamp_writes: None,
-                        occlusion_query_set: None,
-                    });
-                    render_pass.set_pipeline(&render_pipeline);
-                    render_pass.set_bind_group(0, &bind_group, &[]);
-                    render_pass.draw(0..3, 0..1);
-                }
+                    // 入力処理
+                    // カメラの操作（オービットモードでは注視点のまわりを回る）
+                    let held = |control| {
+                        keys_pressed.contains(&match control {
+                            Control::Forward => KeyCode::KeyW,
+                            Control::Backward => KeyCode::KeyS,
+                            Control::Left => KeyCode::KeyA,
+                            Control::Right => KeyCode::KeyD,
+                            Control::Up => KeyCode::Space,
+                            Control::Down => KeyCode::ShiftLeft,
+                            Control::YawLeft => KeyCode::ArrowLeft,
+                            Control::YawRight => KeyCode::ArrowRight,
+                            Control::PitchUp => KeyCode::ArrowUp,
+                            Control::PitchDown => KeyCode::ArrowDown,
+                            Control::RollLeft => KeyCode::KeyQ,
+                            Control::RollRight => KeyCode::KeyE,
+                        })
+                    };
+                    // マウスで見回す移動量は、オービットモードではドラッグと同じに扱う
+                    let look = mouse_look.take();
+                    if orbit_mode {
+                        camera.orbit(
+                            orbit_target,
+                            (drag.0 + look.0, drag.1 + look.1),
+                            wheel,
+                            held,
+                        );
+                    } else {
+                        camera.fly(held);
+                        if look != (0.0, 0.0) {
+                            camera.rotate(
+                                look.0 * mouse_look.sensitivity,
+                                look.1 * mouse_look.sensitivity,
+                                0.0,
+                            );
+                        }
+                    }
+                    drag = (0.0, 0.0);
+                    wheel = 0.0;
 
-                if keys_pressed.contains(&KeyCode::KeyP) {
-                    let u32_size = std::mem::size_of::<u32>() as u32;
-                    let texture_width = config.width;
-                    let texture_height = config.height;
-                    let bytes_per_row = u32_size * texture_width;
-                    let padded_bytes_per_row = (bytes_per_row + 255) & !255;
+                    // 四元数ジュリア集合の c
+                    let c_keys = [
+                        (KeyCode::KeyT, KeyCode::KeyG, Vec4::X),
+                        (KeyCode::KeyY, KeyCode::KeyH, Vec4::Y),
+                        (KeyCode::KeyU, KeyCode::KeyJ, Vec4::Z),
+                        (KeyCode::KeyI, KeyCode::KeyK, Vec4::W),
+                    ];
+                    for (inc, dec, axis) in c_keys {
+                        if keys_pressed.contains(&inc) {
+                            fractal.julia_c += axis * JULIA_C_STEP;
+                        }
+                        if keys_pressed.contains(&dec) {
+                            fractal.julia_c -= axis * JULIA_C_STEP;
+                        }
+                    }
 
-                    let buffer_size = (padded_bytes_per_row * texture_height) as u64;
+                    // 主光源
+                    if keys_pressed.contains(&KeyCode::KeyZ) {
+                        light.rotate(-LIGHT_ROT_SPEED, 0.0);
+                    }
+                    if keys_pressed.contains(&KeyCode::KeyX) {
+                        light.rotate(LIGHT_ROT_SPEED, 0.0);
+                    }
+                    if keys_pressed.contains(&KeyCode::KeyC) {
+                        light.rotate(0.0, -LIGHT_ROT_SPEED);
+                    }
+                    if keys_pressed.contains(&KeyCode::KeyV) {
+                        light.rotate(0.0, LIGHT_ROT_SPEED);
+                    }
 
-                    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
-                        label: Some("Screenshot Buffer"),
-                        size: buffer_size,
-                        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
-                        mapped_at_creation: false,
-                    });
+                    // 断面表示の切断面
+                    if keys_pressed.contains(&KeyCode::Comma) {
+                        fractal.clip.shift(-CLIP_STEP);
+                    }
+                    if keys_pressed.contains(&KeyCode::Period) {
+                        fractal.clip.shift(CLIP_STEP);
+                    }
 
-                    encoder.copy_texture_to_buffer(
-                        wgpu::ImageCopyTexture {
-                            texture: &output.texture,
-                            mip_level: 0,
-                            origin: wgpu::Origin3d::ZERO,
-                            aspect: wgpu::TextureAspect::All,
-                        },
-                        wgpu::ImageCopyBuffer {
-                            buffer: &buffer,
-                            layout: wgpu::ImageDataLayout {
-                                offset: 0,
-                                bytes_per_row: Some(padded_bytes_per_row),
-                                rows_per_image: Some(texture_height),
-                            },
-                        },
-                        wgpu::Extent3d {
-                            width: texture_width,
-                            height: texture_height,
-                            depth_or_array_layers: 1,
-                        },
-                    );
+                    // パラメータの画面（スライダーで変えた値はこのフレームから使う）
+                    let actions =
+                        panel.update(&window, &mut fractal, &mut light, &mut fog, &mut glow);
+                    if actions.reset {
+                        camera = fractal.kind.camera();
+                        light = Light::new();
+                        fog = default_fog;
+                        fractal = fractal.reset(start.power);
+                    }
+                    if actions.screenshot && !screenshot_busy.load(Ordering::Acquire) {
+                        screenshot_requested = true;
+                    }
 
-                    queue.submit(std::iter::once(encoder.finish()));
+                    // シェーダーのファイルが書き換えられていれば作り直す（失敗したら前のまま描く）
+                    if let Some(source) = shader_file.changed() {
+                        match shader::create_pipeline(
+                            &device,
+                            &pipeline_layout,
+                            surface_format,
+                            &source,
+                        ) {
+                            Ok(pipeline) => {
+                                render_pipeline = pipeline;
+                                println!("Shader reloaded from {}", shader_file.path.display());
+                            }
+                            Err(e) => eprintln!(
+                                "{}: {} (keeping the current shader)",
+                                shader_file.path.display(),
+                                e
+                            ),
+                        }
+                    }
 
-                    let slice = buffer.slice(..);
-                    slice.map_async(wgpu::MapMode::Read, move |_| {});
-                    device.poll(wgpu::Maintain::Wait);
+                    // パラメータ更新
+                    let shading = Shading {
+                        fog: &fog,
+                        light: &light,
+                        glow: &glow,
+                    };
+                    let params = Params {
+                        aspect,
+                        ..Params::new(&camera, &fractal, &shading)
+                    };
+                    queue.write_buffer(&param_buffer, 0, bytemuck::cast_slice(&[params]));
 
-                    let data = slice.get_mapped_range();
+                    // レンダリング
+                    let output = match surface.get_current_texture() {
+                        Ok(t) => t,
+                        Err(_) => {
+                            surface.configure(&device, &config);
+                            return;
+                        }
+                    };
+                    let view = output
+                        .texture
+                        .create_view(&wgpu::TextureViewDescriptor::default());
 
-                    let mut img_buf =
-                        Vec::with_capacity((texture_width * texture_height * 4) as usize);
-                    for chunk in data.chunks(padded_bytes_per_row as usize) {
-                        img_buf.extend_from_slice(&chunk[..bytes_per_row as usize]);
-                    }
+                    let mut encoder =
+                        device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: Some("Render Encoder"),
+                        });
 
-                    for pixel in img_buf.chunks_exact_mut(4) {
-                        pixel.swap(0, 2);
+                    {
+                        let mut render_pass =
+                            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                                label: Some("Render Pass"),
+                                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                                    view: &view,
+                                    resolve_target: None,
+                                    ops: wgpu::Operations {
+                                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                                        store: wgpu::StoreOp::Store,
+                                    },
+                                })],
+                                depth_stencil_attachment: None,
+                                timestamp_writes: None,
+                                occlusion_query_set: None,
+                            });
+                        render_pass.set_pipeline(&render_pipeline);
+                        render_pass.set_bind_group(0, &bind_group, &[]);
+                        render_pass.draw(0..3, 0..1);
                     }
 
-                    let _ = std::fs::create_dir_all("../assets");
-
-                    match image::save_buffer_with_format(
-                        "../assets/gpu_screenshot.png",
-                        &img_buf,
-                        texture_width,
-                        texture_height,
-                        image::ColorType::Rgba8,
-                        image::ImageFormat::Png,
-                    ) {
-                        Ok(_) => println!("Screenshot saved to assets/gpu_screenshot.png"),
-                        Err(e) => eprintln!("Failed to save screenshot: {}", e),
+                    let screenshot = std::mem::take(&mut screenshot_requested).then(|| {
+                        screenshot_busy.store(true, Ordering::Release);
+                        Screenshot::record(
+                            &device,
+                            &mut encoder,
+                            &output.texture,
+                            start.screenshot_dir.join("gpu_screenshot.png"),
+                        )
+                    });
+                    // パラメータの画面はスクリーンショットのコピーの後に重ねる（保存する画像には描かない）
+                    let uploads = panel.paint(&device, &queue, &mut encoder, &output.texture);
+                    queue.submit(uploads.into_iter().chain(std::iter::once(encoder.finish())));
+                    if let Some(screenshot) = screenshot {
+                        screenshot.save_in_background(screenshot_busy.clone());
                     }
 
-                    buffer.unmap();
-                } else {
-                    queue.submit(std::iter::once(encoder.finish()));
-                }
-
-                output.present();
+                    output.present();
+                    // 読み出しが終わったスクリーンショットを保存のスレッドに知らせる（待たない）
+                    device.poll(wgpu::Maintain::Poll);
 
-                let elapsed = frame_start.elapsed();
-                window.set_title(&format!(
-                    "Mandelbulb 3D GPU (Power={}) - {:.1} ms ({:.1} fps)",
-                    power as i32,
-                    elapsed.as_secs_f32() * 1000.0,
-                    1.0 / elapsed.as_secs_f32().max(0.001)
-                ));
+                    let elapsed = frame_start.elapsed();
+                    let shape = match fractal.kind {
+                        FractalType::Mandelbulb => {
+                            format!("Mandelbulb 3D GPU (Power={:.1})", fractal.power)
+                        }
+                        FractalType::QuaternionJulia => {
+                            let c = fractal.julia_c;
+                            format!(
+                                "Quaternion Julia GPU (c={:.3}{:+.3}i{:+.3}j{:+.3}k)",
+                                c.x, c.y, c.z, c.w
+                            )
+                        }
+                        FractalType::MengerSponge => {
+                            format!("Menger Sponge GPU (Depth={})", fractal.depth)
+                        }
+                        FractalType::SierpinskiTetrahedron => {
+                            format!("Sierpinski Tetrahedron GPU (Depth={})", fractal.depth)
+                        }
+                        FractalType::Mandelbox => format!(
+                            "Mandelbox GPU (Scale={:.1} MinR={:.2})",
+                            fractal.mandelbox.scale, fractal.mandelbox.min_radius
+                        ),
+                    };
+                    let shape = if fractal.clip.enabled {
+                        format!("{} [Clip={:+.2}]", shape, fractal.clip.offset)
+                    } else {
+                        shape
+                    };
+                    let shape = if orbit_mode {
+                        format!("{} [Orbit]", shape)
+                    } else {
+                        shape
+                    };
+                    let shape = if mouse_look.enabled {
+                        format!("{} [Mouse look]", shape)
+                    } else {
+                        shape
+                    };
+                    window.set_title(&format!(
+                        "{} - steps={} iter={} bailout={:.1} eps={:.2} far={:.1} - color={} - {:.1} ms ({:.1} fps)",
+                        shape,
+                        fractal.quality.max_steps,
+                        fractal.quality.iterations,
+                        fractal.quality.bailout,
+                        fractal.quality.hit_cone,
+                        fractal.quality.max_distance,
+                        fractal.coloring.name(),
+                        elapsed.as_secs_f32() * 1000.0,
+                        1.0 / elapsed.as_secs_f32().max(0.001)
+                    ));
 
-                window.request_redraw();
+                    window.request_redraw();
+                }
+                _ => {}
             }
-            _ => {}
-        },
+        }
+        Event::DeviceEvent {
+            event: DeviceEvent::MouseMotion { delta },
+            ..
+        } => mouse_look.add(delta),
         Event::AboutToWait => {
             window.request_redraw();
         }
@@ -0,0 +1,308 @@
+//! ウィンドウに重ねるパラメータの画面（egui）
+//!
+//! パワー（マンデルボックスでは倍率と最小半径、四元数ジュリア集合では c の 4 成分）・画質・主光源・色の付け方・霧・輪郭の光をスライダーや色の選択で変え、リセットとスクリーンショットを
+//! ボタンで行えます。キーの組み合わせを覚えたり WGSL を書き換えて再コンパイルしたりせずに見た目を調整できます。
+//! F10 キーで表示を切り替えます。画面の上で操作しているマウス（と文字の入力）はカメラの操作に渡しません。
+//! スクリーンショットには描かないよう、描画とスクリーンショットのコピーを積んだ後に重ねて描きます。
+//! egui の既定のフォントには日本語がないため、画面の文字は英語にしています。
+
+use crate::{
+    Fog, Fractal, FractalType, Glow, Light, QualityPreset, BAILOUT_RANGE, HIT_CONE_RANGE,
+    ITER_RANGE, JULIA_C_RANGE, LIGHT_COLORS, MANDELBOX_MIN_RADIUS_RANGE, MANDELBOX_SCALE_RANGE,
+    MAX_DISTANCE_RANGE, MAX_LIGHT_ELEVATION, MAX_POWER, MIN_POWER, STEPS_RANGE,
+};
+use glam::{Vec3, Vec4};
+use mandelbulb_3d_common::coloring::ColorMode;
+use winit::event::WindowEvent;
+use winit::window::Window;
+
+/// 霧の濃さのスライダーの範囲
+const FOG_DENSITY_RANGE: (f32, f32) = (0.0, 2.0);
+
+/// 輪郭の光の強さのスライダーの範囲
+const GLOW_STRENGTH_RANGE: (f32, f32) = (0.0, 4.0);
+
+/// 主光源の色の見本の大きさ（論理ピクセル）
+const SWATCH_SIZE: f32 = 18.0;
+
+/// パラメータの画面のボタンで頼まれた操作
+#[derive(Clone, Copy, Default)]
+pub struct Actions {
+    pub reset: bool,
+    pub screenshot: bool,
+}
+
+/// 1 フレーム分の画面（描く三角形と、GPU に送るフォントなどの画像の変更）
+struct Frame {
+    primitives: Vec<egui::ClippedPrimitive>,
+    textures: egui::TexturesDelta,
+    pixels_per_point: f32,
+}
+
+pub struct Panel {
+    pub visible: bool,
+    context: egui::Context,
+    state: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+    frame: Option<Frame>, // `update` で作り、まだ描いていない画面
+}
+
+impl Panel {
+    /// `format` の画面に重ねて描く（最初は表示する）
+    pub fn new(window: &Window, device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let context = egui::Context::default();
+        let state = egui_winit::State::new(
+            context.clone(),
+            egui::ViewportId::ROOT,
+            window,
+            Some(window.scale_factor() as f32),
+            Some(device.limits().max_texture_dimension_2d as usize),
+        );
+        Self {
+            visible: true,
+            context,
+            state,
+            renderer: egui_wgpu::Renderer::new(device, format, None, 1),
+            frame: None,
+        }
+    }
+
+    /// ウィンドウのイベントを画面に渡し、画面の上での操作だったか（カメラの操作に渡さないか）を返す
+    pub fn on_window_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        self.visible && self.state.on_window_event(window, event).consumed
+    }
+
+    /// 1 フレーム分の画面を作り、スライダーなどで変えた値を書き込む（隠している間は何もしない）
+    pub fn update(
+        &mut self,
+        window: &Window,
+        fractal: &mut Fractal,
+        light: &mut Light,
+        fog: &mut Fog,
+        glow: &mut Glow,
+    ) -> Actions {
+        let mut actions = Actions::default();
+        if !self.visible {
+            return actions;
+        }
+        let input = self.state.take_egui_input(window);
+        let output = self.context.run(input, |context| {
+            egui::Window::new("Parameters")
+                .default_pos([context.screen_rect().right() - 290.0, 8.0])
+                .default_width(270.0)
+                .show(context, |ui| {
+                    fractal_controls(ui, fractal);
+                    ui.separator();
+                    light_controls(ui, light);
+                    ui.separator();
+                    color_controls(ui, fractal, fog, glow);
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        actions.reset = ui.button("Reset (R)").clicked();
+                        actions.screenshot = ui.button("Screenshot (P)").clicked();
+                    });
+                });
+        });
+        self.state
+            .handle_platform_output(window, output.platform_output);
+        self.frame = Some(Frame {
+            primitives: self
+                .context
+                .tessellate(output.shapes, output.pixels_per_point),
+            textures: output.textures_delta,
+            pixels_per_point: output.pixels_per_point,
+        });
+        actions
+    }
+
+    /// `update` で作った画面を `texture` に重ねて描くコマンドを `encoder` に積む
+    ///
+    /// 返したコマンド（頂点などの転送）は `encoder` より先に送ってください。
+    pub fn paint(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let Some(frame) = self.frame.take() else {
+            return Vec::new();
+        };
+        let screen = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [texture.width(), texture.height()],
+            pixels_per_point: frame.pixels_per_point,
+        };
+        for (id, delta) in &frame.textures.set {
+            self.renderer.update_texture(device, queue, *id, delta);
+        }
+        let commands =
+            self.renderer
+                .update_buffers(device, queue, encoder, &frame.primitives, &screen);
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Panel Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.renderer
+                .render(&mut render_pass, &frame.primitives, &screen);
+        }
+        for id in &frame.textures.free {
+            self.renderer.free_texture(id);
+        }
+        commands
+    }
+}
+
+/// 形の値（マンデルボックスでは倍率と最小半径、四元数ジュリア集合では c、それ以外ではパワー）と画質
+fn fractal_controls(ui: &mut egui::Ui, fractal: &mut Fractal) {
+    if fractal.kind == FractalType::QuaternionJulia {
+        let mut c = fractal.julia_c.to_array();
+        for (value, name) in c.iter_mut().zip(["c (real)", "c (i)", "c (j)", "c (k)"]) {
+            ui.add(egui::Slider::new(value, JULIA_C_RANGE.0..=JULIA_C_RANGE.1).text(name));
+        }
+        fractal.julia_c = Vec4::from_array(c);
+    } else if fractal.kind == FractalType::Mandelbox {
+        // 倍率の絶対値が小さい範囲では形ができないため、スライダーで入った値も範囲に収める
+        let mut scale = fractal.mandelbox.scale;
+        let range = MANDELBOX_SCALE_RANGE.1;
+        if ui
+            .add(egui::Slider::new(&mut scale, -range..=range).text("Box scale"))
+            .changed()
+        {
+            fractal.mandelbox.set_scale(scale);
+        }
+        ui.add(
+            egui::Slider::new(
+                &mut fractal.mandelbox.min_radius,
+                MANDELBOX_MIN_RADIUS_RANGE.0..=MANDELBOX_MIN_RADIUS_RANGE.1,
+            )
+            .text("Min radius"),
+        );
+    } else {
+        ui.add(egui::Slider::new(&mut fractal.power, MIN_POWER..=MAX_POWER).text("Power"));
+    }
+    let quality = &mut fractal.quality;
+    egui::ComboBox::from_label("Quality")
+        .selected_text(QualityPreset::of(quality).map_or("custom", |p| p.name()))
+        .show_ui(ui, |ui| {
+            for preset in QualityPreset::ALL {
+                if ui
+                    .selectable_label(QualityPreset::of(quality) == Some(preset), preset.name())
+                    .clicked()
+                {
+                    *quality = preset.quality();
+                }
+            }
+        });
+    ui.add(
+        egui::Slider::new(&mut quality.max_steps, STEPS_RANGE.0..=STEPS_RANGE.1)
+            .logarithmic(true)
+            .text("Max steps"),
+    );
+    ui.add(
+        egui::Slider::new(&mut quality.iterations, ITER_RANGE.0..=ITER_RANGE.1).text("Iterations"),
+    );
+    ui.add(
+        egui::Slider::new(&mut quality.bailout, BAILOUT_RANGE.0..=BAILOUT_RANGE.1).text("Bailout"),
+    );
+    ui.add(
+        egui::Slider::new(&mut quality.hit_cone, HIT_CONE_RANGE.0..=HIT_CONE_RANGE.1)
+            .logarithmic(true)
+            .text("Epsilon (px)"),
+    )
+    .on_hover_text("Surface hit distance, relative to the size of a pixel at that distance");
+    ui.add(
+        egui::Slider::new(
+            &mut quality.max_distance,
+            MAX_DISTANCE_RANGE.0..=MAX_DISTANCE_RANGE.1,
+        )
+        .text("Max distance"),
+    );
+}
+
+/// 主光源の向きと色
+fn light_controls(ui: &mut egui::Ui, light: &mut Light) {
+    ui.add(
+        egui::Slider::new(&mut light.azimuth, 0.0..=360.0)
+            .suffix("°")
+            .text("Light azimuth"),
+    );
+    ui.add(
+        egui::Slider::new(
+            &mut light.elevation,
+            -MAX_LIGHT_ELEVATION..=MAX_LIGHT_ELEVATION,
+        )
+        .suffix("°")
+        .text("Light elevation"),
+    );
+    ui.horizontal(|ui| {
+        for (i, color) in LIGHT_COLORS.iter().enumerate() {
+            let stroke = if i == light.color {
+                egui::Stroke::new(2.0, egui::Color32::WHITE)
+            } else {
+                egui::Stroke::NONE
+            };
+            let swatch = egui::Button::new("")
+                .fill(color32(*color))
+                .stroke(stroke)
+                .min_size(egui::vec2(SWATCH_SIZE, SWATCH_SIZE));
+            if ui.add(swatch).clicked() {
+                light.color = i;
+            }
+        }
+        ui.label("Light color");
+    });
+}
+
+/// 色の付け方・霧・輪郭の光
+fn color_controls(ui: &mut egui::Ui, fractal: &mut Fractal, fog: &mut Fog, glow: &mut Glow) {
+    egui::ComboBox::from_label("Coloring")
+        .selected_text(fractal.coloring.name())
+        .show_ui(ui, |ui| {
+            for mode in ColorMode::ALL {
+                ui.selectable_value(&mut fractal.coloring, mode, mode.name());
+            }
+        });
+    ui.horizontal(|ui| {
+        color_button(ui, &mut fog.color);
+        ui.add(
+            egui::Slider::new(&mut fog.density, FOG_DENSITY_RANGE.0..=FOG_DENSITY_RANGE.1)
+                .text("Fog"),
+        );
+    });
+    ui.horizontal(|ui| {
+        color_button(ui, &mut glow.color);
+        ui.add(
+            egui::Slider::new(
+                &mut glow.strength,
+                GLOW_STRENGTH_RANGE.0..=GLOW_STRENGTH_RANGE.1,
+            )
+            .text("Glow"),
+        );
+    });
+}
+
+/// 色を選ぶボタン
+fn color_button(ui: &mut egui::Ui, color: &mut Vec3) {
+    let mut rgb = color.to_array();
+    if ui.color_edit_button_rgb(&mut rgb).changed() {
+        *color = Vec3::from_array(rgb);
+    }
+}
+
+/// 0〜1 の色を egui の色にする
+fn color32(color: Vec3) -> egui::Color32 {
+    let [r, g, b] = (color * 255.0).round().to_array().map(|c| c as u8);
+    egui::Color32::from_rgb(r, g, b)
+}
@@ -0,0 +1,172 @@
+//! シェーダーの読み込みと、ファイルの変更に合わせた作り直し（ホットリロード）
+//!
+//! WGSL は実行時にファイル（`--shader <ファイル>`、既定: ソースの `shaders/mandelbulb.wgsl`）から読み、
+//! 描画中も更新日時を見張って、変わったらレンダーパイプラインを作り直します。シェーダーを書き換えて保存するだけで
+//! 描画に反映され、cargo でビルドし直す必要はありません。
+//! コンパイルに失敗したときや、ファイルが消えた・読めないときは終了せずにエラーをコンソールに出し
+//! （メッセージはほかの実行中のメッセージと同じく英語）、前のパイプラインのまま描き続けます。
+//! 既定のファイルが読めないとき（実行ファイルだけを別の場所へ移したときなど）は、実行ファイルに埋め込んだシェーダーを使います。
+
+use crate::Args;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+/// ビルドしたときのシェーダー（ファイルが読めない・最初のコンパイルに失敗したときに使う）
+const EMBEDDED: &str = include_str!("../shaders/mandelbulb.wgsl");
+
+/// `--shader` を省略したときに読むファイル
+const DEFAULT_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/mandelbulb.wgsl");
+
+/// ファイルの更新日時を確かめる間隔
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 見張っているシェーダーのファイル
+pub struct ShaderFile {
+    pub path: PathBuf,
+    modified: Option<SystemTime>, // 最後に読んだときの更新日時
+    checked: Instant,             // 最後に更新日時を確かめた時刻
+    failing: bool, // 読めないことを報告済みか（読めるようになるまで繰り返し報告しない）
+}
+
+impl ShaderFile {
+    /// `--shader <ファイル>` で指定したファイル（指定したファイルが見つからなければエラー）
+    pub fn from_args(args: &Args) -> Result<Self, String> {
+        let file = Self {
+            path: args.shader.clone().unwrap_or_else(|| DEFAULT_PATH.into()),
+            modified: None,
+            checked: Instant::now(),
+            failing: false,
+        };
+        if args.shader.is_some() && !file.path.is_file() {
+            return Err(format!(
+                "シェーダーのファイルが見つかりません: {}",
+                file.path.display()
+            ));
+        }
+        Ok(file)
+    }
+
+    /// 今のソース（ファイルが読めなければ埋め込んだシェーダー）
+    pub fn load(&mut self) -> String {
+        self.read().unwrap_or_else(|e| {
+            eprintln!(
+                "Failed to read shader {}: {} (using the embedded shader)",
+                self.path.display(),
+                e
+            );
+            EMBEDDED.to_string()
+        })
+    }
+
+    /// 前に読んでから書き換えられていれば新しいソース（`CHECK_INTERVAL` ごとに確かめる）
+    ///
+    /// ファイルが消えた・読めないときは一度だけ報告し、読めるようになるまで確かめ続けます。
+    pub fn changed(&mut self) -> Option<String> {
+        if self.checked.elapsed() < CHECK_INTERVAL {
+            return None;
+        }
+        self.checked = Instant::now();
+        let source = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .map_err(|e| e.to_string())
+            .and_then(|modified| {
+                if Some(modified) == self.modified {
+                    Ok(None)
+                } else {
+                    self.read().map(Some)
+                }
+            });
+        match source {
+            Ok(source) => {
+                self.failing = false;
+                source
+            }
+            Err(e) => {
+                if !self.failing {
+                    eprintln!(
+                        "Failed to read shader {}: {} (keeping the current shader)",
+                        self.path.display(),
+                        e
+                    );
+                    self.failing = true;
+                }
+                None
+            }
+        }
+    }
+
+    /// ファイルを読み、読めたときだけ更新日時を覚える（読む前の日時を覚え、読んでいる間の書き換えを取りこぼさない）
+    fn read(&mut self) -> Result<String, String> {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok();
+        let source = std::fs::read_to_string(&self.path).map_err(|e| e.to_string())?;
+        self.modified = modified;
+        Ok(source)
+    }
+}
+
+/// `source` のシェーダーで描くレンダーパイプライン（コンパイルやパイプラインの検証のエラーは終了せずに返す）
+pub fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    source: &str,
+) -> Result<wgpu::RenderPipeline, String> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Shader"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    });
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(e) => Err(format!("Failed to compile shader: {}", e)),
+        None => Ok(pipeline),
+    }
+}
+
+/// 最初のパイプライン（ファイルのシェーダーがコンパイルできなければ埋め込んだシェーダーで作る）
+pub fn initial_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    file: &mut ShaderFile,
+) -> wgpu::RenderPipeline {
+    create_pipeline(device, layout, format, &file.load()).unwrap_or_else(|e| {
+        eprintln!("{}: {}", file.path.display(), e);
+        eprintln!("Using the embedded shader (fix the file and save it to reload)");
+        create_pipeline(device, layout, format, EMBEDDED)
+            .expect("Failed to compile the embedded shader")
+    })
+}
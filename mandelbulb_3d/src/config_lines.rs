@@ -0,0 +1,119 @@
+//! 1 行に `<名前> <数値>...` を 1 つずつ書く設定ファイル（式・照明・質感）の読み込み
+//!
+//! `#` 以降はコメントで、空行は読み飛ばします。数値でない引数や行の中身のエラーには行番号を付けます。
+
+/// 設定ファイルの 1 行（名前と数値の引数）
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigLine<'a> {
+    pub name: &'a str,
+    pub args: Vec<f32>,
+}
+
+impl ConfigLine<'_> {
+    /// 引数の数が `counts` のどれかであることを確かめる
+    pub fn expect(&self, counts: &[usize]) -> Result<(), String> {
+        if counts.contains(&self.args.len()) {
+            return Ok(());
+        }
+        Err(format!(
+            "{} の引数の数が不正です（{} 個）: {}",
+            self.name,
+            counts
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>()
+                .join(" / "),
+            self.args.len()
+        ))
+    }
+}
+
+/// テキストの各行を `parse_line` に渡す（エラーには「n 行目」を付けて返す）
+pub fn parse<F>(text: &str, mut parse_line: F) -> Result<(), String>
+where
+    F: FnMut(ConfigLine) -> Result<(), String>,
+{
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        split(line)
+            .and_then(&mut parse_line)
+            .map_err(|e| format!("{} 行目: {}", index + 1, e))?;
+    }
+    Ok(())
+}
+
+/// 1 行を名前と数値の引数に分ける
+fn split(line: &str) -> Result<ConfigLine<'_>, String> {
+    let mut words = line.split_whitespace();
+    let name = words.next().unwrap_or("");
+    let args = words
+        .map(|w| {
+            w.parse::<f32>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .ok_or_else(|| format!("数値が不正です: {}", w))
+        })
+        .collect::<Result<Vec<f32>, String>>()?;
+    Ok(ConfigLine { name, args })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 読み込んだ行（名前と引数）の一覧
+    fn lines(text: &str) -> Result<Vec<(String, Vec<f32>)>, String> {
+        let mut lines = Vec::new();
+        parse(text, |line| {
+            lines.push((line.name.to_string(), line.args));
+            Ok(())
+        })?;
+        Ok(lines)
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let text = "# コメント\n\n  scale 2.0  # 倍率\nadd_c\nrotate 0 1 0 -30\n";
+        assert_eq!(
+            lines(text).unwrap(),
+            vec![
+                ("scale".to_string(), vec![2.0]),
+                ("add_c".to_string(), vec![]),
+                ("rotate".to_string(), vec![0.0, 1.0, 0.0, -30.0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_numbers_with_line_number() {
+        for word in ["abc", "1.0.0", "NaN", "inf"] {
+            let text = format!("scale 2\n\nscale {}\n", word);
+            assert_eq!(
+                lines(&text).unwrap_err(),
+                format!("3 行目: 数値が不正です: {}", word)
+            );
+        }
+    }
+
+    #[test]
+    fn expect_checks_argument_count() {
+        let line = ConfigLine {
+            name: "point",
+            args: vec![1.0, 2.0],
+        };
+        assert!(line.expect(&[2]).is_ok());
+        assert_eq!(
+            line.expect(&[3, 4, 7]).unwrap_err(),
+            "point の引数の数が不正です（3 / 4 / 7 個）: 2"
+        );
+        // 行の処理で返したエラーにも行番号が付く
+        let result = parse("ambient 0.1\npoint 1 2\n", |line| line.expect(&[1]));
+        assert_eq!(
+            result.unwrap_err(),
+            "2 行目: point の引数の数が不正です（1 個）: 2"
+        );
+    }
+}
@@ -0,0 +1,291 @@
+//! 環境（背景と、背景から届く光）
+//!
+//! `--environment <gradient|sky|ファイル>` で、レイが形に当たらなかったときの背景を選びます。
+//!   - `gradient`（既定）: 青紫のグラデーション。環境光は照明の `ambient` の一様な明るさです
+//!   - `sky`: 太陽と地平線のある手続き的な空。太陽は主光源の方向・色に合わせて動きます
+//!   - それ以外: 正距円筒図法の HDR 画像（`.hdr` / `.exr` など、上が天頂で中央が -Z の方向）
+//!
+//! `sky` と画像では、背景をそのまま照明として使います（イメージベースドライティング）。
+//! 拡散光は法線のまわりの半球で背景の明るさを cos で重み付けして平均した放射照度、
+//! 鏡面反射は反射方向の背景をぼかした色で、どちらも読み込んだときに縮小した画像に前計算しておきます。
+//! 画像の明るさは `--environment-intensity <倍率>`（既定: 1）で合わせます。
+
+use crate::Args;
+use glam::Vec3;
+use std::f32::consts::PI;
+
+/// 背景を照明として使うときの縮小画像の大きさ（鏡面反射用。幅、高さ）
+const REFLECTION_SIZE: (usize, usize) = (128, 64);
+
+/// 放射照度の画像の大きさ（拡散光用。幅、高さ）
+const IRRADIANCE_SIZE: (usize, usize) = (32, 16);
+
+/// 放射照度を求めるときに積分する背景の大きさ（幅、高さ）
+const IRRADIANCE_SOURCE_SIZE: (usize, usize) = (64, 32);
+
+/// 空の天頂・地平線・地面の色
+const SKY_ZENITH: Vec3 = Vec3::new(0.10, 0.22, 0.50);
+const SKY_HORIZON: Vec3 = Vec3::new(0.50, 0.58, 0.70);
+const SKY_GROUND: Vec3 = Vec3::new(0.10, 0.09, 0.08);
+
+/// 太陽のまわりの光の広がり（大きいほど狭い）と強さ
+const SUN_GLOW_EXPONENT: f32 = 48.0;
+const SUN_GLOW_STRENGTH: f32 = 0.4;
+
+/// 太陽の見かけの半径の cos（約 1.5°）
+const SUN_DISC_COS: f32 = 0.99966;
+
+/// 正距円筒図法の画像（方向ごとの色）
+pub struct EquirectMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+}
+
+impl EquirectMap {
+    /// 各ピクセルの中心の方向の色を `radiance` で求めた画像
+    fn from_fn(size: (usize, usize), radiance: impl Fn(Vec3) -> Vec3) -> Self {
+        let (width, height) = size;
+        let pixels = (0..width * height)
+            .map(|i| radiance(Self::direction(i % width, i / width, size)))
+            .collect();
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// ピクセル (x, y) の中心の方向（上端が +Y、横の中央が -Z、右へ進むと +X の側へ回る）
+    fn direction(x: usize, y: usize, size: (usize, usize)) -> Vec3 {
+        let azimuth = ((x as f32 + 0.5) / size.0 as f32 - 0.5) * 2.0 * PI;
+        let polar = (y as f32 + 0.5) / size.1 as f32 * PI;
+        Vec3::new(
+            polar.sin() * azimuth.sin(),
+            polar.cos(),
+            -polar.sin() * azimuth.cos(),
+        )
+    }
+
+    /// 方向 `dir`（単位ベクトル）の色（隣り合う 4 ピクセルを線形補間。左右の端はつながっている）
+    pub fn sample(&self, dir: Vec3) -> Vec3 {
+        let u = dir.x.atan2(-dir.z) / (2.0 * PI) + 0.5;
+        let v = dir.y.clamp(-1.0, 1.0).acos() / PI;
+        let x = u * self.width as f32 - 0.5;
+        let y = (v * self.height as f32 - 0.5).clamp(0.0, (self.height - 1) as f32);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let column = |x: f32| (x as isize).rem_euclid(self.width as isize) as usize;
+        let (x0, x1) = (column(x0), column(x0 + 1.0));
+        let (y0, y1) = (y0 as usize, (y0 as usize + 1).min(self.height - 1));
+        let pixel = |x: usize, y: usize| self.pixels[y * self.width + x];
+        pixel(x0, y0)
+            .lerp(pixel(x1, y0), fx)
+            .lerp(pixel(x0, y1).lerp(pixel(x1, y1), fx), fy)
+    }
+
+    /// `size` に縮小した画像（各ピクセルは元の画像の対応する範囲の平均）
+    fn downsample(&self, size: (usize, usize)) -> Self {
+        let (width, height) = size;
+        let mut pixels = vec![Vec3::ZERO; width * height];
+        let mut counts = vec![0u32; width * height];
+        for (i, &color) in self.pixels.iter().enumerate() {
+            let x = (i % self.width) * width / self.width;
+            let y = (i / self.width) * height / self.height;
+            pixels[y * width + x] += color;
+            counts[y * width + x] += 1;
+        }
+        for (pixel, count) in pixels.iter_mut().zip(counts) {
+            *pixel /= count.max(1) as f32;
+        }
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// 各方向を法線とする面に届く光（半球の色を cos で重み付けした平均。一様な背景ではその色になる）
+    fn irradiance(&self) -> Self {
+        let source = self.downsample(IRRADIANCE_SOURCE_SIZE);
+        // 各ピクセルの方向と、色に立体角を掛けたもの
+        let texels: Vec<(Vec3, Vec3)> = source
+            .pixels
+            .iter()
+            .enumerate()
+            .map(|(i, &color)| {
+                let dir =
+                    Self::direction(i % source.width, i / source.width, IRRADIANCE_SOURCE_SIZE);
+                let solid_angle = (2.0 * PI / source.width as f32)
+                    * (PI / source.height as f32)
+                    * (1.0 - dir.y * dir.y).sqrt();
+                (dir, color * solid_angle)
+            })
+            .collect();
+        Self::from_fn(IRRADIANCE_SIZE, |normal| {
+            texels
+                .iter()
+                .map(|&(dir, light)| light * normal.dot(dir).max(0.0))
+                .sum::<Vec3>()
+                / PI
+        })
+    }
+}
+
+/// 背景の種類
+pub enum Environment {
+    /// グラデーションの背景（描画は呼び出し側。環境光は一様）
+    Gradient,
+    /// 手続き的な空（太陽の方向と色、照明用の前計算）
+    Sky {
+        sun: Vec3,
+        sun_color: Vec3,
+        lighting: Lighting,
+    },
+    /// HDR 画像（背景そのものと照明用の前計算）
+    Image {
+        map: EquirectMap,
+        lighting: Lighting,
+    },
+}
+
+/// 背景を照明として使うための前計算（鏡面反射用の縮小画像と放射照度）
+pub struct Lighting {
+    reflection: EquirectMap,
+    irradiance: EquirectMap,
+}
+
+impl Lighting {
+    fn new(reflection: EquirectMap) -> Self {
+        let irradiance = reflection.irradiance();
+        Self {
+            reflection,
+            irradiance,
+        }
+    }
+}
+
+impl Environment {
+    /// `--environment <gradient|sky|ファイル>`・`--environment-intensity <倍率>` で指定した環境（省略時はグラデーション）
+    pub fn from_args(args: &Args) -> Result<Self, String> {
+        let intensity = match args.environment_intensity {
+            Some(i) if !(i.is_finite() && i >= 0.0) => {
+                return Err(format!("環境の明るさには 0 以上の数値が必要です: {}", i))
+            }
+            Some(i) => i,
+            None => 1.0,
+        };
+        match args.environment.as_deref() {
+            None | Some("gradient") => Ok(Environment::Gradient),
+            Some("sky") => Ok(Self::sky(Vec3::new(0.577, 0.577, -0.577), Vec3::ONE)),
+            Some(path) => Self::load(path, intensity),
+        }
+    }
+
+    /// 正距円筒図法の HDR 画像を読み込む（色は `intensity` 倍）
+    fn load(path: &str, intensity: f32) -> Result<Self, String> {
+        let image = image::open(path)
+            .map_err(|e| format!("環境の画像を読み込めません: {}: {}", path, e))?
+            .into_rgb32f();
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        if width < 2 || height < 2 {
+            return Err(format!(
+                "環境の画像が小さすぎます: {}: {}x{}",
+                path, width, height
+            ));
+        }
+        let map = EquirectMap {
+            width,
+            height,
+            pixels: image
+                .pixels()
+                .map(|p| Vec3::from_array(p.0).max(Vec3::ZERO) * intensity)
+                .collect(),
+        };
+        // 縮小画像より小さな画像は補間して引き伸ばす
+        let reflection = if width >= REFLECTION_SIZE.0 && height >= REFLECTION_SIZE.1 {
+            map.downsample(REFLECTION_SIZE)
+        } else {
+            EquirectMap::from_fn(REFLECTION_SIZE, |dir| map.sample(dir))
+        };
+        let lighting = Lighting::new(reflection);
+        Ok(Environment::Image { map, lighting })
+    }
+
+    /// 太陽が `sun` の方向にある空
+    fn sky(sun: Vec3, sun_color: Vec3) -> Self {
+        let sun = sun.normalize();
+        // 照明には太陽の円盤を含めない（太陽の光は主光源として別に当たるため）
+        let lighting = Lighting::new(EquirectMap::from_fn(REFLECTION_SIZE, |dir| {
+            sky_radiance(dir, sun, sun_color)
+        }));
+        Environment::Sky {
+            sun,
+            sun_color,
+            lighting,
+        }
+    }
+
+    /// 空の太陽を主光源の方向・色に合わせる（変わったときだけ照明の前計算をやり直す）
+    pub fn follow_sun(&mut self, sun: Vec3, sun_color: Vec3) {
+        if let Environment::Sky {
+            sun: current,
+            sun_color: current_color,
+            ..
+        } = *self
+        {
+            if sun.length_squared() > 0.0
+                && (sun.normalize() != current || sun_color != current_color)
+            {
+                *self = Self::sky(sun, sun_color);
+            }
+        }
+    }
+
+    /// 方向 `dir` の背景の色（グラデーションでは `None`）
+    pub fn background(&self, dir: Vec3) -> Option<Vec3> {
+        match self {
+            Environment::Gradient => None,
+            Environment::Sky { sun, sun_color, .. } => {
+                let disc = if dir.dot(*sun) > SUN_DISC_COS {
+                    *sun_color
+                } else {
+                    Vec3::ZERO
+                };
+                Some(sky_radiance(dir, *sun, *sun_color) + disc)
+            }
+            Environment::Image { map, .. } => Some(map.sample(dir)),
+        }
+    }
+
+    /// 法線が `normal` の面に背景から届く拡散光（グラデーションでは `None`）
+    pub fn irradiance(&self, normal: Vec3) -> Option<Vec3> {
+        self.lighting().map(|l| l.irradiance.sample(normal))
+    }
+
+    /// 反射方向 `dir` に見える背景のぼかした色（グラデーションでは映り込みなし）
+    pub fn reflection(&self, dir: Vec3) -> Vec3 {
+        self.lighting()
+            .map_or(Vec3::ZERO, |l| l.reflection.sample(dir))
+    }
+
+    fn lighting(&self) -> Option<&Lighting> {
+        match self {
+            Environment::Gradient => None,
+            Environment::Sky { lighting, .. } | Environment::Image { lighting, .. } => {
+                Some(lighting)
+            }
+        }
+    }
+}
+
+/// 空の色（天頂から地平線へのグラデーションと太陽のまわりの光。太陽の円盤は含まない）
+fn sky_radiance(dir: Vec3, sun: Vec3, sun_color: Vec3) -> Vec3 {
+    let base = if dir.y >= 0.0 {
+        SKY_HORIZON.lerp(SKY_ZENITH, dir.y.sqrt())
+    } else {
+        SKY_HORIZON.lerp(SKY_GROUND, (-dir.y).sqrt().min(1.0))
+    };
+    let glow = dir.dot(sun).max(0.0).powf(SUN_GLOW_EXPONENT) * SUN_GLOW_STRENGTH;
+    base + sun_color * glow
+}
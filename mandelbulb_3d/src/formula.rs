@@ -0,0 +1,385 @@
+//! ハイブリッドフラクタルの式（反復ごとに適用する変換の列）
+//!
+//! Mandelbulber のハイブリッドフラクタルのように、1 回の反復で複数の変換を順に適用します。
+//! 変換の列はテキストファイルに 1 行 1 つずつ書き、`--formula <ファイル>` で読み込みます
+//! （`#` 以降はコメント）。
+//!
+//! ```text
+//! # マンデルボックス
+//! iterations 16
+//! box_fold 1.0
+//! sphere_fold 0.5 1.0
+//! scale 2.0
+//! add_c
+//! ```
+//!
+//! 変換:
+//!   - `bulb <パワー>`: 極座標でパワー乗する（マンデルバルブの z^n、c は加えない）
+//!   - `box_fold <限界>`: 各成分を ±限界 で折り返す
+//!   - `sphere_fold <最小半径> <固定半径>`: 球面反転で折り返す
+//!   - `scale <倍率>`: 拡大する
+//!   - `rotate <軸 x> <軸 y> <軸 z> <角度（度）>`: 軸まわりに回転する
+//!   - `add_c [重み]`: 開始位置 c（の重み倍、既定 1）を加える
+//!
+//! 設定:
+//!   - `iterations <回数>`: 反復回数（既定: 12）
+//!   - `bailout <半径>`: 脱出半径（既定: 4）
+//!   - `display_scale <倍率>`: 表示する大きさの倍率（既定: 1。マンデルボックスのような大きな形は 0.3 程度に縮める）
+//!
+//! 距離は `bulb` を含む式では 0.5·r·ln(r)/dr、含まない式（マンデルボックスなど）では r/dr で推定します。
+
+use crate::config_lines::{self, ConfigLine};
+use glam::{Quat, Vec3};
+
+/// 反復回数の既定値
+pub const DEFAULT_ITERATIONS: usize = 12;
+
+/// 反復回数の上限
+pub const MAX_ITERATIONS: usize = 64;
+
+/// 脱出半径の既定値
+pub const DEFAULT_BAILOUT: f32 = 4.0;
+
+/// 式の変換を適用する前に空間を何倍に拡大するか、の既定値
+pub const DEFAULT_DISPLAY_SCALE: f32 = 1.0;
+
+/// 1 回の反復で適用する変換
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FormulaOp {
+    /// 極座標でパワー乗する
+    Bulb { power: f32 },
+    /// 各成分を ±limit で折り返す
+    BoxFold { limit: f32 },
+    /// 球面反転で折り返す（半径の 2 乗で保持）
+    SphereFold {
+        min_radius2: f32,
+        fixed_radius2: f32,
+    },
+    /// 拡大する
+    Scale(f32),
+    /// 回転する
+    Rotate(Quat),
+    /// 開始位置 c の重み倍を加える
+    AddC(f32),
+}
+
+/// ハイブリッドフラクタルの式
+#[derive(Clone, Debug, PartialEq)]
+pub struct Formula {
+    pub ops: Vec<FormulaOp>,
+    pub iterations: usize,
+    pub bailout: f32,
+    pub display_scale: f32,
+}
+
+impl Default for Formula {
+    /// マンデルバルブ（パワー 8）とボックスフォールド・回転を交互に適用する式
+    fn default() -> Self {
+        Self {
+            ops: vec![
+                FormulaOp::Bulb { power: 8.0 },
+                FormulaOp::AddC(1.0),
+                FormulaOp::BoxFold { limit: 1.0 },
+                FormulaOp::Rotate(Quat::from_axis_angle(Vec3::Y, 30f32.to_radians())),
+            ],
+            iterations: DEFAULT_ITERATIONS,
+            bailout: DEFAULT_BAILOUT,
+            display_scale: DEFAULT_DISPLAY_SCALE,
+        }
+    }
+}
+
+impl Formula {
+    /// ファイルから読み込む
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("式のファイルを読み込めません: {}: {}", path, e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// テキストから読み込む
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut formula = Formula {
+            ops: Vec::new(),
+            iterations: DEFAULT_ITERATIONS,
+            bailout: DEFAULT_BAILOUT,
+            display_scale: DEFAULT_DISPLAY_SCALE,
+        };
+
+        config_lines::parse(text, |line| formula.parse_line(line))?;
+
+        if formula.ops.is_empty() {
+            return Err("変換が 1 つもありません".to_string());
+        }
+        Ok(formula)
+    }
+
+    fn parse_line(&mut self, line: ConfigLine) -> Result<(), String> {
+        let (name, args) = (line.name, &line.args);
+        match name {
+            "iterations" => {
+                line.expect(&[1])?;
+                let n = args[0] as usize;
+                if args[0].fract() != 0.0 || !(1..=MAX_ITERATIONS).contains(&n) {
+                    return Err(format!(
+                        "反復回数は 1〜{} の整数にしてください: {}",
+                        MAX_ITERATIONS, args[0]
+                    ));
+                }
+                self.iterations = n;
+            }
+            "bailout" => {
+                line.expect(&[1])?;
+                if args[0] <= 1.0 {
+                    return Err(format!("脱出半径は 1 より大きくしてください: {}", args[0]));
+                }
+                self.bailout = args[0];
+            }
+            "display_scale" => {
+                line.expect(&[1])?;
+                if args[0] <= 0.0 {
+                    return Err(format!("表示倍率は正の数にしてください: {}", args[0]));
+                }
+                self.display_scale = args[0];
+            }
+            "bulb" => {
+                line.expect(&[1])?;
+                if args[0] < 1.0 {
+                    return Err(format!("パワーは 1 以上にしてください: {}", args[0]));
+                }
+                self.ops.push(FormulaOp::Bulb { power: args[0] });
+            }
+            "box_fold" => {
+                line.expect(&[1])?;
+                self.ops.push(FormulaOp::BoxFold {
+                    limit: args[0].abs(),
+                });
+            }
+            "sphere_fold" => {
+                line.expect(&[2])?;
+                let (min_radius, fixed_radius) = (args[0], args[1]);
+                if min_radius <= 0.0 || min_radius > fixed_radius {
+                    return Err(format!(
+                        "半径は 0 < 最小半径 ≦ 固定半径 にしてください: {} {}",
+                        min_radius, fixed_radius
+                    ));
+                }
+                self.ops.push(FormulaOp::SphereFold {
+                    min_radius2: min_radius * min_radius,
+                    fixed_radius2: fixed_radius * fixed_radius,
+                });
+            }
+            "scale" => {
+                line.expect(&[1])?;
+                if args[0] == 0.0 {
+                    return Err("倍率に 0 は指定できません".to_string());
+                }
+                self.ops.push(FormulaOp::Scale(args[0]));
+            }
+            "rotate" => {
+                line.expect(&[4])?;
+                let axis = Vec3::new(args[0], args[1], args[2]);
+                if axis.length_squared() == 0.0 {
+                    return Err("回転軸に 0 ベクトルは指定できません".to_string());
+                }
+                self.ops.push(FormulaOp::Rotate(Quat::from_axis_angle(
+                    axis.normalize(),
+                    args[3].to_radians(),
+                )));
+            }
+            "add_c" => {
+                line.expect(&[0, 1])?;
+                self.ops
+                    .push(FormulaOp::AddC(args.first().copied().unwrap_or(1.0)));
+            }
+            _ => return Err(format!("不明な変換です: {}", name)),
+        }
+        Ok(())
+    }
+
+    /// 距離を 0.5·r·ln(r)/dr（マンデルバルブ系）で推定するか
+    fn uses_log_estimate(&self) -> bool {
+        self.ops
+            .iter()
+            .any(|op| matches!(op, FormulaOp::Bulb { .. }))
+    }
+
+    /// 距離関数（距離、脱出までの反復回数、オービットトラップ）
+    pub fn map_with_iter(&self, pos: Vec3) -> (f32, usize, f32) {
+        // 表示倍率で縮めて表示するため、式の空間では 1/倍率 の位置を評価し、距離を倍率倍して戻す
+        let pos = pos / self.display_scale;
+        let mut z = pos;
+        let mut dr = 1.0f32;
+        let mut trap = f32::MAX; // オービットトラップ
+
+        let mut i = 0;
+        for iter in 0..self.iterations {
+            i = iter;
+            let r = z.length();
+            if r > self.bailout {
+                break;
+            }
+            trap = trap.min(r);
+
+            for op in &self.ops {
+                apply(*op, &mut z, &mut dr, pos);
+            }
+        }
+
+        let r = z.length();
+        let dist = if self.uses_log_estimate() {
+            0.5 * r.ln() * r / dr
+        } else {
+            r / dr
+        };
+        (dist * self.display_scale, i, trap)
+    }
+}
+
+/// 変換を 1 つ適用し、導関数の大きさ dr も合わせて更新する
+fn apply(op: FormulaOp, z: &mut Vec3, dr: &mut f32, c: Vec3) {
+    match op {
+        FormulaOp::Bulb { power } => {
+            let r = z.length();
+            if r == 0.0 {
+                return;
+            }
+            *dr *= r.powf(power - 1.0) * power;
+
+            let theta = z.z.atan2((z.x * z.x + z.y * z.y).sqrt()) * power;
+            let phi = z.y.atan2(z.x) * power;
+            let zr = r.powf(power);
+            *z = Vec3::new(
+                zr * theta.cos() * phi.cos(),
+                zr * theta.cos() * phi.sin(),
+                zr * theta.sin(),
+            );
+        }
+        FormulaOp::BoxFold { limit } => {
+            *z = z.clamp(Vec3::splat(-limit), Vec3::splat(limit)) * 2.0 - *z;
+        }
+        FormulaOp::SphereFold {
+            min_radius2,
+            fixed_radius2,
+        } => {
+            let r2 = z.length_squared();
+            let k = if r2 < min_radius2 {
+                fixed_radius2 / min_radius2
+            } else if r2 < fixed_radius2 {
+                fixed_radius2 / r2
+            } else {
+                1.0
+            };
+            *z *= k;
+            *dr *= k;
+        }
+        FormulaOp::Scale(scale) => {
+            *z *= scale;
+            *dr *= scale.abs();
+        }
+        FormulaOp::Rotate(rotation) => {
+            *z = rotation * *z;
+        }
+        FormulaOp::AddC(weight) => {
+            *z += c * weight;
+            *dr += weight.abs();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mandelbulb_with_iter, Quality};
+
+    #[test]
+    fn parses_mandelbox_formula() {
+        let text = "\
+# マンデルボックス
+iterations 16
+box_fold 1.0
+sphere_fold 0.5 1.0   # 球面反転
+scale -2.0
+
+add_c
+";
+        let formula = Formula::parse(text).unwrap();
+        assert_eq!(formula.iterations, 16);
+        assert_eq!(formula.bailout, DEFAULT_BAILOUT);
+        assert_eq!(
+            formula.ops,
+            vec![
+                FormulaOp::BoxFold { limit: 1.0 },
+                FormulaOp::SphereFold {
+                    min_radius2: 0.25,
+                    fixed_radius2: 1.0,
+                },
+                FormulaOp::Scale(-2.0),
+                FormulaOp::AddC(1.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn bulb_formula_matches_mandelbulb_distance() {
+        // z → z^8 + c を変換の列で書いた式は、マンデルバルブの距離関数と同じ値になる
+        let quality = Quality::new();
+        let formula = Formula::parse(&format!(
+            "bulb 8\nadd_c\niterations {}\nbailout {}\n",
+            quality.iterations, quality.bailout
+        ))
+        .unwrap();
+        for pos in [
+            Vec3::new(1.2, 0.3, -0.4),
+            Vec3::new(0.0, 1.1, 0.2),
+            Vec3::new(-0.8, -0.9, 0.5),
+            Vec3::new(0.4, -0.7, 0.9),
+            Vec3::new(1.5, 1.5, 1.5),
+        ] {
+            let (dist, iter, trap) = formula.map_with_iter(pos);
+            let (expected_dist, expected_iter, expected_trap) =
+                mandelbulb_with_iter(pos, 8.0, &quality);
+            assert_eq!(iter, expected_iter, "{}", pos);
+            assert!(
+                (dist - expected_dist).abs() <= 1e-5 * expected_dist.abs().max(1.0),
+                "{}: {} != {}",
+                pos,
+                dist,
+                expected_dist
+            );
+            assert_eq!(trap, expected_trap, "{}", pos);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_ops_with_line_number() {
+        for (line, message) in [
+            ("bulb", "bulb の引数の数が不正です（1 個）: 0"),
+            ("box_fold 1 2", "box_fold の引数の数が不正です（1 個）: 2"),
+            ("add_c 1 2", "add_c の引数の数が不正です（0 / 1 個）: 2"),
+            ("bulb eight", "数値が不正です: eight"),
+            ("bulb 0.5", "パワーは 1 以上にしてください: 0.5"),
+            (
+                "sphere_fold 1.0 0.5",
+                "半径は 0 < 最小半径 ≦ 固定半径 にしてください: 1 0.5",
+            ),
+            ("scale 0", "倍率に 0 は指定できません"),
+            ("rotate 0 0 0 30", "回転軸に 0 ベクトルは指定できません"),
+            (
+                "iterations 2.5",
+                "反復回数は 1〜64 の整数にしてください: 2.5",
+            ),
+            ("twist 1", "不明な変換です: twist"),
+        ] {
+            let text = format!("# 式\nbulb 8\n{}\nadd_c\n", line);
+            assert_eq!(
+                Formula::parse(&text).unwrap_err(),
+                format!("3 行目: {}", message)
+            );
+        }
+        assert_eq!(
+            Formula::parse("iterations 8\n").unwrap_err(),
+            "変換が 1 つもありません"
+        );
+    }
+}
@@ -0,0 +1,351 @@
+//! 照明（複数の光源と環境光）
+//!
+//! 光源は平行光源と点光源で、それぞれ色と強さを持ちます。テキストファイルに 1 行 1 つずつ書き、
+//! `--lights <ファイル>` で読み込みます（`#` 以降はコメント）。
+//!
+//! ```text
+//! # 3 点照明
+//! ambient 0.1
+//! directional 0.577 0.577 -0.577 1.0 1.0 0.9 0.8
+//! directional -0.5 0.8 0.3 0.4 0.6 0.8 1.0
+//! point 0.0 -1.5 1.5 2.0
+//! ```
+//!
+//! 光源:
+//!   - `directional <x> <y> <z> [強さ] [r g b]`: 表面から (x, y, z) の方向にある平行光源
+//!   - `point <x> <y> <z> [強さ] [r g b]`: 位置 (x, y, z) の点光源（強さは 1 / (1 + 距離²) で減衰）
+//!
+//! 強さの既定値は 1、色の既定値は白 (1 1 1) です。
+//!
+//! 設定:
+//!   - `ambient <明るさ>`: 環境光の明るさ（既定: 0.15）
+//!
+//! 1 つ目の光源を主光源とし、キー操作で向きと色を変えます。
+
+use crate::config_lines::{self, ConfigLine};
+use glam::Vec3;
+use std::fmt::Write as _;
+
+/// 光源の数の上限
+pub const MAX_LIGHTS: usize = 8;
+
+/// 環境光の明るさの既定値
+pub const DEFAULT_AMBIENT: f32 = 0.15;
+
+/// 主光源の仰角の上限（度）
+pub const MAX_ELEVATION: f32 = 89.0;
+
+/// 主光源の色の候補（白、暖色、寒色、マゼンタ、緑）
+pub const PRESET_COLORS: [Vec3; 5] = [
+    Vec3::new(1.0, 1.0, 1.0),
+    Vec3::new(1.0, 0.8, 0.6),
+    Vec3::new(0.6, 0.8, 1.0),
+    Vec3::new(1.0, 0.5, 0.9),
+    Vec3::new(0.6, 1.0, 0.6),
+];
+
+/// 光源の種類
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightKind {
+    /// 平行光源（表面から光源へ向かう単位ベクトル）
+    Directional(Vec3),
+    /// 点光源（位置）
+    Point(Vec3),
+}
+
+/// 光源
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LightSource {
+    pub kind: LightKind,
+    pub color: Vec3,
+    pub intensity: f32,
+}
+
+impl LightSource {
+    /// 表面の点 `p` から見た光源（光源へ向かう単位ベクトル、光源までの距離、減衰後の強さ）
+    pub fn illuminate(&self, p: Vec3) -> (Vec3, f32, f32) {
+        match self.kind {
+            LightKind::Directional(dir) => (dir, f32::MAX, self.intensity),
+            LightKind::Point(pos) => {
+                let to_light = pos - p;
+                let dist = to_light.length();
+                (
+                    to_light / dist.max(f32::EPSILON),
+                    dist,
+                    self.intensity / (1.0 + dist * dist),
+                )
+            }
+        }
+    }
+}
+
+/// 照明
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lights {
+    pub sources: Vec<LightSource>,
+    pub ambient: f32,
+}
+
+impl Default for Lights {
+    /// 右上手前からの白い主光源と、左上奥からの弱い補助光源
+    fn default() -> Self {
+        Self {
+            sources: vec![
+                LightSource {
+                    kind: LightKind::Directional(Vec3::new(0.577, 0.577, -0.577).normalize()),
+                    color: Vec3::ONE,
+                    intensity: 1.0,
+                },
+                LightSource {
+                    kind: LightKind::Directional(Vec3::new(-0.5, 0.8, 0.3).normalize()),
+                    color: Vec3::ONE,
+                    intensity: 0.5,
+                },
+            ],
+            ambient: DEFAULT_AMBIENT,
+        }
+    }
+}
+
+impl Lights {
+    /// ファイルから読み込む
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("照明のファイルを読み込めません: {}: {}", path, e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// テキストから読み込む
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lights = Lights {
+            sources: Vec::new(),
+            ambient: DEFAULT_AMBIENT,
+        };
+
+        config_lines::parse(text, |line| lights.parse_line(line))?;
+
+        if lights.sources.is_empty() {
+            return Err("光源が 1 つもありません".to_string());
+        }
+        Ok(lights)
+    }
+
+    fn parse_line(&mut self, line: ConfigLine) -> Result<(), String> {
+        let (name, args) = (line.name, &line.args);
+        match name {
+            "ambient" => {
+                line.expect(&[1])?;
+                if args[0] < 0.0 {
+                    return Err(format!(
+                        "環境光の明るさは 0 以上にしてください: {}",
+                        args[0]
+                    ));
+                }
+                self.ambient = args[0];
+            }
+            "directional" | "point" => {
+                line.expect(&[3, 4, 7])?;
+                if self.sources.len() >= MAX_LIGHTS {
+                    return Err(format!("光源は {} 個までです", MAX_LIGHTS));
+                }
+                let vector = Vec3::new(args[0], args[1], args[2]);
+                let kind = if name == "directional" {
+                    if vector.length_squared() == 0.0 {
+                        return Err("光源の方向に 0 ベクトルは指定できません".to_string());
+                    }
+                    LightKind::Directional(vector.normalize())
+                } else {
+                    LightKind::Point(vector)
+                };
+                let intensity = args.get(3).copied().unwrap_or(1.0);
+                if intensity < 0.0 {
+                    return Err(format!("強さは 0 以上にしてください: {}", intensity));
+                }
+                let color = if args.len() == 7 {
+                    Vec3::new(args[4], args[5], args[6])
+                } else {
+                    Vec3::ONE
+                };
+                if color.min_element() < 0.0 {
+                    return Err(format!(
+                        "色の各成分は 0 以上にしてください: {} {} {}",
+                        color.x, color.y, color.z
+                    ));
+                }
+                self.sources.push(LightSource {
+                    kind,
+                    color,
+                    intensity,
+                });
+            }
+            _ => return Err(format!("不明な光源です: {}", name)),
+        }
+        Ok(())
+    }
+
+    /// `parse` で読み込めるテキスト
+    pub fn to_text(&self) -> String {
+        let mut text = format!("ambient {}\n", self.ambient);
+        for source in &self.sources {
+            let (name, vector) = match source.kind {
+                LightKind::Directional(dir) => ("directional", dir),
+                LightKind::Point(pos) => ("point", pos),
+            };
+            let _ = writeln!(
+                text,
+                "{} {} {} {} {} {} {} {}",
+                name,
+                vector.x,
+                vector.y,
+                vector.z,
+                source.intensity,
+                source.color.x,
+                source.color.y,
+                source.color.z
+            );
+        }
+        text
+    }
+
+    /// 主光源の方位角・仰角を変える（度。点光源は原点からの距離を保って回す。仰角は真上・真下の手前で止める）
+    pub fn rotate_main(&mut self, azimuth: f32, elevation: f32) {
+        let Some(main) = self.sources.first_mut() else {
+            return;
+        };
+        let rotate = |v: Vec3| {
+            let len = v.length();
+            if len == 0.0 {
+                return v;
+            }
+            let azimuth = v.x.atan2(-v.z) + azimuth.to_radians();
+            let elevation = ((v.y / len).clamp(-1.0, 1.0).asin() + elevation.to_radians())
+                .clamp(-MAX_ELEVATION.to_radians(), MAX_ELEVATION.to_radians());
+            Vec3::new(
+                elevation.cos() * azimuth.sin(),
+                elevation.sin(),
+                -elevation.cos() * azimuth.cos(),
+            ) * len
+        };
+        main.kind = match main.kind {
+            LightKind::Directional(dir) => LightKind::Directional(rotate(dir)),
+            LightKind::Point(pos) => LightKind::Point(rotate(pos)),
+        };
+    }
+
+    /// 主光源の色を `PRESET_COLORS` の次の色にする（候補にない色からは白に戻す）
+    pub fn next_main_color(&mut self) {
+        if let Some(main) = self.sources.first_mut() {
+            let next = PRESET_COLORS
+                .iter()
+                .position(|&c| c == main.color)
+                .map_or(0, |i| (i + 1) % PRESET_COLORS.len());
+            main.color = PRESET_COLORS[next];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_light_sources() {
+        let text = "\
+# 3 点照明
+ambient 0.1
+directional 0 2 0
+point 0 -1.5 1.5 2.0   # 下からの点光源
+directional -1 0 0 0.5 1 0.5 0
+";
+        let lights = Lights::parse(text).unwrap();
+        assert_eq!(lights.ambient, 0.1);
+        assert_eq!(
+            lights.sources,
+            vec![
+                LightSource {
+                    kind: LightKind::Directional(Vec3::Y),
+                    color: Vec3::ONE,
+                    intensity: 1.0,
+                },
+                LightSource {
+                    kind: LightKind::Point(Vec3::new(0.0, -1.5, 1.5)),
+                    color: Vec3::ONE,
+                    intensity: 2.0,
+                },
+                LightSource {
+                    kind: LightKind::Directional(-Vec3::X),
+                    color: Vec3::new(1.0, 0.5, 0.0),
+                    intensity: 0.5,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn text_round_trips() {
+        let mut lights = Lights::default();
+        lights.sources.push(LightSource {
+            kind: LightKind::Point(Vec3::new(0.5, -1.0, 2.0)),
+            color: Vec3::new(1.0, 0.8, 0.6),
+            intensity: 3.0,
+        });
+        lights.rotate_main(20.0, -10.0);
+        let parsed = Lights::parse(&lights.to_text()).unwrap();
+        assert_eq!(parsed.ambient, lights.ambient);
+        assert_eq!(parsed.sources.len(), lights.sources.len());
+        for (a, b) in parsed.sources.iter().zip(&lights.sources) {
+            let (LightKind::Directional(u) | LightKind::Point(u)) = a.kind;
+            let (LightKind::Directional(v) | LightKind::Point(v)) = b.kind;
+            assert!(u.abs_diff_eq(v, 1e-6), "{} != {}", u, v);
+            assert_eq!(
+                std::mem::discriminant(&a.kind),
+                std::mem::discriminant(&b.kind)
+            );
+            assert_eq!((a.color, a.intensity), (b.color, b.intensity));
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_argument_counts() {
+        for (line, message) in [
+            ("ambient", "ambient の引数の数が不正です（1 個）: 0"),
+            ("point 1 2", "point の引数の数が不正です（3 / 4 / 7 個）: 2"),
+            (
+                "directional 1 0 0 1 1 1",
+                "directional の引数の数が不正です（3 / 4 / 7 個）: 6",
+            ),
+        ] {
+            let text = format!("directional 0 1 0\n{}\n", line);
+            assert_eq!(
+                Lights::parse(&text).unwrap_err(),
+                format!("2 行目: {}", message)
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_bad_numbers_and_values() {
+        for (line, message) in [
+            ("point 0 x 0", "数値が不正です: x"),
+            ("ambient NaN", "数値が不正です: NaN"),
+            (
+                "ambient -0.1",
+                "環境光の明るさは 0 以上にしてください: -0.1",
+            ),
+            (
+                "directional 0 0 0",
+                "光源の方向に 0 ベクトルは指定できません",
+            ),
+            ("spot 0 1 0", "不明な光源です: spot"),
+        ] {
+            assert_eq!(
+                Lights::parse(line).unwrap_err(),
+                format!("1 行目: {}", message)
+            );
+        }
+        assert_eq!(
+            Lights::parse("ambient 0.2\n").unwrap_err(),
+            "光源が 1 つもありません"
+        );
+    }
+}
@@ -1,29 +1,412 @@
 //! マンデルバルブ (Mandelbulb) 3Dフラクタルビューア - カラフル版
 //! RayonによるCPU並列レンダリング + レイマーチング法
 //!
+//! Tab キーでマンデルバルブ、四元数ジュリア集合（z → z² + c を四元数で反復し、3 次元の断面を描画）、
+//! メンガーのスポンジ、シェルピンスキーの四面体を切り替えます。後の 2 つは反復関数系 (IFS) の
+//! 距離関数で、再帰の深さを [ / ] キーで変えられます。
+//! さらに、反復ごとに変換の列（パワー乗・ボックスフォールド・球面フォールド・回転など）を適用する
+//! ハイブリッドフラクタルを `--formula <ファイル>` で読み込めます（`formula.rs`）。
+//! 光源の数・種類（平行光源 / 点光源）・色・強さは `--lights <ファイル>` で読み込めます（`lights.rs`）。
+//! ハイライトの鋭さ・強さ、彩度、明るさ、色相の混ぜ方はキーで変えて `--material <ファイル>` に保存できます（`material.rs`）。
+//! F キーの断面表示では、距離関数を切断面の距離との max にして平面の片側を取り除き、形の内部の構造を見せます。
+//! 表示中のフラクタルの種類は画面の左上に表示し、切り替えるとカメラをその形に合った初期位置に戻します。
+//!
 //! 操作方法:
 //!   - W/A/S/D: カメラ移動 (前後左右)
 //!   - Space/LShift: カメラ移動 (上昇/下降)
-//!   - 矢印キー: カメラ回転
-//!   - 1-9: パワー変更 (形状が変化)
-//!   - R: リセット
-//!   - Esc/Q: 終了
+//!   - 矢印キー: カメラ回転 (ヨー/ピッチ)
+//!   - Q/E: カメラを左/右に傾ける (ロール)
+//!   - Enter: オービットモードの切り替え (注視点のまわりを回る。左ドラッグ・矢印キーで回転、ホイール・W/S で距離)
+//!   - PageDown/PageUp: パワーを 0.1 ずつ減らす/増やす (形状が変化、マンデルバルブ。3.5 のような半端な値でも描ける)
+//!   - Tab: フラクタルの種類を切り替え (マンデルバルブ / 四元数ジュリア集合 / メンガーのスポンジ / シェルピンスキーの四面体 / ハイブリッド)
+//!   - `[` / `]`: 再帰の深さを減らす/増やす (メンガーのスポンジ、シェルピンスキーの四面体)
+//!   - T/G, Y/H, U/J, I/K: 四元数ジュリア集合の c の各成分 (実部, i, j, k) を増減
+//!   - Z/X: 主光源 (1 つ目の光源) を左/右に回す, C/V: 主光源を下げる/上げる
+//!   - B: 主光源の色を切り替え (白 / 暖色 / 寒色 / マゼンタ / 緑)
+//!   - /: 色の付け方を切り替え (混合 / オービットトラップ / 反復回数 / 法線 / 位置 / 粘土)
+//!   - ;: 立体視を切り替え (なし / 左右の目の絵を横に並べる / 赤青のアナグリフ)
+//!   - F: 断面表示の切り替え, `,` / `.`: 切断面を動かす, L: 切断面をカメラに向ける
+//!   - F1/F2: レイマーチングの最大ステップ数を減らす/増やす, F3/F4: 反復回数を減らす/増やす, F6/F7: 脱出半径を減らす/増やす
+//!   - `\`: 反射の回数を切り替え (0 / 1 / 2。反射 1 回ごとに描画の手間がおよそ 1 組増える)
+//!   - F10: 描画の統計を表示 (フレーム時間のグラフ、レイ 1 本あたりのステップ数、最大ステップ数を使い切ったレイの割合)
+//!   - M: 変える質感の値を切り替え, -/=: 質感の値を減らす/増やす, F5: 質感を保存
+//!   - P: スクリーンショット (`assets/cpu_screenshot.png`。深度・法線のパスも保存)
+//!   - F12: 高画質キャプチャ (`assets/cpu_capture.png`)
+//!   - F8: 表面の点群を書き出し (`assets/cpu_point_cloud.ply`)
+//!   - N: キーフレームを記録, O: キーフレームアニメーションを書き出し, F9: パワーのモーフィングを書き出し
+//!   - Ctrl+1-9: カメラのプリセットを保存, Alt+1-9: プリセットを呼び出し
+//!   - R: リセット (カメラ・パワー・c・再帰の深さ・切断面・霧・光源・質感。画質の設定と色の付け方はそのまま)
+//!   - Esc: 終了
+//!
+//! カメラの向きはクォータニオンで持ち、移動・回転はいずれもカメラ自身の軸を基準にします
+//! （真上・真下を向いてもジンバルロックしません）。カメラとそのキー操作は GPU版と共有します（`common/src/camera.rs`）。
+//!
+//! レイマーチングの最大ステップ数・マンデルバルブと四元数ジュリア集合の反復回数・マンデルバルブの脱出半径は
+//! 実行中にキーで変え、画質とフレームレートの釣り合いを取れます（現在の値はタイトルバーに表示）。
+//!
+//! 起動時の設定はコマンドラインで指定でき、ソースを書き換えずに大きさや画質ごとの描画の速さを比べられます。
+//!   - `--size <幅>x<高さ>`: ウィンドウの大きさ（論理ピクセル、既定: 640x480）
+//!   - `--fractal <種類>`: 最初に表示する形（mandelbulb / quaternion_julia / menger_sponge / sierpinski_tetrahedron / hybrid）
+//!   - `--power <パワー>`: マンデルバルブのパワーの初期値（1〜16、既定: 2。R キーでもこの値に戻る）
+//!   - `--max-steps <ステップ数>`・`--iterations <回数>`・`--bailout <半径>`: 画質の初期値（既定: 150・12・2）
+//!   - `--screenshot-dir <ディレクトリ>`: スクリーンショット・キャプチャ・点群の保存先（既定: `assets`）
+//!
+//! レイが表面に当たったとみなす距離は、その距離での 1 画素の大きさの半分にします（コーントレーシング）。
+//! 遠くの形は見分けられない細かさまで追わずに済み、近くの形は固定の閾値より細かく捉えます。
+//!
+//! HiDPI ディスプレイでは画面の拡大率倍の物理ピクセルで描画します
+//! （`--scale <auto|1|2|3|4>`、既定: auto。拡大率 2 では描画するピクセル数が 4 倍になります）。
+//!
+//! 背景はグラデーションのほか、太陽と地平線のある手続き的な空や正距円筒図法の HDR 画像にでき、
+//! その背景から届く光で拡散光と映り込みを付けます（`environment.rs`。`--environment <gradient|sky|ファイル>`）。
+//!
+//! 遠くの形ほど霧の色に溶け込ませ、奥行きを分かりやすくします
+//! （`--fog-density <濃さ>`、既定: 0.2、0 で霧なし。`--fog-color <RRGGBB>`、既定: 背景に近い紺色）。
+//! 形に当たらなかったレイには、途中で形に最も近づいた距離に応じた光を足し、シルエットに光の縁を付けられます
+//! （`--glow <強さ>`、既定: 0 で光らない。`--glow-color <RRGGBB>`、既定: 橙色）。
+//!
+//! カメラや形・照明・質感が前のフレームから変わっていなければ、画素内の位置・影のレイの向き・
+//! アンビエントオクルージョンの向きを少しずつずらしたサンプルを重ねて平均し（最大 `ACCUM_MAX_SAMPLES` 枚）、
+//! 止まっている間に滑らかな静止画へ近づけます。何か変わると最初の 1 枚からやり直します。
+//! 変わり続けている（操作中の）間は市松模様の半分のピクセルだけを交互に描き、残りは前のフレームの色を使って
+//! フレームレートを上げます。止まった最初のフレームで全ピクセルを描き直します。
+//!
+//! 左右の目の位置から描いた 2 枚を横に並べたり赤青のアナグリフに合成したりして、立体視用の画像にできます
+//! （`stereo.rs`。`--stereo <off|side-by-side|anaglyph>`、`--eye-separation <距離>`）。
+//!
+//! P キーのスクリーンショットでは、合成用に深度と法線のパス（AOV）も EXR と PNG で保存します。
+//! スクリーンショットとアニメーションの書き出しでは 1 ピクセルに複数のレイを通してアンチエイリアスをかけます
+//! （`--aa-samples <本数>`、既定: 4。スクリーンショットは累積描画で重ねたサンプルが足りていればそれを使います）。
+//! F12 キーでは今のカメラをウィンドウより大きな解像度で、1 ピクセルに複数のレイを通してオフスクリーンに描画し保存します
+//! （`--capture-size <幅>x<高さ>`、既定: ウィンドウの 4 倍。`--capture-samples <本数>`、既定: 4）。
+//! F8 キーでは形を囲む立方体の 6 面から格子状に平行なレイを通し、当たった位置・法線・色を PLY の点群に書き出します
+//! （`--point-cloud-grid <本数>`、既定: 256。CloudCompare や Blender で読み込めます）。
+//!
+//! N キーで今のカメラとパワーをキーフレームとして記録し、O キーでその間をスプライン補間したアニメーションを
+//! 連番画像に書き出します（`keyframes.rs`。`--keyframes <ファイル>`、既定: `camera_keyframes.json`。
+//! `--fps <フレームレート>`、既定: 30。`--animation-size <幅>x<高さ>`、既定: ウィンドウの大きさ。
+//! `--frames-dir <ディレクトリ>`、既定: `animation_frames`）。
+//! F9 キーでは今のカメラのままパワーを 1 から 9 へ少しずつ変えたモーフィングを書き出します
+//! （`--morph-from` / `--morph-to` / `--morph-frames` / `--morph-dir`）。
+//!
+//! Enter キーのオービットモードでは、カメラが注視点（`--orbit-target <x>,<y>,<z>`、既定: 原点）のまわりを
+//! 方位角・仰角・距離で回り、常に注視点を画面の中央に捉えます（`orbit.rs`）。
+//!
+//! Ctrl+数字キーで今のカメラと形・霧・描画（画質・色の付け方・照明・質感・反射）の設定をプリセットとして保存し、
+//! Alt+数字キーで呼び出します（`presets.rs`。`--presets <ファイル>`、既定: `camera_presets.json`。保存するたびに書き出し、次回の起動時に読み込みます）。
+
+mod config_lines;
+mod environment;
+mod formula;
+mod lights;
+mod material;
+mod stereo;
 
-use glam::{Mat3, Vec3};
-use minifb::{Key, Window, WindowOptions};
+use clap::Parser;
+use environment::Environment;
+use formula::Formula;
+use glam::{Vec3, Vec4};
+use lights::Lights;
+use mandelbulb_3d_common::camera::{Camera, Control};
+use mandelbulb_3d_common::coloring::ColorMode;
+use mandelbulb_3d_common::keyframes::{
+    AnimationOptions, CameraKeyframe, CameraPath, PowerMorph, DEFAULT_DURATION,
+};
+use mandelbulb_3d_common::presets::{CameraPreset, CameraPresets, RenderSettings};
+use mandelbulb_3d_common::{cli, font, orbit};
+use material::{Material, MaterialParam};
+use minifb::{Key, MouseButton, MouseMode, Window, WindowOptions};
 use rayon::prelude::*;
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::path::{Path, PathBuf};
 use std::time::Instant;
+use stereo::{Stereo, StereoMode};
 
 // ==========================================
 // 定数設定
 // ==========================================
-const WIDTH: usize = 640;
+const WIDTH: usize = 640; // ウィンドウの大きさの既定値（論理ピクセル）
 const HEIGHT: usize = 480;
-const MAX_STEPS: usize = 150; // レイマーチングの最大ステップ数
-const MAX_ITER: usize = 12; // フラクタル計算の反復回数（増加で複雑に）
-const BAILOUT: f32 = 2.0;
-const EPSILON: f32 = 0.0005; // より精密な衝突判定
+const MAX_WINDOW_SIZE: usize = 4096; // ウィンドウの幅・高さの上限（論理ピクセル）
+const SCREENSHOT_DIR: &str = "assets"; // スクリーンショット・キャプチャ・点群の保存先の既定値
+const MAX_STEPS: usize = 150; // レイマーチングの最大ステップ数の初期値
+const MAX_ITER: usize = 12; // フラクタル計算の反復回数の初期値（増加で複雑に）
+const BAILOUT: f32 = 2.0; // マンデルバルブの脱出半径の初期値
+const STEPS_STEP: usize = 10; // F1/F2 で最大ステップ数を変える量
+const STEPS_RANGE: (usize, usize) = (10, 1000); // 最大ステップ数の範囲
+const ITER_RANGE: (usize, usize) = (1, 64); // 反復回数の範囲
+const BAILOUT_STEP: f32 = 0.5; // F6/F7 で脱出半径を変える量
+const BAILOUT_RANGE: (f32, f32) = (1.5, 16.0); // 脱出半径の範囲
+const MAX_REFLECTIONS: usize = 2; // 反射したレイを飛ばす回数の上限（\ キーで 0 から順に切り替える）
+const REFLECTANCE: f32 = 0.25; // 反射を有効にしたとき、正面から見た面が周りを映し込む割合（フレネル反射の F0）
+const JULIA_BAILOUT: f32 = 4.0; // 四元数ジュリア集合の脱出半径（距離推定の精度のため大きめ）
+const JULIA_C: Vec4 = Vec4::new(-0.291, -0.399, 0.339, 0.437); // 四元数ジュリア集合の c の初期値 (実部, i, j, k)
+const JULIA_C_STEP: f32 = 0.005; // c を 1 フレームで変える量
+const IFS_DEPTH: u32 = 4; // メンガーのスポンジ・シェルピンスキーの四面体の再帰の深さの初期値
+const MAX_IFS_DEPTH: u32 = 8; // 再帰の深さの上限
+const EPSILON: f32 = 0.0005; // 法線の差分・影のレイで使う微小距離
+const HIT_CONE: f32 = 0.5; // 表面に当たったとみなす距離（その距離での 1 画素の大きさに対する割合）
+const MIN_HIT_EPSILON: f32 = 0.00005; // 表面に当たったとみなす距離の下限（カメラのすぐ近くの形）
+const SHADOW_STEPS: usize = 64; // 影のレイの最大ステップ数
+const SHADOW_SOFTNESS: f32 = 8.0; // 影の輪郭の鋭さ k（大きいほどくっきり）
+const SHADOW_MAX_DIST: f32 = 4.0; // 影を探す最大距離
+const AO_SAMPLES: usize = 5; // アンビエントオクルージョンのサンプル数
+const AO_MAX_DIST: f32 = 0.13; // アンビエントオクルージョンで調べる法線方向の最大距離
+const AO_STRENGTH: f32 = 3.0; // アンビエントオクルージョンの強さ
+const AO_JITTER: f32 = 0.3; // 累積描画でアンビエントオクルージョンを調べる向きをずらす大きさ
+const SHADOW_JITTER: f32 = 0.05; // 累積描画で影のレイの向きをずらす大きさ（光源の見かけの半径）
+const ACCUM_MAX_SAMPLES: u32 = 256; // 累積描画で重ねるサンプルの最大数
+const CAPTURE_SCALE: usize = 4; // 高画質キャプチャの解像度の既定値（ウィンドウの論理ピクセルの何倍か）
+const CAPTURE_SAMPLES: u32 = 4; // 高画質キャプチャで 1 ピクセルに通すレイの本数の既定値
+const MAX_CAPTURE_SIZE: usize = 16384; // 高画質キャプチャの幅・高さの上限
+const MAX_CAPTURE_SAMPLES: u32 = 256; // 高画質キャプチャ・アンチエイリアスのレイの本数の上限
+const AA_SAMPLES: u32 = 4; // スクリーンショット・アニメーションの書き出しで 1 ピクセルに通すレイの本数の既定値
+const CAPTURE_BAND_ROWS: usize = 64; // 高画質キャプチャで進み具合を表示する間隔（行数）
+const POINT_CLOUD_GRID: usize = 256; // 点群の書き出しで立方体の 1 面に通すレイの本数（1 辺あたり）の既定値
+const MAX_POINT_CLOUD_GRID: usize = 4096; // 点群の書き出しのレイの本数（1 辺あたり）の上限
+const POINT_CLOUD_EXTENT: f32 = 2.0; // 点群の書き出しでレイを通す立方体の半径（ray_march のレイの長さ 6 の内側）
+const FOG_DENSITY: f32 = 0.2; // 霧の濃さの既定値（距離 t で exp(-濃さ·t) だけ元の色が残る）
+const FOG_COLOR: u32 = 0x0D121A; // 霧の色の既定値（背景のグラデーションの中ほどの色）
+const GLOW_STRENGTH: f32 = 0.0; // 輪郭の光の強さの既定値（0 で光らない）
+const GLOW_COLOR: u32 = 0xFF9A40; // 輪郭の光の色の既定値（橙色）
+const GLOW_RADIUS: f32 = 0.05; // 輪郭の光の広がり（形からこの距離で強さが 1/e になる）
+const ENV_REFLECTANCE: f32 = 0.04; // 正面から見たときに背景が映り込む割合（フレネル反射の F0）
+const CLAY_COLOR: Vec3 = Vec3::new(0.9, 0.87, 0.83); // 粘土の色付けで使う表面の色（わずかに暖かい灰色）
+const LIGHT_ROT_SPEED: f32 = 2.0; // 主光源を 1 フレームで回す角度（度）
+const POWER_STEP: f32 = 0.1; // PageUp/PageDown でパワーを変える量
+const MIN_POWER: f32 = 1.0; // パワーの下限
+const MAX_POWER: f32 = 16.0; // パワーの上限
+const CLIP_STEP: f32 = 0.01; // 切断面を 1 フレームで動かす距離
+const MAX_CLIP_OFFSET: f32 = 4.0; // 切断面の原点からの距離の上限
+const MAX_SCALE_FACTOR: usize = 4; // 画面の拡大率の上限
+const LABEL_MARGIN: usize = 8; // フラクタルの種類の表示の位置（画面左上からの論理ピクセル）
+const LABEL_DOT: usize = 2; // 表示の文字の 1 ドットの大きさ（論理ピクセル）
+const STATS_HISTORY: usize = 150; // 描画の統計でグラフにする直近のフレーム数（1 フレームが横 1 論理ピクセル）
+const STATS_GRAPH_HEIGHT: usize = 32; // フレーム時間のグラフの高さ（論理ピクセル）
+const STATS_TARGET_MS: f32 = 1000.0 / 60.0; // フレーム時間のグラフに横線を引く目標の時間（60 fps）
+const CHAR_ADVANCE: usize = 6; // 文字の横の送り幅（ドット、字間 1 ドットを含む）
+const CHAR_HEIGHT: usize = 7; // 文字の高さ（ドット）
+
+// ==========================================
+// ハイブリッドフラクタルの式
+// ==========================================
+
+/// `--formula <ファイル>` で指定したハイブリッドフラクタルの式（省略時は組み込みの式）
+fn formula(args: &Args) -> Result<Formula, String> {
+    match &args.formula {
+        Some(path) => Formula::load(path),
+        None => Ok(Formula::default()),
+    }
+}
+
+/// `--lights <ファイル>` で指定した照明（省略時は主光源と補助光源の 2 灯）
+fn lights(args: &Args) -> Result<Lights, String> {
+    match &args.lights {
+        Some(path) => Lights::load(path),
+        None => Ok(Lights::default()),
+    }
+}
+
+/// 質感を保存するファイル（`--material <ファイル>`、省略時は `material.txt`）
+fn material_path(args: &Args) -> String {
+    args.material
+        .clone()
+        .unwrap_or_else(|| "material.txt".to_string())
+}
+
+/// `--material <ファイル>` で指定した質感（省略時は既定値）
+fn material(args: &Args) -> Result<Material, String> {
+    match &args.material {
+        Some(path) => Material::load(path),
+        None => Ok(Material::default()),
+    }
+}
+
+// ==========================================
+// 霧
+// ==========================================
+
+/// 陰影付けの設定（霧・照明・質感・背景・輪郭の光）
+struct Shading<'a> {
+    fog: &'a Fog,
+    lights: &'a Lights,
+    material: &'a Material,
+    environment: &'a Environment,
+    glow: &'a Glow,
+}
+
+/// 距離に応じて色を霧の色に近づける指数関数的な霧
+#[derive(Clone, Copy, PartialEq)]
+struct Fog {
+    density: f32,
+    color: Vec3,
+}
+
+impl Fog {
+    /// `--fog-density <濃さ>`・`--fog-color <RRGGBB>` で指定した霧（省略時は既定値）
+    fn from_args(args: &cli::Common) -> Result<Self, String> {
+        let density = match args.fog_density {
+            Some(d) if !(d.is_finite() && d >= 0.0) => {
+                return Err(format!("霧の濃さには 0 以上の数値が必要です: {}", d))
+            }
+            Some(d) => d,
+            None => FOG_DENSITY,
+        };
+        Ok(Self {
+            density,
+            color: cli::rgb(args.fog_color.unwrap_or(FOG_COLOR)),
+        })
+    }
+
+    /// カメラから距離 `t` の点の色 `rgb` に霧をかける
+    fn apply(&self, rgb: Vec3, t: f32) -> Vec3 {
+        rgb.lerp(self.color, 1.0 - (-self.density * t).exp())
+    }
+}
+
+/// 形の輪郭のまわりの光（形をかすめて外れたレイほど強く光り、シルエットに光の縁ができる）
+#[derive(Clone, Copy, PartialEq)]
+struct Glow {
+    strength: f32,
+    color: Vec3,
+}
+
+impl Glow {
+    /// `--glow <強さ>`・`--glow-color <RRGGBB>` で指定した光（省略時は光らない）
+    fn from_args(args: &cli::Common) -> Result<Self, String> {
+        let strength = match args.glow {
+            Some(s) if !(s.is_finite() && s >= 0.0) => {
+                return Err(format!("輪郭の光の強さには 0 以上の数値が必要です: {}", s))
+            }
+            Some(s) => s,
+            None => GLOW_STRENGTH,
+        };
+        Ok(Self {
+            strength,
+            color: cli::rgb(args.glow_color.unwrap_or(GLOW_COLOR)),
+        })
+    }
+
+    /// 形に最も近づいたときの距離が `distance` だった（外れた）レイに足す光
+    fn emission(&self, distance: f32) -> Vec3 {
+        self.color * (self.strength * (-distance.max(0.0) / GLOW_RADIUS).exp())
+    }
+}
+
+// ==========================================
+// 画面の拡大率（HiDPI）
+// ==========================================
+
+/// `--scale <auto|1|2|3|4>` で指定した拡大率（省略時・auto はディスプレイから検出）
+fn scale_factor(args: &Args) -> Result<usize, String> {
+    match args.scale.as_deref() {
+        None | Some("auto") => Ok(detect_scale_factor()),
+        Some(v) => v
+            .parse()
+            .ok()
+            .filter(|factor| (1..=MAX_SCALE_FACTOR).contains(factor))
+            .ok_or_else(|| format!("拡大率の指定が不正です（auto / 1 / 2 / 3 / 4）: {}", v)),
+    }
+}
+
+/// ディスプレイの拡大率（環境変数 GDK_SCALE・QT_SCALE_FACTOR、macOS では backingScaleFactor。分からなければ 1）
+fn detect_scale_factor() -> usize {
+    let from_env = ["GDK_SCALE", "QT_SCALE_FACTOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find_map(|value| value.trim().parse::<f32>().ok());
+    let factor = from_env.or_else(platform_scale_factor).unwrap_or(1.0);
+    if factor.is_finite() {
+        (factor.round() as usize).clamp(1, MAX_SCALE_FACTOR)
+    } else {
+        1
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn platform_scale_factor() -> Option<f32> {
+    let output = std::process::Command::new("osascript")
+        .args([
+            "-l",
+            "JavaScript",
+            "-e",
+            "ObjC.import('AppKit'); $.NSScreen.mainScreen.backingScaleFactor",
+        ])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+#[cfg(not(target_os = "macos"))]
+fn platform_scale_factor() -> Option<f32> {
+    None
+}
+
+// ==========================================
+// 累積描画
+// ==========================================
+
+/// 累積描画でサンプルごとにずらす量を決める乱数（ピクセルの位置とサンプル番号から決まる）
+///
+/// `Jitter::none()` はずらさず、操作中のプレビューと同じ結果になります。
+struct Jitter {
+    state: u32,
+    enabled: bool,
+}
+
+impl Jitter {
+    fn none() -> Self {
+        Self {
+            state: 0,
+            enabled: false,
+        }
+    }
+
+    fn new(x: usize, y: usize, sample: u32) -> Self {
+        Self {
+            state: hash(x as u32 ^ hash(y as u32 ^ hash(sample))),
+            enabled: true,
+        }
+    }
+
+    /// -0.5〜0.5 の値（ずらさない場合は 0）
+    fn offset(&mut self) -> f32 {
+        if !self.enabled {
+            return 0.0;
+        }
+        self.state = self
+            .state
+            .wrapping_mul(747_796_405)
+            .wrapping_add(2_891_336_453);
+        (hash(self.state) >> 8) as f32 / (1u32 << 24) as f32 - 0.5
+    }
+
+    /// 向きが一様に散らばる単位ベクトル（ずらさない場合は 0 ベクトル）
+    fn unit_vector(&mut self) -> Vec3 {
+        if !self.enabled {
+            return Vec3::ZERO;
+        }
+        let z = self.offset() * 2.0;
+        let phi = (self.offset() + 0.5) * std::f32::consts::TAU;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        Vec3::new(r * phi.cos(), r * phi.sin(), z)
+    }
+}
+
+/// 32 ビットの整数ハッシュ（lowbias32）
+fn hash(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^ (x >> 16)
+}
+
+/// 0〜1 の RGB を 0xRRGGBB にする
+fn pack_rgb(rgb: Vec3) -> u32 {
+    let rgb = rgb * 255.0;
+    ((rgb.x as u32) << 16) | ((rgb.y as u32) << 8) | rgb.z as u32
+}
 
 // ==========================================
 // HSVからRGBへの変換
@@ -49,18 +432,325 @@ fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (f32, f32, f32) {
 }
 
 // ==========================================
-// マンデルバルブ距離関数 + 反復回数を返す
+// フラクタルの種類とパラメータ
 // ==========================================
-fn map_with_iter(pos: Vec3, power: f32) -> (f32, usize, f32) {
+#[derive(Clone, Copy, PartialEq)]
+enum FractalType {
+    Mandelbulb,
+    QuaternionJulia,
+    MengerSponge,
+    SierpinskiTetrahedron,
+    Hybrid,
+}
+
+impl FractalType {
+    fn next(self) -> Self {
+        match self {
+            FractalType::Mandelbulb => FractalType::QuaternionJulia,
+            FractalType::QuaternionJulia => FractalType::MengerSponge,
+            FractalType::MengerSponge => FractalType::SierpinskiTetrahedron,
+            FractalType::SierpinskiTetrahedron => FractalType::Hybrid,
+            FractalType::Hybrid => FractalType::Mandelbulb,
+        }
+    }
+
+    /// 画面に表示する名前
+    fn name(self) -> &'static str {
+        match self {
+            FractalType::Mandelbulb => "MANDELBULB",
+            FractalType::QuaternionJulia => "QUATERNION JULIA",
+            FractalType::MengerSponge => "MENGER SPONGE",
+            FractalType::SierpinskiTetrahedron => "SIERPINSKI TETRAHEDRON",
+            FractalType::Hybrid => "HYBRID",
+        }
+    }
+
+    /// キーフレームファイルで使う名前
+    fn key(self) -> &'static str {
+        match self {
+            FractalType::Mandelbulb => "mandelbulb",
+            FractalType::QuaternionJulia => "quaternion_julia",
+            FractalType::MengerSponge => "menger_sponge",
+            FractalType::SierpinskiTetrahedron => "sierpinski_tetrahedron",
+            FractalType::Hybrid => "hybrid",
+        }
+    }
+
+    /// 形全体が見える初期のカメラ（原点を向く）
+    ///
+    /// 立方体・四面体は面が正面を向くと平板に見えるため、斜め上から見下ろします。
+    fn camera(self) -> Camera {
+        match self {
+            FractalType::Mandelbulb | FractalType::Hybrid => Camera::new(),
+            FractalType::QuaternionJulia => Camera::looking_at_origin(Vec3::new(0.0, 0.0, -2.0)),
+            FractalType::MengerSponge => Camera::looking_at_origin(Vec3::new(1.6, 1.4, -2.4)),
+            FractalType::SierpinskiTetrahedron => {
+                Camera::looking_at_origin(Vec3::new(1.2, 1.0, -2.6))
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for FractalType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "mandelbulb" => Ok(FractalType::Mandelbulb),
+            "quaternion_julia" => Ok(FractalType::QuaternionJulia),
+            "menger_sponge" => Ok(FractalType::MengerSponge),
+            "sierpinski_tetrahedron" => Ok(FractalType::SierpinskiTetrahedron),
+            "hybrid" => Ok(FractalType::Hybrid),
+            _ => Err(format!(
+                "フラクタルの種類の指定が不正です（mandelbulb / quaternion_julia / menger_sponge / sierpinski_tetrahedron / hybrid）: {}",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+struct Fractal {
+    kind: FractalType,
+    power: f32,          // マンデルバルブのパワー
+    julia_c: Vec4,       // 四元数ジュリア集合の c (x: 実部, y/z/w: i/j/k)
+    depth: u32,          // メンガーのスポンジ・シェルピンスキーの四面体の再帰の深さ
+    formula: Formula,    // ハイブリッドフラクタルの式
+    clip: ClipPlane,     // 断面表示の切断面
+    quality: Quality,    // 画質（最大ステップ数・反復回数・脱出半径）
+    coloring: ColorMode, // 表面の色の付け方
+}
+
+impl Fractal {
+    fn new() -> Self {
+        Self {
+            kind: FractalType::Mandelbulb,
+            power: 2.0, // デフォルトパワー2
+            julia_c: JULIA_C,
+            depth: IFS_DEPTH,
+            formula: Formula::default(),
+            clip: ClipPlane::new(),
+            quality: Quality::new(),
+            coloring: ColorMode::Blend,
+        }
+    }
+
+    /// パワーを `amount` だけ変える（範囲内に収め、0.1 刻みの誤差を丸める）
+    fn shift_power(&mut self, amount: f32) {
+        self.power = ((self.power + amount) * 10.0).round() / 10.0;
+        self.power = self.power.clamp(MIN_POWER, MAX_POWER);
+    }
+}
+
+/// 断面表示の切断面（法線の側を取り除く）
+#[derive(Clone, Copy, PartialEq)]
+struct ClipPlane {
+    enabled: bool,
+    normal: Vec3, // 取り除く側を向いた単位ベクトル
+    offset: f32,  // 原点から平面までの、法線の向きに沿った距離
+}
+
+impl ClipPlane {
+    /// 原点を通り、初期のカメラ側（-Z 側）の半分を取り除く平面（無効）
+    fn new() -> Self {
+        Self {
+            enabled: false,
+            normal: Vec3::NEG_Z,
+            offset: 0.0,
+        }
+    }
+
+    /// 形の距離 `dist` を、切断面で削った形の距離にする（平面までの距離との max）
+    fn apply(&self, pos: Vec3, dist: f32) -> f32 {
+        if self.enabled {
+            dist.max(pos.dot(self.normal) - self.offset)
+        } else {
+            dist
+        }
+    }
+
+    /// 法線をカメラの方へ向ける（カメラの正面から断面が見える向き。原点からの距離は保つ）
+    fn face(&mut self, camera: &Camera) {
+        self.normal = -camera.forward();
+    }
+
+    /// 法線の向きに `amount` だけ動かす
+    fn shift(&mut self, amount: f32) {
+        self.offset = (self.offset + amount).clamp(-MAX_CLIP_OFFSET, MAX_CLIP_OFFSET);
+    }
+}
+
+/// 画質とフレームレートの釣り合い（実行中にキーで変える）
+#[derive(Clone, Copy, PartialEq)]
+struct Quality {
+    max_steps: usize,   // レイマーチングの最大ステップ数
+    iterations: usize, // マンデルバルブ・四元数ジュリア集合の反復回数（ハイブリッドは式の iterations を使う）
+    bailout: f32,      // マンデルバルブの脱出半径
+    reflections: usize, // 表面で反射したレイを飛ばす回数（0 で反射なし）
+}
+
+impl Quality {
+    fn new() -> Self {
+        Self {
+            max_steps: MAX_STEPS,
+            iterations: MAX_ITER,
+            bailout: BAILOUT,
+            reflections: 0,
+        }
+    }
+
+    /// 反射の回数を 0 → 1 → … → `MAX_REFLECTIONS` → 0 の順に切り替える
+    fn next_reflections(&mut self) {
+        self.reflections = (self.reflections + 1) % (MAX_REFLECTIONS + 1);
+    }
+
+    /// 最大ステップ数を `steps` 段階だけ変える（範囲内に収める）
+    fn adjust_steps(&mut self, steps: i32) {
+        let value = self.max_steps as i32 + steps * STEPS_STEP as i32;
+        self.max_steps = (value.max(0) as usize).clamp(STEPS_RANGE.0, STEPS_RANGE.1);
+    }
+
+    /// 反復回数を `steps` 回だけ変える（範囲内に収める）
+    fn adjust_iterations(&mut self, steps: i32) {
+        let value = self.iterations as i32 + steps;
+        self.iterations = (value.max(0) as usize).clamp(ITER_RANGE.0, ITER_RANGE.1);
+    }
+
+    /// 脱出半径を `steps` 段階だけ変える（範囲内に収める）
+    fn adjust_bailout(&mut self, steps: i32) {
+        self.bailout =
+            (self.bailout + steps as f32 * BAILOUT_STEP).clamp(BAILOUT_RANGE.0, BAILOUT_RANGE.1);
+    }
+}
+
+// ==========================================
+// 起動時の設定
+// ==========================================
+
+// コマンドラインのオプション（GPU版と共通の `cli::Common` に CPU版だけのものを足す。ドキュメントコメントは --help に出る）
+
+/// マンデルバルブ (Mandelbulb) 3Dフラクタルビューア - CPU版
+#[derive(Parser)]
+struct Args {
+    #[command(flatten)]
+    common: cli::Common,
+    /// 最初に表示する形（mandelbulb / quaternion_julia / menger_sponge / sierpinski_tetrahedron / hybrid）
+    #[arg(long, value_name = "KIND")]
+    fractal: Option<FractalType>,
+    /// 画面の拡大率（auto / 1 / 2 / 3 / 4、既定: auto）
+    #[arg(long)]
+    scale: Option<String>,
+    /// ハイブリッドフラクタルの式のファイル（既定: 組み込みの式）
+    #[arg(long, value_name = "FILE")]
+    formula: Option<String>,
+    /// 照明のファイル（既定: 主光源と補助光源の 2 灯）
+    #[arg(long, value_name = "FILE")]
+    lights: Option<String>,
+    /// 質感のファイル（F5 キーの保存先も兼ねる。既定: material.txt）
+    #[arg(long, value_name = "FILE")]
+    material: Option<String>,
+    /// 背景（gradient / sky / 正距円筒図法の HDR 画像のファイル、既定: gradient）
+    #[arg(long)]
+    environment: Option<String>,
+    /// 背景の画像の明るさの倍率（既定: 1）
+    #[arg(long, value_name = "SCALE")]
+    environment_intensity: Option<f32>,
+    /// 立体視（off / side-by-side / anaglyph、既定: off）
+    #[arg(long, value_name = "MODE")]
+    stereo: Option<StereoMode>,
+    /// 立体視の左右の目の間隔（既定: 0.08）
+    #[arg(long, value_name = "DISTANCE")]
+    eye_separation: Option<f32>,
+    /// スクリーンショット・アニメーションで 1 ピクセルに通すレイの本数（既定: 4）
+    #[arg(long, value_name = "SAMPLES")]
+    aa_samples: Option<u32>,
+    /// 高画質キャプチャの大きさ（既定: ウィンドウの 4 倍）
+    #[arg(long, value_name = "WxH", value_parser = cli::parse_size)]
+    capture_size: Option<(usize, usize)>,
+    /// 高画質キャプチャで 1 ピクセルに通すレイの本数（既定: 4）
+    #[arg(long, value_name = "SAMPLES")]
+    capture_samples: Option<u32>,
+    /// 点群の書き出しで立方体の 1 面に通すレイの本数（1 辺あたり、既定: 256）
+    #[arg(long, value_name = "GRID")]
+    point_cloud_grid: Option<usize>,
+}
+
+/// コマンドラインで指定する起動時の設定（ウィンドウの大きさ・形・画質・保存先）
+///
+/// 大きさや画質を変えて描画の速さを比べるときに、ソースを書き換えずに済むようにします。
+struct StartOptions {
+    size: (usize, usize),    // ウィンドウの大きさ（論理ピクセル）
+    kind: FractalType,       // 最初に表示するフラクタルの種類
+    power: f32,              // マンデルバルブのパワーの初期値（R キーのリセットでもこの値に戻す）
+    quality: Quality,        // 画質の初期値
+    screenshot_dir: PathBuf, // スクリーンショット・キャプチャ・点群の保存先
+}
+
+impl StartOptions {
+    /// `--size <幅>x<高さ>`・`--fractal <種類>`・`--power <パワー>`・`--max-steps <ステップ数>`・
+    /// `--iterations <回数>`・`--bailout <半径>`・`--screenshot-dir <ディレクトリ>` で指定した設定（省略時は既定値）
+    fn from_args(args: &Args) -> Result<Self, String> {
+        let common = &args.common;
+        let mut quality = Quality::new();
+        quality.max_steps = cli::in_range(
+            common.max_steps.map(|n| n as usize),
+            quality.max_steps,
+            STEPS_RANGE,
+            "最大ステップ数",
+        )?;
+        quality.iterations = cli::in_range(
+            common.iterations.map(|n| n as usize),
+            quality.iterations,
+            ITER_RANGE,
+            "反復回数",
+        )?;
+        quality.bailout =
+            cli::in_range(common.bailout, quality.bailout, BAILOUT_RANGE, "脱出半径")?;
+        Ok(Self {
+            size: cli::size_within(common.size, (WIDTH, HEIGHT), MAX_WINDOW_SIZE, "ウィンドウ")?,
+            kind: args.fractal.unwrap_or(FractalType::Mandelbulb),
+            power: cli::in_range(
+                common.power,
+                Fractal::new().power,
+                (MIN_POWER, MAX_POWER),
+                "パワー",
+            )?,
+            quality,
+            screenshot_dir: common
+                .screenshot_dir
+                .clone()
+                .unwrap_or_else(|| SCREENSHOT_DIR.into()),
+        })
+    }
+}
+
+// ==========================================
+// 距離関数 + 反復回数を返す
+// ==========================================
+fn map_with_iter(pos: Vec3, fractal: &Fractal) -> (f32, usize, f32) {
+    let (dist, iter, trap) = match fractal.kind {
+        FractalType::Mandelbulb => mandelbulb_with_iter(pos, fractal.power, &fractal.quality),
+        FractalType::QuaternionJulia => {
+            quaternion_julia_with_iter(pos, fractal.julia_c, fractal.quality.iterations)
+        }
+        FractalType::MengerSponge => menger_sponge_with_iter(pos, fractal.depth),
+        FractalType::SierpinskiTetrahedron => sierpinski_tetrahedron_with_iter(pos, fractal.depth),
+        FractalType::Hybrid => fractal.formula.map_with_iter(pos),
+    };
+    (fractal.clip.apply(pos, dist), iter, trap)
+}
+
+// マンデルバルブ
+fn mandelbulb_with_iter(pos: Vec3, power: f32, quality: &Quality) -> (f32, usize, f32) {
     let mut z = pos;
     let mut dr = 1.0;
     let mut r = 0.0;
     let mut trap = f32::MAX; // オービットトラップ
 
     let mut i = 0;
-    for iter in 0..MAX_ITER {
+    for iter in 0..quality.iterations {
         r = z.length();
-        if r > BAILOUT {
+        if r > quality.bailout {
             i = iter;
             break;
         }
@@ -91,42 +781,315 @@ fn map_with_iter(pos: Vec3, power: f32) -> (f32, usize, f32) {
     (dist, i, trap)
 }
 
-fn map(pos: Vec3, power: f32) -> f32 {
-    map_with_iter(pos, power).0
+// 四元数の 2 乗（x を実部、y/z/w を i/j/k の係数とする）
+fn quat_square(q: Vec4) -> Vec4 {
+    Vec4::new(
+        q.x * q.x - q.y * q.y - q.z * q.z - q.w * q.w,
+        2.0 * q.x * q.y,
+        2.0 * q.x * q.z,
+        2.0 * q.x * q.w,
+    )
+}
+
+// 四元数ジュリア集合（k 成分が 0 の断面）
+// 導関数は |z'| だけを追えばよく、|2 z z'| = 2 |z| |z'| で更新する
+fn quaternion_julia_with_iter(pos: Vec3, c: Vec4, iterations: usize) -> (f32, usize, f32) {
+    let mut z = pos.extend(0.0);
+    let mut dr = 1.0;
+    let mut trap = f32::MAX; // オービットトラップ
+
+    let mut i = 0;
+    for iter in 0..iterations {
+        i = iter;
+        let r = z.length();
+        if r > JULIA_BAILOUT {
+            break;
+        }
+
+        trap = trap.min(r);
+        dr *= 2.0 * r;
+        z = quat_square(z) + c;
+    }
+
+    let r = z.length();
+    let dist = 0.5 * r.ln() * r / dr;
+    (dist, i, trap)
+}
+
+// メンガーのスポンジ（一辺 2 の立方体から、各段で 3 分割した十字の穴をくり抜く）
+// 反復回数は常に depth、オービットトラップは各段のセル内の位置の中心からの最小距離
+fn menger_sponge_with_iter(pos: Vec3, depth: u32) -> (f32, usize, f32) {
+    let q = pos.abs() - Vec3::ONE;
+    let mut dist = q.max(Vec3::ZERO).length() + q.max_element().min(0.0);
+    let mut trap = f32::MAX;
+
+    let mut s = 1.0;
+    for _ in 0..depth {
+        // 各段のセルを [-1, 1]³ に写し、中心の十字形の穴までの距離を求める
+        let a = (pos * s).rem_euclid(Vec3::splat(2.0)) - Vec3::ONE;
+        s *= 3.0;
+        let r = (Vec3::ONE - a.abs() * 3.0).abs();
+        let da = r.x.max(r.y);
+        let db = r.y.max(r.z);
+        let dc = r.z.max(r.x);
+        let hole = (da.min(db).min(dc) - 1.0) / s;
+        dist = dist.max(hole);
+        trap = trap.min(a.length());
+    }
+
+    (dist, depth as usize, trap)
+}
+
+// シェルピンスキーの四面体（頂点 (1,1,1), (-1,-1,1), (1,-1,-1), (-1,1,-1)）
+// 対称面で折り返して頂点 (1,1,1) 側に寄せ、2 倍に拡大する操作を depth 回繰り返し、
+// 最後に元の四面体までの距離を拡大率で割る
+fn sierpinski_tetrahedron_with_iter(pos: Vec3, depth: u32) -> (f32, usize, f32) {
+    let mut z = pos;
+    let mut trap = f32::MAX;
+
+    let mut s = 1.0;
+    for _ in 0..depth {
+        if z.x + z.y < 0.0 {
+            z = Vec3::new(-z.y, -z.x, z.z);
+        }
+        if z.x + z.z < 0.0 {
+            z = Vec3::new(-z.z, z.y, -z.x);
+        }
+        if z.y + z.z < 0.0 {
+            z = Vec3::new(z.x, -z.z, -z.y);
+        }
+        z = z * 2.0 - Vec3::ONE;
+        s *= 2.0;
+        trap = trap.min(z.length() / s);
+    }
+
+    let planes = (-z.x - z.y - z.z)
+        .max(z.x + z.y - z.z)
+        .max(-z.x + z.y + z.z)
+        .max(z.x - z.y + z.z);
+    let dist = (planes - 1.0) / 3.0_f32.sqrt() / s;
+    (dist, depth as usize, trap)
+}
+
+fn map(pos: Vec3, fractal: &Fractal) -> f32 {
+    map_with_iter(pos, fractal).0
 }
 
 // ==========================================
 // 法線計算
 // ==========================================
-fn calc_normal(p: Vec3, power: f32) -> Vec3 {
+fn calc_normal(p: Vec3, fractal: &Fractal) -> Vec3 {
     let e = Vec3::new(EPSILON, 0.0, 0.0);
     let n = Vec3::new(
-        map(p + e, power) - map(p - e, power),
-        map(p + Vec3::new(0.0, EPSILON, 0.0), power) - map(p - Vec3::new(0.0, EPSILON, 0.0), power),
-        map(p + Vec3::new(0.0, 0.0, EPSILON), power) - map(p - Vec3::new(0.0, 0.0, EPSILON), power),
+        map(p + e, fractal) - map(p - e, fractal),
+        map(p + Vec3::new(0.0, EPSILON, 0.0), fractal)
+            - map(p - Vec3::new(0.0, EPSILON, 0.0), fractal),
+        map(p + Vec3::new(0.0, 0.0, EPSILON), fractal)
+            - map(p - Vec3::new(0.0, 0.0, EPSILON), fractal),
     );
     n.normalize()
 }
 
+// ==========================================
+// ソフトシャドウ
+// ==========================================
+
+/// 表面の点 `p` から光源の方向 `light_dir` に `max_dist` までレイを進め、光の届く割合（0: 影〜1: 日なた）を返す
+///
+/// レイが表面のそばを通るほど暗くなるよう、各ステップの距離 d と進んだ距離 t から k·d/t の最小値を取ります。
+/// 点光源では `max_dist` を光源までの距離にし、光源より奥の形で影にならないようにします。
+/// 累積描画では `jitter` で向きを光源の見かけの大きさの範囲でずらし、重ねると半影が滑らかになります。
+fn soft_shadow(
+    p: Vec3,
+    normal: Vec3,
+    light_dir: Vec3,
+    max_dist: f32,
+    fractal: &Fractal,
+    jitter: &mut Jitter,
+) -> f32 {
+    let light_dir = (light_dir + jitter.unit_vector() * SHADOW_JITTER).normalize();
+    // 自分自身の表面に当たらないよう、法線方向に少し浮かせてから進める
+    let ro = p + normal * (EPSILON * 4.0);
+    let mut res: f32 = 1.0;
+    let mut t = EPSILON * 10.0;
+
+    for _ in 0..SHADOW_STEPS {
+        let d = map(ro + light_dir * t, fractal);
+        if d < EPSILON {
+            return 0.0;
+        }
+        res = res.min(SHADOW_SOFTNESS * d / t);
+        t += d.clamp(EPSILON * 10.0, 0.2);
+        if t > max_dist {
+            break;
+        }
+    }
+    res.clamp(0.0, 1.0)
+}
+
+// ==========================================
+// アンビエントオクルージョン
+// ==========================================
+
+/// 表面の点 `p` の周囲の開け具合（0: 囲まれている〜1: 開けている）
+///
+/// 法線方向に `AO_SAMPLES` 点を取り、表面からの距離 h に対して距離関数の値 d が小さい
+/// （近くに別の表面がある）ほど暗くします。近い点ほど重みを大きくします。
+/// 累積描画では `jitter` で調べる向きと距離をずらし、重ねると半球全体の開け具合に近づきます。
+fn ambient_occlusion(p: Vec3, normal: Vec3, fractal: &Fractal, jitter: &mut Jitter) -> f32 {
+    let dir = (normal + jitter.unit_vector() * AO_JITTER).normalize();
+    let offset = jitter.offset();
+    let mut occlusion = 0.0;
+    let mut weight = 1.0;
+    for i in 0..AO_SAMPLES {
+        let h =
+            0.01 + (AO_MAX_DIST - 0.01) * (i as f32 + offset).max(0.0) / (AO_SAMPLES - 1) as f32;
+        let d = map(p + dir * h, fractal);
+        occlusion += (h - d) * weight;
+        weight *= 0.95;
+    }
+    (1.0 - AO_STRENGTH * occlusion).clamp(0.0, 1.0)
+}
+
 // ==========================================
 // カラフルなレンダリング
 // ==========================================
-fn ray_march(ro: Vec3, rd: Vec3, power: f32, time: f32) -> u32 {
+
+/// レイマーチングの結果
+struct RaySample {
+    rgb: Vec3,                // 色（0〜1）
+    hit: Option<(f32, Vec3)>, // 形に当たった場合のレイの距離 t と法線
+    steps: u32,               // 距離関数を評価したステップの数（反射したレイの分も含む）
+    exhausted: bool,          // 最初のレイが形に当たる前に最大ステップ数を使い切ったか
+}
+
+impl RaySample {
+    /// 形に当たった場合の（カメラの前方向 `forward` に沿った深度、法線）。`dir` はこのレイの向き
+    fn depth_hit(&self, dir: Vec3, forward: Vec3) -> Option<(f32, Vec3)> {
+        self.hit.map(|(t, normal)| (t * dir.dot(forward), normal))
+    }
+}
+
+/// 1 画素を通るレイの太さ（コーントレーシング）
+///
+/// 距離 t での画素の大きさは `radius + spread·t` で、透視投影では `radius` が 0、平行投影では `spread` が 0 です。
+#[derive(Clone, Copy)]
+struct PixelCone {
+    radius: f32,
+    spread: f32,
+}
+
+impl PixelCone {
+    /// 高さ `height` ピクセルの画面の画素（縦の画角 90 度。画面の中央の 1 画素の見込む角度）
+    fn perspective(height: usize) -> Self {
+        Self {
+            radius: 0.0,
+            spread: 2.0 / height as f32,
+        }
+    }
+
+    /// 距離 `t` で表面に当たったとみなす距離
+    ///
+    /// 1 画素より細かい形は見分けられないため、遠くでは大きくして無駄なステップを省き、
+    /// 近くでは小さくして段差のような縞が出ないようにします。
+    fn hit_epsilon(self, t: f32) -> f32 {
+        ((self.radius + self.spread * t) * HIT_CONE).max(MIN_HIT_EPSILON)
+    }
+}
+
+/// レイの色と当たった位置を求める。`cone` は画素の大きさ、`jitter` は累積描画で影・アンビエントオクルージョンをずらす乱数
+///
+/// 反射を有効にしていれば、表面で反射したレイを `quality.reflections` 回まで飛ばし、面の色と
+/// フレネル反射の割合で混ぜます（1 回ごとにレイマーチングと陰影付けがもう 1 組増えます）。
+/// 霧は最初に当たった面までの距離でかけます。
+fn ray_march(
+    ro: Vec3,
+    rd: Vec3,
+    cone: PixelCone,
+    fractal: &Fractal,
+    shading: &Shading,
+    time: f32,
+    jitter: &mut Jitter,
+) -> RaySample {
+    let first = shade_ray(ro, rd, cone, fractal, shading, time, jitter);
+    let Some((t, normal)) = first.hit else {
+        return first;
+    };
+
+    // 手前の面から順に、(1 - 反射率) だけ面の色を足し、残りを反射したレイの色に任せる
+    let mut rgb = Vec3::ZERO;
+    let mut throughput = 1.0;
+    let mut surface = first.rgb;
+    let mut steps = first.steps;
+    let (mut origin, mut dir, mut cone, mut hit) = (ro, rd, cone, (t, normal));
+    for _ in 0..fractal.quality.reflections {
+        let (t, normal) = hit;
+        let cos_view = -normal.dot(dir);
+        let fresnel = REFLECTANCE + (1.0 - REFLECTANCE) * (1.0 - cos_view.max(0.0)).powi(5);
+        rgb += surface * (throughput * (1.0 - fresnel));
+        throughput *= fresnel;
+
+        // 表面に当たったとみなす距離より外から、反射した向きへ飛ばす（画素の大きさは当たった点から広がり続ける）
+        let offset = cone.hit_epsilon(t) * 2.0 + EPSILON;
+        origin = origin + dir * t + normal * offset;
+        dir = dir - normal * (2.0 * dir.dot(normal));
+        cone = PixelCone {
+            radius: cone.radius + cone.spread * t,
+            spread: cone.spread,
+        };
+        let sample = shade_ray(origin, dir, cone, fractal, shading, time, jitter);
+        surface = sample.rgb;
+        steps += sample.steps;
+        match sample.hit {
+            Some(next) => hit = next,
+            None => break,
+        }
+    }
+    rgb += surface * throughput;
+
+    // 距離に応じた霧
+    RaySample {
+        rgb: shading.fog.apply(rgb.min(Vec3::ONE), t),
+        hit: first.hit,
+        steps,
+        exhausted: false,
+    }
+}
+
+/// 1 本のレイの色（霧をかける前）と当たった位置を求める
+fn shade_ray(
+    ro: Vec3,
+    rd: Vec3,
+    cone: PixelCone,
+    fractal: &Fractal,
+    shading: &Shading,
+    time: f32,
+    jitter: &mut Jitter,
+) -> RaySample {
+    let Shading {
+        lights,
+        material,
+        environment,
+        glow,
+        ..
+    } = *shading;
     let mut t = 0.0;
     let mut hit = false;
-    let mut steps = 0;
     let mut total_iter = 0;
     let mut min_trap = f32::MAX;
+    let mut min_dist = f32::MAX; // 形に最も近づいたときの距離（外れたレイの輪郭の光）
+    let mut steps = 0;
 
-    for i in 0..MAX_STEPS {
+    for _ in 0..fractal.quality.max_steps {
+        steps += 1;
         let p = ro + rd * t;
-        let (d, iter, trap) = map_with_iter(p, power);
+        let (d, iter, trap) = map_with_iter(p, fractal);
         total_iter = iter;
         min_trap = min_trap.min(trap);
+        min_dist = min_dist.min(d);
 
-        if d < EPSILON {
+        if d < cone.hit_epsilon(t) {
             hit = true;
-            steps = i;
             break;
         }
 
@@ -138,26 +1101,55 @@ fn ray_march(ro: Vec3, rd: Vec3, power: f32, time: f32) -> u32 {
 
     if hit {
         let p = ro + rd * t;
-        let normal = calc_normal(p, power);
-
-        // 複数光源
-        let light1 = Vec3::new(0.577, 0.577, -0.577);
-        let light2 = Vec3::new(-0.5, 0.8, 0.3).normalize();
+        let normal = calc_normal(p, fractal);
 
-        let diff1 = normal.dot(light1).max(0.0);
-        let diff2 = normal.dot(light2).max(0.0) * 0.5;
-
-        // スペキュラー（ハイライト）
+        // 光源ごとの拡散光とスペキュラー（光が当たらない面では影のレイを省く。影の中ではどちらも消える）
         let view_dir = -rd;
-        let reflect_dir = (normal * (2.0 * normal.dot(light1))) - light1;
-        let spec = view_dir.dot(reflect_dir).max(0.0).powf(32.0);
+        // 環境光は背景から届く光（グラデーションの背景では一様な明るさ）
+        let mut diffuse = environment
+            .irradiance(normal)
+            .unwrap_or(Vec3::splat(lights.ambient));
+        let mut specular = Vec3::ZERO;
+        for light in &lights.sources {
+            let (light_dir, light_dist, strength) = light.illuminate(p);
+            let n_dot_l = normal.dot(light_dir);
+            if n_dot_l <= 0.0 {
+                continue;
+            }
+            let shadow = soft_shadow(
+                p,
+                normal,
+                light_dir,
+                light_dist.min(SHADOW_MAX_DIST),
+                fractal,
+                jitter,
+            );
+            let radiance = light.color * (strength * shadow);
+            diffuse += radiance * n_dot_l;
+
+            let reflect_dir = (normal * (2.0 * n_dot_l)) - light_dir;
+            specular += radiance
+                * view_dir
+                    .dot(reflect_dir)
+                    .max(0.0)
+                    .powf(material.specular_exponent);
+        }
 
         // AO
-        let ao = 1.0 - (steps as f32 / MAX_STEPS as f32).powf(0.4);
+        let ao = ambient_occlusion(p, normal, fractal, jitter);
+
+        // 背景の映り込み（面をかすめる向きほど強く映る。フレネル反射の Schlick 近似）
+        // 反射したレイを飛ばすときは、映り込みはそのレイの色で表す
+        if fractal.quality.reflections == 0 {
+            let cos_view = normal.dot(view_dir).max(0.0);
+            let fresnel = ENV_REFLECTANCE + (1.0 - ENV_REFLECTANCE) * (1.0 - cos_view).powi(5);
+            specular +=
+                environment.reflection(rd - normal * (2.0 * rd.dot(normal))) * (fresnel * ao);
+        }
 
         // カラフルな色計算
         // 1. 反復回数に基づく虹色
-        let hue1 = (total_iter as f32 / MAX_ITER as f32) + time * 0.1;
+        let hue1 = (total_iter as f32 / fractal.quality.iterations as f32) + time * 0.1;
 
         // 2. 法線方向に基づく色相変化
         let hue2 = (normal.x + normal.y * 0.5 + 1.0) * 0.5;
@@ -168,71 +1160,922 @@ fn ray_march(ro: Vec3, rd: Vec3, power: f32, time: f32) -> u32 {
         // 4. 位置に基づく色
         let hue4 = (p.x + p.y + p.z) * 0.3;
 
-        // 色を合成
-        let final_hue = (hue1 * 0.4 + hue2 * 0.2 + hue3 * 0.2 + hue4 * 0.2).fract();
-        let saturation = 0.8 + (1.0 - ao) * 0.2;
-        let value = (diff1 + diff2 + 0.15) * ao;
+        // 色の付け方に応じて色相を選ぶ（混合では質感の重みで混ぜる。粘土では色相を使わない）
+        let final_hue = match fractal.coloring {
+            ColorMode::Blend => Some(
+                hue1 * material.hue_iteration
+                    + hue2 * material.hue_normal
+                    + hue3 * material.hue_trap
+                    + hue4 * material.hue_position,
+            ),
+            ColorMode::OrbitTrap => Some(hue3),
+            ColorMode::Iteration => Some(hue1),
+            ColorMode::Normal => Some(hue2),
+            ColorMode::Position => Some(hue4),
+            ColorMode::Clay => None,
+        };
+        let saturation = (material.saturation + (1.0 - ao) * material.ao_saturation).min(1.0);
+        // 明るさは光源の色ごとに求める（白色光だけなら明度 (拡散光の和 + 環境光) * ao と同じ）
+        let lighting = (diffuse * ao * material.brightness).min(Vec3::ONE);
 
-        let (r_base, g_base, b_base) = hsv_to_rgb(final_hue, saturation, value.min(1.0));
+        let base = match final_hue {
+            Some(hue) => {
+                let (r, g, b) = hsv_to_rgb(hue.fract(), saturation, 1.0);
+                Vec3::new(r, g, b)
+            }
+            None => CLAY_COLOR,
+        };
 
         // スペキュラーハイライト追加
-        let r = ((r_base + spec * 0.5).min(1.0) * 255.0) as u32;
-        let g = ((g_base + spec * 0.5).min(1.0) * 255.0) as u32;
-        let b = ((b_base + spec * 0.5).min(1.0) * 255.0) as u32;
+        let rgb = (base * lighting + specular * material.specular_strength).min(Vec3::ONE);
 
-        (r << 16) | (g << 8) | b
+        RaySample {
+            rgb,
+            hit: Some((t, normal)),
+            steps,
+            exhausted: false,
+        }
     } else {
-        // グラデーション背景
-        let gradient = (rd.y + 1.0) * 0.5;
-        let bg_hue = 0.6 + time * 0.02; // 青〜紫系
-        let (r, g, b) = hsv_to_rgb(bg_hue, 0.5, gradient * 0.15 + 0.02);
-        let r = (r * 255.0) as u32;
-        let g = (g * 255.0) as u32;
-        let b = (b * 255.0) as u32;
-        (r << 16) | (g << 8) | b
+        // 空・HDR 画像の背景（明るすぎる部分は白に飽和させる）か、グラデーション背景
+        let background = environment.background(rd).unwrap_or_else(|| {
+            let gradient = (rd.y + 1.0) * 0.5;
+            let bg_hue = 0.6 + time * 0.02; // 青〜紫系
+            let (r, g, b) = hsv_to_rgb(bg_hue, 0.5, gradient * 0.15 + 0.02);
+            Vec3::new(r, g, b)
+        });
+        // 形をかすめたレイほど輪郭の光を足す
+        RaySample {
+            rgb: (background + glow.emission(min_dist)).min(Vec3::ONE),
+            hit: None,
+            steps,
+            // 遠くへ抜けずにステップが尽きた（表面をかすめ続けた）レイ
+            exhausted: t <= 6.0,
+        }
+    }
+}
+
+// ==========================================
+// 深度・法線のパス（AOV）
+// ==========================================
+
+/// 深度のパスで背景に書く値（Blender の Z パスと同じ）
+const BACKGROUND_DEPTH: f32 = 1.0e10;
+
+/// スクリーンショットと一緒に深度と法線のパスを `dir` に保存する
+///
+/// `hits` はピクセルごとの（カメラの前方向に沿った深度、ワールド座標の法線）で、背景は `None` です。
+/// EXR には値をそのまま（深度の背景は `BACKGROUND_DEPTH`、法線の背景は 0）、PNG には深度を形に当たった範囲で
+/// 正規化した 16 ビットのグレースケール（近いほど白、背景は黒）と、法線を 0〜1 に写した色を書きます。
+fn save_aovs(
+    hits: &[Option<(f32, Vec3)>],
+    width: usize,
+    height: usize,
+    dir: &Path,
+) -> Result<(), String> {
+    let (width, height) = (width as u32, height as u32);
+    let hit = |x: u32, y: u32| hits[y as usize * width as usize + x as usize];
+    let save = |result: image::ImageResult<()>, path: &Path| {
+        result.map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+    };
+
+    let depth = image::Rgb32FImage::from_fn(width, height, |x, y| {
+        image::Rgb([hit(x, y).map_or(BACKGROUND_DEPTH, |(depth, _)| depth); 3])
+    });
+    let path = dir.join("cpu_screenshot_depth.exr");
+    save(depth.save(&path), &path)?;
+
+    let (near, far) = hits
+        .iter()
+        .flatten()
+        .fold((f32::MAX, f32::MIN), |(near, far), &(depth, _)| {
+            (near.min(depth), far.max(depth))
+        });
+    let range = (far - near).max(f32::EPSILON);
+    let depth = image::ImageBuffer::<image::Luma<u16>, _>::from_fn(width, height, |x, y| {
+        let value = hit(x, y).map_or(0.0, |(depth, _)| 1.0 - (depth - near) / range * 0.9);
+        image::Luma([(value * u16::MAX as f32) as u16])
+    });
+    let path = dir.join("cpu_screenshot_depth.png");
+    save(depth.save(&path), &path)?;
+
+    let normal = image::Rgb32FImage::from_fn(width, height, |x, y| {
+        image::Rgb(
+            hit(x, y)
+                .map_or(Vec3::ZERO, |(_, normal)| normal)
+                .to_array(),
+        )
+    });
+    let path = dir.join("cpu_screenshot_normal.exr");
+    save(normal.save(&path), &path)?;
+
+    let normal = image::RgbImage::from_fn(width, height, |x, y| {
+        let rgb = hit(x, y).map_or(Vec3::ZERO, |(_, normal)| normal * 0.5 + 0.5) * 255.0;
+        image::Rgb([rgb.x as u8, rgb.y as u8, rgb.z as u8])
+    });
+    let path = dir.join("cpu_screenshot_normal.png");
+    save(normal.save(&path), &path)
+}
+
+// ==========================================
+// 高画質キャプチャ
+// ==========================================
+
+/// `--aa-samples <本数>` で指定した、スクリーンショットとアニメーションの書き出しで 1 ピクセルに通すレイの本数
+///
+/// 1 本だと形の縁がぎざぎざになり、アニメーションではフレームごとにちらつくため、
+/// ピクセル内の位置をずらしたレイを平均してアンチエイリアスをかけます（省略時は `AA_SAMPLES`、1 でかけない）。
+fn aa_samples(args: &Args) -> Result<u32, String> {
+    cli::in_range(
+        args.aa_samples,
+        AA_SAMPLES,
+        (1, MAX_CAPTURE_SAMPLES),
+        "アンチエイリアスのレイの本数",
+    )
+}
+
+/// 高画質キャプチャの設定
+struct Capture {
+    size: (usize, usize), // 幅と高さ（ピクセル）
+    samples: u32,         // 1 ピクセルに通すレイの本数
+}
+
+impl Capture {
+    /// `--capture-size <幅>x<高さ>`・`--capture-samples <本数>` で指定した設定
+    /// （省略時は大きさが論理ピクセルで `window_size` のウィンドウの `CAPTURE_SCALE` 倍、レイは `CAPTURE_SAMPLES` 本）
+    fn from_args(args: &Args, window_size: (usize, usize)) -> Result<Self, String> {
+        Ok(Self {
+            size: cli::size_within(
+                args.capture_size,
+                (window_size.0 * CAPTURE_SCALE, window_size.1 * CAPTURE_SCALE),
+                MAX_CAPTURE_SIZE,
+                "キャプチャ",
+            )?,
+            samples: cli::in_range(
+                args.capture_samples,
+                CAPTURE_SAMPLES,
+                (1, MAX_CAPTURE_SAMPLES),
+                "キャプチャのレイの本数",
+            )?,
+        })
+    }
+
+    /// 今のカメラで描画して `dir` の `cpu_capture.png` に保存する（進み具合をコンソールに表示）
+    fn render(
+        &self,
+        camera: &Camera,
+        fractal: &Fractal,
+        shading: &Shading,
+        time: f32,
+        stereo: &Stereo,
+        dir: &Path,
+    ) -> Result<PathBuf, String> {
+        let start = Instant::now();
+        let (pixels, _) = self.pixels(camera, fractal, shading, time, stereo, true);
+        println!(" ({:.1} s)", start.elapsed().as_secs_f32());
+
+        let (width, height) = self.size;
+        let _ = std::fs::create_dir_all(dir);
+        let path = dir.join("cpu_capture.png");
+        image::save_buffer_with_format(
+            &path,
+            &pixels,
+            width as u32,
+            height as u32,
+            image::ColorType::Rgb8,
+            image::ImageFormat::Png,
+        )
+        .map_err(|e| format!("Failed to save capture: {}", e))?;
+        Ok(path)
+    }
+
+    /// 描画した RGB のピクセル列と、ピクセルごとの（深度、法線）（`progress` が true なら進み具合をコンソールに表示）
+    ///
+    /// 1 本目はピクセルの中心、2 本目からは累積描画と同じくピクセル内の位置・影・アンビエントオクルージョンを
+    /// ずらしたレイで、その平均をピクセルの色にします。立体視では `stereo` の描き方で左右の目の絵を合成します。
+    /// 深度と法線は 1 本目のレイが最初に当たった面のもので、背景は `None` です（`save_aovs` の `hits` と同じ）。
+    fn pixels(
+        &self,
+        camera: &Camera,
+        fractal: &Fractal,
+        shading: &Shading,
+        time: f32,
+        stereo: &Stereo,
+        progress: bool,
+    ) -> (Vec<u8>, Vec<Option<(f32, Vec3)>>) {
+        let (width, height) = self.size;
+        let cone = PixelCone::perspective(height);
+        let mut pixels = vec![0u8; width * height * 3];
+        let mut hits = vec![None; width * height];
+
+        let bands = pixels
+            .chunks_mut(width * 3 * CAPTURE_BAND_ROWS)
+            .zip(hits.chunks_mut(width * CAPTURE_BAND_ROWS));
+        for (band, (rows, hit_rows)) in bands.enumerate() {
+            rows.par_chunks_mut(width * 3)
+                .zip(hit_rows.par_chunks_mut(width))
+                .enumerate()
+                .for_each(|(row, (line, hit_line))| {
+                    let y = band * CAPTURE_BAND_ROWS + row;
+                    for (x, (rgb, first_hit)) in line.chunks_mut(3).zip(hit_line).enumerate() {
+                        let mut sum = Vec3::ZERO;
+                        for sample in 1..=self.samples {
+                            let mut jitter = if sample == 1 {
+                                Jitter::none()
+                            } else {
+                                Jitter::new(x, y, sample)
+                            };
+                            let point = (
+                                x as f32 + 0.5 + jitter.offset(),
+                                y as f32 + 0.5 + jitter.offset(),
+                            );
+                            let (rgb, hit) = stereo.trace(camera, self.size, point, |eye, dir| {
+                                let sample =
+                                    ray_march(eye, dir, cone, fractal, shading, time, &mut jitter);
+                                (sample.rgb, sample.depth_hit(dir, camera.forward()))
+                            });
+                            if sample == 1 {
+                                *first_hit = hit;
+                            }
+                            sum += rgb;
+                        }
+                        let color = pack_rgb(sum / self.samples as f32);
+                        rgb.copy_from_slice(&[
+                            (color >> 16) as u8,
+                            (color >> 8) as u8,
+                            color as u8,
+                        ]);
+                    }
+                });
+
+            if !progress {
+                continue;
+            }
+            let done = ((band + 1) * CAPTURE_BAND_ROWS).min(height);
+            print!(
+                "\rCapturing {}x{} ({} samples): {:3}%",
+                width,
+                height,
+                self.samples,
+                done * 100 / height
+            );
+            let _ = std::io::Write::flush(&mut std::io::stdout());
+        }
+        (pixels, hits)
     }
 }
 
 // ==========================================
-// カメラ
+// 点群の書き出し
 // ==========================================
-struct Camera {
-    pos: Vec3,
-    rot_x: f32,
-    rot_y: f32,
+
+/// 点群の書き出しの設定
+struct PointCloud {
+    grid: usize, // 立方体の 1 面に通すレイの本数（1 辺あたり）
 }
 
-impl Camera {
+impl PointCloud {
+    /// `--point-cloud-grid <本数>` で指定した設定（省略時は既定値）
+    fn from_args(args: &Args) -> Result<Self, String> {
+        Ok(Self {
+            grid: cli::in_range(
+                args.point_cloud_grid,
+                POINT_CLOUD_GRID,
+                (2, MAX_POINT_CLOUD_GRID),
+                "点群のレイの本数",
+            )?,
+        })
+    }
+
+    /// 形の表面の点を集めて `dir` の `cpu_point_cloud.ply` に保存する（保存先と点の数を返す）
+    fn export(
+        &self,
+        fractal: &Fractal,
+        shading: &Shading,
+        time: f32,
+        dir: &Path,
+    ) -> Result<(PathBuf, usize), String> {
+        let start = Instant::now();
+        let points = self.points(fractal, shading, time);
+        println!(" ({:.1} s)", start.elapsed().as_secs_f32());
+
+        let _ = std::fs::create_dir_all(dir);
+        let path = dir.join("cpu_point_cloud.ply");
+        write_ply(&path, &points).map_err(|e| format!("Failed to save point cloud: {}", e))?;
+        Ok((path, points.len()))
+    }
+
+    /// 立方体の 6 面から内側へ平行なレイを通し、当たった点の（位置、法線、色）を集める
+    ///
+    /// 色は画面と同じ陰影付けで求めますが、レイの始点が画面のカメラではないため霧はかけません。
+    /// 始点が形の内側にあったレイ（切断面などで形が立方体からはみ出した場合）は面の上に点ができるため除きます。
+    fn points(&self, fractal: &Fractal, shading: &Shading, time: f32) -> Vec<(Vec3, Vec3, Vec3)> {
+        let no_fog = Fog {
+            density: 0.0,
+            ..*shading.fog
+        };
+        let shading = Shading {
+            fog: &no_fog,
+            ..*shading
+        };
+        let axes = [Vec3::X, Vec3::Y, Vec3::Z];
+        let mut points = Vec::new();
+
+        for face in 0..6 {
+            // 面の外向きの法線と、面の上の 2 つの軸
+            let axis = face / 2;
+            let outward = if face % 2 == 0 {
+                axes[axis]
+            } else {
+                -axes[axis]
+            };
+            let (tangent_u, tangent_v) = (axes[(axis + 1) % 3], axes[(axis + 2) % 3]);
+            let grid = self.grid;
+            let coord =
+                |i: usize| ((i as f32 + 0.5) / grid as f32 * 2.0 - 1.0) * POINT_CLOUD_EXTENT;
+            // 平行なレイなので、画素（格子の間隔）の大きさは距離によらない
+            let cone = PixelCone {
+                radius: 2.0 * POINT_CLOUD_EXTENT / grid as f32,
+                spread: 0.0,
+            };
+
+            points.par_extend((0..grid * grid).into_par_iter().filter_map(|index| {
+                let origin = outward * POINT_CLOUD_EXTENT
+                    + tangent_u * coord(index % grid)
+                    + tangent_v * coord(index / grid);
+                let ray_dir = -outward;
+                let sample = ray_march(
+                    origin,
+                    ray_dir,
+                    cone,
+                    fractal,
+                    &shading,
+                    time,
+                    &mut Jitter::none(),
+                );
+                let (t, normal) = sample.hit.filter(|&(t, _)| t > 0.0)?;
+                Some((origin + ray_dir * t, normal, sample.rgb))
+            }));
+
+            print!(
+                "\rExporting point cloud ({}x{} rays x 6 faces): {}/6",
+                grid,
+                grid,
+                face + 1
+            );
+            let _ = std::io::Write::flush(&mut std::io::stdout());
+        }
+        points
+    }
+}
+
+/// 点の（位置、法線、色）をバイナリの PLY で書き出す
+fn write_ply(path: &Path, points: &[(Vec3, Vec3, Vec3)]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    write!(
+        file,
+        "ply\nformat binary_little_endian 1.0\ncomment Mandelbulb 3D Explorer\nelement vertex {}\n",
+        points.len()
+    )?;
+    for name in ["x", "y", "z", "nx", "ny", "nz"] {
+        writeln!(file, "property float {}", name)?;
+    }
+    for name in ["red", "green", "blue"] {
+        writeln!(file, "property uchar {}", name)?;
+    }
+    writeln!(file, "end_header")?;
+
+    for (position, normal, rgb) in points {
+        for value in position.to_array().into_iter().chain(normal.to_array()) {
+            file.write_all(&value.to_le_bytes())?;
+        }
+        let rgb = (rgb.clamp(Vec3::ZERO, Vec3::ONE) * 255.0).round();
+        file.write_all(&[rgb.x as u8, rgb.y as u8, rgb.z as u8])?;
+    }
+    file.flush()
+}
+
+// ==========================================
+// キーフレームアニメーション
+// ==========================================
+
+/// `count` 枚のフレームを描画して連番画像に保存する（進み具合をコンソールに表示）
+///
+/// `frame(i)` が i 番目のフレームのカメラ・形・時刻、`path(i)` が保存先です。1 ピクセルに `samples` 本のレイで描画します。
+fn render_frames(
+    size: (usize, usize),
+    samples: u32,
+    count: usize,
+    shading: &Shading,
+    stereo: &Stereo,
+    frame: impl Fn(usize) -> (Camera, Fractal, f32),
+    path: impl Fn(usize) -> PathBuf,
+) -> Result<(), String> {
+    let (width, height) = size;
+    let renderer = Capture { size, samples };
+    let start = Instant::now();
+    for index in 0..count {
+        print!("\rRendering frame {}/{}", index + 1, count);
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+
+        let (camera, fractal, time) = frame(index);
+        let (pixels, _) = renderer.pixels(&camera, &fractal, shading, time, stereo, false);
+        let frame_path = path(index);
+        image::save_buffer_with_format(
+            &frame_path,
+            &pixels,
+            width as u32,
+            height as u32,
+            image::ColorType::Rgb8,
+            image::ImageFormat::Png,
+        )
+        .map_err(|e| format!("{}: {}", frame_path.display(), e))?;
+    }
+    println!(" ({:.1} s)", start.elapsed().as_secs_f32());
+    Ok(())
+}
+
+/// キーフレームの間を補間したフレームを連番画像に書き出す（書き出したフレーム数を返す）
+///
+/// カメラの位置・向き、フラクタルの種類、パワー以外（c・再帰の深さ・式・切断面・照明・質感）は今の設定を使います。
+fn render_animation(
+    options: &AnimationOptions,
+    samples: u32,
+    fractal: &Fractal,
+    shading: &Shading,
+    stereo: &Stereo,
+) -> Result<usize, String> {
+    let path = CameraPath::load(&options.keyframes)?;
+    let kinds = path
+        .keyframes
+        .iter()
+        .enumerate()
+        .map(|(i, k)| {
+            k.fractal
+                .parse::<FractalType>()
+                .map_err(|e| format!("{}: {}番目: {}", options.keyframes.display(), i + 1, e))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let frames = path.frames(options.fps);
+    std::fs::create_dir_all(&options.frames_dir)
+        .map_err(|e| format!("{}: {}", options.frames_dir.display(), e))?;
+
+    render_frames(
+        options.size,
+        samples,
+        frames.len(),
+        shading,
+        stereo,
+        |index| {
+            let frame = &frames[index];
+            let camera = Camera {
+                pos: frame.position,
+                orientation: frame.orientation,
+            };
+            let fractal = Fractal {
+                kind: kinds[frame.keyframe],
+                power: frame.power,
+                ..fractal.clone()
+            };
+            (camera, fractal, 0.0)
+        },
+        |index| options.frame_path(index),
+    )?;
+    Ok(frames.len())
+}
+
+/// 今のカメラのまま、マンデルバルブのパワーを少しずつ変えたフレームを連番画像に書き出す（書き出したフレーム数を返す）
+///
+/// 大きさとフレームレート（色相をずらす時刻の刻み）はキーフレームアニメーションと同じ設定を使います。
+fn render_morph(
+    morph: &PowerMorph,
+    options: &AnimationOptions,
+    samples: u32,
+    camera: &Camera,
+    fractal: &Fractal,
+    shading: &Shading,
+    stereo: &Stereo,
+) -> Result<usize, String> {
+    std::fs::create_dir_all(&morph.frames_dir)
+        .map_err(|e| format!("{}: {}", morph.frames_dir.display(), e))?;
+
+    render_frames(
+        options.size,
+        samples,
+        morph.frames,
+        shading,
+        stereo,
+        |index| {
+            let fractal = Fractal {
+                kind: FractalType::Mandelbulb,
+                power: morph.power(index),
+                ..fractal.clone()
+            };
+            (*camera, fractal, index as f32 / options.fps as f32)
+        },
+        |index| morph.frame_path(index),
+    )?;
+    Ok(morph.frames)
+}
+
+// ==========================================
+// カメラのプリセット
+// ==========================================
+
+/// 今のカメラ・形・霧・描画の設定のプリセット
+fn camera_preset(
+    camera: &Camera,
+    fractal: &Fractal,
+    fog: &Fog,
+    lights: &Lights,
+    material: &Material,
+) -> CameraPreset {
+    let viewer = [
+        (
+            "reflections",
+            serde_json::json!(fractal.quality.reflections),
+        ),
+        ("lights", serde_json::json!(lights.to_text())),
+        ("material", serde_json::json!(material.to_text())),
+    ];
+    CameraPreset {
+        fractal: fractal.kind.key().to_string(),
+        position: camera.pos,
+        orientation: camera.orientation,
+        power: fractal.power,
+        julia_c: fractal.julia_c,
+        depth: fractal.depth,
+        fog_density: fog.density,
+        fog_color: fog.color,
+        render: Some(RenderSettings {
+            max_steps: fractal.quality.max_steps as u32,
+            iterations: fractal.quality.iterations as u32,
+            bailout: fractal.quality.bailout,
+            coloring: fractal.coloring,
+            viewer: viewer
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        }),
+    }
+}
+
+/// プリセットのカメラ・形・霧・描画の設定に切り替える（式は今のものを使う。
+/// プリセットにない描画の設定は今のまま）
+fn apply_preset(
+    preset: &CameraPreset,
+    camera: &mut Camera,
+    fractal: &mut Fractal,
+    fog: &mut Fog,
+    lights: &mut Lights,
+    material: &mut Material,
+) -> Result<(), String> {
+    let kind = preset.fractal.parse()?;
+    // 照明・質感は読み込めたときだけ切り替える（途中まで切り替えて止まらないよう先に読む）
+    let viewer = preset.render.as_ref().map(|render| &render.viewer);
+    let text = |name: &str| viewer.and_then(|v| v.get(name)).and_then(|v| v.as_str());
+    let preset_lights = text("lights")
+        .map(Lights::parse)
+        .transpose()
+        .map_err(|e| format!("照明: {}", e))?;
+    let preset_material = text("material")
+        .map(Material::parse)
+        .transpose()
+        .map_err(|e| format!("質感: {}", e))?;
+
+    fractal.kind = kind;
+    fractal.power = preset.power.clamp(MIN_POWER, MAX_POWER);
+    fractal.julia_c = preset.julia_c;
+    fractal.depth = preset.depth.clamp(1, MAX_IFS_DEPTH);
+    *camera = Camera {
+        pos: preset.position,
+        orientation: preset.orientation,
+    };
+    *fog = Fog {
+        density: preset.fog_density.max(0.0),
+        color: preset.fog_color,
+    };
+    if let Some(render) = &preset.render {
+        let quality = &mut fractal.quality;
+        quality.max_steps = (render.max_steps as usize).clamp(STEPS_RANGE.0, STEPS_RANGE.1);
+        quality.iterations = (render.iterations as usize).clamp(ITER_RANGE.0, ITER_RANGE.1);
+        quality.bailout = render.bailout.clamp(BAILOUT_RANGE.0, BAILOUT_RANGE.1);
+        if let Some(reflections) = render.viewer.get("reflections").and_then(|v| v.as_u64()) {
+            quality.reflections = (reflections as usize).min(MAX_REFLECTIONS);
+        }
+        fractal.coloring = render.coloring;
+    }
+    if let Some(preset_lights) = preset_lights {
+        *lights = preset_lights;
+    }
+    if let Some(preset_material) = preset_material {
+        *material = preset_material;
+    }
+    Ok(())
+}
+
+// ==========================================
+// 画面上の表示（フラクタルの種類）
+// ==========================================
+
+/// 画面の左上に文字列を描画（背景を暗くした矩形の上に白で描く。`scale` は画面の拡大率）
+fn draw_label(buffer: &mut [u32], size: (usize, usize), scale: usize, text: &str) {
+    let dot = LABEL_DOT * scale;
+    let x0 = LABEL_MARGIN * scale;
+    let y0 = LABEL_MARGIN * scale;
+
+    // 背景（周囲に 2 ドットの余白）
+    let rect = (
+        x0.saturating_sub(2 * dot),
+        y0.saturating_sub(2 * dot),
+        x0 + text_width(text, dot) + 2 * dot,
+        y0 + (CHAR_HEIGHT + 2) * dot,
+    );
+    paint(buffer, size, rect, darken);
+    draw_text(buffer, size, (x0, y0), dot, text);
+}
+
+/// 文字列の幅（ピクセル。`dot` は 1 ドットの大きさ）
+fn text_width(text: &str, dot: usize) -> usize {
+    (text.chars().count() * CHAR_ADVANCE).saturating_sub(1) * dot
+}
+
+/// `origin` を左上にして文字列を白で描く（`dot` は 1 ドットの大きさ、ピクセル）
+fn draw_text(
+    buffer: &mut [u32],
+    size: (usize, usize),
+    origin: (usize, usize),
+    dot: usize,
+    text: &str,
+) {
+    let (x0, y0) = origin;
+    for (i, c) in text.chars().enumerate() {
+        let Some(glyph) = font::glyph(c) else {
+            continue;
+        };
+        let cx = x0 + i * CHAR_ADVANCE * dot;
+        for (row, bits) in glyph.iter().enumerate() {
+            for col in (0..5).filter(|col| (bits >> (4 - col)) & 1 == 1) {
+                let rect = (
+                    cx + col * dot,
+                    y0 + row * dot,
+                    cx + (col + 1) * dot,
+                    y0 + (row + 1) * dot,
+                );
+                paint(buffer, size, rect, |_| 0xFFFFFF);
+            }
+        }
+    }
+}
+
+/// 矩形（左上 (x0, y0) から右下 (x1, y1) の手前まで。画面からはみ出した部分は除く）の各ピクセルの色を `color` で変える
+fn paint(
+    buffer: &mut [u32],
+    size: (usize, usize),
+    rect: (usize, usize, usize, usize),
+    color: impl Fn(u32) -> u32,
+) {
+    let (width, height) = size;
+    let (x0, y0, x1, y1) = rect;
+    let (x0, x1) = (x0.min(width), x1.min(width));
+    for y in y0..y1.min(height) {
+        for pixel in &mut buffer[y * width + x0..y * width + x1.max(x0)] {
+            *pixel = color(*pixel);
+        }
+    }
+}
+
+/// 半分の明るさにした色（表示の背景）
+fn darken(color: u32) -> u32 {
+    (color >> 1) & 0x7F7F7F
+}
+
+// ==========================================
+// 描画の統計
+// ==========================================
+
+/// 1 フレームで画面から飛ばしたレイの統計
+#[derive(Clone, Copy, Default)]
+struct MarchStats {
+    rays: u64,      // レイの数（反射したレイは含まない）
+    steps: u64,     // 距離関数を評価したステップの数（反射したレイの分も含む）
+    exhausted: u64, // 形に当たる前に最大ステップ数を使い切ったレイの数
+}
+
+impl MarchStats {
+    fn add(&mut self, sample: &RaySample) {
+        self.rays += 1;
+        self.steps += sample.steps as u64;
+        self.exhausted += sample.exhausted as u64;
+    }
+
+    fn merge(self, other: Self) -> Self {
+        Self {
+            rays: self.rays + other.rays,
+            steps: self.steps + other.steps,
+            exhausted: self.exhausted + other.exhausted,
+        }
+    }
+}
+
+/// 画面の右上に重ねる描画の統計（F10 キーで表示を切り替え）
+///
+/// 直近のフレーム時間のグラフ（横線は 60 fps）、1 本のレイあたりのステップ数の平均、
+/// 最大ステップ数を使い切ったレイの割合を表示し、画質の設定を調整する目安にします。
+/// 使い切ったレイが多いときは最大ステップ数が足りずに形が欠けており、少ないのにステップ数の平均が大きいときは
+/// 表面をかすめるレイが多く、反復回数や脱出半径を下げると速くなります。
+struct StatsOverlay {
+    visible: bool,
+    frame_times: std::collections::VecDeque<f32>, // 直近のフレーム時間（ミリ秒、古い順）
+    march: MarchStats, // 最後にレイを飛ばしたフレームの統計（止まって累積を終えた後も表示し続ける）
+}
+
+impl StatsOverlay {
     fn new() -> Self {
         Self {
-            pos: Vec3::new(0.0, 0.0, -2.5),
-            rot_x: 0.0,
-            rot_y: 0.0,
+            visible: false,
+            frame_times: std::collections::VecDeque::with_capacity(STATS_HISTORY),
+            march: MarchStats::default(),
         }
     }
 
-    fn get_ray_dir(&self, uv: (f32, f32)) -> Vec3 {
-        let dir = Vec3::new(uv.0, uv.1, 1.0).normalize();
-        let rot = Mat3::from_rotation_y(self.rot_y) * Mat3::from_rotation_x(self.rot_x);
-        rot * dir
+    /// フレーム時間を記録する（古いものから捨てる）
+    fn record_frame(&mut self, ms: f32) {
+        if self.frame_times.len() == STATS_HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(ms);
     }
 
-    fn forward(&self) -> Vec3 {
-        let rot = Mat3::from_rotation_y(self.rot_y) * Mat3::from_rotation_x(self.rot_x);
-        rot * Vec3::new(0.0, 0.0, 1.0)
+    /// このフレームのレイの統計を記録する（レイを飛ばさなかったフレームでは前の値のまま）
+    fn record_march(&mut self, march: MarchStats) {
+        if march.rays > 0 {
+            self.march = march;
+        }
     }
 
-    fn right(&self) -> Vec3 {
-        let rot = Mat3::from_rotation_y(self.rot_y);
-        rot * Vec3::new(1.0, 0.0, 0.0)
+    /// 画面の右上に描く（`scale` は画面の拡大率）
+    fn draw(&self, buffer: &mut [u32], size: (usize, usize), scale: usize) {
+        if !self.visible {
+            return;
+        }
+        let dot = LABEL_DOT * scale;
+        let line_height = (CHAR_HEIGHT + 3) * dot;
+        let graph = (STATS_HISTORY * scale, STATS_GRAPH_HEIGHT * scale);
+
+        let last_ms = self.frame_times.back().copied().unwrap_or(0.0);
+        let MarchStats {
+            rays,
+            steps,
+            exhausted,
+        } = self.march;
+        let lines = [
+            format!("FRAME {:.1} MS", last_ms),
+            if rays > 0 {
+                format!("STEPS/RAY {:.1}", steps as f32 / rays as f32)
+            } else {
+                "STEPS/RAY -".to_string()
+            },
+            if rays > 0 {
+                format!("MAX STEPS {:.1}%", exhausted as f32 * 100.0 / rays as f32)
+            } else {
+                "MAX STEPS -".to_string()
+            },
+        ];
+
+        // 背景（周囲に 2 ドットの余白）
+        let panel_width = lines
+            .iter()
+            .map(|line| text_width(line, dot))
+            .fold(graph.0, usize::max);
+        let panel_height = line_height + graph.1 + dot + 2 * line_height;
+        let x0 = size.0.saturating_sub(LABEL_MARGIN * scale + panel_width);
+        let y0 = LABEL_MARGIN * scale;
+        let rect = (
+            x0.saturating_sub(2 * dot),
+            y0.saturating_sub(2 * dot),
+            x0 + panel_width + 2 * dot,
+            y0 + panel_height + dot,
+        );
+        paint(buffer, size, rect, darken);
+
+        // フレーム時間（新しいものを右端に。目標の時間を超えたものは黄、その 2 倍を超えたものは赤）
+        draw_text(buffer, size, (x0, y0), dot, &lines[0]);
+        let graph_top = y0 + line_height;
+        let graph_bottom = graph_top + graph.1;
+        let max_ms = self
+            .frame_times
+            .iter()
+            .fold(STATS_TARGET_MS * 2.0, |max, &ms| max.max(ms));
+        let graph_x0 = x0 + panel_width - graph.0;
+        let first_column = STATS_HISTORY - self.frame_times.len();
+        for (i, &ms) in self.frame_times.iter().enumerate() {
+            let x = graph_x0 + (first_column + i) * scale;
+            let bar = ((ms / max_ms * graph.1 as f32) as usize).clamp(1, graph.1);
+            let color = if ms <= STATS_TARGET_MS {
+                0x40D060
+            } else if ms <= STATS_TARGET_MS * 2.0 {
+                0xE0C040
+            } else {
+                0xE04040
+            };
+            paint(
+                buffer,
+                size,
+                (x, graph_bottom - bar, x + scale, graph_bottom),
+                |_| color,
+            );
+        }
+        let target_y = graph_bottom - (STATS_TARGET_MS / max_ms * graph.1 as f32) as usize;
+        paint(
+            buffer,
+            size,
+            (graph_x0, target_y, graph_x0 + graph.0, target_y + scale),
+            |_| 0x808080,
+        );
+
+        for (i, line) in lines[1..].iter().enumerate() {
+            let y = graph_bottom + dot + i * line_height + 2 * dot;
+            draw_text(buffer, size, (x0, y), dot, line);
+        }
     }
 }
 
 fn main() {
+    let args = Args::parse();
+    let start = StartOptions::from_args(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let scale = scale_factor(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let formula = formula(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let default_lights = lights(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let default_material = material(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let default_fog = Fog::from_args(&args.common).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let glow = Glow::from_args(&args.common).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let mut environment = Environment::from_args(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let mut stereo = Stereo::from_args(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let capture = Capture::from_args(&args, start.size).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let aa_samples = aa_samples(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let point_cloud = PointCloud::from_args(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let animation = AnimationOptions::from_args(&args.common, start.size).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let morph = PowerMorph::from_args(&args.common).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let mut presets = CameraPresets::from_args(&args.common).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let orbit_target = orbit::target_from_args(&args.common);
+    // 描画は物理ピクセルで行う。macOS の minifb はウィンドウを論理ピクセルで作り、バッファを引き伸ばして表示する
+    let (width, height) = (start.size.0 * scale, start.size.1 * scale);
+    let (window_width, window_height) = if cfg!(target_os = "macos") {
+        start.size
+    } else {
+        (width, height)
+    };
     let mut window = Window::new(
         "Mandelbulb 3D Explorer - Colorful Edition",
-        WIDTH,
-        HEIGHT,
+        window_width,
+        window_height,
         WindowOptions::default(),
     )
     .unwrap_or_else(|e| {
@@ -241,143 +2084,605 @@ fn main() {
 
     window.set_target_fps(60);
 
-    let mut buffer: Vec<u32> = vec![0; WIDTH * HEIGHT];
-    let mut camera = Camera::new();
-    let power = AtomicU32::new(2); // デフォルトパワー2（キー1）
+    let mut buffer: Vec<u32> = vec![0; width * height];
+    // 累積描画（サンプルの RGB の和と重ねた枚数、前のフレームのカメラ・形・照明・質感・霧）
+    let mut accum: Vec<Vec3> = vec![Vec3::ZERO; width * height];
+    // 深度と法線のパス（ずらさずに描いたレイの結果。背景は None）
+    let mut hits: Vec<Option<(f32, Vec3)>> = vec![None; width * height];
+    let mut samples: u32 = 0;
+    let mut last_scene = None;
+    let mut checker_parity = 0; // 操作中に描く市松模様の側（フレームごとに入れ替える）
+    let mut camera = start.kind.camera();
+    let mut fractal = Fractal {
+        kind: start.kind,
+        power: start.power,
+        formula,
+        quality: start.quality,
+        ..Fractal::new()
+    };
+    let mut lights = default_lights.clone();
+    let mut material = default_material;
+    let mut material_param = MaterialParam::SpecularExponent;
+    let mut fog = default_fog;
+    let mut orbit_mode = false;
+    let mut stats = StatsOverlay::new();
+    let mut drag_from: Option<(f32, f32)> = None; // 左ドラッグ中の前のフレームのマウスの位置
 
     println!("=== Mandelbulb 3D Explorer - Colorful Edition ===");
     println!("  Move: W/A/S/D + Space/Shift");
-    println!("  Look: Arrow Keys");
-    println!("  Power: 1-9 keys (changes shape complexity)");
+    println!("  Look: Arrow Keys (yaw/pitch), Q/E (roll)");
+    println!(
+        "  Orbit mode: Enter (drag / arrows to orbit, wheel / W/S to zoom, target {})",
+        orbit_target
+    );
+    println!(
+        "  Power: PageDown/PageUp (-/+ {}, changes shape complexity)",
+        POWER_STEP
+    );
+    println!(
+        "  Fractal: Tab (Mandelbulb / Quaternion Julia / Menger Sponge / Sierpinski Tetrahedron / Hybrid)"
+    );
+    println!("  Julia c: T/G, Y/H, U/J, I/K (real, i, j, k)");
+    println!("  Depth: [ / ] (Menger Sponge, Sierpinski Tetrahedron)");
+    println!("  Light: Z/X (azimuth), C/V (elevation), B (color)");
+    println!("  Cross-section: F (toggle), , / . (move plane), L (face camera)");
+    println!(
+        "  Quality: F1/F2 (ray-march steps), F3/F4 (iterations), F6/F7 (bailout), \\ (reflections)"
+    );
+    println!("  Render statistics: F10 (frame-time graph, steps per ray, rays out of steps)");
+    println!("  Coloring: / (blend / orbit trap / iteration / normal / position / clay)");
+    println!(
+        "  Stereo: ; (off / side-by-side / anaglyph, eye separation {})",
+        stereo.separation
+    );
+    println!("  Material: M (select), - / = (adjust), F5 (save)");
+    println!(
+        "  Screenshot: P (at least {} samples per pixel)",
+        aa_samples
+    );
+    println!(
+        "  High-quality capture: F12 ({}x{}, {} samples)",
+        capture.size.0, capture.size.1, capture.samples
+    );
+    println!(
+        "  Point cloud: F8 ({}x{} rays x 6 faces)",
+        point_cloud.grid, point_cloud.grid
+    );
+    println!(
+        "  Animation: N (record keyframe), O (render {}x{} at {} fps, {} samples, to {})",
+        animation.size.0,
+        animation.size.1,
+        animation.fps,
+        aa_samples,
+        animation.frames_dir.display()
+    );
+    println!(
+        "  Power morph: F9 (power {} to {}, {} frames to {})",
+        morph.powers.0,
+        morph.powers.1,
+        morph.frames,
+        morph.frames_dir.display()
+    );
+    println!(
+        "  Camera presets: Ctrl+1-9 (save), Alt+1-9 (recall) ({})",
+        presets.path.display()
+    );
     println!("  Reset: R");
+    println!("  Quit: Esc");
+    if scale > 1 {
+        println!("  Scale: x{} ({}x{} pixels)", scale, width, height);
+    }
 
-    while window.is_open() && !window.is_key_down(Key::Escape) && !window.is_key_down(Key::Q) {
+    while window.is_open() && !window.is_key_down(Key::Escape) {
         let frame_start = Instant::now();
         let time = 0.0; // アニメーション停止
 
         // --- 入力処理 ---
-        let move_speed = 0.05;
-        let rot_speed = 0.05;
 
         // スクリーンショット撮影
+        // 止まっている間に重ねたサンプルが `aa_samples` 枚あればその平均を、なければその本数のレイで描き直して保存する
+        // （どちらも画面左上の表示は含まない）。深度・法線のパスも色と同じ描画のものを書き出す
         if window.is_key_pressed(Key::P, minifb::KeyRepeat::No) {
-            let mut img_buf: Vec<u8> = Vec::with_capacity(WIDTH * HEIGHT * 3);
-            for pixel in &buffer {
-                let r = ((pixel >> 16) & 0xFF) as u8;
-                let g = ((pixel >> 8) & 0xFF) as u8;
-                let b = (pixel & 0xFF) as u8;
-                img_buf.push(r);
-                img_buf.push(g);
-                img_buf.push(b);
-            }
+            let shot_samples = samples.max(aa_samples);
+            let (img_buf, shot_hits) = if samples >= aa_samples {
+                let pixels = accum
+                    .iter()
+                    .flat_map(|sum| {
+                        let color = pack_rgb(*sum / samples as f32);
+                        [(color >> 16) as u8, (color >> 8) as u8, color as u8]
+                    })
+                    .collect();
+                (pixels, hits.clone())
+            } else {
+                let shading = Shading {
+                    fog: &fog,
+                    lights: &lights,
+                    material: &material,
+                    environment: &environment,
+                    glow: &glow,
+                };
+                let renderer = Capture {
+                    size: (width, height),
+                    samples: aa_samples,
+                };
+                renderer.pixels(&camera, &fractal, &shading, time, &stereo, false)
+            };
 
-            // assets ディレクトリが存在しない場合は作成
-            let _ = std::fs::create_dir_all("assets");
+            // 保存先のディレクトリが存在しない場合は作成
+            let _ = std::fs::create_dir_all(&start.screenshot_dir);
 
+            let path = start.screenshot_dir.join("cpu_screenshot.png");
             match image::save_buffer_with_format(
-                "assets/cpu_screenshot.png",
+                &path,
                 &img_buf,
-                WIDTH as u32,
-                HEIGHT as u32,
+                width as u32,
+                height as u32,
                 image::ColorType::Rgb8,
                 image::ImageFormat::Png,
             ) {
-                Ok(_) => println!("Screenshot saved to assets/cpu_screenshot.png"),
+                Ok(_) => println!(
+                    "Screenshot saved to {} ({} samples)",
+                    path.display(),
+                    shot_samples
+                ),
                 Err(e) => eprintln!("Failed to save screenshot: {}", e),
             }
+            match save_aovs(&shot_hits, width, height, &start.screenshot_dir) {
+                Ok(()) => println!(
+                    "Depth and normal passes saved to {}",
+                    start
+                        .screenshot_dir
+                        .join("cpu_screenshot_{depth,normal}.{exr,png}")
+                        .display()
+                ),
+                Err(e) => eprintln!("{}", e),
+            }
         }
 
-        if window.is_key_down(Key::W) {
-            camera.pos += camera.forward() * move_speed;
+        // 高画質キャプチャ（描画が終わるまでウィンドウは止まる）
+        if window.is_key_pressed(Key::F12, minifb::KeyRepeat::No) {
+            let shading = Shading {
+                fog: &fog,
+                lights: &lights,
+                material: &material,
+                environment: &environment,
+                glow: &glow,
+            };
+            match capture.render(
+                &camera,
+                &fractal,
+                &shading,
+                time,
+                &stereo,
+                &start.screenshot_dir,
+            ) {
+                Ok(path) => println!("Capture saved to {}", path.display()),
+                Err(e) => eprintln!("{}", e),
+            }
         }
-        if window.is_key_down(Key::S) {
-            camera.pos -= camera.forward() * move_speed;
+
+        // 表面の点群の書き出し（描画が終わるまでウィンドウは止まる）
+        if window.is_key_pressed(Key::F8, minifb::KeyRepeat::No) {
+            let shading = Shading {
+                fog: &fog,
+                lights: &lights,
+                material: &material,
+                environment: &environment,
+                glow: &glow,
+            };
+            match point_cloud.export(&fractal, &shading, time, &start.screenshot_dir) {
+                Ok((path, count)) => println!("{} points saved to {}", count, path.display()),
+                Err(e) => eprintln!("{}", e),
+            }
         }
-        if window.is_key_down(Key::A) {
-            camera.pos -= camera.right() * move_speed;
+
+        // キーフレームの記録と、アニメーション・モーフィングの書き出し（描画が終わるまでウィンドウは止まる）
+        if window.is_key_pressed(Key::N, minifb::KeyRepeat::No) {
+            let keyframe = CameraKeyframe {
+                fractal: fractal.kind.key().to_string(),
+                position: camera.pos,
+                orientation: camera.orientation,
+                power: fractal.power,
+                duration: DEFAULT_DURATION,
+            };
+            match CameraPath::append(&animation.keyframes, keyframe) {
+                Ok(count) => println!(
+                    "Keyframe {} saved to {}",
+                    count,
+                    animation.keyframes.display()
+                ),
+                Err(e) => eprintln!("{}", e),
+            }
         }
-        if window.is_key_down(Key::D) {
-            camera.pos += camera.right() * move_speed;
+        if window.is_key_pressed(Key::O, minifb::KeyRepeat::No) {
+            let shading = Shading {
+                fog: &fog,
+                lights: &lights,
+                material: &material,
+                environment: &environment,
+                glow: &glow,
+            };
+            match render_animation(&animation, aa_samples, &fractal, &shading, &stereo) {
+                Ok(count) => println!(
+                    "{} frames saved to {}",
+                    count,
+                    animation.frames_dir.display()
+                ),
+                Err(e) => eprintln!("{}", e),
+            }
         }
-        if window.is_key_down(Key::Space) {
-            camera.pos += Vec3::new(0.0, move_speed, 0.0);
+        if window.is_key_pressed(Key::F9, minifb::KeyRepeat::No) {
+            let shading = Shading {
+                fog: &fog,
+                lights: &lights,
+                material: &material,
+                environment: &environment,
+                glow: &glow,
+            };
+            match render_morph(
+                &morph, &animation, aa_samples, &camera, &fractal, &shading, &stereo,
+            ) {
+                Ok(count) => println!("{} frames saved to {}", count, morph.frames_dir.display()),
+                Err(e) => eprintln!("{}", e),
+            }
         }
-        if window.is_key_down(Key::LeftShift) {
-            camera.pos -= Vec3::new(0.0, move_speed, 0.0);
+
+        // カメラの操作（オービットモードでは注視点のまわりを回る）
+        if window.is_key_pressed(Key::Enter, minifb::KeyRepeat::No) {
+            orbit_mode = !orbit_mode;
+            println!(
+                "Camera: {}",
+                if orbit_mode { "orbit" } else { "free flight" }
+            );
+            if orbit_mode {
+                camera.face(orbit_target);
+            }
+        }
+        let mouse = window
+            .get_mouse_pos(MouseMode::Discard)
+            .filter(|_| window.get_mouse_down(MouseButton::Left));
+        let drag = match (drag_from, mouse) {
+            (Some(from), Some(to)) => (to.0 - from.0, to.1 - from.1),
+            _ => (0.0, 0.0),
+        };
+        drag_from = mouse;
+        // ホイールを上に回すと近づく（トラックパッドの大きな量は 1 段に丸める）
+        let wheel = window
+            .get_scroll_wheel()
+            .map_or(0.0, |(_, wheel)| wheel.clamp(-1.0, 1.0));
+        let held = |control| {
+            window.is_key_down(match control {
+                Control::Forward => Key::W,
+                Control::Backward => Key::S,
+                Control::Left => Key::A,
+                Control::Right => Key::D,
+                Control::Up => Key::Space,
+                Control::Down => Key::LeftShift,
+                Control::YawLeft => Key::Left,
+                Control::YawRight => Key::Right,
+                Control::PitchUp => Key::Up,
+                Control::PitchDown => Key::Down,
+                Control::RollLeft => Key::Q,
+                Control::RollRight => Key::E,
+            })
+        };
+        if orbit_mode {
+            camera.orbit(orbit_target, drag, wheel, held);
+        } else {
+            camera.fly(held);
+        }
+
+        // パワー変更（押し続けると繰り返す）
+        if window.is_key_pressed(Key::PageDown, minifb::KeyRepeat::Yes) {
+            fractal.shift_power(-POWER_STEP);
+        }
+        if window.is_key_pressed(Key::PageUp, minifb::KeyRepeat::Yes) {
+            fractal.shift_power(POWER_STEP);
+        }
+
+        // カメラのプリセット（Ctrl+数字キーで保存、Alt+数字キーで呼び出し）
+        let ctrl = window.is_key_down(Key::LeftCtrl) || window.is_key_down(Key::RightCtrl);
+        let alt = window.is_key_down(Key::LeftAlt) || window.is_key_down(Key::RightAlt);
+        let digit_keys = [
+            Key::Key1,
+            Key::Key2,
+            Key::Key3,
+            Key::Key4,
+            Key::Key5,
+            Key::Key6,
+            Key::Key7,
+            Key::Key8,
+            Key::Key9,
+        ];
+        for (index, key) in digit_keys.into_iter().enumerate() {
+            if !window.is_key_pressed(key, minifb::KeyRepeat::No) {
+                continue;
+            }
+            let slot = index + 1;
+            if ctrl {
+                match presets.store(
+                    slot,
+                    camera_preset(&camera, &fractal, &fog, &lights, &material),
+                ) {
+                    Ok(()) => println!("Preset {} saved to {}", slot, presets.path.display()),
+                    Err(e) => eprintln!("{}", e),
+                }
+            } else if alt {
+                match presets.get(slot) {
+                    Some(preset) => match apply_preset(
+                        preset,
+                        &mut camera,
+                        &mut fractal,
+                        &mut fog,
+                        &mut lights,
+                        &mut material,
+                    ) {
+                        Ok(()) => println!("Preset {} recalled", slot),
+                        Err(e) => eprintln!("Preset {}: {}", slot, e),
+                    },
+                    None => println!("Preset {} is empty", slot),
+                }
+            }
         }
 
-        if window.is_key_down(Key::Left) {
-            camera.rot_y -= rot_speed;
+        // フラクタルの切り替え（カメラはその形の初期位置に戻す）・再帰の深さ・四元数ジュリア集合の c
+        if window.is_key_pressed(Key::Tab, minifb::KeyRepeat::No) {
+            fractal.kind = fractal.kind.next();
+            camera = fractal.kind.camera();
         }
-        if window.is_key_down(Key::Right) {
-            camera.rot_y += rot_speed;
+        if window.is_key_pressed(Key::LeftBracket, minifb::KeyRepeat::No) {
+            fractal.depth = (fractal.depth - 1).max(1);
         }
-        if window.is_key_down(Key::Up) {
-            camera.rot_x -= rot_speed;
+        if window.is_key_pressed(Key::RightBracket, minifb::KeyRepeat::No) {
+            fractal.depth = (fractal.depth + 1).min(MAX_IFS_DEPTH);
         }
-        if window.is_key_down(Key::Down) {
-            camera.rot_x += rot_speed;
+        let c_keys = [
+            (Key::T, Key::G, Vec4::X),
+            (Key::Y, Key::H, Vec4::Y),
+            (Key::U, Key::J, Vec4::Z),
+            (Key::I, Key::K, Vec4::W),
+        ];
+        for (inc, dec, axis) in c_keys {
+            if window.is_key_down(inc) {
+                fractal.julia_c += axis * JULIA_C_STEP;
+            }
+            if window.is_key_down(dec) {
+                fractal.julia_c -= axis * JULIA_C_STEP;
+            }
         }
 
-        // パワー変更
-        if window.is_key_pressed(Key::Key1, minifb::KeyRepeat::No) {
-            power.store(2, Ordering::Relaxed);
+        // 主光源
+        if window.is_key_down(Key::Z) {
+            lights.rotate_main(-LIGHT_ROT_SPEED, 0.0);
         }
-        if window.is_key_pressed(Key::Key2, minifb::KeyRepeat::No) {
-            power.store(3, Ordering::Relaxed);
+        if window.is_key_down(Key::X) {
+            lights.rotate_main(LIGHT_ROT_SPEED, 0.0);
         }
-        if window.is_key_pressed(Key::Key3, minifb::KeyRepeat::No) {
-            power.store(4, Ordering::Relaxed);
+        if window.is_key_down(Key::C) {
+            lights.rotate_main(0.0, -LIGHT_ROT_SPEED);
         }
-        if window.is_key_pressed(Key::Key4, minifb::KeyRepeat::No) {
-            power.store(5, Ordering::Relaxed);
+        if window.is_key_down(Key::V) {
+            lights.rotate_main(0.0, LIGHT_ROT_SPEED);
         }
-        if window.is_key_pressed(Key::Key5, minifb::KeyRepeat::No) {
-            power.store(6, Ordering::Relaxed);
+        if window.is_key_pressed(Key::B, minifb::KeyRepeat::No) {
+            lights.next_main_color();
         }
-        if window.is_key_pressed(Key::Key6, minifb::KeyRepeat::No) {
-            power.store(7, Ordering::Relaxed);
+
+        // 色の付け方
+        if window.is_key_pressed(Key::Slash, minifb::KeyRepeat::No) {
+            fractal.coloring = fractal.coloring.next();
         }
-        if window.is_key_pressed(Key::Key7, minifb::KeyRepeat::No) {
-            power.store(8, Ordering::Relaxed);
+
+        // 断面表示
+        if window.is_key_pressed(Key::F, minifb::KeyRepeat::No) {
+            fractal.clip.enabled = !fractal.clip.enabled;
+        }
+        if window.is_key_down(Key::Comma) {
+            fractal.clip.shift(-CLIP_STEP);
+        }
+        if window.is_key_down(Key::Period) {
+            fractal.clip.shift(CLIP_STEP);
+        }
+        if window.is_key_pressed(Key::L, minifb::KeyRepeat::No) {
+            fractal.clip.face(&camera);
+        }
+
+        // 画質（押し続けると繰り返す）
+        if window.is_key_pressed(Key::F1, minifb::KeyRepeat::Yes) {
+            fractal.quality.adjust_steps(-1);
+        }
+        if window.is_key_pressed(Key::F2, minifb::KeyRepeat::Yes) {
+            fractal.quality.adjust_steps(1);
+        }
+        if window.is_key_pressed(Key::F3, minifb::KeyRepeat::Yes) {
+            fractal.quality.adjust_iterations(-1);
+        }
+        if window.is_key_pressed(Key::F4, minifb::KeyRepeat::Yes) {
+            fractal.quality.adjust_iterations(1);
+        }
+        if window.is_key_pressed(Key::F6, minifb::KeyRepeat::Yes) {
+            fractal.quality.adjust_bailout(-1);
+        }
+        if window.is_key_pressed(Key::F7, minifb::KeyRepeat::Yes) {
+            fractal.quality.adjust_bailout(1);
+        }
+        if window.is_key_pressed(Key::Backslash, minifb::KeyRepeat::No) {
+            fractal.quality.next_reflections();
+        }
+        if window.is_key_pressed(Key::Semicolon, minifb::KeyRepeat::No) {
+            stereo.next_mode();
+        }
+        if window.is_key_pressed(Key::F10, minifb::KeyRepeat::No) {
+            stats.visible = !stats.visible;
         }
-        if window.is_key_pressed(Key::Key8, minifb::KeyRepeat::No) {
-            power.store(9, Ordering::Relaxed);
+
+        // 質感
+        if window.is_key_pressed(Key::M, minifb::KeyRepeat::No) {
+            material_param = material_param.next();
+        }
+        if window.is_key_down(Key::Minus) {
+            material.adjust(material_param, -1.0);
         }
-        if window.is_key_pressed(Key::Key9, minifb::KeyRepeat::No) {
-            power.store(12, Ordering::Relaxed);
+        if window.is_key_down(Key::Equal) {
+            material.adjust(material_param, 1.0);
+        }
+        if window.is_key_pressed(Key::F5, minifb::KeyRepeat::No) {
+            let path = material_path(&args);
+            match material.save(&path) {
+                Ok(()) => println!("Material saved to {}", path),
+                Err(e) => eprintln!("{}", e),
+            }
         }
 
         if window.is_key_pressed(Key::R, minifb::KeyRepeat::No) {
-            camera = Camera::new();
-            power.store(2, Ordering::Relaxed);
+            camera = fractal.kind.camera();
+            lights = default_lights.clone();
+            material = default_material;
+            fog = default_fog;
+            fractal = Fractal {
+                kind: fractal.kind,
+                formula: std::mem::take(&mut fractal.formula),
+                power: start.power,
+                quality: fractal.quality,
+                coloring: fractal.coloring,
+                ..Fractal::new()
+            };
         }
 
-        let current_power = power.load(Ordering::Relaxed) as f32;
+        // 空の太陽を主光源に合わせる
+        if let Some(main) = lights.sources.first() {
+            environment.follow_sun(main.illuminate(Vec3::ZERO).0, main.color);
+        }
+
+        // 前のフレームから何も変わっていなければサンプルを重ね、変わっていれば市松模様の半分だけ描く
+        let scene = (
+            camera,
+            fractal.clone(),
+            lights.clone(),
+            material,
+            fog,
+            stereo,
+        );
+        let moving = last_scene.as_ref().is_some_and(|last| *last != scene);
+        let previous_samples = samples.max(1);
+        if moving {
+            samples = 0;
+            checker_parity ^= 1;
+        }
+        last_scene = Some(scene);
+        let render = moving || samples < ACCUM_MAX_SAMPLES;
+        if !moving && render {
+            samples += 1;
+        }
 
         // --- 並列レンダリング ---
-        buffer
-            .par_chunks_mut(WIDTH)
+        let shading = Shading {
+            fog: &fog,
+            lights: &lights,
+            material: &material,
+            environment: &environment,
+            glow: &glow,
+        };
+        let cone = PixelCone::perspective(height);
+        // 色と、形に当たった場合の（カメラの前方向に沿った深度、法線）。飛ばしたレイは `march` に数える
+        let trace = |x: usize, y: usize, jitter: &mut Jitter, march: &mut MarchStats| {
+            let point = (x as f32 + jitter.offset(), y as f32 + jitter.offset());
+            stereo.trace(&camera, (width, height), point, |eye, dir| {
+                let sample = ray_march(eye, dir, cone, &fractal, &shading, time, jitter);
+                march.add(&sample);
+                (sample.rgb, sample.depth_hit(dir, camera.forward()))
+            })
+        };
+        let march = buffer
+            .par_chunks_mut(width)
+            .zip(accum.par_chunks_mut(width))
+            .zip(hits.par_chunks_mut(width))
             .enumerate()
-            .for_each(|(y, row)| {
-                let v = -((y as f32 / HEIGHT as f32) * 2.0 - 1.0);
-
-                for (x, pixel) in row.iter_mut().enumerate() {
-                    let u = (x as f32 / WIDTH as f32) * 2.0 - 1.0;
-                    let aspect = WIDTH as f32 / HEIGHT as f32;
-                    let u = u * aspect;
-
-                    let ray_dir = camera.get_ray_dir((u, v));
-                    *pixel = ray_march(camera.pos, ray_dir, current_power, time);
+            .map(|(y, ((row, accum_row), hit_row))| {
+                let mut march = MarchStats::default();
+                let pixels = row.iter_mut().zip(accum_row.iter_mut()).zip(hit_row);
+                for (x, ((pixel, sum), hit)) in pixels.enumerate() {
+                    if moving {
+                        // 半分のピクセルだけ描き、残りは前のフレームの色（重ねていた場合は平均）を使う
+                        if (x + y) % 2 == checker_parity {
+                            (*sum, *hit) = trace(x, y, &mut Jitter::none(), &mut march);
+                        } else {
+                            *sum /= previous_samples as f32;
+                        }
+                    } else if render {
+                        // 1 枚目は操作中と同じレイ、2 枚目からはピクセルの幅の範囲でずらしたレイ
+                        let mut jitter = if samples == 1 {
+                            Jitter::none()
+                        } else {
+                            Jitter::new(x, y, samples)
+                        };
+                        let (rgb, first_hit) = trace(x, y, &mut jitter, &mut march);
+                        if samples == 1 {
+                            (*sum, *hit) = (rgb, first_hit);
+                        } else {
+                            *sum += rgb;
+                        }
+                    }
+                    *pixel = pack_rgb(*sum / samples.max(1) as f32);
                 }
-            });
+                march
+            })
+            .reduce(MarchStats::default, MarchStats::merge);
+        stats.record_march(march);
 
-        window.update_with_buffer(&buffer, WIDTH, HEIGHT).unwrap();
+        draw_label(&mut buffer, (width, height), scale, fractal.kind.name());
+        stats.draw(&mut buffer, (width, height), scale);
+
+        window.update_with_buffer(&buffer, width, height).unwrap();
 
         let elapsed = frame_start.elapsed();
+        stats.record_frame(elapsed.as_secs_f32() * 1000.0);
+        let shape = match fractal.kind {
+            FractalType::Mandelbulb => format!("Mandelbulb 3D (Power={:.1})", fractal.power),
+            FractalType::QuaternionJulia => {
+                let c = fractal.julia_c;
+                format!(
+                    "Quaternion Julia (c={:.3}{:+.3}i{:+.3}j{:+.3}k)",
+                    c.x, c.y, c.z, c.w
+                )
+            }
+            FractalType::MengerSponge => format!("Menger Sponge (Depth={})", fractal.depth),
+            FractalType::SierpinskiTetrahedron => {
+                format!("Sierpinski Tetrahedron (Depth={})", fractal.depth)
+            }
+            FractalType::Hybrid => format!("Hybrid ({} ops)", fractal.formula.ops.len()),
+        };
+        let shape = if fractal.clip.enabled {
+            format!("{} [Clip={:+.2}]", shape, fractal.clip.offset)
+        } else {
+            shape
+        };
+        let shape = if orbit_mode {
+            format!("{} [Orbit]", shape)
+        } else {
+            shape
+        };
+        let shape = if stereo.mode != stereo::StereoMode::Off {
+            format!("{} [Stereo={}]", shape, stereo.mode.name())
+        } else {
+            shape
+        };
+        let quality = if moving {
+            "interleaved".to_string()
+        } else {
+            format!("{} samples", samples)
+        };
         window.set_title(&format!(
-            "Mandelbulb 3D (Power={}) - {:.1} ms ({:.1} fps)",
-            current_power as i32,
+            "{} - steps={} iter={} bailout={:.1} refl={} - color={} - {}={:.2} - {} - {:.1} ms ({:.1} fps)",
+            shape,
+            fractal.quality.max_steps,
+            fractal.quality.iterations,
+            fractal.quality.bailout,
+            fractal.quality.reflections,
+            fractal.coloring.name(),
+            material_param.name(),
+            material.get(material_param),
+            quality,
             elapsed.as_secs_f32() * 1000.0,
             1.0 / elapsed.as_secs_f32().max(0.001)
         ));
@@ -0,0 +1,203 @@
+//! 質感（ハイライト・彩度・明るさ・色相の混ぜ方）
+//!
+//! 陰影付けで使う係数をまとめたもので、M キーで選んだ値を -/= キーで変えられます。
+//! F5 キーで `--material <ファイル>`（省略時は `material.txt`）に保存し、次回の起動時に同じ指定で読み込めます。
+//! ファイルは 1 行に `<名前> <値>` を 1 つずつ書きます（`#` 以降はコメント、書かなかった値は既定値）。
+//!
+//! ```text
+//! specular_exponent 64
+//! specular_strength 0.3
+//! hue_normal 0.5
+//! ```
+
+use crate::config_lines::{self, ConfigLine};
+use std::fmt::Write as _;
+
+/// 質感の値の種類
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MaterialParam {
+    /// ハイライトの鋭さ（鏡面反射の指数）
+    SpecularExponent,
+    /// ハイライトの強さ
+    SpecularStrength,
+    /// 彩度
+    Saturation,
+    /// 影になった（AO で暗くなった）部分で彩度を上げる量
+    AoSaturation,
+    /// 明るさの倍率
+    Brightness,
+    /// 色相に反復回数を混ぜる重み
+    HueIteration,
+    /// 色相に法線の向きを混ぜる重み
+    HueNormal,
+    /// 色相にオービットトラップを混ぜる重み
+    HueTrap,
+    /// 色相に位置を混ぜる重み
+    HuePosition,
+}
+
+impl MaterialParam {
+    pub const ALL: [MaterialParam; 9] = [
+        MaterialParam::SpecularExponent,
+        MaterialParam::SpecularStrength,
+        MaterialParam::Saturation,
+        MaterialParam::AoSaturation,
+        MaterialParam::Brightness,
+        MaterialParam::HueIteration,
+        MaterialParam::HueNormal,
+        MaterialParam::HueTrap,
+        MaterialParam::HuePosition,
+    ];
+
+    /// ファイルと画面で使う名前
+    pub fn name(self) -> &'static str {
+        match self {
+            MaterialParam::SpecularExponent => "specular_exponent",
+            MaterialParam::SpecularStrength => "specular_strength",
+            MaterialParam::Saturation => "saturation",
+            MaterialParam::AoSaturation => "ao_saturation",
+            MaterialParam::Brightness => "brightness",
+            MaterialParam::HueIteration => "hue_iteration",
+            MaterialParam::HueNormal => "hue_normal",
+            MaterialParam::HueTrap => "hue_trap",
+            MaterialParam::HuePosition => "hue_position",
+        }
+    }
+
+    /// 値の範囲（最小、最大）と 1 フレームで変える量
+    fn range(self) -> (f32, f32, f32) {
+        match self {
+            MaterialParam::SpecularExponent => (1.0, 256.0, 1.0),
+            MaterialParam::SpecularStrength => (0.0, 2.0, 0.02),
+            MaterialParam::Saturation | MaterialParam::AoSaturation => (0.0, 1.0, 0.01),
+            MaterialParam::Brightness => (0.0, 3.0, 0.02),
+            MaterialParam::HueIteration
+            | MaterialParam::HueNormal
+            | MaterialParam::HueTrap
+            | MaterialParam::HuePosition => (0.0, 2.0, 0.01),
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&p| p == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// 質感
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Material {
+    pub specular_exponent: f32,
+    pub specular_strength: f32,
+    pub saturation: f32,
+    pub ao_saturation: f32,
+    pub brightness: f32,
+    pub hue_iteration: f32,
+    pub hue_normal: f32,
+    pub hue_trap: f32,
+    pub hue_position: f32,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            specular_exponent: 32.0,
+            specular_strength: 0.5,
+            saturation: 0.8,
+            ao_saturation: 0.2,
+            brightness: 1.0,
+            hue_iteration: 0.4,
+            hue_normal: 0.2,
+            hue_trap: 0.2,
+            hue_position: 0.2,
+        }
+    }
+}
+
+impl Material {
+    pub fn get(&self, param: MaterialParam) -> f32 {
+        match param {
+            MaterialParam::SpecularExponent => self.specular_exponent,
+            MaterialParam::SpecularStrength => self.specular_strength,
+            MaterialParam::Saturation => self.saturation,
+            MaterialParam::AoSaturation => self.ao_saturation,
+            MaterialParam::Brightness => self.brightness,
+            MaterialParam::HueIteration => self.hue_iteration,
+            MaterialParam::HueNormal => self.hue_normal,
+            MaterialParam::HueTrap => self.hue_trap,
+            MaterialParam::HuePosition => self.hue_position,
+        }
+    }
+
+    fn get_mut(&mut self, param: MaterialParam) -> &mut f32 {
+        match param {
+            MaterialParam::SpecularExponent => &mut self.specular_exponent,
+            MaterialParam::SpecularStrength => &mut self.specular_strength,
+            MaterialParam::Saturation => &mut self.saturation,
+            MaterialParam::AoSaturation => &mut self.ao_saturation,
+            MaterialParam::Brightness => &mut self.brightness,
+            MaterialParam::HueIteration => &mut self.hue_iteration,
+            MaterialParam::HueNormal => &mut self.hue_normal,
+            MaterialParam::HueTrap => &mut self.hue_trap,
+            MaterialParam::HuePosition => &mut self.hue_position,
+        }
+    }
+
+    /// 値を `steps` 段階だけ変える（範囲内に収める）
+    pub fn adjust(&mut self, param: MaterialParam, steps: f32) {
+        let (min, max, step) = param.range();
+        let value = self.get_mut(param);
+        *value = (*value + step * steps).clamp(min, max);
+    }
+
+    /// ファイルから読み込む
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("質感のファイルを読み込めません: {}: {}", path, e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// テキストから読み込む
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut material = Material::default();
+        config_lines::parse(text, |line| material.parse_line(line))?;
+        Ok(material)
+    }
+
+    fn parse_line(&mut self, line: ConfigLine) -> Result<(), String> {
+        let name = line.name;
+        let param = MaterialParam::ALL
+            .into_iter()
+            .find(|p| p.name() == name)
+            .ok_or_else(|| format!("不明な値です: {}", name))?;
+        line.expect(&[1])?;
+        let value = line.args[0];
+        let (min, max, _) = param.range();
+        if !(min..=max).contains(&value) {
+            return Err(format!(
+                "{} は {}〜{} にしてください: {}",
+                name, min, max, value
+            ));
+        }
+        *self.get_mut(param) = value;
+        Ok(())
+    }
+
+    /// `parse` で読み込めるテキスト
+    pub fn to_text(self) -> String {
+        let mut text = String::new();
+        for param in MaterialParam::ALL {
+            // 少しずつ変えた値の誤差（0.52000004 など）を丸める
+            let value = (self.get(param) * 1000.0).round() / 1000.0;
+            let _ = writeln!(text, "{} {}", param.name(), value);
+        }
+        text
+    }
+
+    /// ファイルに保存する（`load` で読み込める形式）
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let text = format!("# Mandelbulb 3D Explorer の質感\n{}", self.to_text());
+        std::fs::write(path, text)
+            .map_err(|e| format!("質感のファイルを保存できません: {}: {}", path, e))
+    }
+}
@@ -0,0 +1,126 @@
+//! 立体視（左右の目の位置から描いた 2 枚の合成）
+//!
+//! `--stereo <off|side-by-side|anaglyph>`（既定: off）と `;` キーで描き方を選びます。
+//!   - `side-by-side`: 画像の左半分に左目、右半分に右目から見た絵を並べる（VR 動画向け。各目の縦横比は半分の幅で決まる）
+//!   - `anaglyph`: 左目の明るさを赤、右目の色を緑と青に入れた赤青メガネ用の 1 枚（ハーフカラー。色の競合を抑える）
+//!
+//! 左右の目はカメラの位置から右方向に ∓ 目の間隔の半分だけずらし、向きはカメラと同じ（平行法）にします。
+//! 目の間隔は `--eye-separation <距離>`（既定: `EYE_SEPARATION`）で、形との距離の 1/30 ほどが見やすい目安です。
+//! 画面の描画・スクリーンショット・高画質キャプチャ・アニメーションの書き出しのすべてに同じ描き方を使います。
+
+use crate::Args;
+use glam::Vec3;
+use mandelbulb_3d_common::camera::Camera;
+
+/// 目の間隔の既定値（初期のカメラと原点の距離 2.5 の 1/30 ほど）
+pub const EYE_SEPARATION: f32 = 0.08;
+
+/// 赤青の合成で左目の色から赤に入れる明るさの重み（ITU-R BT.601）
+const LUMA: Vec3 = Vec3::new(0.299, 0.587, 0.114);
+
+/// 立体視の描き方
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StereoMode {
+    Off,
+    SideBySide,
+    Anaglyph,
+}
+
+impl StereoMode {
+    /// タイトルバーに表示する名前
+    pub fn name(self) -> &'static str {
+        match self {
+            StereoMode::Off => "off",
+            StereoMode::SideBySide => "side-by-side",
+            StereoMode::Anaglyph => "anaglyph",
+        }
+    }
+}
+
+impl std::str::FromStr for StereoMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "off" => Ok(StereoMode::Off),
+            "side-by-side" => Ok(StereoMode::SideBySide),
+            "anaglyph" => Ok(StereoMode::Anaglyph),
+            _ => Err(format!(
+                "立体視の指定が不正です（off / side-by-side / anaglyph）: {}",
+                s
+            )),
+        }
+    }
+}
+
+/// 立体視の設定
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stereo {
+    pub mode: StereoMode,
+    pub separation: f32, // 左右の目の間隔
+}
+
+impl Stereo {
+    /// `--stereo <off|side-by-side|anaglyph>`・`--eye-separation <距離>` で指定した設定（省略時は立体視なし）
+    pub fn from_args(args: &Args) -> Result<Self, String> {
+        let separation = match args.eye_separation {
+            Some(d) if !(d.is_finite() && d > 0.0) => {
+                return Err(format!("目の間隔には正の数値が必要です: {}", d))
+            }
+            Some(d) => d,
+            None => EYE_SEPARATION,
+        };
+        Ok(Self {
+            mode: args.stereo.unwrap_or(StereoMode::Off),
+            separation,
+        })
+    }
+
+    /// 描き方を なし → 横に並べる → 赤青 → なし の順に切り替える
+    pub fn next_mode(&mut self) {
+        self.mode = match self.mode {
+            StereoMode::Off => StereoMode::SideBySide,
+            StereoMode::SideBySide => StereoMode::Anaglyph,
+            StereoMode::Anaglyph => StereoMode::Off,
+        };
+    }
+
+    /// 大きさ `size` の画像の点 `point`（ピクセル単位、左上が原点）に写る色
+    ///
+    /// `march(eye, dir)` は目の位置 `eye` から向き `dir` に飛ばしたレイの色と、その他の結果（深度など）を返します。
+    /// 赤青の合成では両目のレイを飛ばし、色以外の結果は左目のものを使います。
+    pub fn trace<T>(
+        &self,
+        camera: &Camera,
+        size: (usize, usize),
+        point: (f32, f32),
+        mut march: impl FnMut(Vec3, Vec3) -> (Vec3, T),
+    ) -> (Vec3, T) {
+        let (width, height) = (size.0 as f32, size.1 as f32);
+        let v = -((point.1 / height) * 2.0 - 1.0);
+        let eye = |side: f32| camera.pos + camera.right() * (side * self.separation * 0.5);
+        match self.mode {
+            StereoMode::Off => {
+                let u = (point.0 / width) * 2.0 - 1.0;
+                march(camera.pos, camera.get_ray_dir((u * width / height, v)))
+            }
+            StereoMode::SideBySide => {
+                let half = width * 0.5;
+                let (side, x) = if point.0 < half {
+                    (-1.0, point.0)
+                } else {
+                    (1.0, point.0 - half)
+                };
+                let u = (x / half) * 2.0 - 1.0;
+                march(eye(side), camera.get_ray_dir((u * half / height, v)))
+            }
+            StereoMode::Anaglyph => {
+                let u = (point.0 / width) * 2.0 - 1.0;
+                let dir = camera.get_ray_dir((u * width / height, v));
+                let (left, result) = march(eye(-1.0), dir);
+                let (right, _) = march(eye(1.0), dir);
+                (Vec3::new(left.dot(LUMA), right.y, right.z), result)
+            }
+        }
+    }
+}
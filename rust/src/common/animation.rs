@@ -0,0 +1,614 @@
+//! ズームアニメーション（キーフレームパスを含む）の連番画像・動画出力
+
+use super::cli::arg_value;
+use super::colors::Palette;
+use super::constants::{INITIAL_PRECISION, MAX_ITER, MAX_PRECISION};
+use super::video::VideoEncoder;
+use image::{ImageBuffer, Rgb};
+use rug::Float;
+use std::path::{Path, PathBuf};
+
+/// 既定のフレーム数
+pub const DEFAULT_FRAMES: usize = 120;
+
+/// 既定の出力ディレクトリ
+pub const DEFAULT_FRAMES_DIR: &str = "zoom_frames";
+
+/// 動画の既定のフレームレート
+pub const DEFAULT_FPS: u32 = 30;
+
+/// キーフレームファイルの既定のパス
+pub const DEFAULT_KEYFRAMES_FILE: &str = "keyframes.json";
+
+/// 次のキーフレームまでの既定のフレーム数
+pub const DEFAULT_SEGMENT_FRAMES: usize = 60;
+
+/// 初期表示の幅と高さ（ビューアの初期表示 -2.5〜1.0, -1.5〜1.5 と同じ）
+pub const INITIAL_WIDTH: f64 = 3.5;
+pub const INITIAL_HEIGHT: f64 = 3.0;
+
+/// 表示範囲（中心は任意精度、幅と高さは f64）
+///
+/// `rotation` だけ中心のまわりに反時計回りに回転した範囲を、画面では回転を戻して表示します。
+#[derive(Clone)]
+pub struct View {
+    pub center_x: Float,
+    pub center_y: Float,
+    pub width: f64,
+    pub height: f64,
+    /// 回転角（度、反時計回り）
+    pub rotation: f64,
+}
+
+impl View {
+    /// 初期表示
+    pub fn initial(precision: u32) -> Self {
+        Self {
+            center_x: Float::with_val(precision, -0.75),
+            center_y: Float::with_val(precision, 0.0),
+            width: INITIAL_WIDTH,
+            height: INITIAL_HEIGHT,
+            rotation: 0.0,
+        }
+    }
+
+    /// `<実部>,<虚部>,<ズーム倍率>` 形式の文字列から表示範囲を作成
+    ///
+    /// 中心座標は10進文字列のまま任意精度で読み込むため、f64 を超える桁数も指定できます。
+    /// 精度はズーム倍率から自動で決めます。
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = spec.split(',').map(str::trim).collect();
+        let [re, im, zoom] = fields[..] else {
+            return Err(format!(
+                "<実部>,<虚部>,<ズーム倍率> の形式で指定してください: {}",
+                spec
+            ));
+        };
+
+        let zoom: f64 = zoom
+            .parse()
+            .map_err(|_| format!("ズーム倍率が不正です: {}", zoom))?;
+        Self::from_decimal(re, im, zoom)
+    }
+
+    /// 10進文字列の中心座標とズーム倍率から表示範囲を作成
+    pub fn from_decimal(re: &str, im: &str, zoom: f64) -> Result<Self, String> {
+        if !(zoom.is_finite() && zoom > 0.0) {
+            return Err(format!("ズーム倍率が不正です: {}", zoom));
+        }
+
+        // ビューアの精度自動拡張と同じ基準
+        let precision = ((zoom.max(1.0).log2() * 3.5) as u32 + 64)
+            .next_power_of_two()
+            .clamp(INITIAL_PRECISION, MAX_PRECISION);
+        let parse_float = |s: &str| {
+            Float::parse(s)
+                .map(|p| Float::with_val(precision, p))
+                .map_err(|_| format!("数値ではありません: {}", s))
+        };
+
+        Ok(Self {
+            center_x: parse_float(re)?,
+            center_y: parse_float(im)?,
+            width: INITIAL_WIDTH / zoom,
+            height: INITIAL_HEIGHT / zoom,
+            rotation: 0.0,
+        })
+    }
+
+    /// 中心座標の10進文字列（精度を落とさずに保存・読み込みできる）
+    pub fn center_decimal(&self) -> (String, String) {
+        (
+            self.center_x.to_string_radix(10, None),
+            self.center_y.to_string_radix(10, None),
+        )
+    }
+
+    /// ズーム倍率（初期表示に対する倍率）
+    pub fn zoom(&self) -> f64 {
+        INITIAL_WIDTH / self.width
+    }
+
+    /// `frame_width` × `frame_height` ピクセルで描画するときの、ピクセル (x, y) の中心からのずれ（複素平面上）
+    pub fn pixel_offset(
+        &self,
+        x: f64,
+        y: f64,
+        frame_width: usize,
+        frame_height: usize,
+    ) -> (f64, f64) {
+        let u = (x - frame_width as f64 / 2.0) * (self.width / frame_width as f64);
+        let v = (frame_height as f64 / 2.0 - y) * (self.height / frame_height as f64);
+        self.rotate(u, v)
+    }
+
+    /// 画面の向きのずれ (u, v)（右・上が正）を複素平面上のずれに変換
+    pub fn rotate(&self, u: f64, v: f64) -> (f64, f64) {
+        if self.rotation == 0.0 {
+            return (u, v);
+        }
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        (u * cos - v * sin, u * sin + v * cos)
+    }
+
+    /// 表示範囲の端 (x_min, x_max, y_min, y_max)（回転前）
+    pub fn bounds(&self) -> (Float, Float, Float, Float) {
+        let prec = self.center_x.prec();
+        let half_w = self.width / 2.0;
+        let half_h = self.height / 2.0;
+        (
+            Float::with_val(prec, &self.center_x - half_w),
+            Float::with_val(prec, &self.center_x + half_w),
+            Float::with_val(prec, &self.center_y - half_h),
+            Float::with_val(prec, &self.center_y + half_h),
+        )
+    }
+}
+
+/// ズーム速度の緩急
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Easing {
+    /// 一定の倍率でズーム
+    Linear,
+    /// ゆっくり始まる
+    EaseIn,
+    /// ゆっくり終わる
+    EaseOut,
+    /// ゆっくり始まり、ゆっくり終わる
+    EaseInOut,
+}
+
+impl Easing {
+    /// 進行度 0.0〜1.0 を緩急をつけた進行度に変換
+    pub fn apply(self, t: f64) -> f64 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+impl std::str::FromStr for Easing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(Easing::Linear),
+            "ease-in" => Ok(Easing::EaseIn),
+            "ease-out" => Ok(Easing::EaseOut),
+            "ease-in-out" => Ok(Easing::EaseInOut),
+            _ => Err(format!(
+                "緩急の指定が不正です（linear / ease-in / ease-out / ease-in-out）: {}",
+                s
+            )),
+        }
+    }
+}
+
+/// `start` から `end` まで指数的にズームする `frames` 枚分の表示範囲
+///
+/// 幅は等比で変化させ（`Easing::Linear` なら毎フレーム同じ倍率でズーム）、中心は幅の縮み具合に
+/// 合わせて `end` の中心へ近づけます。深いズームでも目標点が画面外に外れません。
+/// 回転角は進行度に比例して変えます。
+pub fn zoom_path(start: &View, end: &View, frames: usize, easing: Easing) -> Vec<View> {
+    let prec = end.center_x.prec();
+    let dx = Float::with_val(prec, &start.center_x - &end.center_x).to_f64();
+    let dy = Float::with_val(prec, &start.center_y - &end.center_y).to_f64();
+    let ratio = end.width / start.width;
+
+    (0..frames)
+        .map(|k| {
+            let t = easing.apply(if frames > 1 {
+                k as f64 / (frames - 1) as f64
+            } else {
+                1.0
+            });
+            let width = start.width * ratio.powf(t);
+
+            // 残りの移動量の割合（開始時 1.0、終了時 0.0）
+            let remaining = if (start.width - end.width).abs() > 0.0 {
+                (width - end.width) / (start.width - end.width)
+            } else {
+                1.0 - t
+            };
+
+            View {
+                center_x: Float::with_val(prec, &end.center_x + dx * remaining),
+                center_y: Float::with_val(prec, &end.center_y + dy * remaining),
+                width,
+                height: end.height * (width / end.width),
+                rotation: start.rotation + (end.rotation - start.rotation) * t,
+            }
+        })
+        .collect()
+}
+
+/// 連番画像のファイル名
+pub fn frame_path(dir: &Path, index: usize) -> PathBuf {
+    dir.join(format!("frame_{:05}.png", index))
+}
+
+/// 着色済みのバッファ（0xRRGGBB）を PNG として保存
+pub fn save_frame(path: &Path, colors: &[u32], width: usize, height: usize) -> Result<(), String> {
+    let img: ImageBuffer<Rgb<u8>, Vec<u8>> =
+        ImageBuffer::from_fn(width as u32, height as u32, |x, y| {
+            let pixel = colors[(y as usize) * width + (x as usize)];
+            let r = ((pixel >> 16) & 0xFF) as u8;
+            let g = ((pixel >> 8) & 0xFF) as u8;
+            let b = (pixel & 0xFF) as u8;
+            Rgb([r, g, b])
+        });
+
+    img.save(path)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// キーフレーム
+pub struct Keyframe {
+    pub view: View,
+    pub max_iter: u32,
+    /// このキーフレームから次のキーフレームまでに使うパレットファイル（省略時はビューアのパレット）
+    pub palette: Option<PathBuf>,
+    /// 次のキーフレームまでのフレーム数
+    pub frames: usize,
+}
+
+/// キーフレームパスの1フレーム
+pub struct PathFrame {
+    pub view: View,
+    pub max_iter: u32,
+    /// 属する区間の開始キーフレーム（パレットの選択に使う）
+    pub keyframe: usize,
+}
+
+/// キーフレームを順にたどるカメラパス
+///
+/// JSON ファイルとして保存・読み込みします:
+///
+/// ```json
+/// {
+///   "keyframes": [
+///     { "re": "-0.75", "im": "0", "zoom": 1.0, "max_iter": 256, "frames": 60 },
+///     { "re": "-0.7436438870371", "im": "0.1318259042053", "zoom": 1e10,
+///       "max_iter": 2000, "palette": "fire.map", "rotation": 30 }
+///   ]
+/// }
+/// ```
+///
+/// 中心座標は精度を保つため10進文字列で記録します。`max_iter` と `frames`、`rotation`（度）は省略可能です。
+#[derive(Default)]
+pub struct KeyframePath {
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl KeyframePath {
+    /// JSON ファイルから読み込む（パレットの相対パスは JSON ファイルの場所を基準にする）
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        Self::parse(&text, base).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn parse(text: &str, base: &Path) -> Result<Self, String> {
+        let value: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        let entries = value["keyframes"]
+            .as_array()
+            .ok_or_else(|| "\"keyframes\" の配列が必要です".to_string())?;
+
+        let keyframes = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let field = |name: &str| {
+                    entry[name]
+                        .as_str()
+                        .ok_or_else(|| format!("{}番目: \"{}\" に文字列が必要です", i + 1, name))
+                };
+                let zoom = entry["zoom"]
+                    .as_f64()
+                    .ok_or_else(|| format!("{}番目: \"zoom\" に数値が必要です", i + 1))?;
+                let mut view = View::from_decimal(field("re")?, field("im")?, zoom)
+                    .map_err(|e| format!("{}番目: {}", i + 1, e))?;
+                view.rotation = match &entry["rotation"] {
+                    serde_json::Value::Null => 0.0,
+                    v => v
+                        .as_f64()
+                        .ok_or_else(|| format!("{}番目: \"rotation\" が不正です: {}", i + 1, v))?,
+                };
+                let max_iter = match &entry["max_iter"] {
+                    serde_json::Value::Null => MAX_ITER,
+                    v => v
+                        .as_u64()
+                        .filter(|&n| n > 0 && n <= u32::MAX as u64)
+                        .ok_or_else(|| format!("{}番目: \"max_iter\" が不正です: {}", i + 1, v))?
+                        as u32,
+                };
+                let frames = match &entry["frames"] {
+                    serde_json::Value::Null => DEFAULT_SEGMENT_FRAMES,
+                    v => v
+                        .as_u64()
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| format!("{}番目: \"frames\" が不正です: {}", i + 1, v))?
+                        as usize,
+                };
+                let palette = entry["palette"].as_str().map(|p| base.join(p));
+
+                Ok(Keyframe {
+                    view,
+                    max_iter,
+                    palette,
+                    frames,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        if keyframes.is_empty() {
+            return Err("キーフレームが1つもありません".to_string());
+        }
+        Ok(Self { keyframes })
+    }
+
+    /// キーフレームファイルに1つ追加して保存（ファイルがなければ新規作成）
+    ///
+    /// 追加後のキーフレーム数を返します。
+    pub fn append(path: &Path, keyframe: Keyframe) -> Result<usize, String> {
+        let mut keyframes = if path.exists() {
+            Self::load(path)?
+        } else {
+            Self::default()
+        };
+        keyframes.keyframes.push(keyframe);
+        keyframes.save(path)?;
+        Ok(keyframes.keyframes.len())
+    }
+
+    /// JSON ファイルに保存
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let keyframes: Vec<serde_json::Value> = self
+            .keyframes
+            .iter()
+            .map(|k| {
+                let (re, im) = k.view.center_decimal();
+                let mut entry = serde_json::json!({
+                    "re": re,
+                    "im": im,
+                    "zoom": k.view.zoom(),
+                    "max_iter": k.max_iter,
+                    "frames": k.frames,
+                });
+                if let Some(palette) = &k.palette {
+                    entry["palette"] = palette.display().to_string().into();
+                }
+                if k.view.rotation != 0.0 {
+                    entry["rotation"] = k.view.rotation.into();
+                }
+                entry
+            })
+            .collect();
+
+        let text = serde_json::to_string_pretty(&serde_json::json!({ "keyframes": keyframes }))
+            .map_err(|e| e.to_string())?;
+        std::fs::write(path, text + "\n").map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// 各キーフレームのパレット（指定のないキーフレームは `default`）
+    pub fn palettes(&self, default: &Palette) -> Result<Vec<Palette>, String> {
+        self.keyframes
+            .iter()
+            .map(|k| match &k.palette {
+                Some(path) => Palette::load(path),
+                None => Ok(default.clone()),
+            })
+            .collect()
+    }
+
+    /// キーフレーム間を補間した全フレーム
+    ///
+    /// 各区間はズーム倍率を対数で補間し（`zoom_path` と同じく中心は幅の縮み具合に合わせて移動）、
+    /// 最大反復回数も等比で補間します。区間ごとの速度の緩急は `easing` で指定します。
+    pub fn frames(&self, easing: Easing) -> Vec<PathFrame> {
+        let mut frames = Vec::new();
+        for (index, pair) in self.keyframes.windows(2).enumerate() {
+            let (from, to) = (&pair[0], &pair[1]);
+            // 区間の終点は次の区間の始点と重なるため含めない
+            let views = zoom_path(&from.view, &to.view, from.frames + 1, easing);
+            let iter_ratio = to.max_iter as f64 / from.max_iter as f64;
+
+            for (j, view) in views.into_iter().take(from.frames).enumerate() {
+                let t = easing.apply(j as f64 / from.frames as f64);
+                frames.push(PathFrame {
+                    view,
+                    max_iter: (from.max_iter as f64 * iter_ratio.powf(t)).round() as u32,
+                    keyframe: index,
+                });
+            }
+        }
+
+        if let Some(last) = self.keyframes.last() {
+            frames.push(PathFrame {
+                view: last.view.clone(),
+                max_iter: last.max_iter,
+                keyframe: self.keyframes.len() - 1,
+            });
+        }
+        frames
+    }
+}
+
+/// アニメーション出力の設定（コマンドライン引数から作成）
+pub struct RecordingOptions {
+    /// 連番画像の出力先
+    pub frames_dir: PathBuf,
+    /// 動画の出力先（指定時は連番画像の代わりに動画を出力）
+    pub video: Option<PathBuf>,
+    pub frames: usize,
+    pub fps: u32,
+    pub easing: Easing,
+}
+
+impl RecordingOptions {
+    /// `--frames-dir` / `--video` / `--frames` / `--fps` / `--duration` / `--easing` を読み込む
+    ///
+    /// `--duration <秒>` を指定するとフレーム数は `--fps` との積になります。
+    pub fn from_args() -> Result<Self, String> {
+        let fps = match arg_value("--fps") {
+            Some(v) => v
+                .parse()
+                .ok()
+                .filter(|&fps| fps > 0)
+                .ok_or_else(|| format!("フレームレートが不正です: {}", v))?,
+            None => DEFAULT_FPS,
+        };
+        let frames = match (arg_value("--duration"), arg_value("--frames")) {
+            (Some(v), _) => v
+                .parse::<f64>()
+                .ok()
+                .filter(|d| d.is_finite() && *d > 0.0)
+                .map(|d| ((d * fps as f64).round() as usize).max(1))
+                .ok_or_else(|| format!("長さ（秒）が不正です: {}", v))?,
+            (None, Some(v)) => v
+                .parse()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| format!("フレーム数が不正です: {}", v))?,
+            (None, None) => DEFAULT_FRAMES,
+        };
+        let easing = match arg_value("--easing") {
+            Some(v) => v.parse()?,
+            None => Easing::Linear,
+        };
+
+        Ok(Self {
+            frames_dir: PathBuf::from(
+                arg_value("--frames-dir").unwrap_or_else(|| DEFAULT_FRAMES_DIR.into()),
+            ),
+            video: arg_value("--video").map(PathBuf::from),
+            frames,
+            fps,
+            easing,
+        })
+    }
+
+    /// 出力先（動画ファイルまたは連番画像のディレクトリ）
+    pub fn destination(&self) -> &Path {
+        self.video.as_deref().unwrap_or(&self.frames_dir)
+    }
+
+    /// フレームの出力先を開く
+    pub fn open_sink(&self, width: usize, height: usize) -> Result<FrameSink, String> {
+        match &self.video {
+            Some(path) => Ok(FrameSink::Video(VideoEncoder::spawn(
+                path, width, height, self.fps,
+            )?)),
+            None => {
+                std::fs::create_dir_all(&self.frames_dir)
+                    .map_err(|e| format!("{}: {}", self.frames_dir.display(), e))?;
+                Ok(FrameSink::Images {
+                    dir: self.frames_dir.clone(),
+                    width,
+                    height,
+                    next_index: 0,
+                })
+            }
+        }
+    }
+}
+
+/// フレームの出力先（連番画像または動画）
+pub enum FrameSink {
+    Images {
+        dir: PathBuf,
+        width: usize,
+        height: usize,
+        next_index: usize,
+    },
+    Video(VideoEncoder),
+}
+
+impl FrameSink {
+    /// 着色済みのバッファ（0xRRGGBB）を次のフレームとして書き込む
+    pub fn write(&mut self, colors: &[u32]) -> Result<(), String> {
+        match self {
+            FrameSink::Images {
+                dir,
+                width,
+                height,
+                next_index,
+            } => {
+                save_frame(&frame_path(dir, *next_index), colors, *width, *height)?;
+                *next_index += 1;
+                Ok(())
+            }
+            FrameSink::Video(encoder) => encoder.write_frame(colors),
+        }
+    }
+
+    /// 出力を完了（動画はエンコードの終了を待つ）
+    pub fn finish(self) -> Result<(), String> {
+        match self {
+            FrameSink::Images { .. } => Ok(()),
+            FrameSink::Video(encoder) => encoder.finish(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `pixel_offset` の逆変換（複素平面上のずれからピクセル座標に戻す）
+    fn offset_to_pixel(view: &View, (dx, dy): (f64, f64), size: (usize, usize)) -> (f64, f64) {
+        let (sin, cos) = view.rotation.to_radians().sin_cos();
+        let (u, v) = (dx * cos + dy * sin, dy * cos - dx * sin);
+        (
+            u / (view.width / size.0 as f64) + size.0 as f64 / 2.0,
+            size.1 as f64 / 2.0 - v / (view.height / size.1 as f64),
+        )
+    }
+
+    #[test]
+    fn pixel_offset_maps_center_and_corners() {
+        let view = View::initial(INITIAL_PRECISION);
+        let (w, h) = (800, 600);
+        let close =
+            |(a, b): (f64, f64), (c, d): (f64, f64)| (a - c).abs() < 1e-12 && (b - d).abs() < 1e-12;
+        assert_eq!(view.pixel_offset(400.0, 300.0, w, h), (0.0, 0.0));
+        assert!(close(
+            view.pixel_offset(0.0, 0.0, w, h),
+            (-view.width / 2.0, view.height / 2.0)
+        ));
+        assert!(close(
+            view.pixel_offset(800.0, 600.0, w, h),
+            (view.width / 2.0, -view.height / 2.0)
+        ));
+    }
+
+    #[test]
+    fn pixel_offset_round_trips_with_rotation() {
+        let size = (640, 480);
+        for rotation in [0.0, 30.0, 90.0, 215.0] {
+            let view = View {
+                rotation,
+                width: 0.01,
+                height: 0.0075,
+                ..View::initial(INITIAL_PRECISION)
+            };
+            for (x, y) in [(0.0, 0.0), (12.5, 400.0), (320.0, 240.0), (639.0, 1.0)] {
+                let offset = view.pixel_offset(x, y, size.0, size.1);
+                let (bx, by) = offset_to_pixel(&view, offset, size);
+                assert!(
+                    (bx - x).abs() < 1e-9 && (by - y).abs() < 1e-9,
+                    "回転 {}°: ({}, {}) → ({}, {})",
+                    rotation,
+                    x,
+                    y,
+                    bx,
+                    by
+                );
+            }
+        }
+    }
+}
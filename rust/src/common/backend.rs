@@ -0,0 +1,86 @@
+//! 計算バックエンドと計算モード
+//!
+//! バックエンド（`--backend`）は起動時に選び、計算モードはバックエンドとズーム倍率から決まります。
+//! GPU の初期化と描画は実行ファイル側で行い、ここでは GPU に依存しない選択規則だけを持ちます。
+
+use super::config::Thresholds;
+use super::constants::FIXED_POINT_THRESHOLD;
+
+/// 計算モード
+#[derive(Clone, Copy, PartialEq)]
+pub enum ComputeMode {
+    Gpu,
+    GpuDf64,
+    CpuF64,
+    CpuFixed,
+    CpuHighPrecision,
+    FullHighPrecision,
+}
+
+impl std::fmt::Display for ComputeMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ComputeMode::Gpu => write!(f, "🎮 GPU (f32)"),
+            ComputeMode::GpuDf64 => write!(f, "🎮 GPU (df64)"),
+            ComputeMode::CpuF64 => write!(f, "🚀 CPU (f64)"),
+            ComputeMode::CpuFixed => write!(f, "🧮 CPU (固定小数点)"),
+            ComputeMode::CpuHighPrecision => write!(f, "🔬 高精度 (摂動法)"),
+            ComputeMode::FullHighPrecision => write!(f, "🔬 高精度 (全ピクセル任意精度)"),
+        }
+    }
+}
+
+/// 計算バックエンド（`--backend`）
+#[derive(Clone, Copy, PartialEq)]
+pub enum Backend {
+    /// GPU f32 → GPU df64 → CPU f64 → 固定小数点 → 摂動法
+    Gpu,
+    /// CPU f64 → 固定小数点 → 摂動法
+    Cpu,
+    /// 全ピクセルを任意精度で計算
+    Hp,
+    /// GPU が使えれば Gpu、使えなければ Cpu（起動時にどちらかに決まる）
+    Auto,
+}
+
+impl std::str::FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gpu" => Ok(Backend::Gpu),
+            "cpu" => Ok(Backend::Cpu),
+            "hp" => Ok(Backend::Hp),
+            "auto" => Ok(Backend::Auto),
+            _ => Err(format!(
+                "計算バックエンドの指定が不正です（gpu / cpu / hp / auto）: {}",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Backend::Gpu => write!(f, "GPUハイブリッド"),
+            Backend::Cpu => write!(f, "CPUハイブリッド"),
+            Backend::Hp => write!(f, "高精度専用"),
+            Backend::Auto => write!(f, "自動選択"),
+        }
+    }
+}
+
+impl Backend {
+    /// ズーム倍率 `zoom` で使う計算モード
+    pub fn mode(self, zoom: f64, thresholds: &Thresholds) -> ComputeMode {
+        match self {
+            Backend::Hp => ComputeMode::FullHighPrecision,
+            _ if zoom > FIXED_POINT_THRESHOLD => ComputeMode::CpuHighPrecision,
+            _ if zoom > thresholds.high_precision => ComputeMode::CpuFixed,
+            Backend::Gpu if zoom <= thresholds.gpu_df64 => ComputeMode::Gpu,
+            Backend::Gpu if zoom <= thresholds.gpu_to_cpu => ComputeMode::GpuDf64,
+            _ => ComputeMode::CpuF64,
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! コマンドライン引数の解析
+
+use std::path::PathBuf;
+
+/// `--name value` 形式のオプションの値を取得
+pub fn arg_value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == name {
+            return args.next();
+        }
+        if let Some(value) = arg
+            .strip_prefix(name)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// `--name` 形式のフラグが指定されているか
+pub fn has_flag(name: &str) -> bool {
+    std::env::args().skip(1).any(|arg| arg == name)
+}
+
+/// `<幅>x<高さ>` 形式の大きさ（幅・高さとも正の整数）
+pub fn parse_size(spec: &str) -> Option<(usize, usize)> {
+    spec.split_once('x')
+        .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)))
+        .filter(|&(w, h): &(usize, usize)| w > 0 && h > 0)
+}
+
+/// 先頭の引数として渡されたファイル（アプリのアイコンへのドラッグ＆ドロップを含む）
+///
+/// `--name value` 形式のオプションの値と区別するため、先頭の引数のみを対象とします。
+pub fn file_arg() -> Option<PathBuf> {
+    std::env::args()
+        .nth(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .map(PathBuf::from)
+}
+
+/// 先頭の引数が `name` のサブコマンド（`render` など）か
+pub fn is_subcommand(name: &str) -> bool {
+    std::env::args().nth(1).is_some_and(|arg| arg == name)
+}
@@ -0,0 +1,177 @@
+//! 着色の対応を示すカラーバー
+//!
+//! ウィンドウの右端（マンデルブロ描画領域の右）に、現在のパレット・最大反復回数・着色モードでの色の対応と
+//! 目盛り・数値ラベルを描きます。線形・ヒストグラム平坦化では 0〜最大反復回数の色を 1/4 ごとの目盛りで、
+//! 距離推定では境界からの距離（ピクセル）ごとの明るさを 1 ピクセルごとの目盛りで示します。
+
+use super::colors::{encode_distance, ColoringMode, Colorizer, Palette, DISTANCE_FALLOFF};
+use super::constants::{COLORBAR_BAR_WIDTH, COLORBAR_MARGIN, COLORBAR_WIDTH};
+use super::draw::{draw_line, draw_rect, fill_rect};
+use super::font::draw_text_outlined;
+
+/// カラーバーの下端 0.0 〜上端 1.0 の位置 `t` に対応する、バッファに格納する値
+///
+/// 線形・ヒストグラム平坦化では反復回数 0〜最大反復回数、距離推定では境界からの距離 0〜`DISTANCE_FALLOFF` ピクセルです。
+fn bar_value(colorizer: &Colorizer, t: f64) -> u32 {
+    if colorizer.mode() == ColoringMode::Distance {
+        encode_distance(t * DISTANCE_FALLOFF, 1.0)
+    } else {
+        (t * colorizer.max_iter() as f64) as u32
+    }
+}
+
+/// ウィンドウのバッファ（描画領域 `area` の右に幅 `COLORBAR_WIDTH` × `scale` を足した大きさ）の右端に、
+/// `colorizer` の着色の対応を示すカラーバーを描画（寸法と文字は HiDPI の拡大率 `scale` 倍）
+pub fn draw_colorbar(
+    buffer: &mut [u32],
+    (area_width, area_height): (usize, usize),
+    scale: usize,
+    colorizer: &Colorizer,
+    palette: &Palette,
+) {
+    let window_width = area_width + COLORBAR_WIDTH * scale;
+    let bar_x_start = area_width + COLORBAR_MARGIN * scale;
+    let bar_x_end = bar_x_start + COLORBAR_BAR_WIDTH * scale;
+    let bar_y_start = 40 * scale;
+    let bar_y_end = area_height - 40 * scale;
+    let bar_height = bar_y_end - bar_y_start;
+
+    // 下端 0.0 〜上端 1.0 の位置に対応する値の色と、目盛りを付ける位置・ラベル
+    let max_iter = colorizer.max_iter();
+    let distance = colorizer.mode() == ColoringMode::Distance;
+    let color_at = |t: f64| colorizer.color(bar_value(colorizer, t), palette);
+    let ticks: Vec<(f64, String)> = if distance {
+        (0..=DISTANCE_FALLOFF as u32)
+            .map(|px| (px as f64 / DISTANCE_FALLOFF, px.to_string()))
+            .collect()
+    } else {
+        (0..=4)
+            .map(|k| max_iter * k / 4)
+            .map(|value| (value as f64 / max_iter as f64, value.to_string()))
+            .collect()
+    };
+
+    let clip = (window_width, area_height);
+
+    // 背景をグレーに
+    fill_rect(
+        buffer,
+        window_width,
+        clip,
+        (area_width as f64, 0.0),
+        ((window_width - 1) as f64, (area_height - 1) as f64),
+        0x404040,
+    );
+
+    // 上端の行が t = 1.0、下端の行が t = 0.0 ちょうどになるように対応させる
+    let last_row = (bar_height - 1) as f64;
+
+    // カラーバー本体を描画
+    for y in bar_y_start..bar_y_end {
+        let t = 1.0 - (y - bar_y_start) as f64 / last_row;
+        fill_rect(
+            buffer,
+            window_width,
+            clip,
+            (bar_x_start as f64, y as f64),
+            ((bar_x_end - 1) as f64, y as f64),
+            color_at(t),
+        );
+    }
+
+    // 枠線
+    draw_rect(
+        buffer,
+        window_width,
+        clip,
+        ((bar_x_start - 1) as f64, (bar_y_start - 1) as f64),
+        (bar_x_end as f64, bar_y_end as f64),
+        0xFFFFFF,
+    );
+
+    // 目盛りとラベルを描画
+    for (t, label) in ticks {
+        let y = bar_y_start + ((1.0 - t) * last_row).round() as usize;
+
+        // 目盛り線
+        draw_line(
+            buffer,
+            window_width,
+            clip,
+            (bar_x_end as f64, y as f64),
+            ((bar_x_end + 4 * scale) as f64, y as f64),
+            0xFFFFFF,
+        );
+
+        // 数値ラベルを描画（明るい色の上でも読めるよう縁取りする）
+        let label_x = bar_x_end + 7 * scale;
+        let label_y = y.saturating_sub(3 * scale);
+        draw_text_outlined(buffer, clip, label_x, label_y, &label, 0xCCCCCC, scale);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_ITER: u32 = 100;
+
+    fn colorizer(mode: ColoringMode) -> Colorizer {
+        let iters: Vec<u32> = (0..=MAX_ITER).collect();
+        Colorizer::new(&iters, MAX_ITER, mode)
+    }
+
+    #[test]
+    fn bar_value_endpoints_map_to_iteration_range() {
+        for mode in [ColoringMode::Linear, ColoringMode::Histogram] {
+            let colorizer = colorizer(mode);
+            assert_eq!(bar_value(&colorizer, 0.0), 0);
+            assert_eq!(bar_value(&colorizer, 0.5), MAX_ITER / 2);
+            assert_eq!(bar_value(&colorizer, 1.0), MAX_ITER);
+        }
+    }
+
+    #[test]
+    fn bar_value_endpoints_map_to_distance_range() {
+        let colorizer = colorizer(ColoringMode::Distance);
+        assert_eq!(bar_value(&colorizer, 0.0), encode_distance(0.0, 1.0));
+        assert_eq!(
+            bar_value(&colorizer, 1.0),
+            encode_distance(DISTANCE_FALLOFF, 1.0)
+        );
+    }
+
+    #[test]
+    fn drawn_bar_ends_match_colorizer() {
+        // 下端の行は 0、上端の行は最大反復回数（距離推定では 0 と DISTANCE_FALLOFF ピクセル）の色
+        let (area_width, area_height) = (200, 400);
+        let window_width = area_width + COLORBAR_WIDTH;
+        let x = area_width + COLORBAR_MARGIN + COLORBAR_BAR_WIDTH / 2;
+        let (top, bottom) = (40, area_height - 40 - 1);
+        let palette = Palette::default();
+        for mode in [
+            ColoringMode::Linear,
+            ColoringMode::Histogram,
+            ColoringMode::Distance,
+        ] {
+            let colorizer = colorizer(mode);
+            let mut buffer = vec![0; window_width * area_height];
+            draw_colorbar(
+                &mut buffer,
+                (area_width, area_height),
+                1,
+                &colorizer,
+                &palette,
+            );
+            let pixel = |y: usize| buffer[y * window_width + x];
+            assert_eq!(
+                pixel(top),
+                colorizer.color(bar_value(&colorizer, 1.0), &palette)
+            );
+            assert_eq!(
+                pixel(bottom),
+                colorizer.color(bar_value(&colorizer, 0.0), &palette)
+            );
+        }
+    }
+}
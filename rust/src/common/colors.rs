@@ -1,7 +1,10 @@
 //! カラーマップと色変換関数
 
-/// Python版と同じカラーマップ
-pub const COLORS: [(f64, f64, f64); 10] = [
+use super::gradient::Gradient;
+use std::path::Path;
+
+/// Python版と同じカラーマップ（既定のパレット）
+const DEFAULT_COLORS: [(f64, f64, f64); 10] = [
     (0.0, 0.0, 0.2), // 深い青
     (0.1, 0.2, 0.5), // 青
     (0.2, 0.5, 0.8), // 水色
@@ -14,23 +17,545 @@ pub const COLORS: [(f64, f64, f64); 10] = [
     (0.0, 0.0, 0.0), // 黒
 ];
 
+/// matplotlib の viridis（知覚的に均一、暗い紫 → 青緑 → 黄）
+const VIRIDIS: [(f64, f64, f64); 10] = [
+    (0.267, 0.004, 0.329),
+    (0.282, 0.157, 0.471),
+    (0.243, 0.286, 0.537),
+    (0.192, 0.408, 0.557),
+    (0.149, 0.510, 0.557),
+    (0.122, 0.620, 0.537),
+    (0.208, 0.718, 0.475),
+    (0.431, 0.808, 0.345),
+    (0.710, 0.871, 0.169),
+    (0.992, 0.906, 0.145),
+];
+
+/// matplotlib の inferno（知覚的に均一、黒 → 赤紫 → 橙 → 淡い黄）
+const INFERNO: [(f64, f64, f64); 10] = [
+    (0.000, 0.000, 0.016),
+    (0.106, 0.047, 0.255),
+    (0.290, 0.047, 0.420),
+    (0.471, 0.110, 0.427),
+    (0.647, 0.173, 0.376),
+    (0.812, 0.267, 0.275),
+    (0.929, 0.412, 0.145),
+    (0.984, 0.608, 0.024),
+    (0.969, 0.820, 0.239),
+    (0.988, 1.000, 0.643),
+];
+
+/// Google の Turbo（jet の改良版、青 → 緑 → 赤。中間は公開されている多項式近似から求めた値）
+const TURBO: [(f64, f64, f64); 16] = [
+    (0.188, 0.071, 0.231),
+    (0.290, 0.255, 0.710),
+    (0.259, 0.439, 0.949),
+    (0.184, 0.620, 0.961),
+    (0.145, 0.776, 0.843),
+    (0.184, 0.898, 0.678),
+    (0.306, 0.976, 0.514),
+    (0.486, 0.992, 0.369),
+    (0.690, 0.957, 0.267),
+    (0.875, 0.863, 0.196),
+    (1.000, 0.722, 0.153),
+    (1.000, 0.549, 0.122),
+    (0.957, 0.361, 0.090),
+    (0.808, 0.192, 0.051),
+    (0.639, 0.075, 0.008),
+    (0.478, 0.016, 0.012),
+];
+
+/// 0.0〜1.0 の RGB 成分
+pub type Rgb = (f64, f64, f64);
+
+/// HSV パレット（`hsv`）で 0〜最大反復回数の間に色相が回る回数の既定値
+pub const DEFAULT_HSV_FREQUENCY: f64 = 8.0;
+
+/// HSV パレットの彩度と明度
+const HSV_SATURATION: f64 = 0.8;
+const HSV_VALUE: f64 = 1.0;
+
+/// 組み込みのパレット（名前と色の列）。先頭が既定のパレット
+///
+/// このほか `hsv`（`hsv:<周波数>`）で HSV パレットを使えます。
+pub const BUILTIN_PALETTES: [(&str, &[Rgb]); 4] = [
+    ("default", &DEFAULT_COLORS),
+    ("viridis", &VIRIDIS),
+    ("inferno", &INFERNO),
+    ("turbo", &TURBO),
+];
+
+/// 反復回数の位置（0.0〜1.0）から色を決めるパレット
+#[derive(Clone, Debug, PartialEq)]
+pub enum Palette {
+    /// 制御点の色を補間するグラデーション
+    Gradient(Gradient),
+    /// 色相が周期的に変わる HSV パレット
+    ///
+    /// 0.0〜1.0（0〜最大反復回数）の間に色相が `frequency` 周し、`phase` 周ずらした色から始まります。
+    /// グラデーションと違って最大反復回数を大きくしても色が足りなくならず、`phase` を少しずつ変えると
+    /// 色が流れるように動きます（カラーサイクリング）。
+    Hsv { frequency: f64, phase: f64 },
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::Gradient(Gradient::uniform(&DEFAULT_COLORS).expect("既定のパレットは2色以上"))
+    }
+}
+
+impl Palette {
+    /// 等間隔に並んだ色の列を線形補間するパレットを作成（補間のため2色以上必要）
+    pub fn new(colors: Vec<(f64, f64, f64)>) -> Result<Self, String> {
+        Gradient::uniform(&colors).map(Self::Gradient)
+    }
+
+    /// 名前で組み込みのパレットを取得（`BUILTIN_PALETTES` の名前か `hsv` / `hsv:<周波数>`。それ以外は None）
+    pub fn builtin(name: &str) -> Option<Self> {
+        if let Some(frequency) = name.strip_prefix("hsv") {
+            let frequency = match frequency.strip_prefix(':') {
+                Some(value) => value.parse().ok().filter(|&f: &f64| f > 0.0)?,
+                None if frequency.is_empty() => DEFAULT_HSV_FREQUENCY,
+                None => return None,
+            };
+            return Some(Self::Hsv {
+                frequency,
+                phase: 0.0,
+            });
+        }
+        let (_, colors) = BUILTIN_PALETTES.iter().find(|(n, _)| *n == name)?;
+        Gradient::uniform(colors).ok().map(Self::Gradient)
+    }
+
+    /// ファイルからパレットを読み込む
+    ///
+    /// 拡張子で形式を判別します:
+    ///   - `.map`: Fractint 形式（1行に `R G B`、0〜255）
+    ///   - `.csv`: 1行に `r,g,b`（0〜255）
+    ///   - `.json`: `[[r, g, b], ...]` または `["#RRGGBB", ...]`、
+    ///     あるいは制御点と補間方法を指定したグラデーション（`Gradient::from_json` の形式）
+    ///
+    /// 拡張子がなく、そのファイルもない場合は `viridis` などの組み込みのパレットの名前として扱います。
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if path.extension().is_none() && !path.exists() {
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            return Self::builtin(name).ok_or_else(|| {
+                let mut names: Vec<&str> = BUILTIN_PALETTES.iter().map(|(n, _)| *n).collect();
+                names.push("hsv[:<周波数>]");
+                format!(
+                    "パレットの指定が不正です（ファイルまたは {}）: {}",
+                    names.join(" / "),
+                    path.display()
+                )
+            });
+        }
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+
+        match ext.as_deref() {
+            Some("map") => parse_map(&text).and_then(Self::new),
+            Some("csv") => parse_csv(&text).and_then(Self::new),
+            Some("json") => parse_json(&text),
+            _ => Err("対応していない形式です（.map / .csv / .json）".to_string()),
+        }
+        .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// 0.0〜1.0 の位置に対応する色（u32形式: 0xRRGGBB）
+    pub fn color_at(&self, t: f64) -> u32 {
+        match self {
+            Self::Gradient(gradient) => gradient.color_at(t),
+            Self::Hsv { frequency, phase } => {
+                let hue = (t.clamp(0.0, 1.0) * frequency + phase).rem_euclid(1.0);
+                hsv_to_color_u32(hue, HSV_SATURATION, HSV_VALUE)
+            }
+        }
+    }
+}
+
+/// HSV（いずれも 0.0〜1.0）を u32 形式の色に変換
+fn hsv_to_color_u32(hue: f64, saturation: f64, value: f64) -> u32 {
+    let h = hue * 6.0;
+    let sector = (h as u32).min(5);
+    let f = h - sector as f64;
+    let p = value * (1.0 - saturation);
+    let q = value * (1.0 - saturation * f);
+    let t = value * (1.0 - saturation * (1.0 - f));
+    let (r, g, b) = match sector {
+        0 => (value, t, p),
+        1 => (q, value, p),
+        2 => (p, value, t),
+        3 => (p, q, value),
+        4 => (t, p, value),
+        _ => (value, p, q),
+    };
+    let channel = |c: f64| (c * 255.0) as u32;
+    (channel(r) << 16) | (channel(g) << 8) | channel(b)
+}
+
+/// 0〜255 の成分値を 0.0〜1.0 に変換
+fn channel(value: f64) -> Result<f64, String> {
+    if !(0.0..=255.0).contains(&value) {
+        return Err(format!("色の値が範囲外です: {}", value));
+    }
+    Ok(value / 255.0)
+}
+
+/// Fractint の .map 形式を解析（4列目以降はコメントとして無視）
+fn parse_map(text: &str) -> Result<Vec<(f64, f64, f64)>, String> {
+    let mut colors = Vec::new();
+    for (lineno, line) in text.lines().enumerate() {
+        let fields: Vec<&str> = line.split_whitespace().take(3).collect();
+        if fields.is_empty() {
+            continue;
+        }
+        let rgb = fields
+            .iter()
+            .map(|f| {
+                f.parse::<f64>()
+                    .map_err(|_| format!("{}行目: 数値ではありません: {}", lineno + 1, f))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if rgb.len() != 3 {
+            return Err(format!("{}行目: R G B の3値が必要です", lineno + 1));
+        }
+        colors.push((channel(rgb[0])?, channel(rgb[1])?, channel(rgb[2])?));
+    }
+    Ok(colors)
+}
+
+/// `r,g,b` 形式の CSV を解析（空行と `#` で始まる行は無視）
+fn parse_csv(text: &str) -> Result<Vec<(f64, f64, f64)>, String> {
+    let mut colors = Vec::new();
+    for (lineno, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let rgb = line
+            .split(',')
+            .map(|f| {
+                f.trim()
+                    .parse::<f64>()
+                    .map_err(|_| format!("{}行目: 数値ではありません: {}", lineno + 1, f))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if rgb.len() != 3 {
+            return Err(format!("{}行目: r,g,b の3値が必要です", lineno + 1));
+        }
+        colors.push((channel(rgb[0])?, channel(rgb[1])?, channel(rgb[2])?));
+    }
+    Ok(colors)
+}
+
+/// `[[r, g, b], ...]` または `["#RRGGBB", ...]` 形式、あるいはグラデーションの JSON を解析
+fn parse_json(text: &str) -> Result<Palette, String> {
+    let value: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    if value.is_object() {
+        return Gradient::from_json(&value).map(Palette::Gradient);
+    }
+    let entries = value
+        .as_array()
+        .ok_or_else(|| "色の配列が必要です".to_string())?;
+
+    entries
+        .iter()
+        .map(parse_json_color)
+        .collect::<Result<Vec<_>, _>>()
+        .and_then(Palette::new)
+}
+
+/// JSON の色（`"#RRGGBB"` または `[r, g, b]`、0〜255）を解析
+pub fn parse_json_color(entry: &serde_json::Value) -> Result<Rgb, String> {
+    match entry {
+        serde_json::Value::String(hex) => parse_hex(hex),
+        serde_json::Value::Array(rgb) if rgb.len() == 3 => {
+            let mut c = [0.0; 3];
+            for (dst, v) in c.iter_mut().zip(rgb) {
+                let v = v
+                    .as_f64()
+                    .ok_or_else(|| format!("数値ではありません: {}", v))?;
+                *dst = channel(v)?;
+            }
+            Ok((c[0], c[1], c[2]))
+        }
+        other => Err(format!("色として解釈できません: {}", other)),
+    }
+}
+
+/// `#RRGGBB` 形式の色を解析
+fn parse_hex(hex: &str) -> Result<(f64, f64, f64), String> {
+    let digits = hex.trim_start_matches('#');
+    let value = u32::from_str_radix(digits, 16)
+        .ok()
+        .filter(|_| digits.len() == 6)
+        .ok_or_else(|| format!("#RRGGBB 形式ではありません: {}", hex))?;
+    Ok((
+        ((value >> 16) & 0xFF) as f64 / 255.0,
+        ((value >> 8) & 0xFF) as f64 / 255.0,
+        (value & 0xFF) as f64 / 255.0,
+    ))
+}
+
 /// 反復回数から色を計算（u32形式: 0xRRGGBB）
-pub fn iter_to_color_u32(iter: u32, max_iter: u32) -> u32 {
+pub fn iter_to_color_u32(iter: u32, max_iter: u32, palette: &Palette) -> u32 {
     if iter >= max_iter {
         return 0x000000;
     }
 
-    let t = iter as f64 / max_iter as f64;
-    let scaled = t * (COLORS.len() - 1) as f64;
-    let idx = (scaled as usize).min(COLORS.len() - 2);
-    let frac = scaled - idx as f64;
+    palette.color_at(iter as f64 / max_iter as f64)
+}
+
+/// 距離推定の値を格納したピクセルに立てるビット
+///
+/// 距離推定モードでは、発散した点の反復回数の代わりに `DISTANCE_FLAG | 境界までの距離` を格納します
+/// （距離はピクセル単位の固定小数点数）。どの最大反復回数よりも大きいため、他の着色モードでは
+/// 集合の内部と同じ黒になり、発散しなかった点は通常どおり最大反復回数を格納します。
+pub const DISTANCE_FLAG: u32 = 1 << 31;
+
+/// 距離の固定小数点表現の1ピクセルあたりの値
+const DISTANCE_SCALE: f64 = 256.0;
+
+/// 背景色になる境界からの距離（ピクセル）
+pub const DISTANCE_FALLOFF: f64 = 2.0;
+
+/// 境界までの距離（複素平面上）をピクセル単位に直してバッファに格納する値に変換
+pub fn encode_distance(distance: f64, pixel_size: f64) -> u32 {
+    let fixed = (distance / pixel_size * DISTANCE_SCALE).clamp(0.0, (DISTANCE_FLAG - 2) as f64);
+    DISTANCE_FLAG | fixed as u32
+}
+
+/// 距離推定の値から色を計算（境界付近ほど暗いグレー、離れると白）
+fn distance_to_color_u32(value: u32) -> u32 {
+    let pixels = (value & !DISTANCE_FLAG) as f64 / DISTANCE_SCALE;
+    let level = ((pixels / DISTANCE_FALLOFF).min(1.0).sqrt() * 255.0) as u32;
+    (level << 16) | (level << 8) | level
+}
+
+/// 着色モード
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColoringMode {
+    /// 反復回数をそのままパレット位置に対応させる
+    Linear,
+    /// 反復回数のヒストグラムを平坦化してパレット位置に対応させる
+    Histogram,
+    /// 境界までの距離推定で明暗を付ける（描画時に距離を計算する必要がある）
+    Distance,
+}
+
+impl ColoringMode {
+    /// 次の着色モード
+    pub fn next(self) -> Self {
+        match self {
+            ColoringMode::Linear => ColoringMode::Histogram,
+            ColoringMode::Histogram => ColoringMode::Distance,
+            ColoringMode::Distance => ColoringMode::Linear,
+        }
+    }
+
+    /// 設定ファイルなどで使う名前
+    pub fn name(self) -> &'static str {
+        match self {
+            ColoringMode::Linear => "linear",
+            ColoringMode::Histogram => "histogram",
+            ColoringMode::Distance => "distance",
+        }
+    }
+}
+
+impl std::str::FromStr for ColoringMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(ColoringMode::Linear),
+            "histogram" => Ok(ColoringMode::Histogram),
+            "distance" => Ok(ColoringMode::Distance),
+            _ => Err(format!(
+                "着色モードの指定が不正です（linear / histogram / distance）: {}",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for ColoringMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColoringMode::Linear => write!(f, "線形"),
+            ColoringMode::Histogram => write!(f, "ヒストグラム平坦化"),
+            ColoringMode::Distance => write!(f, "距離推定"),
+        }
+    }
+}
+
+/// 反復回数から色への対応（ヒストグラムモードでは累積分布を保持）
+///
+/// 累積分布を求めた反復回数とは別のバッファにも同じ対応で着色できるため、
+/// 画像を分割して描画する場合でも分割位置で色がずれません。
+pub struct Colorizer {
+    max_iter: u32,
+    mode: ColoringMode,
+    /// 反復回数ごとの累積分布（その反復回数より少ない点の割合、0.0〜1.0未満）
+    cdf: Option<Vec<f64>>,
+    /// 色調補正の表（補正しない場合は None）
+    tone: Option<[u8; 256]>,
+}
+
+impl Colorizer {
+    /// `iters` の反復回数の分布から着色の対応を作成
+    pub fn new(iters: &[u32], max_iter: u32, mode: ColoringMode) -> Self {
+        let cdf = match mode {
+            ColoringMode::Linear | ColoringMode::Distance => None,
+            ColoringMode::Histogram => {
+                let mut histogram = vec![0u64; max_iter as usize];
+                for &iter in iters {
+                    if iter < max_iter {
+                        histogram[iter as usize] += 1;
+                    }
+                }
+
+                let total = histogram.iter().sum::<u64>().max(1) as f64;
+                let mut cdf = Vec::with_capacity(histogram.len());
+                let mut running = 0u64;
+                for &count in &histogram {
+                    cdf.push(running as f64 / total);
+                    running += count;
+                }
+                Some(cdf)
+            }
+        };
+        Self {
+            max_iter,
+            mode,
+            cdf,
+            tone: None,
+        }
+    }
+
+    /// 着色した色に `tone` の色調補正を掛ける
+    pub fn with_tone(mut self, tone: &Tone) -> Self {
+        self.tone = (*tone != Tone::default()).then(|| tone.table());
+        self
+    }
+
+    /// 最大反復回数
+    pub fn max_iter(&self) -> u32 {
+        self.max_iter
+    }
 
-    let (r1, g1, b1) = COLORS[idx];
-    let (r2, g2, b2) = COLORS[idx + 1];
+    /// 着色モード
+    pub fn mode(&self) -> ColoringMode {
+        self.mode
+    }
 
-    let r = ((r1 + (r2 - r1) * frac) * 255.0) as u8;
-    let g = ((g1 + (g2 - g1) * frac) * 255.0) as u8;
-    let b = ((b1 + (b2 - b1) * frac) * 255.0) as u8;
+    /// 反復回数から色を計算（u32形式: 0xRRGGBB）
+    ///
+    /// 距離推定モードで距離が格納されていないピクセル（距離推定に対応しない計算モードの結果）は
+    /// 線形モードと同じ色になります。
+    pub fn color(&self, iter: u32, palette: &Palette) -> u32 {
+        let color = if self.mode == ColoringMode::Distance && iter & DISTANCE_FLAG != 0 {
+            distance_to_color_u32(iter)
+        } else {
+            match &self.cdf {
+                None => iter_to_color_u32(iter, self.max_iter, palette),
+                Some(_) if iter >= self.max_iter => 0x000000,
+                Some(cdf) => palette.color_at(cdf[iter as usize]),
+            }
+        };
+        match &self.tone {
+            None => color,
+            Some(table) => {
+                let channel =
+                    |shift: u32| (table[((color >> shift) & 0xFF) as usize] as u32) << shift;
+                channel(16) | channel(8) | channel(0)
+            }
+        }
+    }
+}
 
-    ((r as u32) << 16) | ((g as u32) << 8) | (b as u32)
+/// パレットで着色した後に掛ける色調補正
+///
+/// 各成分（0.0〜1.0）をガンマ補正 `v^(1/gamma)` した後、0.5 を中心に `contrast` 倍して `brightness` を足します。
+/// 既定値（ガンマ 1・明るさ 0・コントラスト 1）では色は変わりません。
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tone {
+    /// ガンマ（1 より大きいと中間調が明るくなる）
+    pub gamma: f64,
+    /// 明るさ（-1.0〜1.0）
+    pub brightness: f64,
+    /// コントラスト（1 より大きいと明暗の差が広がる）
+    pub contrast: f64,
+}
+
+impl Default for Tone {
+    fn default() -> Self {
+        Self {
+            gamma: 1.0,
+            brightness: 0.0,
+            contrast: 1.0,
+        }
+    }
+}
+
+impl Tone {
+    /// 0〜255 の成分値ごとの補正後の値の表
+    pub fn table(&self) -> [u8; 256] {
+        let mut table = [0; 256];
+        for (value, entry) in table.iter_mut().enumerate() {
+            let v = (value as f64 / 255.0).powf(1.0 / self.gamma);
+            let v = (v - 0.5) * self.contrast + 0.5 + self.brightness;
+            *entry = (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+        table
+    }
+}
+
+/// 反復回数バッファ全体を着色（u32形式: 0xRRGGBB）
+///
+/// ヒストグラムモードでは、発散した点の反復回数の累積分布を色の位置として使うため、
+/// 境界付近と外側の平坦な領域の両方で色の階調が保たれます。
+pub fn colorize(
+    iters: &[u32],
+    max_iter: u32,
+    palette: &Palette,
+    mode: ColoringMode,
+    tone: &Tone,
+) -> Vec<u32> {
+    let colorizer = Colorizer::new(iters, max_iter, mode).with_tone(tone);
+    iters
+        .iter()
+        .map(|&iter| colorizer.color(iter, palette))
+        .collect()
+}
+
+/// `samples` × `samples` 点ずつの色を平均して、横 `width` ピクセルの画像に縮小
+///
+/// `colors` は横 `width × samples` ピクセルで、行数は `samples` の倍数であること。
+/// 反復回数を平均すると境界がにじむため、サンプルごとに着色してから平均します。
+pub fn average_samples(colors: &[u32], width: usize, samples: usize) -> Vec<u32> {
+    let line = width * samples;
+    let height = colors.len() / line / samples;
+    let count = (samples * samples) as u32;
+    let mut averaged = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = [0u32; 3];
+            for sy in 0..samples {
+                let start = (y * samples + sy) * line + x * samples;
+                for &color in &colors[start..start + samples] {
+                    sum[0] += (color >> 16) & 0xFF;
+                    sum[1] += (color >> 8) & 0xFF;
+                    sum[2] += color & 0xFF;
+                }
+            }
+            let [r, g, b] = sum.map(|total| (total + count / 2) / count);
+            averaged.push((r << 16) | (g << 8) | b);
+        }
+    }
+    averaged
 }
@@ -0,0 +1,812 @@
+//! ユーザー設定ファイル（`~/.config/flactal/config.toml`）の読み込み
+//!
+//! 設定ファイルは TOML のサブセット（`[テーブル]`、`キー = 値`、値は文字列・数値、
+//! `#` 以降はコメント）として読み込みます。省略した項目は `constants.rs` の既定値になります。
+//! 最大反復回数・描画領域の大きさ・精度・ズームの倍率・切替閾値は `Settings` にまとめて保持し、
+//! コマンドライン引数（`--window-size`、`--max-iter`、`--scale`）でさらに上書きできます:
+//!
+//! ```toml
+//! max_iter = 512
+//! palette = "~/palettes/fire.map"
+//! screenshot_dir = "~/Pictures/flactal"
+//!
+//! [window]
+//! width = 1200   # 描画領域の幅（4 の倍数、カラーバーは含まない）
+//! height = 900   # 描画領域の高さ（4 の倍数）
+//! scale = "auto" # HiDPI の拡大率（auto / 1〜4。描画領域は拡大率倍の物理ピクセルで描画する）
+//!
+//! [precision]
+//! initial = 128  # 高精度モードで使う精度（ビット）の初期値
+//! max = 4096     # 精度の上限（ズーム倍率に応じて自動で上げる）
+//!
+//! [zoom]
+//! in = 0.8    # ホイール上・右クリックでの表示範囲の倍率
+//! out = 1.25  # ホイール下での表示範囲の倍率
+//!
+//! [mouse]
+//! scroll = "zoom"  # 2 本指スクロール・ホイールの操作（zoom: ズーム、pan: 移動して Ctrl / ⌘ + スクロールでズーム）
+//!
+//! [tone]
+//! gamma = 1.0       # パレットで着色した後のガンマ補正（1 より大きいと中間調が明るくなる）
+//! brightness = 0.0  # 明るさ（-1.0〜1.0）
+//! contrast = 1.0    # コントラスト（1 より大きいと明暗の差が広がる）
+//!
+//! [threshold]
+//! gpu_df64 = 1e4         # GPU の f32 → df64 に切り替えるズーム倍率（--backend gpu）
+//! gpu_to_cpu = 1e12      # GPU (df64) → CPU (f64) に切り替えるズーム倍率（--backend gpu）
+//! high_precision = 1e13  # CPU (f64) → 固定小数点に切り替えるズーム倍率
+//!
+//! [keys]
+//! reset = "R"
+//! save_image = "F12"
+//! ```
+
+use super::cli::arg_value;
+use super::colors::Tone;
+use super::settings::Settings;
+use minifb::Key;
+use std::path::{Path, PathBuf};
+
+/// 計算モードを切り替えるズーム倍率
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Thresholds {
+    /// GPU の f32 → df64（--backend gpu のみ）
+    pub gpu_df64: f64,
+    /// GPU (df64) → CPU (f64)（--backend gpu のみ）
+    pub gpu_to_cpu: f64,
+    /// CPU (f64) → 固定小数点
+    pub high_precision: f64,
+}
+
+/// マウスホイール・トラックパッドの 2 本指スクロールで行う操作
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ScrollAction {
+    /// カーソル位置を固定してズーム（マウス向け）
+    #[default]
+    Zoom,
+    /// スクロールした方向に表示範囲を移動し、Ctrl / ⌘ を押しながらでズーム（トラックパッド向け）
+    Pan,
+}
+
+impl std::str::FromStr for ScrollAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "zoom" => Ok(ScrollAction::Zoom),
+            "pan" => Ok(ScrollAction::Pan),
+            _ => Err(format!(
+                "スクロールの操作の指定が不正です（zoom / pan）: {}",
+                s
+            )),
+        }
+    }
+}
+
+/// 操作に割り当てるキー（Escape キーは常に終了）
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyBindings {
+    pub reset: Key,
+    pub save_image: Key,
+    pub save_location: Key,
+    pub reload_palette: Key,
+    pub coloring_mode: Key,
+    pub fractal: Key,
+    pub orbit: Key,
+    pub grid: Key,
+    pub julia_preview: Key,
+    pub julia: Key,
+    pub record: Key,
+    pub keyframe: Key,
+    pub export: Key,
+    pub antialias: Key,
+    pub pan_left: Key,
+    pub pan_right: Key,
+    pub pan_up: Key,
+    pub pan_down: Key,
+    pub zoom_in: Key,
+    pub zoom_out: Key,
+    pub rotate_left: Key,
+    pub rotate_right: Key,
+    pub phase_down: Key,
+    pub phase_up: Key,
+    pub gamma_down: Key,
+    pub gamma_up: Key,
+    pub brightness_down: Key,
+    pub brightness_up: Key,
+    pub contrast_down: Key,
+    pub contrast_up: Key,
+    pub tone_reset: Key,
+    pub quit: Key,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            reset: Key::R,
+            save_image: Key::S,
+            save_location: Key::P,
+            reload_palette: Key::L,
+            coloring_mode: Key::H,
+            fractal: Key::T,
+            orbit: Key::O,
+            grid: Key::G,
+            julia_preview: Key::I,
+            julia: Key::J,
+            record: Key::A,
+            keyframe: Key::K,
+            export: Key::E,
+            antialias: Key::X,
+            pan_left: Key::Left,
+            pan_right: Key::Right,
+            pan_up: Key::Up,
+            pan_down: Key::Down,
+            zoom_in: Key::Equal,
+            zoom_out: Key::Minus,
+            rotate_left: Key::LeftBracket,
+            rotate_right: Key::RightBracket,
+            phase_down: Key::Comma,
+            phase_up: Key::Period,
+            gamma_down: Key::Key1,
+            gamma_up: Key::Key2,
+            brightness_down: Key::Key3,
+            brightness_up: Key::Key4,
+            contrast_down: Key::Key5,
+            contrast_up: Key::Key6,
+            tone_reset: Key::Key0,
+            quit: Key::Q,
+        }
+    }
+}
+
+impl KeyBindings {
+    /// 設定ファイルでの操作名に対応するキー
+    fn action_mut(&mut self, action: &str) -> Option<&mut Key> {
+        let key = match action {
+            "reset" => &mut self.reset,
+            "save_image" => &mut self.save_image,
+            "save_location" => &mut self.save_location,
+            "reload_palette" => &mut self.reload_palette,
+            "coloring_mode" => &mut self.coloring_mode,
+            "fractal" => &mut self.fractal,
+            "orbit" => &mut self.orbit,
+            "grid" => &mut self.grid,
+            "julia_preview" => &mut self.julia_preview,
+            "julia" => &mut self.julia,
+            "record" => &mut self.record,
+            "keyframe" => &mut self.keyframe,
+            "export" => &mut self.export,
+            "antialias" => &mut self.antialias,
+            "pan_left" => &mut self.pan_left,
+            "pan_right" => &mut self.pan_right,
+            "pan_up" => &mut self.pan_up,
+            "pan_down" => &mut self.pan_down,
+            "zoom_in" => &mut self.zoom_in,
+            "zoom_out" => &mut self.zoom_out,
+            "rotate_left" => &mut self.rotate_left,
+            "rotate_right" => &mut self.rotate_right,
+            "phase_down" => &mut self.phase_down,
+            "phase_up" => &mut self.phase_up,
+            "gamma_down" => &mut self.gamma_down,
+            "gamma_up" => &mut self.gamma_up,
+            "brightness_down" => &mut self.brightness_down,
+            "brightness_up" => &mut self.brightness_up,
+            "contrast_down" => &mut self.contrast_down,
+            "contrast_up" => &mut self.contrast_up,
+            "tone_reset" => &mut self.tone_reset,
+            "quit" => &mut self.quit,
+            _ => return None,
+        };
+        Some(key)
+    }
+
+    /// 同じキーが複数の操作に割り当てられていないか確認
+    fn check_conflicts(&self) -> Result<(), String> {
+        let entries = [
+            ("reset", self.reset),
+            ("save_image", self.save_image),
+            ("save_location", self.save_location),
+            ("reload_palette", self.reload_palette),
+            ("coloring_mode", self.coloring_mode),
+            ("fractal", self.fractal),
+            ("orbit", self.orbit),
+            ("grid", self.grid),
+            ("julia_preview", self.julia_preview),
+            ("julia", self.julia),
+            ("record", self.record),
+            ("keyframe", self.keyframe),
+            ("export", self.export),
+            ("antialias", self.antialias),
+            ("pan_left", self.pan_left),
+            ("pan_right", self.pan_right),
+            ("pan_up", self.pan_up),
+            ("pan_down", self.pan_down),
+            ("zoom_in", self.zoom_in),
+            ("zoom_out", self.zoom_out),
+            ("rotate_left", self.rotate_left),
+            ("rotate_right", self.rotate_right),
+            ("phase_down", self.phase_down),
+            ("phase_up", self.phase_up),
+            ("gamma_down", self.gamma_down),
+            ("gamma_up", self.gamma_up),
+            ("brightness_down", self.brightness_down),
+            ("brightness_up", self.brightness_up),
+            ("contrast_down", self.contrast_down),
+            ("contrast_up", self.contrast_up),
+            ("tone_reset", self.tone_reset),
+            ("quit", self.quit),
+        ];
+        for (i, (name, key)) in entries.iter().enumerate() {
+            if let Some((other, _)) = entries[i + 1..].iter().find(|(_, k)| k == key) {
+                return Err(format!(
+                    "キー {} が keys.{} と keys.{} の両方に割り当てられています",
+                    key_name(*key),
+                    name,
+                    other
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// ユーザー設定
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// 最大反復回数・描画領域の大きさ・精度・ズームの倍率・切替閾値
+    pub settings: Settings,
+    /// `--palette` を指定しなかった場合のパレットファイル
+    pub palette: Option<PathBuf>,
+    /// ホイール・2 本指スクロールの操作
+    pub scroll: ScrollAction,
+    /// パレットで着色した後の色調補正
+    pub tone: Tone,
+    /// 設定ファイルで GPU の f32 → df64・GPU → CPU・CPU → 高精度の切替閾値を指定しているか（GPU を使う場合は未指定なら起動時に計測）
+    pub thresholds_set: bool,
+    /// 画像・位置ファイルの保存先（S キーの画像、P キーの位置ファイル、E キーの高解像度画像。必要になったときに作成）
+    pub screenshot_dir: PathBuf,
+    pub keys: KeyBindings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            settings: Settings::default(),
+            palette: None,
+            scroll: ScrollAction::default(),
+            tone: Tone::default(),
+            thresholds_set: false,
+            screenshot_dir: PathBuf::from("."),
+            keys: KeyBindings::default(),
+        }
+    }
+}
+
+impl Config {
+    /// `--config <file>` の設定ファイル、指定がなければ既定の場所の設定ファイルを読み込む
+    ///
+    /// 既定の場所（`$XDG_CONFIG_HOME/flactal/config.toml`、未設定なら
+    /// `~/.config/flactal/config.toml`）にファイルがなければ既定値を使います。
+    /// `--output-dir <dir>` を指定すると設定ファイルの `screenshot_dir` より優先し、
+    /// `Settings` の項目もコマンドライン引数で上書きします。
+    pub fn from_args() -> Result<Self, String> {
+        let mut config = match arg_value("--config") {
+            Some(path) => Self::load(Path::new(&path))?,
+            None => match default_path() {
+                Some(path) if path.exists() => Self::load(&path)?,
+                _ => Self::default(),
+            },
+        };
+        if let Some(dir) = arg_value("--output-dir") {
+            config.screenshot_dir = expand_home(&dir);
+        }
+        config.settings.apply_args()?;
+        Ok(config)
+    }
+
+    /// 読み書きする設定ファイルの場所（`--config <file>`、指定がなければ既定の場所）
+    pub fn path_from_args() -> Option<PathBuf> {
+        arg_value("--config")
+            .map(PathBuf::from)
+            .or_else(default_path)
+    }
+
+    /// 設定ファイルを読み込む
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut table = String::new();
+
+        for (lineno, line) in text.lines().enumerate() {
+            let at_line = |e: String| format!("{}行目: {}", lineno + 1, e);
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                table = name.trim().to_string();
+                if ![
+                    "window",
+                    "precision",
+                    "zoom",
+                    "mouse",
+                    "tone",
+                    "threshold",
+                    "keys",
+                ]
+                .contains(&table.as_str())
+                {
+                    return Err(at_line(format!("不明なテーブルです: [{}]", table)));
+                }
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| at_line(format!("`キー = 値` の形式ではありません: {}", line)))?;
+            let value = Value::parse(value.trim()).map_err(at_line)?;
+            config.set(&table, key.trim(), value).map_err(at_line)?;
+        }
+
+        config.keys.check_conflicts()?;
+        config.settings.check()?;
+        Ok(config)
+    }
+
+    /// テーブル `table` の項目 `key` に値を設定
+    fn set(&mut self, table: &str, key: &str, value: Value) -> Result<(), String> {
+        let name = if table.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", table, key)
+        };
+        match (table, key) {
+            ("", "max_iter") => self.settings.max_iter = positive_integer(&name, &value)?,
+            ("", "palette") => self.palette = Some(expand_home(&value.string(&name)?)),
+            ("", "screenshot_dir") => self.screenshot_dir = expand_home(&value.string(&name)?),
+            ("window", "width") => {
+                self.settings.width = positive_integer(&name, &value)? as usize;
+            }
+            ("window", "height") => {
+                self.settings.height = positive_integer(&name, &value)? as usize;
+            }
+            ("window", "scale") => {
+                self.settings.scale = match value {
+                    Value::Number(n) => n.to_string().parse()?,
+                    Value::String(s) => s.parse()?,
+                };
+            }
+            ("precision", "initial") => {
+                self.settings.initial_precision = positive_integer(&name, &value)?;
+            }
+            ("precision", "max") => {
+                self.settings.max_precision = positive_integer(&name, &value)?;
+            }
+            ("zoom", "in") => {
+                self.settings.zoom_in = zoom_factor(&name, value.number(&name)?, false)?;
+            }
+            ("zoom", "out") => {
+                self.settings.zoom_out = zoom_factor(&name, value.number(&name)?, true)?;
+            }
+            ("mouse", "scroll") => self.scroll = value.string(&name)?.parse()?,
+            ("tone", "gamma") => self.tone.gamma = positive(&name, value.number(&name)?)?,
+            ("tone", "brightness") => {
+                let brightness = value.number(&name)?;
+                if !(-1.0..=1.0).contains(&brightness) {
+                    return Err(format!(
+                        "{} には -1.0〜1.0 の値が必要です: {}",
+                        name, brightness
+                    ));
+                }
+                self.tone.brightness = brightness;
+            }
+            ("tone", "contrast") => self.tone.contrast = positive(&name, value.number(&name)?)?,
+            ("threshold", "gpu_df64") => {
+                self.settings.thresholds.gpu_df64 = positive(&name, value.number(&name)?)?;
+                self.thresholds_set = true;
+            }
+            ("threshold", "gpu_to_cpu") => {
+                self.settings.thresholds.gpu_to_cpu = positive(&name, value.number(&name)?)?;
+                self.thresholds_set = true;
+            }
+            ("threshold", "high_precision") => {
+                self.settings.thresholds.high_precision = positive(&name, value.number(&name)?)?;
+                self.thresholds_set = true;
+            }
+            ("keys", action) => {
+                let key_text = value.string(&name)?;
+                let key = parse_key(&key_text)
+                    .ok_or_else(|| format!("{} のキー名が不正です: {}", name, key_text))?;
+                *self
+                    .keys
+                    .action_mut(action)
+                    .ok_or_else(|| format!("不明な操作です: {}", name))? = key;
+            }
+            _ => return Err(format!("不明な設定項目です: {}", name)),
+        }
+        Ok(())
+    }
+}
+
+/// 設定ファイル `path` の `[threshold]` テーブルに GPU の f32 → df64・GPU → CPU・CPU → 高精度の切替閾値を書き込む
+///
+/// 既存の `gpu_df64`・`gpu_to_cpu`・`high_precision` の行は置き換え、それ以外の行やコメントはそのまま残します。
+/// ファイルがなければ作成します。
+pub fn store_thresholds(path: &Path, thresholds: &Thresholds) -> Result<(), String> {
+    store_table(
+        path,
+        "threshold",
+        &[
+            ("gpu_df64", format!("{:.1e}", thresholds.gpu_df64)),
+            ("gpu_to_cpu", format!("{:.1e}", thresholds.gpu_to_cpu)),
+            (
+                "high_precision",
+                format!("{:.1e}", thresholds.high_precision),
+            ),
+        ],
+    )
+}
+
+/// 設定ファイル `path` の `[tone]` テーブルに色調補正を書き込む（`store_thresholds` と同様）
+pub fn store_tone(path: &Path, tone: &Tone) -> Result<(), String> {
+    store_table(
+        path,
+        "tone",
+        &[
+            ("gamma", format!("{:.3}", tone.gamma)),
+            ("brightness", format!("{:.3}", tone.brightness)),
+            ("contrast", format!("{:.3}", tone.contrast)),
+        ],
+    )
+}
+
+/// 設定ファイル `path` のテーブル `table` に `キー = 値` の項目を書き込む
+///
+/// 既存の同じキーの行は置き換え、それ以外の行やコメントはそのまま残します。
+/// テーブルがなければ末尾に追加し、ファイルがなければ作成します。
+fn store_table(path: &Path, table_name: &str, values: &[(&str, String)]) -> Result<(), String> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    let entries: Vec<String> = values
+        .iter()
+        .map(|(key, value)| format!("{} = {}", key, value))
+        .collect();
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut table = String::new();
+    let mut inserted = false;
+    for line in text.lines() {
+        let content = strip_comment(line).trim();
+        if let Some(name) = content.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            table = name.trim().to_string();
+            lines.push(line.to_string());
+            if table == table_name && !inserted {
+                lines.extend(entries.iter().cloned());
+                inserted = true;
+            }
+            continue;
+        }
+        let key = content.split_once('=').map(|(key, _)| key.trim());
+        if table == table_name && values.iter().any(|(name, _)| Some(*name) == key) {
+            continue;
+        }
+        lines.push(line.to_string());
+    }
+    if !inserted {
+        if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.push(format!("[{}]", table_name));
+        lines.extend(entries);
+    }
+
+    if let Some(dir) = path.parent() {
+        ensure_dir(dir)?;
+    }
+    let mut text = lines.join("\n");
+    text.push('\n');
+    std::fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// 既定の設定ファイルの場所
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_home.join("flactal").join("config.toml"))
+}
+
+/// 先頭の `~/` をホームディレクトリに展開
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => Path::new(&home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// ズームの倍率を検証（ズームインは 1 未満、ズームアウトは 1 より大きい）
+fn zoom_factor(name: &str, factor: f64, zoom_out: bool) -> Result<f64, String> {
+    let valid = if zoom_out {
+        factor > 1.0 && factor.is_finite()
+    } else {
+        factor > 0.0 && factor < 1.0
+    };
+    if !valid {
+        let range = if zoom_out {
+            "1 より大きい値"
+        } else {
+            "0〜1 の値"
+        };
+        return Err(format!("{} には {}が必要です: {}", name, range, factor));
+    }
+    Ok(factor)
+}
+
+/// 正の有限値であることを検証（切替閾値のズーム倍率・ガンマ・コントラスト）
+fn positive(name: &str, value: f64) -> Result<f64, String> {
+    if !(value > 0.0 && value.is_finite()) {
+        return Err(format!("{} には正の値が必要です: {}", name, value));
+    }
+    Ok(value)
+}
+
+/// 正の整数であることを検証（最大反復回数・描画領域の大きさ・精度）
+fn positive_integer(name: &str, value: &Value) -> Result<u32, String> {
+    value
+        .number(name)
+        .ok()
+        .filter(|&n| n >= 1.0 && n <= u32::MAX as f64 && n.fract() == 0.0)
+        .map(|n| n as u32)
+        .ok_or_else(|| format!("{} には正の整数が必要です", name))
+}
+
+/// 文字列の外にある `#` 以降を取り除く
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, ch) in line.char_indices() {
+        match quote {
+            None if ch == '#' => return &line[..i],
+            None if ch == '"' || ch == '\'' => quote = Some(ch),
+            // 基本文字列の中では `\` の次の文字を読み飛ばす
+            Some('"') if escaped => escaped = false,
+            Some('"') if ch == '\\' => escaped = true,
+            Some(q) if ch == q => quote = None,
+            _ => {}
+        }
+    }
+    line
+}
+
+/// 設定値
+enum Value {
+    String(String),
+    Number(f64),
+}
+
+impl Value {
+    /// `"基本文字列"`・`'リテラル文字列'`・数値（`_` 区切り可）を解析
+    fn parse(text: &str) -> Result<Self, String> {
+        if let Some(inner) = text.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+            return Ok(Value::String(inner.to_string()));
+        }
+        if let Some(inner) = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+            let mut s = String::new();
+            let mut chars = inner.chars();
+            while let Some(ch) = chars.next() {
+                if ch != '\\' {
+                    s.push(ch);
+                    continue;
+                }
+                match chars.next() {
+                    Some('\\') => s.push('\\'),
+                    Some('"') => s.push('"'),
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    other => {
+                        return Err(format!(
+                            "文字列のエスケープが不正です: \\{}",
+                            other.map(String::from).unwrap_or_default()
+                        ))
+                    }
+                }
+            }
+            return Ok(Value::String(s));
+        }
+        text.replace('_', "")
+            .parse()
+            .map(Value::Number)
+            .map_err(|_| format!("値として解釈できません: {}", text))
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "文字列",
+            Value::Number(_) => "数値",
+        }
+    }
+
+    fn string(self, name: &str) -> Result<String, String> {
+        match self {
+            Value::String(s) => Ok(s),
+            other => Err(format!(
+                "{} には文字列が必要です（{}）",
+                name,
+                other.type_name()
+            )),
+        }
+    }
+
+    fn number(&self, name: &str) -> Result<f64, String> {
+        match self {
+            Value::Number(n) => Ok(*n),
+            other => Err(format!(
+                "{} には数値が必要です（{}）",
+                name,
+                other.type_name()
+            )),
+        }
+    }
+}
+
+/// 設定ファイルで使うキー名とキーの対応（英字・数字・F1〜F12 以外）
+const NAMED_KEYS: [(&str, Key); 20] = [
+    ("Space", Key::Space),
+    ("Tab", Key::Tab),
+    ("Enter", Key::Enter),
+    ("Backspace", Key::Backspace),
+    ("Delete", Key::Delete),
+    ("Insert", Key::Insert),
+    ("Home", Key::Home),
+    ("End", Key::End),
+    ("PageUp", Key::PageUp),
+    ("PageDown", Key::PageDown),
+    ("Left", Key::Left),
+    ("Right", Key::Right),
+    ("Up", Key::Up),
+    ("Down", Key::Down),
+    ("Equal", Key::Equal),
+    ("Minus", Key::Minus),
+    ("LeftBracket", Key::LeftBracket),
+    ("RightBracket", Key::RightBracket),
+    ("Comma", Key::Comma),
+    ("Period", Key::Period),
+];
+
+/// 英字・数字・F1〜F12 のキー
+const LETTER_KEYS: [Key; 26] = [
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+];
+const DIGIT_KEYS: [Key; 10] = [
+    Key::Key0,
+    Key::Key1,
+    Key::Key2,
+    Key::Key3,
+    Key::Key4,
+    Key::Key5,
+    Key::Key6,
+    Key::Key7,
+    Key::Key8,
+    Key::Key9,
+];
+const FUNCTION_KEYS: [Key; 12] = [
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+];
+
+/// キー名（`"R"`、`"7"`、`"F5"`、`"Space"` など、大文字小文字は区別しない）を解析
+pub fn parse_key(name: &str) -> Option<Key> {
+    let upper = name.to_ascii_uppercase();
+    let mut chars = upper.chars();
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        return match ch {
+            'A'..='Z' => Some(LETTER_KEYS[(ch as u8 - b'A') as usize]),
+            '0'..='9' => Some(DIGIT_KEYS[(ch as u8 - b'0') as usize]),
+            _ => None,
+        };
+    }
+    if let Some(n) = upper
+        .strip_prefix('F')
+        .and_then(|n| n.parse::<usize>().ok())
+    {
+        return FUNCTION_KEYS.get(n.checked_sub(1)?).copied();
+    }
+    NAMED_KEYS
+        .iter()
+        .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+        .map(|&(_, key)| key)
+}
+
+/// 操作説明などに表示するキー名（`parse_key` で解析できる形式）
+pub fn key_name(key: Key) -> String {
+    if let Some(i) = LETTER_KEYS.iter().position(|&k| k == key) {
+        return ((b'A' + i as u8) as char).to_string();
+    }
+    if let Some(i) = DIGIT_KEYS.iter().position(|&k| k == key) {
+        return i.to_string();
+    }
+    if let Some(i) = FUNCTION_KEYS.iter().position(|&k| k == key) {
+        return format!("F{}", i + 1);
+    }
+    NAMED_KEYS
+        .iter()
+        .find(|&&(_, k)| k == key)
+        .map_or_else(|| format!("{:?}", key), |(name, _)| name.to_string())
+}
+
+/// 保存先のディレクトリがなければ作成
+pub fn ensure_dir(dir: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_thresholds_are_read_back() {
+        let path =
+            std::env::temp_dir().join(format!("flactal-thresholds-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "max_iter = 300\n\n[threshold]\ngpu_df64 = 1e3  # 古い値\n",
+        )
+        .unwrap();
+        let thresholds = Thresholds {
+            gpu_df64: 3.2e4,
+            gpu_to_cpu: 1.0e11,
+            high_precision: 5.6e12,
+        };
+        store_thresholds(&path, &thresholds).unwrap();
+        let config = Config::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let config = config.unwrap();
+        assert!(config.thresholds_set);
+        assert_eq!(config.settings.thresholds, thresholds);
+        assert_eq!(config.settings.max_iter, 300);
+    }
+}
@@ -1,37 +1,89 @@
 //! 共通定数
+//!
+//! 描画領域の大きさ・最大反復回数・精度・切替閾値・ズームの倍率は既定値で、
+//! 実行時の値は設定ファイルとコマンドライン引数で上書きした `Settings` から読みます。
 
-/// マンデルブロ描画領域の幅
+/// マンデルブロ描画領域の既定の幅
 pub const MANDELBROT_WIDTH: usize = 800;
-/// マンデルブロ描画領域の高さ
+/// マンデルブロ描画領域の既定の高さ
 pub const MANDELBROT_HEIGHT: usize = 600;
 
-/// 高精度モード時の低解像度設定
-pub const HP_RENDER_WIDTH: usize = 200;
-pub const HP_RENDER_HEIGHT: usize = 150;
+/// 高精度モードは描画領域の縦横 1/4 の解像度から描画を始める
+pub const HP_RENDER_DIVISOR: usize = 4;
+
+/// ジュリア集合プレビューの解像度
+pub const JULIA_PREVIEW_WIDTH: usize = 200;
+pub const JULIA_PREVIEW_HEIGHT: usize = 150;
+
+/// ミニマップの解像度
+pub const MINIMAP_WIDTH: usize = 160;
+pub const MINIMAP_HEIGHT: usize = 120;
 
 /// カラーバーの設定
 pub const COLORBAR_WIDTH: usize = 60;
 pub const COLORBAR_MARGIN: usize = 20;
 pub const COLORBAR_BAR_WIDTH: usize = 20;
 
-/// 全体のウィンドウサイズ
-pub const WINDOW_WIDTH: usize = MANDELBROT_WIDTH + COLORBAR_WIDTH;
-pub const WINDOW_HEIGHT: usize = MANDELBROT_HEIGHT;
-
-/// 最大反復回数
+/// 既定の最大反復回数
 pub const MAX_ITER: u32 = 256;
 
-/// 初期精度（ビット）
+/// 既定の初期精度（ビット）
 pub const INITIAL_PRECISION: u32 = 128;
 
-/// 最大精度（ビット）
+/// 既定の最大精度（ビット）
 pub const MAX_PRECISION: u32 = 4096;
 
-/// 高精度計算モードへの切り替え閾値（ズーム倍率）
+/// CPU (f64) から固定小数点への既定の切り替え閾値（ズーム倍率）
 pub const PRECISION_THRESHOLD: f64 = 1e13;
 
+/// 固定小数点数（`common::fixed`）から摂動法への切り替え閾値（ズーム倍率）
+///
+/// 固定小数点数の小数部は 120 ビット（約 7.5e-37）で、×10^30 ではピクセル間隔が約 5e-33 になり、
+/// 反復による誤差の蓄積を見込んでも十分な余裕があります。
+pub const FIXED_POINT_THRESHOLD: f64 = 1e30;
+
+/// GPU の f32 から double-double (df64) への切り替え閾値（ズーム倍率）
+///
+/// f32 の仮数部は24ビットのため、隣り合うピクセルの座標の差が座標の大きさの 2^-23 程度になる
+/// 倍率を超えるとピクセルが区別できなくなります。
+pub const GPU_DF64_THRESHOLD: f64 = 1e4;
+
+/// GPU (df64) から CPU (f64) への切り替え閾値（ズーム倍率）
+///
+/// df64 の精度は約48ビットで f64 より5ビットほど少ないため、高精度モードへの閾値の 1/10 とします。
+pub const GPU_TO_CPU_THRESHOLD: f64 = 1e12;
+
 /// マウスホイールによるズームアウト倍率
 pub const ZOOM_FACTOR_OUT: f64 = 1.25;
 
 /// マウスホイールによるズームイン倍率（右クリックも同様）
 pub const ZOOM_FACTOR_IN: f64 = 0.8;
+
+/// ホイール 1 ノッチあたりのスクロール量（minifb が返す値。Windows は WHEEL_DELTA の 1/10）
+///
+/// これより小さいスクロール（トラックパッドの 2 本指スクロール）は、量に比例した倍率でなめらかにズームします。
+pub const SCROLL_NOTCH: f32 = if cfg!(windows) { 12.0 } else { 1.0 };
+
+/// トラックパッドでのパン（`[mouse] scroll = "pan"`）で、スクロール量 1 あたりに動かすピクセル数
+pub const SCROLL_PAN_PIXELS: f64 = 10.0;
+
+/// 矢印キー 1 回で動かす距離（表示幅に対する割合）
+pub const KEY_PAN_FRACTION: f64 = 0.05;
+
+/// 矢印キー・ズームキーを押し続けたとき、キーリピート 1 回ごとに増える移動量・ズーム量の割合
+pub const KEY_ACCELERATION: f64 = 0.1;
+
+/// 押し続けたときの移動量・ズーム量の上限（最初の 1 回に対する倍率）
+pub const KEY_MAX_ACCELERATION: f64 = 4.0;
+
+/// 回転キー 1 回で回す角度（度）
+pub const ROTATION_STEP_DEGREES: f64 = 5.0;
+
+/// 色相の位相キー 1 回でずらす量（HSV パレットの色相の周）
+pub const PALETTE_PHASE_STEP: f64 = 1.0 / 32.0;
+
+/// 色調補正キー 1 回で掛ける・割るガンマとコントラストの倍率
+pub const TONE_FACTOR_STEP: f64 = 1.1;
+
+/// 色調補正キー 1 回で増減する明るさ
+pub const BRIGHTNESS_STEP: f64 = 0.05;
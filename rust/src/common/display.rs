@@ -0,0 +1,101 @@
+//! 画面の拡大率（HiDPI）
+//!
+//! HiDPI ディスプレイでは描画領域を拡大率倍の物理ピクセルで描画し、ぼやけずに表示します。
+//! 拡大率は `--scale <auto|1|2|3|4>`（設定ファイルの `[window] scale`）で指定し、`auto` では
+//! 環境変数 `GDK_SCALE`・`QT_SCALE_FACTOR`、macOS ではメインディスプレイの backingScaleFactor から決めます。
+//!
+//! minifb は macOS ではウィンドウを論理ピクセル（ポイント）の大きさで作り、バッファをウィンドウ全体に
+//! 引き伸ばして表示するため、ウィンドウは拡大率で割った大きさで作り、マウス座標は拡大率を掛けて
+//! バッファのピクセル座標に戻します。その他の環境ではウィンドウもバッファと同じ物理ピクセルの大きさです。
+
+use std::fmt;
+use std::str::FromStr;
+
+/// 拡大率の上限
+pub const MAX_SCALE_FACTOR: usize = 4;
+
+/// ウィンドウの大きさを論理ピクセルで指定する環境か（macOS）
+pub const WINDOW_IN_POINTS: bool = cfg!(target_os = "macos");
+
+/// 拡大率の指定
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScaleFactor {
+    /// ディスプレイから検出する
+    #[default]
+    Auto,
+    /// 1〜`MAX_SCALE_FACTOR` の整数倍
+    Fixed(usize),
+}
+
+impl ScaleFactor {
+    /// 拡大率を決める（`Auto` なら検出する）
+    pub fn resolve(self) -> usize {
+        match self {
+            ScaleFactor::Auto => detect_scale_factor(),
+            ScaleFactor::Fixed(factor) => factor,
+        }
+    }
+}
+
+impl FromStr for ScaleFactor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ScaleFactor::Auto),
+            _ => s
+                .parse()
+                .ok()
+                .filter(|factor| (1..=MAX_SCALE_FACTOR).contains(factor))
+                .map(ScaleFactor::Fixed)
+                .ok_or_else(|| format!("拡大率の指定が不正です（auto / 1 / 2 / 3 / 4）: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for ScaleFactor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScaleFactor::Auto => write!(f, "auto"),
+            ScaleFactor::Fixed(factor) => write!(f, "{}", factor),
+        }
+    }
+}
+
+/// ディスプレイの拡大率を検出（分からなければ 1、小数の倍率は四捨五入）
+pub fn detect_scale_factor() -> usize {
+    let factor = env_scale_factor()
+        .or_else(platform_scale_factor)
+        .unwrap_or(1.0);
+    (factor.round() as usize).clamp(1, MAX_SCALE_FACTOR)
+}
+
+/// GTK・Qt の拡大率の環境変数（X11・Wayland のデスクトップ環境が設定する）
+fn env_scale_factor() -> Option<f64> {
+    ["GDK_SCALE", "QT_SCALE_FACTOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .filter_map(|value| value.trim().parse::<f64>().ok())
+        .find(|factor| factor.is_finite() && *factor > 0.0)
+}
+
+/// メインディスプレイの backingScaleFactor（Retina なら 2）を AppKit に問い合わせる
+#[cfg(target_os = "macos")]
+fn platform_scale_factor() -> Option<f64> {
+    let output = std::process::Command::new("osascript")
+        .args([
+            "-l",
+            "JavaScript",
+            "-e",
+            "ObjC.import('AppKit'); $.NSScreen.mainScreen.backingScaleFactor",
+        ])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+#[cfg(not(target_os = "macos"))]
+fn platform_scale_factor() -> Option<f64> {
+    None
+}
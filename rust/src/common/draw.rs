@@ -0,0 +1,141 @@
+//! バッファへの図形の描画
+//!
+//! いずれも横 `buffer_width` ピクセルのバッファの左上 `clip.0` × `clip.1` ピクセルの範囲にだけ描画し、
+//! 座標が範囲外や非常に遠くてもかまいません。範囲の左上をずらすときはバッファの部分スライスを渡します。
+//! 座標はピクセル単位で、小数は最も近いピクセルに丸めます。
+
+/// 線分を描画
+pub fn draw_line(
+    buffer: &mut [u32],
+    buffer_width: usize,
+    clip: (usize, usize),
+    from: (f64, f64),
+    to: (f64, f64),
+    color: u32,
+) {
+    // Liang-Barsky 法で線分を描画範囲に切り詰める
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let (max_x, max_y) = (clip.0 as f64 - 1.0, clip.1 as f64 - 1.0);
+    let mut t0: f64 = 0.0;
+    let mut t1: f64 = 1.0;
+    for (p, q) in [
+        (-dx, from.0),
+        (dx, max_x - from.0),
+        (-dy, from.1),
+        (dy, max_y - from.1),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return;
+            }
+        } else if p < 0.0 {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+    if t0 > t1 {
+        return;
+    }
+
+    let (x0, y0) = (from.0 + dx * t0, from.1 + dy * t0);
+    let (x1, y1) = (from.0 + dx * t1, from.1 + dy * t1);
+    let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as usize;
+    for i in 0..=steps {
+        let t = i as f64 / steps as f64;
+        plot(
+            buffer,
+            buffer_width,
+            clip,
+            x0 + (x1 - x0) * t,
+            y0 + (y1 - y0) * t,
+            color,
+        );
+    }
+}
+
+/// 点を 1 つ描画（範囲外なら何もしない）
+fn plot(buffer: &mut [u32], buffer_width: usize, clip: (usize, usize), x: f64, y: f64, color: u32) {
+    let (px, py) = (x.round(), y.round());
+    if px >= 0.0 && py >= 0.0 && (px as usize) < clip.0 && (py as usize) < clip.1 {
+        buffer[py as usize * buffer_width + px as usize] = color;
+    }
+}
+
+/// 長方形の枠を描画（`top_left` と `bottom_right` の角のピクセルを含む）
+pub fn draw_rect(
+    buffer: &mut [u32],
+    buffer_width: usize,
+    clip: (usize, usize),
+    top_left: (f64, f64),
+    bottom_right: (f64, f64),
+    color: u32,
+) {
+    let (x0, y0) = top_left;
+    let (x1, y1) = bottom_right;
+    for (from, to) in [
+        ((x0, y0), (x1, y0)),
+        ((x1, y0), (x1, y1)),
+        ((x1, y1), (x0, y1)),
+        ((x0, y1), (x0, y0)),
+    ] {
+        draw_line(buffer, buffer_width, clip, from, to, color);
+    }
+}
+
+/// 長方形を塗りつぶす（`top_left` と `bottom_right` の角のピクセルを含む）
+pub fn fill_rect(
+    buffer: &mut [u32],
+    buffer_width: usize,
+    clip: (usize, usize),
+    top_left: (f64, f64),
+    bottom_right: (f64, f64),
+    color: u32,
+) {
+    let clamp = |value: f64, size: usize| value.round().clamp(0.0, size as f64) as usize;
+    let (left, top) = (clamp(top_left.0, clip.0), clamp(top_left.1, clip.1));
+    let (right, bottom) = (
+        clamp(bottom_right.0 + 1.0, clip.0),
+        clamp(bottom_right.1 + 1.0, clip.1),
+    );
+    for y in top..bottom {
+        buffer[y * buffer_width + left..y * buffer_width + right.max(left)].fill(color);
+    }
+}
+
+/// 円周を描画
+pub fn draw_circle(
+    buffer: &mut [u32],
+    buffer_width: usize,
+    clip: (usize, usize),
+    center: (f64, f64),
+    radius: f64,
+    color: u32,
+) {
+    // 中点アルゴリズムで 1/8 円を求め、対称な 8 点を描く
+    let (cx, cy) = (center.0.round(), center.1.round());
+    let mut x = radius.round().max(0.0);
+    let mut y = 0.0;
+    let mut error = 1.0 - x;
+    while y <= x {
+        for (dx, dy) in [
+            (x, y),
+            (y, x),
+            (-y, x),
+            (-x, y),
+            (-x, -y),
+            (-y, -x),
+            (y, -x),
+            (x, -y),
+        ] {
+            plot(buffer, buffer_width, clip, cx + dx, cy + dy, color);
+        }
+        y += 1.0;
+        if error < 0.0 {
+            error += 2.0 * y + 1.0;
+        } else {
+            x -= 1.0;
+            error += 2.0 * (y - x) + 1.0;
+        }
+    }
+}
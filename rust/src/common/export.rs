@@ -0,0 +1,207 @@
+//! ウィンドウサイズに依存しない高解像度の書き出し
+
+use super::animation::View;
+use super::cli::{arg_value, parse_size};
+use super::colors::{average_samples, Colorizer, Palette};
+use super::render::SharedFrame;
+use super::snapshot::Snapshot;
+use rayon::prelude::*;
+use rug::Float;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// 既定の書き出しサイズ（8K）
+pub const DEFAULT_EXPORT_WIDTH: usize = 7680;
+pub const DEFAULT_EXPORT_HEIGHT: usize = 4320;
+
+/// 既定のスーパーサンプリング数（1ピクセルあたり 2×2 点）
+pub const DEFAULT_EXPORT_SAMPLES: usize = 2;
+
+/// ヘッドレス書き出しの既定の出力ファイル
+pub const DEFAULT_EXPORT_FILE: &str = "mandelbrot_export.png";
+
+/// `render` サブコマンドの既定の出力サイズ（フルHD）
+pub const DEFAULT_RENDER_WIDTH: usize = 1920;
+pub const DEFAULT_RENDER_HEIGHT: usize = 1080;
+
+/// `render` サブコマンドの既定の出力ファイル
+pub const DEFAULT_RENDER_FILE: &str = "mandelbrot_render.png";
+
+/// 一度に描画する出力画像の行数（メモリ使用量を帯1本分に抑える）
+const BAND_ROWS: usize = 64;
+
+/// 高解像度書き出しの設定
+pub struct ExportOptions {
+    pub width: usize,
+    pub height: usize,
+    /// 1ピクセルあたりの縦横のサンプル数（samples × samples 点の平均）
+    pub samples: usize,
+}
+
+impl ExportOptions {
+    /// コマンドライン引数（`--export-size <幅>x<高さ>`、`--export-samples <数>`）から作成
+    pub fn from_args() -> Result<Self, String> {
+        Self::from_named_args(
+            "--export-size",
+            "--export-samples",
+            (DEFAULT_EXPORT_WIDTH, DEFAULT_EXPORT_HEIGHT),
+            DEFAULT_EXPORT_SAMPLES,
+        )
+    }
+
+    /// 名前 `size_flag`（`<幅>x<高さ>`）・`samples_flag` のオプションから作成し、省略時は既定値を使う
+    fn from_named_args(
+        size_flag: &str,
+        samples_flag: &str,
+        (default_width, default_height): (usize, usize),
+        default_samples: usize,
+    ) -> Result<Self, String> {
+        let (width, height) = match arg_value(size_flag) {
+            Some(v) => parse_size(&v)
+                .ok_or_else(|| format!("書き出しサイズは <幅>x<高さ> で指定してください: {}", v))?,
+            None => (default_width, default_height),
+        };
+        let samples = match arg_value(samples_flag) {
+            Some(v) => v
+                .parse()
+                .ok()
+                .filter(|&n| (1..=8).contains(&n))
+                .ok_or_else(|| format!("サンプル数は 1〜8 で指定してください: {}", v))?,
+            None => default_samples,
+        };
+
+        Ok(Self {
+            width,
+            height,
+            samples,
+        })
+    }
+}
+
+/// `render` サブコマンド（ウィンドウを開かずに静止画を描画）の指定
+pub struct RenderRequest {
+    pub view: View,
+    /// 最大反復回数（省略時は設定ファイルの値）
+    pub max_iter: Option<u32>,
+    pub file: PathBuf,
+    pub options: ExportOptions,
+}
+
+impl RenderRequest {
+    /// `--center <実部>,<虚部>` / `--zoom` / `--size <幅>x<高さ>` / `--samples` / `--iter` / `--out` を読み込む
+    ///
+    /// 中心を省略すると初期表示の中心、ズーム倍率を省略すると 1 倍、サイズを省略すると
+    /// 1920×1080（スーパーサンプリングなし）になります。
+    pub fn from_args() -> Result<Self, String> {
+        let zoom = match arg_value("--zoom") {
+            Some(v) => v
+                .parse()
+                .map_err(|_| format!("ズーム倍率が不正です: {}", v))?,
+            None => 1.0,
+        };
+        let view = match arg_value("--center") {
+            Some(v) => {
+                let (re, im) = v.split_once(',').ok_or_else(|| {
+                    format!("中心座標は <実部>,<虚部> の形式で指定してください: {}", v)
+                })?;
+                View::from_decimal(re.trim(), im.trim(), zoom)?
+            }
+            None => View::from_decimal("-0.75", "0", zoom)?,
+        };
+        let max_iter = match arg_value("--iter") {
+            Some(v) => Some(
+                v.parse()
+                    .ok()
+                    .filter(|&n: &u32| n > 0)
+                    .ok_or_else(|| format!("最大反復回数が不正です: {}", v))?,
+            ),
+            None => None,
+        };
+        let options = ExportOptions::from_named_args(
+            "--size",
+            "--samples",
+            (DEFAULT_RENDER_WIDTH, DEFAULT_RENDER_HEIGHT),
+            1,
+        )?;
+
+        Ok(Self {
+            view,
+            max_iter,
+            file: PathBuf::from(arg_value("--out").unwrap_or_else(|| DEFAULT_RENDER_FILE.into())),
+            options,
+        })
+    }
+}
+
+/// `snapshot` の表示を高解像度で描画して PNG に書き出す
+///
+/// 表示範囲全体が収まるようにピクセルを正方形として範囲を決め、出力画像を横長の帯に分けて
+/// `render_band` で帯ごとに `samples` 倍の解像度で描画します。各ピクセルはサンプルの色の平均で、
+/// 書き出した帯は順に PNG へ流し込むため、出力サイズによらずメモリ使用量は帯1本分です。
+/// `render_band` は渡されたフレームの全ピクセルを表示範囲に合わせて描画してください。
+pub fn export_image<F>(
+    path: &Path,
+    snapshot: &Snapshot,
+    options: &ExportOptions,
+    palette: &Palette,
+    colorizer: &Colorizer,
+    mut render_band: F,
+) -> Result<(), String>
+where
+    F: FnMut(&View, &SharedFrame),
+{
+    let png_error = |e: png::EncodingError| format!("{}: {}", path.display(), e);
+    let (width, height, samples) = (options.width, options.height, options.samples);
+    let view = &snapshot.view;
+    let prec = view.center_x.prec();
+    // 出力1ピクセルの大きさ
+    let scale = (view.width / width as f64).max(view.height / height as f64);
+
+    let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut writer = snapshot
+        .png_encoder(BufWriter::new(file), width, height)
+        .and_then(|encoder| encoder.write_header())
+        .map_err(png_error)?;
+    let mut stream = writer.stream_writer().map_err(png_error)?;
+    let started = Instant::now();
+
+    for top in (0..height).step_by(BAND_ROWS) {
+        let rows = BAND_ROWS.min(height - top);
+        // 帯の中心は画像の中心から (height/2 - 帯の中心の行) ピクセル上（回転している場合はその向きに）
+        let offset = (height as f64 / 2.0 - (top as f64 + rows as f64 / 2.0)) * scale;
+        let (offset_x, offset_y) = view.rotate(0.0, offset);
+        let band = View {
+            center_x: Float::with_val(prec, &view.center_x + offset_x),
+            center_y: Float::with_val(prec, &view.center_y + offset_y),
+            width: width as f64 * scale,
+            height: rows as f64 * scale,
+            rotation: view.rotation,
+        };
+
+        let frame = SharedFrame::new(width * samples, rows * samples);
+        render_band(&band, &frame);
+        let colors: Vec<u32> = frame
+            .snapshot()
+            .iters
+            .par_iter()
+            .map(|&iter| colorizer.color(iter, palette))
+            .collect();
+        let rgb: Vec<u8> = average_samples(&colors, width, samples)
+            .into_iter()
+            .flat_map(|color| [(color >> 16) as u8, (color >> 8) as u8, color as u8])
+            .collect();
+        stream
+            .write_all(&rgb)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        println!(
+            "🖨 書き出し中: {}/{} 行 ({:.2?})",
+            top + rows,
+            height,
+            started.elapsed()
+        );
+    }
+
+    stream.finish().map_err(png_error)
+}
@@ -0,0 +1,73 @@
+//! 保存する画像・位置ファイルの名前
+//!
+//! ファイル名には保存した日時（UTC、ISO 8601 の基本形式）と表示の中心・ズーム倍率の要約を入れます:
+//!
+//! ```text
+//! mandelbrot_20261016T153012Z_-0.743644+0.131826i_x1.5e10.png
+//! ```
+//!
+//! 同じ名前のファイルが既にあれば `_2`、`_3`、… を付けて、前回までの実行で保存したファイルを上書きしません。
+
+use super::animation::View;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `dir` に保存するファイルのパス（`<prefix>_<日時>_<中心>_x<ズーム倍率>.<extension>`）
+pub fn output_path(dir: &Path, prefix: &str, view: &View, extension: &str) -> PathBuf {
+    let stem = format!("{}_{}_{}", prefix, utc_timestamp(), view_summary(view));
+    let mut path = dir.join(format!("{}.{}", stem, extension));
+    let mut suffix = 2;
+    while path.exists() {
+        path = dir.join(format!("{}_{}.{}", stem, suffix, extension));
+        suffix += 1;
+    }
+    path
+}
+
+/// 中心座標とズーム倍率の要約（`-0.743644+0.131826i_x1.5e10`）
+fn view_summary(view: &View) -> String {
+    format!(
+        "{:.6}{:+.6}i_x{:.1e}",
+        view.center_x.to_f64(),
+        view.center_y.to_f64(),
+        view.zoom()
+    )
+}
+
+/// 現在の日時（UTC）を `YYYYMMDDTHHMMSSZ` の形式で取得
+fn utc_timestamp() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let (days, time) = (seconds / 86400, seconds % 86400);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// 1970-01-01 からの日数をグレゴリオ暦の (年, 月, 日) に変換
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // 3月始まりの400年周期で数える（2月末の閏日が年の最後に来る）
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
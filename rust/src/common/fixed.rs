@@ -0,0 +1,246 @@
+//! 128 ビット固定小数点数による反復
+//!
+//! f64 の精度が足りなくなる倍率（×10^13）から ×10^30 程度までは、任意精度（rug）を使わずに
+//! 小数部 120 ビットの固定小数点数（i128）で各ピクセルを反復します。
+//! 値はメモリを確保せず整数演算だけで計算できるため、同じ精度の `Float` より大幅に速くなります。
+
+use super::mandelbrot::{EscapeKind, EscapeTimeParams};
+use rug::Float;
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// 小数部のビット数（整数部は符号を除いて 7 ビットで、±128 未満の値を表せる）
+pub const FRAC_BITS: u32 = 120;
+
+/// 固定小数点数で反復できる累乗の次数の上限
+///
+/// |z| ≤ 2 の z を累乗しても整数部があふれないよう、2^power + |c| が 128 未満になる次数までとします。
+pub const MAX_FIXED_POWER: u32 = 6;
+
+/// 小数部 `FRAC_BITS` ビットの符号付き固定小数点数
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Fixed(i128);
+
+impl Fixed {
+    pub const ZERO: Self = Self(0);
+
+    /// f64 から変換（小数部 `FRAC_BITS` ビットより細かい部分は切り捨て）
+    pub fn from_f64(value: f64) -> Self {
+        Self((value * 2f64.powi(FRAC_BITS as i32)) as i128)
+    }
+
+    /// 任意精度の値から変換
+    ///
+    /// f64 で表せる上位の桁から順に 3 つの f64 に分けて足し合わせるため、
+    /// 約 159 ビット分の仮数部を引き継げます（小数部 `FRAC_BITS` ビットには十分です）。
+    pub fn from_float(value: &Float) -> Self {
+        let mut rest = value.clone();
+        let mut fixed = Self::ZERO;
+        for _ in 0..3 {
+            let part = rest.to_f64();
+            fixed = fixed + Self::from_f64(part);
+            rest -= part;
+        }
+        fixed
+    }
+
+    /// f64 に変換
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / 2f64.powi(FRAC_BITS as i32)
+    }
+
+    /// 絶対値
+    pub fn abs(self) -> Self {
+        Self(self.0.abs())
+    }
+
+    /// 2乗
+    pub fn square(self) -> Self {
+        self * self
+    }
+
+    /// 2倍
+    pub fn double(self) -> Self {
+        Self(self.0 << 1)
+    }
+}
+
+impl Add for Fixed {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self(self.0 + other.0)
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self(self.0 - other.0)
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(-self.0)
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Self;
+
+    /// 256 ビットの積を 64 ビットずつの部分積から求め、`FRAC_BITS` ビット右にずらす（0 方向に切り捨て）
+    fn mul(self, other: Self) -> Self {
+        let (a, b) = (self.0.unsigned_abs(), other.0.unsigned_abs());
+        let (a1, a0) = (a >> 64, a & u64::MAX as u128);
+        let (b1, b0) = (b >> 64, b & u64::MAX as u128);
+
+        // 積 = hi·2^128 + (mid1 + mid2)·2^64 + lo
+        let lo = a0 * b0;
+        let (mid, mid_carry) = (a1 * b0).overflowing_add(a0 * b1);
+        let (low, low_carry) = lo.overflowing_add(mid << 64);
+        let high = a1 * b1 + (mid >> 64) + ((mid_carry as u128) << 64) + low_carry as u128;
+
+        let magnitude = ((high << (128 - FRAC_BITS)) | (low >> FRAC_BITS)) as i128;
+        if (self.0 < 0) != (other.0 < 0) {
+            Self(-magnitude)
+        } else {
+            Self(magnitude)
+        }
+    }
+}
+
+/// 固定小数点数で反復できる式か（累乗の次数が `MAX_FIXED_POWER` 以下で、発散判定の半径が 2 以下）
+pub fn supports(params: &EscapeTimeParams) -> bool {
+    params.power <= MAX_FIXED_POWER && params.bailout <= 2.0
+}
+
+/// z_0 = (z_re, z_im) から `params` の式を固定小数点数で反復し、発散までの回数を返す
+///
+/// 整数部があふれないよう、実部か虚部の絶対値が半径を超えた時点でも発散とみなします
+/// （このとき |z| も半径を超えているため、回数は |z|² だけで判定した場合と同じです）。
+/// `params` は `supports` を満たすこと。
+pub fn iter_fixed(
+    params: &EscapeTimeParams,
+    z_re: Fixed,
+    z_im: Fixed,
+    c_re: Fixed,
+    c_im: Fixed,
+    max_iter: u32,
+) -> u32 {
+    let bailout = Fixed::from_f64(params.bailout);
+    let bailout_sqr = bailout.square();
+    let (mut z_re, mut z_im) = (z_re, z_im);
+
+    for i in 0..max_iter {
+        if z_re.abs() > bailout || z_im.abs() > bailout {
+            return i;
+        }
+        let (re2, im2) = (z_re.square(), z_im.square());
+        if re2 + im2 > bailout_sqr {
+            return i;
+        }
+
+        // 累乗する前の変換（re2・im2 は変わらない）
+        match params.kind {
+            EscapeKind::Standard => {}
+            EscapeKind::BurningShip => (z_re, z_im) = (z_re.abs(), z_im.abs()),
+            EscapeKind::Tricorn => z_im = -z_im,
+        }
+
+        (z_re, z_im) = if params.power == 2 {
+            (re2 - im2 + c_re, (z_re * z_im).double() + c_im)
+        } else {
+            // z^power（z を power - 1 回掛ける）
+            let (mut w_re, mut w_im) = (z_re, z_im);
+            for _ in 1..params.power {
+                (w_re, w_im) = (w_re * z_re - w_im * z_im, w_re * z_im + w_im * z_re);
+            }
+            (w_re + c_re, w_im + c_im)
+        };
+    }
+    max_iter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 256 ビットの積を丸めずに持てる精度
+    const PREC: u32 = 256;
+
+    /// 1.0 の生の値
+    const ONE: i128 = 1 << FRAC_BITS;
+
+    /// 符号・整数部・64 ビットずつの上位と下位の境界をまたぐ値の生の値
+    const VALUES: [i128; 8] = [
+        1,
+        u64::MAX as i128,
+        ONE - 1,
+        ONE,
+        0x0123_4567_89AB_CDEF_FEDC_BA98_7654_3210,
+        (5 << (FRAC_BITS - 1)) | u64::MAX as i128,
+        (((1 << 64) - 1) << 56) | u64::MAX as i128,
+        // 2 乗すると整数部の上限（128）の近く
+        0x0B4F_5C28_F5C2_8F80_0000_0000_0000_0000,
+    ];
+
+    /// `a * b` を任意精度で計算し、小数部 `FRAC_BITS` ビットより細かい部分を 0 方向に切り捨てた値
+    fn expected_product(a: i128, b: i128) -> Float {
+        let product = Float::with_val(PREC, a) * Float::with_val(PREC, b);
+        (product >> FRAC_BITS).trunc()
+    }
+
+    fn assert_product(a: i128, b: i128) {
+        let product = (Fixed(a) * Fixed(b)).0;
+        assert_eq!(
+            Float::with_val(PREC, product),
+            expected_product(a, b),
+            "{:#x} * {:#x}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn mul_matches_float_for_all_signs() {
+        for &a in &VALUES {
+            for &b in &VALUES {
+                assert_product(a, b);
+                assert_product(-a, b);
+                assert_product(a, -b);
+                assert_product(-a, -b);
+            }
+        }
+    }
+
+    #[test]
+    fn mul_keeps_values_near_integer_limit() {
+        for a in [i128::MAX, -i128::MAX, i128::MAX - u64::MAX as i128] {
+            assert_eq!((Fixed(a) * Fixed(ONE)).0, a);
+            assert_product(a, ONE - 1);
+            assert_product(a, ONE >> 1);
+            assert_product(a, -(ONE >> 3) - 1);
+        }
+        // 約 11.31 の 2 乗（約 127.92）
+        assert!((Fixed(VALUES[7]).square().to_f64() - 127.92).abs() < 0.01);
+    }
+
+    #[test]
+    fn mul_carries_across_halves() {
+        // 下位 64 ビットがすべて 1 の値どうしの積では、部分積の和が下位 128 ビットからあふれる
+        let all_ones = ONE - 1;
+        for a in [
+            all_ones,
+            all_ones - (1 << 63),
+            (7 << (FRAC_BITS - 1)) | u64::MAX as i128,
+        ] {
+            for b in [all_ones, u64::MAX as i128, (3 << 64) | u64::MAX as i128] {
+                assert_product(a, b);
+                assert_product(-a, b);
+            }
+        }
+    }
+}
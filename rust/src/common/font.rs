@@ -1,7 +1,7 @@
-//! ビットマップフォントと描画関数
+//! ビットマップフォントと文字の描画
 
-/// 5x7 ビットマップフォント（0-9）
-pub const FONT_5X7: [[u8; 7]; 10] = [
+/// 5x7 ビットマップフォント（0-9, A-Z, a-z の順）
+pub const FONT_5X7: [[u8; 7]; 62] = [
     [
         0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110,
     ], // 0
@@ -32,27 +32,257 @@ pub const FONT_5X7: [[u8; 7]; 10] = [
     [
         0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100,
     ], // 9
+    [
+        0b01110, 0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001,
+    ], // A
+    [
+        0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110,
+    ], // B
+    [
+        0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110,
+    ], // C
+    [
+        0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100,
+    ], // D
+    [
+        0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111,
+    ], // E
+    [
+        0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000,
+    ], // F
+    [
+        0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111,
+    ], // G
+    [
+        0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+    ], // H
+    [
+        0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+    ], // I
+    [
+        0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100,
+    ], // J
+    [
+        0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001,
+    ], // K
+    [
+        0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111,
+    ], // L
+    [
+        0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001,
+    ], // M
+    [
+        0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001,
+    ], // N
+    [
+        0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+    ], // O
+    [
+        0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000,
+    ], // P
+    [
+        0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101,
+    ], // Q
+    [
+        0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001,
+    ], // R
+    [
+        0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110,
+    ], // S
+    [
+        0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+    ], // T
+    [
+        0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+    ], // U
+    [
+        0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100,
+    ], // V
+    [
+        0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010,
+    ], // W
+    [
+        0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001,
+    ], // X
+    [
+        0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100,
+    ], // Y
+    [
+        0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111,
+    ], // Z
+    [
+        0b00000, 0b00000, 0b01110, 0b00001, 0b01111, 0b10001, 0b01111,
+    ], // a
+    [
+        0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b11110,
+    ], // b
+    [
+        0b00000, 0b00000, 0b01110, 0b10000, 0b10000, 0b10001, 0b01110,
+    ], // c
+    [
+        0b00001, 0b00001, 0b01101, 0b10011, 0b10001, 0b10001, 0b01111,
+    ], // d
+    [
+        0b00000, 0b00000, 0b01110, 0b10001, 0b11111, 0b10000, 0b01110,
+    ], // e
+    [
+        0b00110, 0b01001, 0b01000, 0b11100, 0b01000, 0b01000, 0b01000,
+    ], // f
+    [
+        0b00000, 0b01111, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110,
+    ], // g
+    [
+        0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001,
+    ], // h
+    [
+        0b00100, 0b00000, 0b01100, 0b00100, 0b00100, 0b00100, 0b01110,
+    ], // i
+    [
+        0b00010, 0b00000, 0b00110, 0b00010, 0b00010, 0b10010, 0b01100,
+    ], // j
+    [
+        0b10000, 0b10000, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010,
+    ], // k
+    [
+        0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+    ], // l
+    [
+        0b00000, 0b00000, 0b11010, 0b10101, 0b10101, 0b10001, 0b10001,
+    ], // m
+    [
+        0b00000, 0b00000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001,
+    ], // n
+    [
+        0b00000, 0b00000, 0b01110, 0b10001, 0b10001, 0b10001, 0b01110,
+    ], // o
+    [
+        0b00000, 0b00000, 0b11110, 0b10001, 0b11110, 0b10000, 0b10000,
+    ], // p
+    [
+        0b00000, 0b00000, 0b01101, 0b10011, 0b01111, 0b00001, 0b00001,
+    ], // q
+    [
+        0b00000, 0b00000, 0b10110, 0b11001, 0b10000, 0b10000, 0b10000,
+    ], // r
+    [
+        0b00000, 0b00000, 0b01110, 0b10000, 0b01110, 0b00001, 0b11110,
+    ], // s
+    [
+        0b01000, 0b01000, 0b11100, 0b01000, 0b01000, 0b01001, 0b00110,
+    ], // t
+    [
+        0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b10011, 0b01101,
+    ], // u
+    [
+        0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100,
+    ], // v
+    [
+        0b00000, 0b00000, 0b10001, 0b10001, 0b10101, 0b10101, 0b01010,
+    ], // w
+    [
+        0b00000, 0b00000, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001,
+    ], // x
+    [
+        0b00000, 0b00000, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110,
+    ], // y
+    [
+        0b00000, 0b00000, 0b11111, 0b00010, 0b00100, 0b01000, 0b11111,
+    ], // z
 ];
 
-/// 1文字を描画
+/// 5x7 ビットマップフォント（記号）
+pub const SYMBOLS_5X7: [(char, [u8; 7]); 6] = [
+    (
+        '-',
+        [
+            0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000,
+        ],
+    ),
+    (
+        '.',
+        [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100,
+        ],
+    ),
+    (
+        '+',
+        [
+            0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000,
+        ],
+    ),
+    (
+        ':',
+        [
+            0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000,
+        ],
+    ),
+    (
+        '(',
+        [
+            0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010,
+        ],
+    ),
+    (
+        ')',
+        [
+            0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000,
+        ],
+    ),
+];
+
+/// 1文字の幅（文字幅5 + 間隔1、拡大前）
+pub const CHAR_ADVANCE: usize = 6;
+/// 1文字の高さ（拡大前）
+pub const CHAR_HEIGHT: usize = 7;
+
+/// 文字のビットマップ（フォントにない文字は None）
+fn glyph(c: char) -> Option<&'static [u8; 7]> {
+    let index = match c {
+        '0'..='9' => Some(c as usize - '0' as usize),
+        'A'..='Z' => Some(10 + c as usize - 'A' as usize),
+        'a'..='z' => Some(36 + c as usize - 'a' as usize),
+        _ => None,
+    };
+    match index {
+        Some(index) => Some(&FONT_5X7[index]),
+        None => SYMBOLS_5X7
+            .iter()
+            .find(|(symbol, _)| *symbol == c)
+            .map(|(_, glyph)| glyph),
+    }
+}
+
+/// 文字列を `scale` 倍で描画したときの幅（ピクセル）
+pub fn text_width(text: &str, scale: usize) -> usize {
+    (text.chars().count() * CHAR_ADVANCE).saturating_sub(1) * scale
+}
+
+/// 1文字を描画（フォントにない文字は空白として扱う）
+///
+/// `buffer_size` はバッファの（幅, 高さ）。`scale` 倍に拡大して（フォントの 1 ドットを
+/// `scale` × `scale` ピクセルで）描画し、高 DPI の画面でも読める大きさにできます。
 pub fn draw_char(
     buffer: &mut [u32],
-    buffer_width: usize,
-    buffer_height: usize,
+    buffer_size: (usize, usize),
     x: usize,
     y: usize,
     c: char,
     color: u32,
+    scale: usize,
 ) {
-    if let Some(digit) = c.to_digit(10) {
-        let glyph = &FONT_5X7[digit as usize];
+    let (buffer_width, buffer_height) = buffer_size;
+    if let Some(glyph) = glyph(c) {
         for (row, &bits) in glyph.iter().enumerate() {
             for col in 0..5 {
                 if (bits >> (4 - col)) & 1 == 1 {
-                    let px = x + col;
-                    let py = y + row;
-                    if px < buffer_width && py < buffer_height {
-                        buffer[py * buffer_width + px] = color;
+                    for py in (y + row * scale..y + (row + 1) * scale)
+                        .take_while(|&py| py < buffer_height)
+                    {
+                        for px in (x + col * scale..x + (col + 1) * scale)
+                            .take_while(|&px| px < buffer_width)
+                        {
+                            buffer[py * buffer_width + px] = color;
+                        }
                     }
                 }
             }
@@ -60,19 +290,84 @@ pub fn draw_char(
     }
 }
 
-/// 文字列を描画
+/// 文字列を `scale` 倍で描画
 pub fn draw_text(
     buffer: &mut [u32],
-    buffer_width: usize,
-    buffer_height: usize,
+    buffer_size: (usize, usize),
     x: usize,
     y: usize,
     text: &str,
     color: u32,
+    scale: usize,
 ) {
     let mut cursor_x = x;
     for c in text.chars() {
-        draw_char(buffer, buffer_width, buffer_height, cursor_x, y, c, color);
-        cursor_x += 6; // 文字幅5 + 間隔1
+        draw_char(buffer, buffer_size, cursor_x, y, c, color, scale);
+        cursor_x += CHAR_ADVANCE * scale;
+    }
+}
+
+/// `color` の文字の縁取り・背景に使う対照的な色（明るい色には黒、暗い色には白）
+pub fn contrast_color(color: u32) -> u32 {
+    let r = (color >> 16) & 0xFF;
+    let g = (color >> 8) & 0xFF;
+    let b = color & 0xFF;
+    // ITU-R BT.601 の輝度（× 1000）
+    if r * 299 + g * 587 + b * 114 >= 128 * 1000 {
+        0x000000
+    } else {
+        0xFFFFFF
+    }
+}
+
+/// 文字列を対照的な色の縁取り付きで描画
+///
+/// 8 方向に 1 ドット（`scale` ピクセル）ずらした縁取りの色の文字を先に描き、
+/// 明るい領域の上でも暗い領域の上でも読めるようにします。
+pub fn draw_text_outlined(
+    buffer: &mut [u32],
+    buffer_size: (usize, usize),
+    x: usize,
+    y: usize,
+    text: &str,
+    color: u32,
+    scale: usize,
+) {
+    let outline = contrast_color(color);
+    let offset = scale as isize;
+    for dy in [-offset, 0, offset] {
+        for dx in [-offset, 0, offset] {
+            if dx == 0 && dy == 0 {
+                continue;
+            }
+            if let (Some(ox), Some(oy)) = (x.checked_add_signed(dx), y.checked_add_signed(dy)) {
+                draw_text(buffer, buffer_size, ox, oy, text, outline, scale);
+            }
+        }
+    }
+    draw_text(buffer, buffer_size, x, y, text, color, scale);
+}
+
+/// 文字列を対照的な色で塗りつぶした矩形の上に描画
+///
+/// 矩形は文字の周囲に 1 ドット（`scale` ピクセル）の余白を取ります。
+pub fn draw_text_boxed(
+    buffer: &mut [u32],
+    buffer_size: (usize, usize),
+    x: usize,
+    y: usize,
+    text: &str,
+    color: u32,
+    scale: usize,
+) {
+    let (buffer_width, buffer_height) = buffer_size;
+    let background = contrast_color(color);
+    let left = x.saturating_sub(scale).min(buffer_width);
+    let right = (x + text_width(text, scale) + scale).min(buffer_width);
+    let top = y.saturating_sub(scale);
+    let bottom = (y + CHAR_HEIGHT * scale + scale).min(buffer_height);
+    for py in top..bottom {
+        buffer[py * buffer_width + left..py * buffer_width + right].fill(background);
     }
+    draw_text(buffer, buffer_size, x, y, text, color, scale);
 }
@@ -0,0 +1,93 @@
+//! ゲームパッドの入力（`gamepad` フィーチャー）
+//!
+//! 接続されているゲームパッドの操作を毎フレーム、ビューアの状態に反映します:
+//!
+//! | 操作 | 動作 |
+//! |------|------|
+//! | 左スティック | 表示範囲を移動（傾けている間ずっと） |
+//! | 右トリガー / 左トリガー | ズームイン / ズームアウト（押し込み量に比例） |
+//! | A（South） | リセット |
+//! | B（East） | 画像を保存 |
+//! | X（West） | 着色モードを切り替え |
+//! | Y（North） | パレットファイルを再読み込み |
+
+use super::viewer::ViewerState;
+use gilrs::{Axis, Button, Event, EventType, Gilrs};
+
+/// スティックの遊び（これより小さい傾きは無視）
+const STICK_DEAD_ZONE: f32 = 0.2;
+/// スティックを倒しきったときに 1 フレームで移動する距離（表示幅に対する割合）
+const PAN_SPEED: f64 = 0.02;
+/// トリガーを押しきったときに 1 フレームで進むズーム量（ホイール 1 ノッチに対する割合）
+const ZOOM_SPEED: f64 = 0.125;
+
+/// ゲームパッドの入力
+pub struct GamepadInput {
+    gilrs: Gilrs,
+}
+
+impl GamepadInput {
+    pub fn new() -> Result<Self, String> {
+        let gilrs =
+            Gilrs::new().map_err(|e| format!("ゲームパッドの初期化に失敗しました: {}", e))?;
+        Ok(Self { gilrs })
+    }
+
+    /// このフレームのゲームパッドの入力をビューアの状態に反映
+    pub fn handle(&mut self, state: &mut ViewerState) {
+        // ボタンは押した瞬間だけ反応させる（イベントを読むとスティック・トリガーの状態も更新される）
+        while let Some(Event { event, .. }) = self.gilrs.next_event() {
+            match event {
+                EventType::ButtonPressed(Button::South, _) => {
+                    state.reset();
+                    println!("リセット");
+                }
+                EventType::ButtonPressed(Button::East, _) => state.save_image(),
+                EventType::ButtonPressed(Button::West, _) => state.toggle_coloring_mode(),
+                EventType::ButtonPressed(Button::North, _) => state.reload_palette(),
+                EventType::Connected => println!("ゲームパッドが接続されました"),
+                EventType::Disconnected => println!("ゲームパッドが切断されました"),
+                _ => {}
+            }
+        }
+
+        // 複数接続されている場合は最初に操作されているものを使う
+        let Some((stick_x, stick_y, zoom)) = self.gilrs.gamepads().find_map(|(_, gamepad)| {
+            let stick_x = dead_zone(gamepad.value(Axis::LeftStickX));
+            let stick_y = dead_zone(gamepad.value(Axis::LeftStickY));
+            let trigger = |button| gamepad.button_data(button).map_or(0.0, |d| d.value());
+            let zoom = trigger(Button::RightTrigger2) - trigger(Button::LeftTrigger2);
+            (stick_x != 0.0 || stick_y != 0.0 || zoom != 0.0).then_some((stick_x, stick_y, zoom))
+        }) else {
+            return;
+        };
+
+        let center_x = state.settings.width as f64 / 2.0;
+        let center_y = state.settings.height as f64 / 2.0;
+        if stick_x != 0.0 || stick_y != 0.0 {
+            // スティックの上はプラス、画面の y は下向き
+            let step = state.settings.width as f64 * PAN_SPEED;
+            state.pan_to(
+                center_x + stick_x as f64 * step,
+                center_y - stick_y as f64 * step,
+            );
+        }
+        if zoom != 0.0 {
+            let factor = if zoom > 0.0 {
+                state.settings.zoom_in.powf(zoom as f64 * ZOOM_SPEED)
+            } else {
+                state.settings.zoom_out.powf(-zoom as f64 * ZOOM_SPEED)
+            };
+            state.zoom(center_x, center_y, factor);
+        }
+    }
+}
+
+/// 遊びの範囲の傾きを 0 にする
+fn dead_zone(value: f32) -> f32 {
+    if value.abs() < STICK_DEAD_ZONE {
+        0.0
+    } else {
+        value
+    }
+}
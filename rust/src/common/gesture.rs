@@ -0,0 +1,135 @@
+//! トラックパッドのジェスチャー（ピンチ・2 本指スクロール）
+//!
+//! minifb はピンチのイベントを受け取れず、スクロール量も 1 フレームの最後のイベントの分しか返しません。
+//! macOS ではアプリに届くイベントをそのまま通しつつ覗く（ローカルモニター）ことで、
+//! ピンチの拡大率と 2 本指スクロールの移動量（ポイント単位）をフレームごとに合計します。
+//! macOS のピンチの中心はマウスカーソルの位置なので、ズームはカーソル位置を固定して行います。
+//! macOS 以外ではジェスチャーを受け取らず、これまでどおり minifb のスクロール量だけを使います。
+
+/// 前のフレームから届いたジェスチャーの合計
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gesture {
+    /// ピンチで画像を何倍に拡大するか（1 で変化なし）
+    pub magnification: f64,
+    /// 2 本指スクロールの移動量（論理ポイント。トラックパッドのスクロールが届いたときだけ）
+    pub scroll: Option<(f64, f64)>,
+}
+
+impl Default for Gesture {
+    fn default() -> Self {
+        Self {
+            magnification: 1.0,
+            scroll: None,
+        }
+    }
+}
+
+impl Gesture {
+    /// イベントの拡大率（macOS の `magnification`、0 で変化なし）を合成
+    pub fn add_magnification(&mut self, magnification: f64) {
+        // 指を閉じきったときの -1 で倍率が 0 にならないよう抑える
+        self.magnification *= (1.0 + magnification).max(0.1);
+    }
+
+    /// 2 本指スクロールの移動量を足す
+    pub fn add_scroll(&mut self, dx: f64, dy: f64) {
+        let (x, y) = self.scroll.unwrap_or((0.0, 0.0));
+        self.scroll = Some((x + dx, y + dy));
+    }
+}
+
+/// トラックパッドのジェスチャーの受け取り
+pub struct GestureMonitor {
+    #[cfg(target_os = "macos")]
+    monitor: macos::Monitor,
+}
+
+impl GestureMonitor {
+    /// ジェスチャーの受け取りを始める（メインスレッドで呼ぶこと）
+    pub fn new() -> Self {
+        Self {
+            #[cfg(target_os = "macos")]
+            monitor: macos::Monitor::new(),
+        }
+    }
+
+    /// 前回から届いたジェスチャーの合計を取り出す
+    pub fn take(&mut self) -> Gesture {
+        #[cfg(target_os = "macos")]
+        {
+            self.monitor.take()
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            Gesture::default()
+        }
+    }
+}
+
+impl Default for GestureMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use super::Gesture;
+    use block2::RcBlock;
+    use objc2::rc::Retained;
+    use objc2::runtime::AnyObject;
+    use objc2_app_kit::{NSEvent, NSEventMask, NSEventType};
+    use std::cell::Cell;
+    use std::ptr::NonNull;
+    use std::rc::Rc;
+
+    /// NSEvent のローカルモニター（イベントはメインスレッドで minifb がウィンドウを更新するときに届く）
+    pub struct Monitor {
+        pending: Rc<Cell<Gesture>>,
+        token: Option<Retained<AnyObject>>,
+    }
+
+    impl Monitor {
+        pub fn new() -> Self {
+            let pending = Rc::new(Cell::new(Gesture::default()));
+            let sink = Rc::clone(&pending);
+            let handler = RcBlock::new(move |event: NonNull<NSEvent>| -> *mut NSEvent {
+                // SAFETY: AppKit はモニターに有効なイベントを渡す
+                let e = unsafe { event.as_ref() };
+                let mut gesture = sink.get();
+                match e.r#type() {
+                    NSEventType::Magnify => gesture.add_magnification(e.magnification()),
+                    // マウスホイール（行単位のスクロール）は minifb のスクロール量で扱う
+                    NSEventType::ScrollWheel if e.hasPreciseScrollingDeltas() => {
+                        gesture.add_scroll(e.scrollingDeltaX(), e.scrollingDeltaY())
+                    }
+                    _ => {}
+                }
+                sink.set(gesture);
+                // イベントはそのまま minifb のウィンドウに届ける
+                event.as_ptr()
+            });
+            // SAFETY: ハンドラーはイベントを読むだけで、受け取ったイベントをそのまま返す
+            let token = unsafe {
+                NSEvent::addLocalMonitorForEventsMatchingMask_handler(
+                    NSEventMask::Magnify | NSEventMask::ScrollWheel,
+                    &handler,
+                )
+            };
+            Self { pending, token }
+        }
+
+        pub fn take(&mut self) -> Gesture {
+            self.pending.take()
+        }
+    }
+
+    impl Drop for Monitor {
+        fn drop(&mut self) {
+            if let Some(token) = self.token.take() {
+                // SAFETY: token は addLocalMonitorForEventsMatchingMask_handler が返したもの
+                unsafe { NSEvent::removeMonitor(&token) };
+            }
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! 制御点を補間するグラデーション
+//!
+//! 任意の位置（0.0〜1.0）に置いた色の制御点を、線形または単調 3 次（制御点の間で色が行き過ぎない
+//! エルミート補間）で補間します。JSON ファイルで読み書きできます:
+//!
+//! ```json
+//! {
+//!   "interpolation": "cubic",
+//!   "stops": [
+//!     { "position": 0.0, "color": "#000033" },
+//!     { "position": 0.3, "color": [255, 255, 204] },
+//!     { "position": 1.0, "color": "#000000" }
+//!   ]
+//! }
+//! ```
+
+use super::colors::{parse_json_color, Rgb};
+use std::path::Path;
+use std::str::FromStr;
+
+/// 制御点の間の補間方法
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    /// 線形補間
+    #[default]
+    Linear,
+    /// 単調 3 次補間（制御点で色の変化がなめらかにつながり、制御点の間で値が行き過ぎない）
+    Cubic,
+}
+
+impl Interpolation {
+    /// 設定ファイル・JSON で使う名前
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Linear => "linear",
+            Self::Cubic => "cubic",
+        }
+    }
+}
+
+impl FromStr for Interpolation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "linear" => Ok(Self::Linear),
+            "cubic" => Ok(Self::Cubic),
+            _ => Err(format!("補間方法の指定が不正です（linear / cubic）: {}", s)),
+        }
+    }
+}
+
+/// 制御点（位置と色）を補間するグラデーション
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    /// 位置の昇順に並んだ制御点
+    stops: Vec<(f64, Rgb)>,
+    interpolation: Interpolation,
+    /// 単調 3 次補間での各制御点の傾き（成分ごと）
+    tangents: Vec<Rgb>,
+}
+
+impl Gradient {
+    /// 制御点からグラデーションを作成
+    ///
+    /// 制御点は 2 つ以上、位置は 0.0〜1.0 の範囲で真に昇順であること。
+    /// 最初の制御点より前・最後の制御点より後は端の色になります。
+    pub fn new(stops: Vec<(f64, Rgb)>, interpolation: Interpolation) -> Result<Self, String> {
+        if stops.len() < 2 {
+            return Err(format!("制御点が2つ以上必要です（{}個）", stops.len()));
+        }
+        if let Some((position, _)) = stops.iter().find(|(p, _)| !(0.0..=1.0).contains(p)) {
+            return Err(format!(
+                "制御点の位置が範囲外です（0.0〜1.0）: {}",
+                position
+            ));
+        }
+        if stops.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return Err("制御点の位置は昇順に並べてください".to_string());
+        }
+        let tangents = monotone_tangents(&stops);
+        Ok(Self {
+            stops,
+            interpolation,
+            tangents,
+        })
+    }
+
+    /// 等間隔に並んだ色の列を線形補間するグラデーションを作成（2色以上必要）
+    pub fn uniform(colors: &[Rgb]) -> Result<Self, String> {
+        if colors.len() < 2 {
+            return Err(format!("色が2つ以上必要です（{}個）", colors.len()));
+        }
+        let last = (colors.len() - 1) as f64;
+        let stops = colors
+            .iter()
+            .enumerate()
+            .map(|(i, &color)| (i as f64 / last, color))
+            .collect();
+        Self::new(stops, Interpolation::Linear)
+    }
+
+    /// 制御点
+    pub fn stops(&self) -> &[(f64, Rgb)] {
+        &self.stops
+    }
+
+    /// 補間方法
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    /// 0.0〜1.0 の位置に対応する色（u32形式: 0xRRGGBB）
+    pub fn color_at(&self, t: f64) -> u32 {
+        let t = t.clamp(self.stops[0].0, self.stops[self.stops.len() - 1].0);
+        // t を含む区間 [idx, idx + 1]
+        let idx = self
+            .stops
+            .partition_point(|(position, _)| *position <= t)
+            .clamp(1, self.stops.len() - 1)
+            - 1;
+        let (x1, c1) = self.stops[idx];
+        let (x2, c2) = self.stops[idx + 1];
+        let h = x2 - x1;
+        let s = (t - x1) / h;
+
+        let channel = |y1: f64, y2: f64, m1: f64, m2: f64| {
+            let value = match self.interpolation {
+                Interpolation::Linear => y1 + (y2 - y1) * s,
+                Interpolation::Cubic => {
+                    // エルミート基底関数
+                    let (s2, s3) = (s * s, s * s * s);
+                    (2.0 * s3 - 3.0 * s2 + 1.0) * y1
+                        + (s3 - 2.0 * s2 + s) * h * m1
+                        + (-2.0 * s3 + 3.0 * s2) * y2
+                        + (s3 - s2) * h * m2
+                }
+            };
+            (value.clamp(0.0, 1.0) * 255.0) as u8 as u32
+        };
+        let (m1, m2) = (self.tangents[idx], self.tangents[idx + 1]);
+        let r = channel(c1.0, c2.0, m1.0, m2.0);
+        let g = channel(c1.1, c2.1, m1.1, m2.1);
+        let b = channel(c1.2, c2.2, m1.2, m2.2);
+
+        (r << 16) | (g << 8) | b
+    }
+
+    /// JSON の値から作成（`interpolation` は省略時 `linear`）
+    pub fn from_json(value: &serde_json::Value) -> Result<Self, String> {
+        let interpolation = match value["interpolation"].as_str() {
+            Some(name) => name.parse()?,
+            None => Interpolation::Linear,
+        };
+        let stops = value["stops"]
+            .as_array()
+            .ok_or_else(|| "\"stops\" に制御点の配列が必要です".to_string())?
+            .iter()
+            .enumerate()
+            .map(|(i, stop)| {
+                let position = stop["position"].as_f64().ok_or_else(|| {
+                    format!("{}番目の制御点の \"position\" に数値が必要です", i + 1)
+                })?;
+                let color = parse_json_color(&stop["color"])
+                    .map_err(|e| format!("{}番目の制御点の \"color\": {}", i + 1, e))?;
+                Ok((position, color))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Self::new(stops, interpolation)
+    }
+
+    /// JSON の値に変換（色は `#RRGGBB`）
+    pub fn to_json(&self) -> serde_json::Value {
+        let hex = |c: f64| (c.clamp(0.0, 1.0) * 255.0).round() as u32;
+        let stops: Vec<serde_json::Value> = self
+            .stops
+            .iter()
+            .map(|&(position, (r, g, b))| {
+                serde_json::json!({
+                    "position": position,
+                    "color": format!("#{:02X}{:02X}{:02X}", hex(r), hex(g), hex(b)),
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "interpolation": self.interpolation.name(),
+            "stops": stops,
+        })
+    }
+
+    /// JSON ファイルから読み込む
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        serde_json::from_str(&text)
+            .map_err(|e| e.to_string())
+            .and_then(|value| Self::from_json(&value))
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// JSON ファイルに保存
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(&self.to_json()).map_err(|e| e.to_string())?;
+        std::fs::write(path, text + "\n").map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+/// 単調 3 次補間（Fritsch–Butland）の各制御点での傾きを成分ごとに求める
+///
+/// 隣り合う区間の傾きの重み付き調和平均を使い、増減が変わる制御点（極値）では傾きを 0 にすることで、
+/// 制御点の間で色が両端の色の範囲からはみ出さないようにします。
+fn monotone_tangents(stops: &[(f64, Rgb)]) -> Vec<Rgb> {
+    let component = |k: usize| -> Vec<f64> {
+        let value = |i: usize| {
+            let (r, g, b) = stops[i].1;
+            [r, g, b][k]
+        };
+        let h: Vec<f64> = stops.windows(2).map(|p| p[1].0 - p[0].0).collect();
+        let d: Vec<f64> = (0..h.len())
+            .map(|i| (value(i + 1) - value(i)) / h[i])
+            .collect();
+
+        let mut m = vec![0.0; stops.len()];
+        m[0] = d[0];
+        m[stops.len() - 1] = d[h.len() - 1];
+        for i in 1..stops.len() - 1 {
+            if d[i - 1] * d[i] > 0.0 {
+                let w1 = 2.0 * h[i] + h[i - 1];
+                let w2 = h[i] + 2.0 * h[i - 1];
+                m[i] = (w1 + w2) / (w1 / d[i - 1] + w2 / d[i]);
+            }
+        }
+        m
+    };
+    let (r, g, b) = (component(0), component(1), component(2));
+    (0..stops.len()).map(|i| (r[i], g[i], b[i])).collect()
+}
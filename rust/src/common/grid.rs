@@ -0,0 +1,152 @@
+//! 実軸・虚軸の目盛り線と座標ラベルのオーバーレイ
+//!
+//! 表示範囲に応じて 1・2・5 × 10^k の切りのよい間隔を選び、目盛り線と座標値を画像に重ねます。
+//! 座標は f64 で扱うため、目盛りの間隔が f64 で区別できないほど深いズームでは描画しません。
+
+use super::font::{draw_text_outlined, text_width, CHAR_HEIGHT};
+
+/// 目盛り線の間隔の目安（ピクセル）
+const TARGET_SPACING: f64 = 100.0;
+
+/// 目盛り線の色（画像と半々に混ぜる）
+const GRID_COLOR: u32 = 0x808080;
+
+/// 実軸・虚軸（座標 0 の線）の色
+const AXIS_COLOR: u32 = 0xFFFFFF;
+
+/// ラベルの色
+const LABEL_COLOR: u32 = 0xFFFFFF;
+
+/// ラベルと描画領域の端・隣のラベルとの間隔（ピクセル）
+const LABEL_MARGIN: usize = 4;
+
+/// 目盛りの間隔が座標の大きさのこの割合を下回ったら描画しない
+const MIN_RELATIVE_STEP: f64 = 1e-14;
+
+/// `pixels` ピクセルに幅 `span` の範囲を表示するときの目盛りの間隔（1・2・5 × 10^k）
+pub fn tick_step(span: f64, pixels: usize) -> f64 {
+    let raw = span * TARGET_SPACING / pixels as f64;
+    let base = 10f64.powf(raw.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|m| m * base)
+        .find(|&step| step >= raw)
+        .unwrap_or(10.0 * base)
+}
+
+/// 範囲 `min`〜`max` に入る目盛りの座標（f64 で区別できない場合は空）
+pub fn ticks(min: f64, max: f64, step: f64) -> Vec<f64> {
+    if !step.is_normal() || step <= min.abs().max(max.abs()) * MIN_RELATIVE_STEP {
+        return Vec::new();
+    }
+    let first = (min / step).ceil() as i64;
+    let last = (max / step).floor() as i64;
+    (first..=last).map(|i| i as f64 * step).collect()
+}
+
+/// 目盛りのラベル（間隔 `step` の桁まで表示し、非常に大きい・小さい値は指数表記）
+pub fn format_tick(value: f64, step: f64) -> String {
+    if value.abs() < step / 2.0 {
+        return "0".to_string();
+    }
+    // step は 1・2・5 × 10^k なので、誤差で k が1つずれないよう少し足してから切り捨てる
+    let step_exp = (step.log10() + 1e-9).floor() as i32;
+    let value_exp = value.abs().log10().floor() as i32;
+    if !(-4..5).contains(&value_exp) {
+        let digits = (value_exp - step_exp).max(0) as usize;
+        format!("{:.*e}", digits, value)
+    } else {
+        let decimals = (-step_exp).max(0) as usize;
+        format!("{:.*}", decimals, value)
+    }
+}
+
+/// 2色を半々に混ぜる
+fn blend(a: u32, b: u32) -> u32 {
+    ((a >> 1) & 0x7F7F7F) + ((b >> 1) & 0x7F7F7F)
+}
+
+/// 表示範囲（`x_min`, `x_max`, `y_min`, `y_max`）の目盛り線とラベルを、
+/// ウィンドウのバッファ（幅 `stride`）の左上 `width` × `height` ピクセルの描画領域に重ねる
+///
+/// 目盛りの間隔・余白・文字は HiDPI の拡大率 `scale` 倍にします。
+pub fn draw_grid(
+    buffer: &mut [u32],
+    stride: usize,
+    (width, height): (usize, usize),
+    scale: usize,
+    (x_min, x_max, y_min, y_max): (f64, f64, f64, f64),
+) {
+    let x_step = tick_step(x_max - x_min, width / scale);
+    let y_step = tick_step(y_max - y_min, height / scale);
+    let (margin, char_height) = (LABEL_MARGIN * scale, CHAR_HEIGHT * scale);
+    let x_ticks = ticks(x_min, x_max, x_step);
+    let y_ticks = ticks(y_min, y_max, y_step);
+    let to_px = |x: f64| ((x - x_min) / (x_max - x_min) * width as f64).round() as usize;
+    let to_py = |y: f64| ((y_max - y) / (y_max - y_min) * height as f64).round() as usize;
+
+    for &x in &x_ticks {
+        let px = to_px(x).min(width - 1);
+        for py in 0..height {
+            let pixel = &mut buffer[py * stride + px];
+            *pixel = if x == 0.0 {
+                AXIS_COLOR
+            } else {
+                blend(*pixel, GRID_COLOR)
+            };
+        }
+    }
+    for &y in &y_ticks {
+        let py = to_py(y).min(height - 1);
+        for px in 0..width {
+            let pixel = &mut buffer[py * stride + px];
+            *pixel = if y == 0.0 {
+                AXIS_COLOR
+            } else {
+                blend(*pixel, GRID_COLOR)
+            };
+        }
+    }
+
+    // 実部のラベルは下端に、線の中央に揃えて並べ、重なるものは省く
+    let label_y = height - margin - char_height;
+    let mut next_x = 0;
+    for &x in &x_ticks {
+        let label = format_tick(x, x_step);
+        let label_width = text_width(&label, scale);
+        let left = to_px(x)
+            .saturating_sub(label_width / 2)
+            .clamp(margin, width - margin - label_width);
+        if left < next_x {
+            continue;
+        }
+        draw_text_outlined(
+            buffer,
+            (stride, height),
+            left,
+            label_y,
+            &label,
+            LABEL_COLOR,
+            scale,
+        );
+        next_x = left + label_width + margin * 2;
+    }
+
+    // 虚部のラベルは左端、線のすぐ上に置く（下端の実部のラベルとは重ねない）
+    for &y in &y_ticks {
+        let label = format!("{}i", format_tick(y, y_step));
+        let top = to_py(y).saturating_sub(char_height + 2 * scale);
+        if top + char_height >= label_y {
+            continue;
+        }
+        draw_text_outlined(
+            buffer,
+            (stride, height),
+            margin,
+            top,
+            &label,
+            LABEL_COLOR,
+            scale,
+        );
+    }
+}
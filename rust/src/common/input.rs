@@ -0,0 +1,432 @@
+//! キー・マウス入力の処理
+//!
+//! 毎フレーム、押されたキーとマウスの操作をビューアの状態（表示範囲の変更、表示の切替、保存など）に反映します。
+//! 描画方法に依存する操作（アニメーション出力・高解像度書き出し）とキーフレームの追加は
+//! `Command` として返し、呼び出し側で処理します。
+
+use super::colors::Tone;
+use super::config::{Config, ScrollAction};
+use super::constants::{
+    BRIGHTNESS_STEP, KEY_ACCELERATION, KEY_MAX_ACCELERATION, KEY_PAN_FRACTION, PALETTE_PHASE_STEP,
+    ROTATION_STEP_DEGREES, SCROLL_NOTCH, SCROLL_PAN_PIXELS, TONE_FACTOR_STEP,
+};
+use super::gesture::GestureMonitor;
+use super::location::LocationFormat;
+use super::viewer::ViewerState;
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window};
+
+/// 呼び出し側で処理する操作
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Command {
+    /// 初期表示から現在の表示までのズームアニメーションを出力
+    Record,
+    /// 現在の表示をキーフレームファイルに追加
+    AddKeyframe,
+    /// 現在の表示を高解像度で書き出し
+    Export,
+}
+
+/// 前のフレームからの変化を調べるためのマウスの状態
+#[derive(Default)]
+pub struct InputHandler {
+    /// トラックパッドのピンチ・2 本指スクロール（macOS のみ）
+    gestures: GestureMonitor,
+    prev_left_down: bool,
+    /// 矢印キー・ズームキーを押し続けている間のキーリピートの回数（加速に使う）
+    key_repeats: u32,
+}
+
+impl InputHandler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// このフレームの入力をビューアの状態に反映し、呼び出し側で処理する操作を返す
+    pub fn handle(
+        &mut self,
+        window: &Window,
+        state: &mut ViewerState,
+        config: &Config,
+        location_format: LocationFormat,
+    ) -> Vec<Command> {
+        let keys = &config.keys;
+        let mut commands = Vec::new();
+
+        if window.is_key_pressed(keys.reset, KeyRepeat::No) {
+            state.reset();
+            println!("リセット");
+        }
+
+        if window.is_key_pressed(keys.save_image, KeyRepeat::No) {
+            state.save_image();
+        }
+
+        if window.is_key_pressed(keys.save_location, KeyRepeat::No) {
+            state.save_location(location_format);
+        }
+
+        if window.is_key_pressed(keys.reload_palette, KeyRepeat::No) {
+            state.reload_palette();
+        }
+
+        if window.is_key_pressed(keys.coloring_mode, KeyRepeat::No) {
+            state.toggle_coloring_mode();
+        }
+
+        if window.is_key_pressed(keys.antialias, KeyRepeat::No) {
+            state.toggle_antialias();
+        }
+
+        if window.is_key_pressed(keys.fractal, KeyRepeat::No) {
+            state.set_fractal(state.fractal.next());
+        }
+
+        if window.is_key_pressed(keys.orbit, KeyRepeat::No) {
+            state.show_orbit = !state.show_orbit;
+            println!(
+                "軌道の表示: {}",
+                if state.show_orbit {
+                    "表示"
+                } else {
+                    "非表示"
+                }
+            );
+        }
+
+        if window.is_key_pressed(keys.grid, KeyRepeat::No) {
+            state.show_grid = !state.show_grid;
+            if state.show_grid && state.rotation != 0.0 {
+                println!("回転中は座標の目盛りを表示しません");
+            }
+            state.compose_buffer();
+            println!(
+                "目盛りの表示: {}",
+                if state.show_grid {
+                    "表示"
+                } else {
+                    "非表示"
+                }
+            );
+        }
+
+        if window.is_key_pressed(keys.julia_preview, KeyRepeat::No) {
+            state.julia_preview.toggle();
+            println!(
+                "ジュリア集合プレビュー: {}",
+                if state.julia_preview.enabled {
+                    "表示"
+                } else {
+                    "非表示"
+                }
+            );
+            state.compose_buffer();
+        }
+
+        if window.is_key_pressed(keys.julia, KeyRepeat::No) {
+            state.toggle_julia(mouse_pos(window, state));
+        }
+
+        // カーソルが動いたら軌道とジュリア集合のプレビューを計算し直す
+        let mouse = mouse_pos(window, state);
+        state.update_orbit(mouse);
+        state.update_julia_preview(mouse);
+
+        if window.is_key_pressed(keys.record, KeyRepeat::No) {
+            commands.push(Command::Record);
+        }
+
+        if window.is_key_pressed(keys.keyframe, KeyRepeat::No) {
+            commands.push(Command::AddKeyframe);
+        }
+
+        if window.is_key_pressed(keys.export, KeyRepeat::No) {
+            commands.push(Command::Export);
+        }
+
+        self.handle_navigation_keys(window, state, config);
+
+        // 画像を左回り（反時計回り）に回す = 表示範囲を時計回りに回す
+        if window.is_key_pressed(keys.rotate_left, KeyRepeat::Yes) {
+            state.rotate(-ROTATION_STEP_DEGREES);
+        }
+        if window.is_key_pressed(keys.rotate_right, KeyRepeat::Yes) {
+            state.rotate(ROTATION_STEP_DEGREES);
+        }
+
+        // 押し続けると色が流れる（カラーサイクリング）
+        if window.is_key_pressed(keys.phase_down, KeyRepeat::Yes) {
+            state.shift_palette_phase(-PALETTE_PHASE_STEP);
+        }
+        if window.is_key_pressed(keys.phase_up, KeyRepeat::Yes) {
+            state.shift_palette_phase(PALETTE_PHASE_STEP);
+        }
+
+        handle_tone_keys(window, state, config);
+
+        let gesture = self.gestures.take();
+        if let Some((mx, my)) = mouse_pos(window, state) {
+            // ピンチはジェスチャーの中心（カーソル位置）を固定してズーム
+            pinch_zoom(state, mx, my, gesture.magnification);
+
+            let zoom_modifier = [
+                Key::LeftCtrl,
+                Key::RightCtrl,
+                Key::LeftSuper,
+                Key::RightSuper,
+            ]
+            .into_iter()
+            .any(|key| window.is_key_down(key));
+            let scroll_pans = config.scroll == ScrollAction::Pan && !zoom_modifier;
+            let scale = state.settings.mouse_scale();
+            match (gesture.scroll, window.get_scroll_wheel()) {
+                // トラックパッドはフレーム内のスクロールを合計した移動量（ポイント）でなめらかに移動
+                (Some((dx, dy)), _) if scroll_pans => state.pan_by(dx * scale, dy * scale),
+                (_, Some(scroll)) if scroll_pans => {
+                    let pixels = SCROLL_PAN_PIXELS * scale;
+                    state.pan_by(scroll.0 as f64 * pixels, scroll.1 as f64 * pixels);
+                }
+                (_, Some(scroll)) => scroll_zoom(state, mx, my, scroll.1),
+                _ => {}
+            }
+
+            // 左クリックでパン移動、Ctrl+左クリックで点の周期を表示（押した瞬間のみ）
+            let left_down = window.get_mouse_down(MouseButton::Left);
+            if left_down && !self.prev_left_down {
+                if window.is_key_down(Key::LeftCtrl) || window.is_key_down(Key::RightCtrl) {
+                    state.inspect_point(mx, my);
+                } else {
+                    state.pan_to(mx, my);
+                }
+            }
+            self.prev_left_down = left_down;
+
+            if window.get_mouse_down(MouseButton::Right) {
+                state.zoom(mx, my, state.settings.zoom_in);
+            }
+        }
+
+        commands
+    }
+
+    /// 矢印キーで表示範囲の何分の 1 かずつ移動、ズームキーで画面中心を固定してズーム
+    ///
+    /// 押し続けるとキーリピートのたびに移動量・ズーム量が増えます（最大で最初の `KEY_MAX_ACCELERATION` 倍）。
+    fn handle_navigation_keys(
+        &mut self,
+        window: &Window,
+        state: &mut ViewerState,
+        config: &Config,
+    ) {
+        let keys = &config.keys;
+        let navigation_keys = [
+            keys.pan_left,
+            keys.pan_right,
+            keys.pan_up,
+            keys.pan_down,
+            keys.zoom_in,
+            keys.zoom_out,
+        ];
+        if !navigation_keys.iter().any(|&key| window.is_key_down(key)) {
+            self.key_repeats = 0;
+            return;
+        }
+
+        let acceleration =
+            (1.0 + self.key_repeats as f64 * KEY_ACCELERATION).min(KEY_MAX_ACCELERATION);
+        let step = state.settings.width as f64 * KEY_PAN_FRACTION * acceleration;
+        let pressed = |key| window.is_key_pressed(key, KeyRepeat::Yes);
+        let mut acted = false;
+
+        // 表示範囲を右（上）へ動かす = 画像を左（下）へずらす
+        let (mut dx, mut dy) = (0.0, 0.0);
+        if pressed(keys.pan_left) {
+            dx += step;
+        }
+        if pressed(keys.pan_right) {
+            dx -= step;
+        }
+        if pressed(keys.pan_up) {
+            dy += step;
+        }
+        if pressed(keys.pan_down) {
+            dy -= step;
+        }
+        if dx != 0.0 || dy != 0.0 {
+            state.pan_by(dx, dy);
+            acted = true;
+        }
+
+        let center = (
+            state.settings.width as f64 / 2.0,
+            state.settings.height as f64 / 2.0,
+        );
+        if pressed(keys.zoom_in) {
+            state.zoom(
+                center.0,
+                center.1,
+                state.settings.zoom_in.powf(acceleration),
+            );
+            acted = true;
+        }
+        if pressed(keys.zoom_out) {
+            state.zoom(
+                center.0,
+                center.1,
+                state.settings.zoom_out.powf(acceleration),
+            );
+            acted = true;
+        }
+
+        if acted {
+            self.key_repeats += 1;
+        }
+    }
+}
+
+/// 色調補正キーでガンマ・明るさ・コントラストを変える（押し続けると連続して変わる）
+fn handle_tone_keys(window: &Window, state: &mut ViewerState, config: &Config) {
+    let keys = &config.keys;
+    let pressed = |key| window.is_key_pressed(key, KeyRepeat::Yes);
+    let mut tone = state.tone;
+    if pressed(keys.gamma_down) {
+        tone.gamma /= TONE_FACTOR_STEP;
+    }
+    if pressed(keys.gamma_up) {
+        tone.gamma *= TONE_FACTOR_STEP;
+    }
+    if pressed(keys.brightness_down) {
+        tone.brightness = (tone.brightness - BRIGHTNESS_STEP).max(-1.0);
+    }
+    if pressed(keys.brightness_up) {
+        tone.brightness = (tone.brightness + BRIGHTNESS_STEP).min(1.0);
+    }
+    if pressed(keys.contrast_down) {
+        tone.contrast /= TONE_FACTOR_STEP;
+    }
+    if pressed(keys.contrast_up) {
+        tone.contrast *= TONE_FACTOR_STEP;
+    }
+    if window.is_key_pressed(keys.tone_reset, KeyRepeat::No) {
+        tone = Tone::default();
+    }
+    if tone != state.tone {
+        state.set_tone(tone);
+    }
+}
+
+/// マウスカーソルのバッファ上のピクセル座標（ウィンドウ外なら None）
+fn mouse_pos(window: &Window, state: &ViewerState) -> Option<(f64, f64)> {
+    let scale = state.settings.mouse_scale();
+    window
+        .get_mouse_pos(MouseMode::Discard)
+        .map(|(mx, my)| (mx as f64 * scale, my as f64 * scale))
+}
+
+/// 縦スクロールの量に比例した倍率でズーム（ホイール 1 ノッチで設定の倍率、トラックパッドではなめらかに）
+fn scroll_zoom(state: &mut ViewerState, mouse_x: f64, mouse_y: f64, scroll_y: f32) {
+    let notches = (scroll_y / SCROLL_NOTCH).clamp(-1.0, 1.0) as f64;
+    if notches == 0.0 {
+        return;
+    }
+    let factor = if notches > 0.0 {
+        state.settings.zoom_in.powf(notches)
+    } else {
+        state.settings.zoom_out.powf(-notches)
+    };
+    state.zoom(mouse_x, mouse_y, factor);
+}
+
+/// ピンチの拡大率（1 で変化なし）で、カーソル位置を固定してズーム
+fn pinch_zoom(state: &mut ViewerState, mouse_x: f64, mouse_y: f64, magnification: f64) {
+    if magnification != 1.0 {
+        state.zoom(mouse_x, mouse_y, 1.0 / magnification);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::backend::Backend;
+    use crate::common::gesture::Gesture;
+    use rug::Float;
+
+    #[test]
+    fn scroll_zoom_keeps_point_under_cursor() {
+        let mut state = ViewerState::new(None, &Config::default(), Backend::Cpu);
+        let cursor = (300.0, 120.0);
+        let (re, im) = state.pixel_to_complex(cursor.0, cursor.1);
+        // ホイールの 1 ノッチ・トラックパッドの少しのスクロールで拡大し、逆向きで縮小
+        for scroll in [SCROLL_NOTCH, SCROLL_NOTCH * 0.25, -SCROLL_NOTCH, 0.0] {
+            let width = state.view_width();
+            scroll_zoom(&mut state, cursor.0, cursor.1, scroll);
+            if scroll == 0.0 {
+                assert_eq!(state.view_width(), width);
+            }
+            let (after_re, after_im) = state.pixel_to_complex(cursor.0, cursor.1);
+            let tolerance = state.view_width() * 1e-9;
+            assert!(
+                Float::with_val(state.precision, &after_re - &re)
+                    .to_f64()
+                    .abs()
+                    < tolerance
+            );
+            assert!(
+                Float::with_val(state.precision, &after_im - &im)
+                    .to_f64()
+                    .abs()
+                    < tolerance
+            );
+        }
+    }
+
+    #[test]
+    fn pinch_zoom_keeps_point_under_cursor() {
+        let mut state = ViewerState::new(None, &Config::default(), Backend::Cpu);
+        let cursor = (500.0, 400.0);
+        let (re, im) = state.pixel_to_complex(cursor.0, cursor.1);
+        // 指を広げるイベントが 2 回届くと、その積の倍率で拡大する
+        let mut gesture = Gesture::default();
+        gesture.add_magnification(0.25);
+        gesture.add_magnification(0.6);
+        assert!((gesture.magnification - 2.0).abs() < 1e-12);
+
+        let width = state.view_width();
+        pinch_zoom(&mut state, cursor.0, cursor.1, gesture.magnification);
+        assert!((state.view_width() - width / 2.0).abs() < width * 1e-12);
+        let (after_re, after_im) = state.pixel_to_complex(cursor.0, cursor.1);
+        let tolerance = state.view_width() * 1e-9;
+        assert!(
+            Float::with_val(state.precision, &after_re - &re)
+                .to_f64()
+                .abs()
+                < tolerance
+        );
+        assert!(
+            Float::with_val(state.precision, &after_im - &im)
+                .to_f64()
+                .abs()
+                < tolerance
+        );
+
+        // ジェスチャーが届かなければ変わらない
+        let width = state.view_width();
+        pinch_zoom(
+            &mut state,
+            cursor.0,
+            cursor.1,
+            Gesture::default().magnification,
+        );
+        assert_eq!(state.view_width(), width);
+    }
+
+    #[test]
+    fn scroll_zoom_direction_follows_settings() {
+        let mut state = ViewerState::new(None, &Config::default(), Backend::Cpu);
+        let width = state.view_width();
+        scroll_zoom(&mut state, 10.0, 10.0, SCROLL_NOTCH);
+        assert!((state.view_width() - width * state.settings.zoom_in).abs() < width * 1e-12);
+        let width = state.view_width();
+        scroll_zoom(&mut state, 10.0, 10.0, -SCROLL_NOTCH);
+        assert!((state.view_width() - width * state.settings.zoom_out).abs() < width * 1e-12);
+    }
+}
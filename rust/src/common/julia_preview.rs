@@ -0,0 +1,133 @@
+//! カーソル位置の点を c としたジュリア集合の小窓プレビュー
+//!
+//! マンデルブロ集合の各点とジュリア集合の対応を探索するため、カーソルが動くたびに
+//! 低解像度のジュリア集合を f64 で計算し、描画領域の隅に重ねて表示します。
+
+use super::animation::{INITIAL_HEIGHT, INITIAL_WIDTH};
+use super::colors::{ColoringMode, Colorizer, Palette};
+use super::constants::{JULIA_PREVIEW_HEIGHT, JULIA_PREVIEW_WIDTH};
+use super::draw::draw_rect;
+use super::mandelbrot::FractalType;
+use num_complex::Complex;
+use rayon::prelude::*;
+
+/// 描画領域の端からの余白
+const MARGIN: usize = 10;
+
+/// 枠線の色
+const BORDER_COLOR: u32 = 0xFFFFFF;
+
+/// ジュリア集合のプレビュー
+pub struct JuliaPreview {
+    pub enabled: bool,
+    /// 表示中のプレビューの c・式の種類・最大反復回数（非表示なら None）
+    shown: Option<(Complex<f64>, FractalType, u32)>,
+    iters: Vec<u32>,
+    /// 左上の位置（描画領域内のピクセル）
+    origin: (usize, usize),
+}
+
+impl Default for JuliaPreview {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JuliaPreview {
+    /// 非表示の状態で作成
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            shown: None,
+            iters: vec![0; JULIA_PREVIEW_WIDTH * JULIA_PREVIEW_HEIGHT],
+            origin: (0, 0),
+        }
+    }
+
+    /// 表示・非表示を切り替える
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        if !self.enabled {
+            self.shown = None;
+        }
+    }
+
+    /// カーソル位置（描画領域内のピクセルとその点の座標）に合わせてプレビューを更新
+    ///
+    /// プレビューは大きさ `area` の描画領域の右下（カーソルが重なる場合は左下）に置きます。
+    /// `cursor` が None ならプレビューを隠します。表示が変わった場合は true を返すので、
+    /// 呼び出し側で画面を合成し直してください。
+    pub fn update(
+        &mut self,
+        cursor: Option<((f64, f64), Complex<f64>)>,
+        (area_width, area_height): (usize, usize),
+        fractal: FractalType,
+        max_iter: u32,
+    ) -> bool {
+        let Some(((mouse_x, mouse_y), c)) = cursor.filter(|_| self.enabled) else {
+            return self.shown.take().is_some();
+        };
+
+        // 右下に置き、カーソルが重なる場合は左下に逃がす
+        let right = area_width - MARGIN - JULIA_PREVIEW_WIDTH - 2;
+        let bottom = area_height - MARGIN - JULIA_PREVIEW_HEIGHT - 2;
+        let over_right = mouse_x >= right as f64 && mouse_y >= bottom as f64;
+        let origin = (if over_right { MARGIN } else { right }, bottom);
+        let moved = origin != self.origin;
+        self.origin = origin;
+
+        let shown = Some((c, fractal, max_iter));
+        if self.shown == shown {
+            return moved;
+        }
+        self.render(c, fractal, max_iter);
+        self.shown = shown;
+        true
+    }
+
+    /// 初期表示と同じ範囲のジュリア集合を計算（ピクセルは正方形）
+    fn render(&mut self, c: Complex<f64>, fractal: FractalType, max_iter: u32) {
+        let scale = (INITIAL_WIDTH / JULIA_PREVIEW_WIDTH as f64)
+            .max(INITIAL_HEIGHT / JULIA_PREVIEW_HEIGHT as f64);
+        let half_w = JULIA_PREVIEW_WIDTH as f64 / 2.0;
+        let half_h = JULIA_PREVIEW_HEIGHT as f64 / 2.0;
+
+        self.iters
+            .par_chunks_mut(JULIA_PREVIEW_WIDTH)
+            .enumerate()
+            .for_each(|(y, row)| {
+                let zy = (half_h - y as f64) * scale;
+                for (x, iter) in row.iter_mut().enumerate() {
+                    let zx = (x as f64 - half_w) * scale;
+                    *iter = fractal.iter_fast(Complex::new(zx, zy), c, max_iter);
+                }
+            });
+    }
+
+    /// 表示中ならプレビューを枠付きでウィンドウのバッファ（幅 `stride`）に重ねる
+    pub fn draw(&self, buffer: &mut [u32], stride: usize, palette: &Palette, mode: ColoringMode) {
+        let Some((_, _, max_iter)) = self.shown else {
+            return;
+        };
+        let (left, top) = self.origin;
+        let colorizer = Colorizer::new(&self.iters, max_iter, mode);
+
+        for y in 0..JULIA_PREVIEW_HEIGHT {
+            for x in 0..JULIA_PREVIEW_WIDTH {
+                let iter = self.iters[y * JULIA_PREVIEW_WIDTH + x];
+                buffer[(top + 1 + y) * stride + left + 1 + x] = colorizer.color(iter, palette);
+            }
+        }
+        draw_rect(
+            buffer,
+            stride,
+            (stride, buffer.len() / stride),
+            (left as f64, top as f64),
+            (
+                (left + JULIA_PREVIEW_WIDTH + 1) as f64,
+                (top + JULIA_PREVIEW_HEIGHT + 1) as f64,
+            ),
+            BORDER_COLOR,
+        );
+    }
+}
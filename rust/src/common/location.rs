@@ -0,0 +1,232 @@
+//! 他のフラクタルソフトと位置を交換するためのパラメータファイルの読み書き
+//!
+//! Kalles Fraktaler の `.kfr` と Fractint の `.par` に対応します。
+//! 位置として扱うのは中心座標・ズーム倍率・最大反復回数で、それ以外の設定は読み飛ばします。
+
+use super::animation::{View, INITIAL_HEIGHT};
+use super::constants::MAX_ITER;
+use rug::Float;
+use std::path::Path;
+
+/// Kalles Fraktaler の `Zoom: 1` のときの表示の高さ（虚軸方向 ±2）
+const KFR_HEIGHT_AT_ZOOM_1: f64 = 4.0;
+
+/// Fractint の `center-mag` の倍率 1 のときの表示の高さ（虚軸方向 ±1）
+const PAR_HEIGHT_AT_MAG_1: f64 = 2.0;
+
+/// パラメータファイルの形式
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LocationFormat {
+    /// Kalles Fraktaler（`Re:` / `Im:` / `Zoom:` / `Iterations:` の各行）
+    KallesFraktaler,
+    /// Fractint（`center-mag=` / `maxiter=` を含むパラメータエントリ）
+    Fractint,
+}
+
+impl LocationFormat {
+    /// 拡張子から形式を判別
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "kfr" => Some(LocationFormat::KallesFraktaler),
+            "par" => Some(LocationFormat::Fractint),
+            _ => None,
+        }
+    }
+
+    /// ファイルの拡張子
+    pub fn extension(self) -> &'static str {
+        match self {
+            LocationFormat::KallesFraktaler => "kfr",
+            LocationFormat::Fractint => "par",
+        }
+    }
+}
+
+impl std::str::FromStr for LocationFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "kfr" => Ok(LocationFormat::KallesFraktaler),
+            "par" => Ok(LocationFormat::Fractint),
+            _ => Err(format!(
+                "パラメータファイルの形式の指定が不正です（kfr / par）: {}",
+                s
+            )),
+        }
+    }
+}
+
+/// パラメータファイルで交換する位置
+pub struct Location {
+    pub view: View,
+    pub max_iter: u32,
+}
+
+impl Location {
+    /// 拡張子で形式を判別して読み込む
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let format = LocationFormat::from_path(path).ok_or_else(|| {
+            format!(
+                "{}: 拡張子からパラメータファイルの形式を判別できません（.kfr / .par）",
+                path.display()
+            )
+        })?;
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let location = match format {
+            LocationFormat::KallesFraktaler => Self::parse_kfr(&text),
+            LocationFormat::Fractint => Self::parse_par(&text),
+        };
+        location.map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// 指定した形式で保存
+    pub fn save(&self, path: &Path, format: LocationFormat) -> Result<(), String> {
+        let text = match format {
+            LocationFormat::KallesFraktaler => self.to_kfr(),
+            LocationFormat::Fractint => {
+                let name = path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("mandelbrot");
+                self.to_par(name)
+            }
+        };
+        std::fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Kalles Fraktaler 形式の文字列から読み込む
+    pub fn parse_kfr(text: &str) -> Result<Self, String> {
+        let field = |name: &str| {
+            text.lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(key, _)| key.trim() == name)
+                .map(|(_, value)| value.trim())
+        };
+        let required =
+            |name: &str| field(name).ok_or_else(|| format!("\"{}:\" がありません", name));
+
+        let zoom_text = required("Zoom")?;
+        let zoom: f64 = zoom_text
+            .parse()
+            .map_err(|_| format!("ズーム倍率が不正です: {}", zoom_text))?;
+        let max_iter = parse_max_iter(field("Iterations"))?;
+        let view = View::from_decimal(
+            required("Re")?,
+            required("Im")?,
+            zoom * INITIAL_HEIGHT / KFR_HEIGHT_AT_ZOOM_1,
+        )?;
+        Ok(Self { view, max_iter })
+    }
+
+    /// Fractint 形式の文字列から読み込む（複数のエントリがある場合は最初のもの）
+    pub fn parse_par(text: &str) -> Result<Self, String> {
+        let field = |name: &str| {
+            text.split_whitespace()
+                .filter_map(|token| token.split_once('='))
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value)
+        };
+
+        let center_mag = field("center-mag").ok_or("\"center-mag=\" がありません")?;
+        // <実部>/<虚部>/<倍率>[/<縦横比>/<回転>/<傾き>]
+        let parts: Vec<&str> = center_mag.split('/').collect();
+        let [re, im, mag, ..] = parts[..] else {
+            return Err(format!("center-mag の形式が不正です: {}", center_mag));
+        };
+        let mag: f64 = mag
+            .parse()
+            .map_err(|_| format!("倍率が不正です: {}", mag))?;
+        let max_iter = parse_max_iter(field("maxiter"))?;
+        let view = View::from_decimal(re, im, mag * INITIAL_HEIGHT / PAR_HEIGHT_AT_MAG_1)?;
+        Ok(Self { view, max_iter })
+    }
+
+    /// Kalles Fraktaler 形式の文字列に変換
+    pub fn to_kfr(&self) -> String {
+        format!(
+            "Re: {}\r\nIm: {}\r\nZoom: {:E}\r\nIterations: {}\r\n",
+            plain_decimal(&self.view.center_x),
+            plain_decimal(&self.view.center_y),
+            KFR_HEIGHT_AT_ZOOM_1 / self.view.height,
+            self.max_iter
+        )
+    }
+
+    /// Fractint 形式のパラメータエントリに変換
+    pub fn to_par(&self, name: &str) -> String {
+        format!(
+            "{} {{\n  reset type=mandel\n  center-mag={}/{}/{}\n  maxiter={}\n  }}\n",
+            name,
+            plain_decimal(&self.view.center_x),
+            plain_decimal(&self.view.center_y),
+            PAR_HEIGHT_AT_MAG_1 / self.view.height,
+            self.max_iter
+        )
+    }
+}
+
+/// 最大反復回数の値を読み込む（省略時は既定値）
+fn parse_max_iter(value: Option<&str>) -> Result<u32, String> {
+    match value {
+        Some(v) => v
+            .parse()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| format!("最大反復回数が不正です: {}", v)),
+        None => Ok(MAX_ITER),
+    }
+}
+
+/// 指数表記を使わない10進文字列（精度に見合う桁数）
+///
+/// 他のソフトでも読めるよう `-0.000123` のような形式で出力します。
+fn plain_decimal(value: &Float) -> String {
+    let digits = (value.prec() as f64 * std::f64::consts::LOG10_2).ceil() as usize + 1;
+    let text = value.to_string_radix(10, Some(digits));
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", text.as_str()),
+    };
+    let (mantissa, exponent) = match text.split_once('e') {
+        Some((m, e)) => (m, e.parse::<i64>().unwrap_or(0)),
+        None => (text, 0),
+    };
+    let point = mantissa.find('.').unwrap_or(mantissa.len()) as i64 + exponent;
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+
+    let (int_part, frac_part) = if point <= 0 {
+        (
+            "0".to_string(),
+            "0".repeat((-point) as usize) + digits.as_str(),
+        )
+    } else if point as usize >= digits.len() {
+        (
+            digits.clone() + &"0".repeat(point as usize - digits.len()),
+            String::new(),
+        )
+    } else {
+        (
+            digits[..point as usize].to_string(),
+            digits[point as usize..].to_string(),
+        )
+    };
+    let int_part = match int_part.trim_start_matches('0') {
+        "" => "0",
+        trimmed => trimmed,
+    };
+    let frac_part = frac_part.trim_end_matches('0');
+    let sign = if int_part == "0" && frac_part.is_empty() {
+        ""
+    } else {
+        sign
+    };
+
+    if frac_part.is_empty() {
+        format!("{}{}", sign, int_part)
+    } else {
+        format!("{}{}.{}", sign, int_part, frac_part)
+    }
+}
@@ -2,63 +2,1217 @@
 
 use num_complex::Complex;
 use rug::{Assign, Float};
+use wide::{f64x8, CmpLe};
 
-/// マンデルブロ集合の反復回数を計算（f64高速版）
-pub fn mandelbrot_iter_fast(c: Complex<f64>, max_iter: u32) -> u32 {
-    let mut z = Complex::new(0.0, 0.0);
+/// 反復する式の種類
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FractalType {
+    /// z ← z² + c
+    Mandelbrot,
+    /// z ← (|Re z| + i|Im z|)² + c
+    BurningShip,
+    /// z ← conj(z)² + c
+    Tricorn,
+    /// z ← z^d + c（d ≥ 2）
+    Multibrot(u32),
+}
+
+/// 累乗する前に z に施す変換
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EscapeKind {
+    /// そのまま
+    Standard,
+    /// 実部・虚部の絶対値を取る（バーニングシップ）
+    BurningShip,
+    /// 複素共役を取る（トリコーン）
+    Tricorn,
+}
+
+impl EscapeKind {
+    /// z に変換を施す（f64版）
+    pub fn fold(self, z: Complex<f64>) -> Complex<f64> {
+        match self {
+            EscapeKind::Standard => z,
+            EscapeKind::BurningShip => Complex::new(z.re.abs(), z.im.abs()),
+            EscapeKind::Tricorn => z.conj(),
+        }
+    }
+}
+
+/// 脱出時間法の反復の設定
+///
+/// z ← fold(z)^power + c を |z| が `bailout` を超えるまで反復します。
+/// `FractalType` の各式はこの設定の組み合わせとして計算します。
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct EscapeTimeParams {
+    /// 累乗の次数（2 以上）
+    pub power: u32,
+    /// 発散判定の半径
+    pub bailout: f64,
+    /// 累乗する前の変換
+    pub kind: EscapeKind,
+}
+
+impl Default for EscapeTimeParams {
+    /// マンデルブロ集合の z ← z² + c（発散判定の半径 2）
+    fn default() -> Self {
+        Self {
+            power: 2,
+            bailout: 2.0,
+            kind: EscapeKind::Standard,
+        }
+    }
+}
 
+impl EscapeTimeParams {
+    /// 1回分の反復 z ← fold(z)^power + c（f64版）
+    pub fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        let w = self.kind.fold(z);
+        if self.power == 2 {
+            w * w + c
+        } else {
+            w.powu(self.power) + c
+        }
+    }
+
+    /// `step` の z についての微分に `dz` を掛けたもの（f64版）
+    ///
+    /// バーニングシップとトリコーンは z について正則でないため、絶対値の等しい z^power の微分で近似します。
+    pub fn step_derivative(&self, z: Complex<f64>, dz: Complex<f64>) -> Complex<f64> {
+        if self.power == 2 {
+            2.0 * z * dz
+        } else {
+            z.powu(self.power - 1) * dz * self.power as f64
+        }
+    }
+
+    /// z_0 = `z` から反復し、(発散までの回数, 発散した時点の z) を返す（f64高速版）
+    ///
+    /// `max_iter` 回で発散しなかった場合は (`max_iter`, 最後の z) です。
+    pub fn escape_fast(
+        &self,
+        z: Complex<f64>,
+        c: Complex<f64>,
+        max_iter: u32,
+    ) -> (u32, Complex<f64>) {
+        let bailout_sqr = self.bailout * self.bailout;
+        // 最もよく使う z² + c は変換と累乗の分岐を含まないループで計算する
+        match (self.kind, self.power) {
+            (EscapeKind::Standard, 2) => escape_loop(z, max_iter, bailout_sqr, |z| z * z + c),
+            _ => escape_loop(z, max_iter, bailout_sqr, |z| self.step(z, c)),
+        }
+    }
+
+    /// z_0 = `z` から反復し、発散までの回数を返す（f64高速版）
+    pub fn iter_fast(&self, z: Complex<f64>, c: Complex<f64>, max_iter: u32) -> u32 {
+        self.escape_fast(z, c, max_iter).0
+    }
+}
+
+/// |z|² が `bailout_sqr` を超えるまで `step` を反復する
+fn escape_loop<F>(
+    mut z: Complex<f64>,
+    max_iter: u32,
+    bailout_sqr: f64,
+    step: F,
+) -> (u32, Complex<f64>)
+where
+    F: Fn(Complex<f64>) -> Complex<f64>,
+{
     for i in 0..max_iter {
+        if z.norm_sqr() > bailout_sqr {
+            return (i, z);
+        }
+        z = step(z);
+    }
+    (max_iter, z)
+}
+
+impl FractalType {
+    /// 反復の設定（発散判定の半径は 2）
+    pub fn params(self) -> EscapeTimeParams {
+        let (kind, power) = match self {
+            FractalType::Mandelbrot => (EscapeKind::Standard, 2),
+            FractalType::BurningShip => (EscapeKind::BurningShip, 2),
+            FractalType::Tricorn => (EscapeKind::Tricorn, 2),
+            FractalType::Multibrot(power) => (EscapeKind::Standard, power),
+        };
+        EscapeTimeParams {
+            power,
+            kind,
+            ..EscapeTimeParams::default()
+        }
+    }
+
+    /// ジュリア集合の定数 `julia_c`（マンデルブロ集合なら None）の画像が実軸について対称か
+    ///
+    /// バーニングシップ以外の式は、c が実数なら z と共役な点の軌道が互いに共役になります。
+    pub fn is_symmetric(self, julia_c: Option<Complex<f64>>) -> bool {
+        self != FractalType::BurningShip && julia_c.is_none_or(|c| c.im == 0.0)
+    }
+
+    /// 次の種類（キー操作での切替順）
+    pub fn next(self) -> Self {
+        match self {
+            FractalType::Mandelbrot => FractalType::BurningShip,
+            FractalType::BurningShip => FractalType::Tricorn,
+            FractalType::Tricorn => FractalType::Multibrot(3),
+            FractalType::Multibrot(3) => FractalType::Multibrot(4),
+            FractalType::Multibrot(_) => FractalType::Mandelbrot,
+        }
+    }
+
+    /// `--fractal` やファイルで使う名前
+    pub fn name(self) -> String {
+        match self {
+            FractalType::Mandelbrot => "mandelbrot".to_string(),
+            FractalType::BurningShip => "burning-ship".to_string(),
+            FractalType::Tricorn => "tricorn".to_string(),
+            FractalType::Multibrot(power) => format!("multibrot-{}", power),
+        }
+    }
+
+    /// パラメータ平面での初期表示の中心
+    pub fn initial_center(self) -> (f64, f64) {
+        match self {
+            FractalType::Mandelbrot => (-0.75, 0.0),
+            FractalType::BurningShip => (-0.45, -0.5),
+            FractalType::Tricorn => (-0.3, 0.0),
+            FractalType::Multibrot(_) => (0.0, 0.0),
+        }
+    }
+
+    /// 1回分の反復 z ← f(z) + c（f64版）
+    pub fn step(self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        self.params().step(z, c)
+    }
+
+    /// `step` の z についての微分に `dz` を掛けたもの（f64版）
+    pub fn step_derivative(self, z: Complex<f64>, dz: Complex<f64>) -> Complex<f64> {
+        self.params().step_derivative(z, dz)
+    }
+
+    /// z_0 = `z` から反復し、発散までの回数を返す（f64高速版）
+    pub fn iter_fast(self, z: Complex<f64>, c: Complex<f64>, max_iter: u32) -> u32 {
+        self.params().iter_fast(z, c, max_iter)
+    }
+}
+
+impl std::fmt::Display for FractalType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FractalType::Mandelbrot => write!(f, "マンデルブロ集合"),
+            FractalType::BurningShip => write!(f, "バーニングシップ"),
+            FractalType::Tricorn => write!(f, "トリコーン"),
+            FractalType::Multibrot(power) => write!(f, "マルチブロ集合 (d={})", power),
+        }
+    }
+}
+
+impl std::str::FromStr for FractalType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mandelbrot" => Ok(FractalType::Mandelbrot),
+            "burning-ship" => Ok(FractalType::BurningShip),
+            "tricorn" => Ok(FractalType::Tricorn),
+            _ => s
+                .strip_prefix("multibrot-")
+                .and_then(|d| d.parse().ok())
+                .filter(|d| (2..=16).contains(d))
+                .map(FractalType::Multibrot)
+                .ok_or_else(|| {
+                    format!(
+                        "フラクタルの種類が不正です（mandelbrot / burning-ship / tricorn / multibrot-<2〜16>）: {}",
+                        s
+                    )
+                }),
+        }
+    }
+}
+
+/// 描画する平面
+#[derive(Clone, PartialEq)]
+pub enum Plane {
+    /// マンデルブロ集合（各点を c として z_0 = 0 から反復）
+    Mandelbrot,
+    /// ジュリア集合（各点を z_0 として固定の c で反復）
+    Julia { c_real: Float, c_imag: Float },
+}
+
+impl Plane {
+    /// 初期表示の中心
+    pub fn initial_center(&self, fractal: FractalType) -> (f64, f64) {
+        match self {
+            Plane::Mandelbrot => fractal.initial_center(),
+            Plane::Julia { .. } => (0.0, 0.0),
+        }
+    }
+
+    /// ウィンドウタイトルなどに表示する名前
+    pub fn label(&self, fractal: FractalType) -> String {
+        match (self, fractal) {
+            (Plane::Mandelbrot, _) => fractal.to_string(),
+            (Plane::Julia { .. }, FractalType::Mandelbrot) => "ジュリア集合".to_string(),
+            (Plane::Julia { .. }, _) => format!("ジュリア集合 ({})", fractal),
+        }
+    }
+
+    /// f64 版の反復に渡すジュリア集合の定数（マンデルブロ集合なら None）
+    pub fn julia_c(&self) -> Option<Complex<f64>> {
+        match self {
+            Plane::Mandelbrot => None,
+            Plane::Julia { c_real, c_imag } => Some(Complex::new(c_real.to_f64(), c_imag.to_f64())),
+        }
+    }
+
+    /// 点 (x, y) の反復回数を計算（高精度版、作業用変数を再利用）
+    pub fn iter_hp_with(
+        &self,
+        fractal: FractalType,
+        x: &Float,
+        y: &Float,
+        max_iter: u32,
+        scratch: &mut HpScratch,
+    ) -> u32 {
+        match self {
+            Plane::Mandelbrot => {
+                scratch.z_real.assign(0.0);
+                scratch.z_imag.assign(0.0);
+                iterate_hp(&fractal.params(), x, y, max_iter, scratch)
+            }
+            Plane::Julia { c_real, c_imag } => {
+                scratch.z_real.assign(x);
+                scratch.z_imag.assign(y);
+                iterate_hp(&fractal.params(), c_real, c_imag, max_iter, scratch)
+            }
+        }
+    }
+}
+
+/// 点 `point` の反復回数を計算（f64高速版、`julia_c` が None ならパラメータ平面）
+pub fn escape_iter_fast(
+    fractal: FractalType,
+    point: Complex<f64>,
+    julia_c: Option<Complex<f64>>,
+    max_iter: u32,
+) -> u32 {
+    match julia_c {
+        Some(c) => fractal.iter_fast(point, c, max_iter),
+        None => fractal.iter_fast(Complex::new(0.0, 0.0), point, max_iter),
+    }
+}
+
+/// 距離推定で使う発散判定の半径の2乗（距離の式の誤差を抑えるため通常の 4 より大きくする）
+const DISTANCE_BAILOUT_SQR: f64 = 1e4;
+
+/// 点 `point` から集合の境界までの距離の推定値（f64版、`julia_c` が None ならパラメータ平面）
+///
+/// 反復と同時に微分 dz/dc（ジュリア集合では dz/dz_0）を追跡し、発散した点について
+/// 0.5·|z|·ln|z| / |dz| を返します。`max_iter` 回で発散しなかった点は None です。
+pub fn escape_distance_fast(
+    fractal: FractalType,
+    point: Complex<f64>,
+    julia_c: Option<Complex<f64>>,
+    max_iter: u32,
+) -> Option<f64> {
+    escape_with_distance(fractal, point, julia_c, max_iter).map(|(_, distance)| distance)
+}
+
+/// [`escape_distance_fast`] と同じ反復で、発散した点について (発散までの回数, 距離の推定値) を返す
+fn escape_with_distance(
+    fractal: FractalType,
+    point: Complex<f64>,
+    julia_c: Option<Complex<f64>>,
+    max_iter: u32,
+) -> Option<(u32, f64)> {
+    let zero = Complex::new(0.0, 0.0);
+    let one = Complex::new(1.0, 0.0);
+    let (mut z, c, mut dz, dc) = match julia_c {
+        Some(c) => (point, c, one, zero),
+        None => (zero, point, zero, one),
+    };
+
+    for i in 0..max_iter {
+        let norm_sqr = z.norm_sqr();
+        if norm_sqr > DISTANCE_BAILOUT_SQR {
+            let norm = norm_sqr.sqrt();
+            return Some((i, 0.5 * norm * norm.ln() / dz.norm()));
+        }
+        dz = fractal.step_derivative(z, dz) + dc;
+        z = fractal.step(z, c);
+    }
+    None
+}
+
+/// 点 `point` の軌道 z_0, z_1, … を計算（f64版、`julia_c` が None ならパラメータ平面）
+///
+/// 発散した最初の点まで含め、最大で `max_iter + 1` 点を返します。
+pub fn escape_orbit(
+    fractal: FractalType,
+    point: Complex<f64>,
+    julia_c: Option<Complex<f64>>,
+    max_iter: u32,
+) -> Vec<Complex<f64>> {
+    let (mut z, c) = match julia_c {
+        Some(c) => (point, c),
+        None => (Complex::new(0.0, 0.0), point),
+    };
+    let mut orbit = vec![z];
+    for _ in 0..max_iter {
         if z.norm_sqr() > 4.0 {
-            return i;
+            break;
+        }
+        z = fractal.step(z, c);
+        orbit.push(z);
+    }
+    orbit
+}
+
+/// 周期軌道に戻ったとみなす距離の2乗
+const PERIOD_EPSILON_SQR: f64 = 1e-20;
+
+/// 点の周期の検出結果
+pub struct PeriodInfo {
+    /// 内部の点なら、軌道が収束する周期軌道の周期（発散した点や収束しきらない点は None）
+    pub period: Option<u32>,
+    /// 発散した点なら発散までの反復回数
+    pub escaped_at: Option<u32>,
+    /// 原子領域の周期（|z_n| が最小になる n、n ≥ 1）
+    pub atom_domain: u32,
+}
+
+/// 点 `point` の周期と原子領域を検出（f64版、`julia_c` が None ならパラメータ平面）
+///
+/// `max_iter` 回反復しても発散しなければ内部の点とみなし、そこからさらに最大 `max_iter` 回反復して
+/// 軌道が z_max_iter の近くに戻ってくるまでの回数を周期とします。
+pub fn detect_period(
+    fractal: FractalType,
+    point: Complex<f64>,
+    julia_c: Option<Complex<f64>>,
+    max_iter: u32,
+) -> PeriodInfo {
+    let (mut z, c) = match julia_c {
+        Some(c) => (point, c),
+        None => (Complex::new(0.0, 0.0), point),
+    };
+    let mut atom_domain = 1;
+    let mut min_norm = f64::INFINITY;
+
+    for n in 0..max_iter {
+        if z.norm_sqr() > 4.0 {
+            return PeriodInfo {
+                period: None,
+                escaped_at: Some(n),
+                atom_domain,
+            };
+        }
+        z = fractal.step(z, c);
+        if z.norm_sqr() < min_norm {
+            min_norm = z.norm_sqr();
+            atom_domain = n + 1;
+        }
+    }
+
+    let reference = z;
+    let period = (1..=max_iter).find(|_| {
+        z = fractal.step(z, c);
+        (z - reference).norm_sqr() < PERIOD_EPSILON_SQR
+    });
+    PeriodInfo {
+        period,
+        escaped_at: None,
+        atom_domain,
+    }
+}
+
+impl std::fmt::Display for PeriodInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.period, self.escaped_at) {
+            (Some(period), _) => write!(f, "周期 {} の内部の点", period)?,
+            (None, Some(n)) => write!(f, "外部の点（{} 回で発散）", n)?,
+            (None, None) => write!(f, "内部の点（周期軌道に収束しきらず周期は不明）")?,
+        }
+        write!(f, " / 原子領域の周期: {}", self.atom_domain)
+    }
+}
+
+/// マンデルブロ集合の反復回数を計算（f64高速版）
+pub fn mandelbrot_iter_fast(c: Complex<f64>, max_iter: u32) -> u32 {
+    julia_iter_fast(Complex::new(0.0, 0.0), c, max_iter)
+}
+
+/// [`mandelbrot_iter_fast_x8`] で一度に計算する点の数
+pub const LANES: usize = 8;
+
+/// マンデルブロ集合の反復回数を 8 点まとめて計算（f64高速版）
+///
+/// 8 点を 1 本の SIMD ベクトル（`wide::f64x8`。AVX-512 なら 1 命令、AVX なら 2 命令、SSE2 なら 4 命令、
+/// SIMD のない環境ではスカラーの演算になる）に並べ、同じ演算を全レーンに適用します。
+/// 発散の判定は比較命令のマスクで行い、発散したレーンはマスクで z と回数の更新を止め、
+/// 全レーンが発散した時点で打ち切ります。結果は各点について [`mandelbrot_iter_fast`] と同じです。
+pub fn mandelbrot_iter_fast_x8(cx: [f64; LANES], cy: [f64; LANES], max_iter: u32) -> [u32; LANES] {
+    let (cx, cy) = (f64x8::new(cx), f64x8::new(cy));
+    let (one, two, four) = (f64x8::splat(1.0), f64x8::splat(2.0), f64x8::splat(4.0));
+    let mut zr = f64x8::ZERO;
+    let mut zi = f64x8::ZERO;
+    let mut iters = f64x8::ZERO;
+
+    for _ in 0..max_iter {
+        let (x2, y2) = (zr * zr, zi * zi);
+        // |z|² ≤ 4 のレーンは全ビットが 1、それ以外は 0（発散したレーンは z を止めるので 0 のまま）
+        let alive = (x2 + y2).simd_le(four);
+        if alive.none() {
+            break;
+        }
+        iters += alive & one;
+        let next_r = x2 - y2 + cx;
+        let next_i = two * zr * zi + cy;
+        zr = alive.blend(next_r, zr);
+        zi = alive.blend(next_i, zi);
+    }
+    iters.to_array().map(|n| n as u32)
+}
+
+/// ジュリア集合の反復回数を計算（f64高速版、z_0 = `z`）
+pub fn julia_iter_fast(z: Complex<f64>, c: Complex<f64>, max_iter: u32) -> u32 {
+    EscapeTimeParams::default().iter_fast(z, c, max_iter)
+}
+
+/// 連続的な反復回数で使う反復の設定
+///
+/// 発散判定の半径 2 では補正の誤差で色の帯が残るため、十分大きな半径（256）まで反復します。
+const SMOOTH_PARAMS: EscapeTimeParams = EscapeTimeParams {
+    power: 2,
+    bailout: 256.0,
+    kind: EscapeKind::Standard,
+};
+
+/// 発散までの回数 `n` と発散時の |z|² から連続的な反復回数 n + 1 − log₂(ln|z|) を求める
+fn smooth_value(n: u32, norm_sqr: f64) -> f64 {
+    n as f64 + 1.0 - (0.5 * norm_sqr.ln()).log2()
+}
+
+/// マンデルブロ集合の連続的な反復回数を計算（f64高速版）
+///
+/// 発散までの回数を発散時の |z| で補間した小数値を返します。
+/// `max_iter` 回で発散しなかった点は `max_iter as f64` です。
+pub fn mandelbrot_smooth_fast(c: Complex<f64>, max_iter: u32) -> f64 {
+    julia_smooth_fast(Complex::new(0.0, 0.0), c, max_iter)
+}
+
+/// ジュリア集合の連続的な反復回数を計算（f64高速版、z_0 = `z`）
+///
+/// 値の意味は [`mandelbrot_smooth_fast`] と同じです。
+pub fn julia_smooth_fast(z: Complex<f64>, c: Complex<f64>, max_iter: u32) -> f64 {
+    match SMOOTH_PARAMS.escape_fast(z, c, max_iter) {
+        (n, _) if n == max_iter => max_iter as f64,
+        (n, z) => smooth_value(n, z.norm_sqr()),
+    }
+}
+
+/// マンデルブロ集合の反復回数と境界までの距離推定を計算（f64高速版）
+///
+/// 反復と同時に dz/dc を追跡し、発散した点について (発散までの回数, 0.5·|z|·ln|z| / |dz/dc|) を返します。
+/// 発散判定の半径は [`escape_distance_fast`] と同じく大きめに取るため、回数は [`mandelbrot_iter_fast`]
+/// より少し大きくなります。`max_iter` 回で発散しなかった点は `(max_iter, 0.0)` です。
+pub fn mandelbrot_de_fast(c: Complex<f64>, max_iter: u32) -> (u32, f64) {
+    escape_with_distance(FractalType::Mandelbrot, c, None, max_iter).unwrap_or((max_iter, 0.0))
+}
+
+/// 高精度版の反復で使う作業用変数
+///
+/// ピクセルごとに `Float` を確保し直さないよう、スレッドごとに1つ作って使い回します。
+pub struct HpScratch {
+    z_real: Float,
+    z_imag: Float,
+    zr2: Float,
+    zi2: Float,
+    norm_sqr: Float,
+    next_r: Float,
+    next_i: Float,
+}
+
+impl HpScratch {
+    pub fn new(precision: u32) -> Self {
+        Self {
+            z_real: Float::new(precision),
+            z_imag: Float::new(precision),
+            zr2: Float::new(precision),
+            zi2: Float::new(precision),
+            norm_sqr: Float::new(precision),
+            next_r: Float::new(precision),
+            next_i: Float::new(precision),
         }
-        z = z * z + c;
     }
-    max_iter
 }
 
 /// マンデルブロ集合の反復回数を計算（高精度版）
 pub fn mandelbrot_iter_hp(c_real: &Float, c_imag: &Float, max_iter: u32, precision: u32) -> u32 {
-    let mut z_real = Float::with_val(precision, 0.0);
-    let mut z_imag = Float::with_val(precision, 0.0);
+    mandelbrot_iter_hp_with(c_real, c_imag, max_iter, &mut HpScratch::new(precision))
+}
+
+/// マンデルブロ集合の反復回数を計算（高精度版、作業用変数を再利用）
+///
+/// 精度は `scratch` を作成したときの精度になります。
+pub fn mandelbrot_iter_hp_with(
+    c_real: &Float,
+    c_imag: &Float,
+    max_iter: u32,
+    scratch: &mut HpScratch,
+) -> u32 {
+    scratch.z_real.assign(0.0);
+    scratch.z_imag.assign(0.0);
+    iterate_hp(
+        &EscapeTimeParams::default(),
+        c_real,
+        c_imag,
+        max_iter,
+        scratch,
+    )
+}
+
+/// ジュリア集合の反復回数を計算（高精度版、z_0 = (z_real, z_imag)）
+pub fn julia_iter_hp(
+    z_real: &Float,
+    z_imag: &Float,
+    c_real: &Float,
+    c_imag: &Float,
+    max_iter: u32,
+    precision: u32,
+) -> u32 {
+    julia_iter_hp_with(
+        z_real,
+        z_imag,
+        c_real,
+        c_imag,
+        max_iter,
+        &mut HpScratch::new(precision),
+    )
+}
+
+/// ジュリア集合の反復回数を計算（高精度版、作業用変数を再利用）
+pub fn julia_iter_hp_with(
+    z_real: &Float,
+    z_imag: &Float,
+    c_real: &Float,
+    c_imag: &Float,
+    max_iter: u32,
+    scratch: &mut HpScratch,
+) -> u32 {
+    scratch.z_real.assign(z_real);
+    scratch.z_imag.assign(z_imag);
+    iterate_hp(
+        &EscapeTimeParams::default(),
+        c_real,
+        c_imag,
+        max_iter,
+        scratch,
+    )
+}
+
+/// マンデルブロ集合の連続的な反復回数を計算（高精度版）
+pub fn mandelbrot_smooth_hp(c_real: &Float, c_imag: &Float, max_iter: u32, precision: u32) -> f64 {
+    mandelbrot_smooth_hp_with(c_real, c_imag, max_iter, &mut HpScratch::new(precision))
+}
+
+/// マンデルブロ集合の連続的な反復回数を計算（高精度版、作業用変数を再利用）
+///
+/// 値の意味は [`mandelbrot_smooth_fast`] と同じです。
+pub fn mandelbrot_smooth_hp_with(
+    c_real: &Float,
+    c_imag: &Float,
+    max_iter: u32,
+    scratch: &mut HpScratch,
+) -> f64 {
+    scratch.z_real.assign(0.0);
+    scratch.z_imag.assign(0.0);
+    iterate_smooth_hp(c_real, c_imag, max_iter, scratch)
+}
+
+/// ジュリア集合の連続的な反復回数を計算（高精度版、z_0 = (z_real, z_imag)）
+pub fn julia_smooth_hp(
+    z_real: &Float,
+    z_imag: &Float,
+    c_real: &Float,
+    c_imag: &Float,
+    max_iter: u32,
+    precision: u32,
+) -> f64 {
+    julia_smooth_hp_with(
+        z_real,
+        z_imag,
+        c_real,
+        c_imag,
+        max_iter,
+        &mut HpScratch::new(precision),
+    )
+}
+
+/// ジュリア集合の連続的な反復回数を計算（高精度版、作業用変数を再利用）
+pub fn julia_smooth_hp_with(
+    z_real: &Float,
+    z_imag: &Float,
+    c_real: &Float,
+    c_imag: &Float,
+    max_iter: u32,
+    scratch: &mut HpScratch,
+) -> f64 {
+    scratch.z_real.assign(z_real);
+    scratch.z_imag.assign(z_imag);
+    iterate_smooth_hp(c_real, c_imag, max_iter, scratch)
+}
+
+/// `scratch` に設定済みの z_0 から z ← z² + c を反復し、連続的な反復回数を返す
+fn iterate_smooth_hp(
+    c_real: &Float,
+    c_imag: &Float,
+    max_iter: u32,
+    scratch: &mut HpScratch,
+) -> f64 {
+    let n = iterate_hp(&SMOOTH_PARAMS, c_real, c_imag, max_iter, scratch);
+    if n == max_iter {
+        return max_iter as f64;
+    }
+    smooth_value(n, scratch.norm_sqr.to_f64())
+}
 
-    // 作業用変数を事前に確保（アロケーション削減）
-    let mut zr2 = Float::with_val(precision, 0.0);
-    let mut zi2 = Float::with_val(precision, 0.0);
-    let mut norm_sqr = Float::with_val(precision, 0.0);
-    let mut next_r = Float::with_val(precision, 0.0);
-    let mut next_i = Float::with_val(precision, 0.0);
+/// `scratch` に設定済みの z_0 から `params` の式を反復し、発散までの回数を返す
+///
+/// 発散した場合、`scratch.norm_sqr` には発散した時点の |z|² が残ります。
+fn iterate_hp(
+    params: &EscapeTimeParams,
+    c_real: &Float,
+    c_imag: &Float,
+    max_iter: u32,
+    scratch: &mut HpScratch,
+) -> u32 {
+    let HpScratch {
+        z_real,
+        z_imag,
+        zr2,
+        zi2,
+        norm_sqr,
+        next_r,
+        next_i,
+    } = scratch;
+    let bailout_sqr = params.bailout * params.bailout;
 
     for i in 0..max_iter {
         // zr2 = z_real^2
-        zr2.assign(&z_real);
+        zr2.assign(&*z_real);
         zr2.square_mut();
 
         // zi2 = z_imag^2
-        zi2.assign(&z_imag);
+        zi2.assign(&*z_imag);
         zi2.square_mut();
 
         // norm_sqr = zr2 + zi2
-        norm_sqr.assign(&zr2);
-        norm_sqr += &zi2;
+        norm_sqr.assign(&*zr2);
+        *norm_sqr += &*zi2;
 
-        if norm_sqr > 4.0 {
+        if *norm_sqr > bailout_sqr {
             return i;
         }
 
-        // next_r = zr2 - zi2 + c_real
-        next_r.assign(&zr2);
-        next_r -= &zi2;
-        next_r += c_real;
+        // 累乗する前の変換（zr2・zi2 は変わらない）
+        match params.kind {
+            EscapeKind::Standard => {}
+            EscapeKind::BurningShip => {
+                z_real.abs_mut();
+                z_imag.abs_mut();
+            }
+            EscapeKind::Tricorn => *z_imag *= -1.0,
+        }
+
+        if params.power != 2 {
+            // next = z^power（z を power - 1 回掛ける、zr2・zi2・norm_sqr は作業用に流用）
+            next_r.assign(&*z_real);
+            next_i.assign(&*z_imag);
+            for _ in 1..params.power {
+                zr2.assign(&*next_r);
+                *zr2 *= &*z_real;
+                zi2.assign(&*next_i);
+                *zi2 *= &*z_imag;
+                norm_sqr.assign(&*next_r);
+                *norm_sqr *= &*z_imag;
+                *next_i *= &*z_real;
+                *next_i += &*norm_sqr;
+                next_r.assign(&*zr2);
+                *next_r -= &*zi2;
+            }
+            *next_r += c_real;
+            *next_i += c_imag;
+        } else {
+            // next_r = zr2 - zi2 + c_real
+            next_r.assign(&*zr2);
+            *next_r -= &*zi2;
+            *next_r += c_real;
 
-        // next_i = 2 * z_real * z_imag + c_imag
-        next_i.assign(&z_real);
-        next_i *= &z_imag;
-        next_i *= 2.0;
-        next_i += c_imag;
+            // next_i = 2 * z_real * z_imag + c_imag
+            next_i.assign(&*z_real);
+            *next_i *= &*z_imag;
+            *next_i *= 2.0;
+            *next_i += c_imag;
+        }
 
         // update z
-        z_real.assign(&next_r);
-        z_imag.assign(&next_i);
+        z_real.assign(&*next_r);
+        z_imag.assign(&*next_i);
+    }
+    max_iter
+}
+
+/// 摂動法の参照軌道
+///
+/// 中心点 C の軌道 Z_n を任意精度で一度だけ計算し、f64 に丸めて保持します。
+/// |Z_n| は発散判定の 2 を大きく超えないため、f64 でも十分な精度で表せます。
+pub struct ReferenceOrbit {
+    orbit: Vec<Complex<f64>>,
+}
+
+impl ReferenceOrbit {
+    /// 参照点 (c_real, c_imag) の軌道を計算（参照点が発散した時点で打ち切る）
+    pub fn new(c_real: &Float, c_imag: &Float, max_iter: u32, precision: u32) -> Self {
+        let mut z_real = Float::with_val(precision, 0.0);
+        let mut z_imag = Float::with_val(precision, 0.0);
+
+        let mut zr2 = Float::with_val(precision, 0.0);
+        let mut zi2 = Float::with_val(precision, 0.0);
+        let mut norm_sqr = Float::with_val(precision, 0.0);
+        let mut next_r = Float::with_val(precision, 0.0);
+        let mut next_i = Float::with_val(precision, 0.0);
+
+        let mut orbit = Vec::with_capacity(max_iter as usize + 1);
+        for _ in 0..=max_iter {
+            orbit.push(Complex::new(z_real.to_f64(), z_imag.to_f64()));
+
+            zr2.assign(&z_real);
+            zr2.square_mut();
+            zi2.assign(&z_imag);
+            zi2.square_mut();
+            norm_sqr.assign(&zr2);
+            norm_sqr += &zi2;
+            if norm_sqr > 4.0 {
+                break;
+            }
+
+            next_r.assign(&zr2);
+            next_r -= &zi2;
+            next_r += c_real;
+
+            next_i.assign(&z_real);
+            next_i *= &z_imag;
+            next_i *= 2.0;
+            next_i += c_imag;
+
+            z_real.assign(&next_r);
+            z_imag.assign(&next_i);
+        }
+
+        Self { orbit }
+    }
+
+    /// f64 に丸めた軌道 Z_0, Z_1, …（参照点が発散した場合は発散した最初の点まで）
+    pub fn points(&self) -> &[Complex<f64>] {
+        &self.orbit
+    }
+
+    /// 参照点が `max_iter` 回以内に発散したか（発散していれば以降の反復はリベースで補われる）
+    pub fn escaped(&self) -> bool {
+        self.orbit.last().is_some_and(|z| z.norm_sqr() > 4.0)
+    }
+
+    /// 保持している軌道の長さ（Z_0 を含む）
+    pub fn len(&self) -> usize {
+        self.orbit.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orbit.is_empty()
+    }
+}
+
+/// 級数近似の打ち切り誤差の許容値（高次の項と1次の項の比）
+const SERIES_TOLERANCE: f64 = 1e-6;
+
+/// 摂動法の級数近似
+///
+/// 差分 δ_n を δc の多項式 A_n δc + B_n δc² + C_n δc³ で近似し、
+/// 近似が有効な反復回数まではピクセルごとの反復を省略します。
+/// 係数は参照軌道から δ の漸化式と同じ形で求めます:
+///   A_{n+1} = 2Z_n A_n + 1, B_{n+1} = 2Z_n B_n + A_n², C_{n+1} = 2Z_n C_n + 2A_n B_n
+pub struct SeriesApproximation {
+    a: Complex<f64>,
+    b: Complex<f64>,
+    c: Complex<f64>,
+    skip: u32,
+}
+
+impl SeriesApproximation {
+    /// 画面内の |δc| の最大値 `max_delta` に対して近似が有効な範囲まで係数を求める
+    pub fn new(orbit: &ReferenceOrbit, max_delta: f64) -> Self {
+        let r2 = max_delta * max_delta;
+        let zero = Complex::new(0.0, 0.0);
+        let mut series = Self {
+            a: zero,
+            b: zero,
+            c: zero,
+            skip: 0,
+        };
+
+        // 発散直前の参照点までに留める（最後の点は発散済みの可能性がある）
+        for &z in orbit.orbit.iter().take(orbit.len().saturating_sub(2)) {
+            let a = z * 2.0 * series.a + 1.0;
+            let b = z * 2.0 * series.b + series.a * series.a;
+            let c = z * 2.0 * series.c + series.a * series.b * 2.0;
+
+            // 2次・3次の項が1次の項に比べて無視できなくなったら打ち切る（オーバーフロー時も同様）
+            // 2次の項は次数が1つ低いため、許容値の平方根で比べる
+            let bound = SERIES_TOLERANCE * a.norm();
+            let quadratic = b.norm() * max_delta;
+            let cubic = c.norm() * r2;
+            if !(bound.is_finite() && quadratic.is_finite() && cubic.is_finite())
+                || quadratic > SERIES_TOLERANCE.sqrt() * a.norm()
+                || cubic > bound
+            {
+                break;
+            }
+            series.a = a;
+            series.b = b;
+            series.c = c;
+            series.skip += 1;
+        }
+        series
+    }
+
+    /// 省略できる反復回数
+    pub fn skip(&self) -> u32 {
+        self.skip
+    }
+
+    /// 省略後の差分 δ_skip
+    fn evaluate(&self, delta_c: Complex<f64>) -> Complex<f64> {
+        ((self.c * delta_c + self.b) * delta_c + self.a) * delta_c
+    }
+}
+
+/// 摂動法による反復回数の計算
+///
+/// `delta_c` は参照点からのオフセット。各ピクセルの軌道を z_n = Z_n + δ_n と表し、
+/// δ_{n+1} = (2Z_n + δ_n)δ_n + δc だけを f64 で反復します。
+/// ピクセルの軌道が差分より原点に近づいた場合や参照軌道が先に発散した場合は、
+/// 参照軌道の先頭に乗り換えて（リベース）誤差の蓄積（グリッチ）を防ぎます。
+pub fn perturbation_iter(delta_c: Complex<f64>, orbit: &ReferenceOrbit, max_iter: u32) -> u32 {
+    perturbation_iter_from(delta_c, orbit, 0, Complex::new(0.0, 0.0), max_iter)
+}
+
+/// 級数近似で序盤の反復を省略してから摂動法で反復
+pub fn perturbation_iter_with_series(
+    delta_c: Complex<f64>,
+    orbit: &ReferenceOrbit,
+    series: &SeriesApproximation,
+    max_iter: u32,
+) -> u32 {
+    let start = series.skip.min(max_iter);
+    let dz = series.evaluate(delta_c);
+
+    // 省略した区間内で発散した点は（|z| > 2 になると以後は発散し続けるため）
+    // 省略後の時点でも発散しているので、最初から反復し直して正確な回数を求める
+    if (orbit.orbit[start as usize] + dz).norm_sqr() > 4.0 {
+        return perturbation_iter(delta_c, orbit, max_iter);
+    }
+    perturbation_iter_from(delta_c, orbit, start, dz, max_iter)
+}
+
+/// 反復 `start` 回目の差分 `dz` から摂動法の反復を続ける
+fn perturbation_iter_from(
+    delta_c: Complex<f64>,
+    orbit: &ReferenceOrbit,
+    start: u32,
+    mut dz: Complex<f64>,
+    max_iter: u32,
+) -> u32 {
+    let reference = &orbit.orbit;
+    let mut m = start as usize;
+
+    for i in start..max_iter {
+        let z = reference[m] + dz;
+        if z.norm_sqr() > 4.0 {
+            return i;
+        }
+
+        if z.norm_sqr() < dz.norm_sqr() || m + 1 >= reference.len() {
+            dz = z;
+            m = 0;
+        }
+
+        dz = (reference[m] * 2.0 + dz) * dz + delta_c;
+        m += 1;
     }
     max_iter
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PREC: u32 = 128;
+
+    /// 参照点 (re, im) のまわりの格子の点（参照点からのオフセット、`step` 間隔で各辺 `n` 点）
+    fn offsets(n: i32, step: f64) -> Vec<Complex<f64>> {
+        let half = n / 2;
+        (-half..=half)
+            .flat_map(|j| {
+                (-half..=half).map(move |i| Complex::new(i as f64 * step, j as f64 * step))
+            })
+            .collect()
+    }
+
+    /// 参照点からのオフセット `delta` の点の反復回数を高精度版で直接計算
+    fn direct_iter(re: f64, im: f64, delta: Complex<f64>, max_iter: u32) -> u32 {
+        let c_real = Float::with_val(PREC, re) + delta.re;
+        let c_imag = Float::with_val(PREC, im) + delta.im;
+        mandelbrot_iter_hp(&c_real, &c_imag, max_iter, PREC)
+    }
+
+    /// 摂動法の回数が直接計算の ±1 に収まることを確かめ、発散した点と発散しなかった点の数を返す
+    fn assert_matches_direct(re: f64, im: f64, step: f64, max_iter: u32) -> (usize, usize) {
+        let orbit = ReferenceOrbit::new(
+            &Float::with_val(PREC, re),
+            &Float::with_val(PREC, im),
+            max_iter,
+            PREC,
+        );
+        let series = SeriesApproximation::new(&orbit, step * 10.0);
+        let (mut escaped, mut bounded) = (0, 0);
+        for delta in offsets(21, step) {
+            let expected = direct_iter(re, im, delta, max_iter);
+            for actual in [
+                perturbation_iter(delta, &orbit, max_iter),
+                perturbation_iter_with_series(delta, &orbit, &series, max_iter),
+            ] {
+                assert!(
+                    actual.abs_diff(expected) <= 1,
+                    "c = ({} + {}, {} + {}): 摂動法 {} / 直接計算 {}",
+                    re,
+                    delta.re,
+                    im,
+                    delta.im,
+                    actual,
+                    expected
+                );
+            }
+            if expected == max_iter {
+                bounded += 1;
+            } else {
+                escaped += 1;
+            }
+        }
+        (escaped, bounded)
+    }
+
+    #[test]
+    fn perturbation_matches_direct_iteration_around_bounded_reference() {
+        let (re, im, max_iter) = (-1.25, 0.02, 500);
+        let orbit = ReferenceOrbit::new(
+            &Float::with_val(PREC, re),
+            &Float::with_val(PREC, im),
+            max_iter,
+            PREC,
+        );
+        assert!(!orbit.escaped());
+        assert_eq!(orbit.len(), max_iter as usize + 1);
+
+        let (escaped, bounded) = assert_matches_direct(re, im, 3e-4, max_iter);
+        assert!(escaped > 0 && bounded > 0);
+    }
+
+    #[test]
+    fn perturbation_rebases_when_reference_escapes() {
+        // 主カージオイドの尖点のすぐ外側の参照点は途中で発散し、まわりには発散しない点も含まれる
+        let (re, im, max_iter) = (0.28, 0.01, 300);
+        let orbit = ReferenceOrbit::new(
+            &Float::with_val(PREC, re),
+            &Float::with_val(PREC, im),
+            max_iter,
+            PREC,
+        );
+        assert!(orbit.escaped());
+        assert!(orbit.points().len() < max_iter as usize);
+
+        let (escaped, bounded) = assert_matches_direct(re, im, 3e-3, max_iter);
+        assert!(escaped > 0 && bounded > 0);
+    }
+
+    #[test]
+    fn perturbation_from_start_matches_full_iteration() {
+        // 途中の反復回数から続けても、最初から反復した結果と同じになる
+        let (re, im, max_iter) = (-1.25, 0.02, 500);
+        let orbit = ReferenceOrbit::new(
+            &Float::with_val(PREC, re),
+            &Float::with_val(PREC, im),
+            max_iter,
+            PREC,
+        );
+        let start = 5;
+        for delta in offsets(7, 3e-4) {
+            let mut dz = Complex::new(0.0, 0.0);
+            for n in 0..start {
+                dz = (orbit.points()[n] * 2.0 + dz) * dz + delta;
+            }
+            assert_eq!(
+                perturbation_iter_from(delta, &orbit, start as u32, dz, max_iter),
+                perturbation_iter(delta, &orbit, max_iter)
+            );
+        }
+    }
+
+    /// 集合の内側（主カージオイド・周期 2 の円板）と外側（発散の早い点・境界の近くの点）
+    const SAMPLES: [(f64, f64); 6] = [
+        (-0.2, 0.1),
+        (-1.0, 0.05),
+        (0.5, 0.5),
+        (-0.75, 0.2),
+        (0.3, 0.0),
+        (-2.5, 1.0),
+    ];
+
+    #[test]
+    fn julia_from_origin_equals_mandelbrot() {
+        // z_0 = 0 のジュリア集合の反復はマンデルブロ集合の反復そのもの
+        let zero = Float::with_val(PREC, 0.0);
+        let mut scratch = HpScratch::new(PREC);
+        for (re, im) in SAMPLES {
+            let c = Complex::new(re, im);
+            let (c_real, c_imag) = (Float::with_val(PREC, re), Float::with_val(PREC, im));
+            assert_eq!(
+                julia_smooth_fast(Complex::new(0.0, 0.0), c, 200),
+                mandelbrot_smooth_fast(c, 200)
+            );
+            assert_eq!(
+                julia_smooth_hp(&zero, &zero, &c_real, &c_imag, 200, PREC),
+                mandelbrot_smooth_hp(&c_real, &c_imag, 200, PREC)
+            );
+            assert_eq!(
+                julia_smooth_hp_with(&zero, &zero, &c_real, &c_imag, 200, &mut scratch),
+                mandelbrot_smooth_hp_with(&c_real, &c_imag, 200, &mut scratch)
+            );
+        }
+    }
+
+    #[test]
+    fn julia_from_c_is_one_step_ahead_of_mandelbrot() {
+        // z_0 = c から始めると、z_1 = c から続くマンデルブロ集合の反復より回数が 1 だけ少ない
+        for (re, im) in SAMPLES {
+            let c = Complex::new(re, im);
+            let (c_real, c_imag) = (Float::with_val(PREC, re), Float::with_val(PREC, im));
+            let mandelbrot = mandelbrot_smooth_fast(c, 200);
+            let julia = julia_smooth_fast(c, c, 199);
+            let julia_hp = julia_smooth_hp(&c_real, &c_imag, &c_real, &c_imag, 199, PREC);
+            if mandelbrot == 200.0 {
+                assert_eq!(julia, 199.0);
+                assert_eq!(julia_hp, 199.0);
+            } else {
+                assert!(
+                    (julia - (mandelbrot - 1.0)).abs() < 1e-9,
+                    "c = ({}, {})",
+                    re,
+                    im
+                );
+                assert!(
+                    (julia_hp - (mandelbrot - 1.0)).abs() < 1e-6,
+                    "c = ({}, {})",
+                    re,
+                    im
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn julia_fast_and_hp_agree() {
+        let (c_re, c_im) = (-0.12, 0.75);
+        let c = Complex::new(c_re, c_im);
+        let (c_real, c_imag) = (Float::with_val(PREC, c_re), Float::with_val(PREC, c_im));
+        let mut scratch = HpScratch::new(PREC);
+        // ダウディのうさぎ: 原点付近は集合の内側、離れた点は外側
+        let points = [
+            (0.0, 0.0),
+            (0.05, -0.05),
+            (-0.1, 0.05),
+            (0.6, 0.3),
+            (0.0, 1.0),
+            (-0.9, -0.5),
+        ];
+        let (mut inside, mut outside) = (0, 0);
+        for (re, im) in points {
+            let (z_real, z_imag) = (Float::with_val(PREC, re), Float::with_val(PREC, im));
+            let fast = julia_smooth_fast(Complex::new(re, im), c, 300);
+            let hp = julia_smooth_hp(&z_real, &z_imag, &c_real, &c_imag, 300, PREC);
+            let hp_with =
+                julia_smooth_hp_with(&z_real, &z_imag, &c_real, &c_imag, 300, &mut scratch);
+            assert!(
+                (fast - hp).abs() < 1e-6,
+                "z = ({}, {}): {} / {}",
+                re,
+                im,
+                fast,
+                hp
+            );
+            assert_eq!(hp, hp_with);
+            assert_eq!(
+                julia_iter_fast(Complex::new(re, im), c, 300),
+                julia_iter_hp(&z_real, &z_imag, &c_real, &c_imag, 300, PREC)
+            );
+            if fast == 300.0 {
+                inside += 1;
+            } else {
+                outside += 1;
+            }
+        }
+        assert!(inside > 0 && outside > 0);
+    }
+
+    #[test]
+    fn iter_fast_x8_matches_scalar() {
+        // 各行の 8 点は内部・境界付近・すぐに発散する点が混ざるように並べる
+        for max_iter in [0, 1, 2, 50, 1000] {
+            for row in 0..40 {
+                let y = -1.3 + 2.6 * row as f64 / 39.0;
+                for start in 0..12 {
+                    let x0 = -2.2 + 0.25 * start as f64;
+                    let cx: [f64; LANES] = std::array::from_fn(|k| x0 + 0.0371 * k as f64);
+                    let cy = [y; LANES];
+                    let batch = mandelbrot_iter_fast_x8(cx, cy, max_iter);
+                    for k in 0..LANES {
+                        let c = Complex::new(cx[k], cy[k]);
+                        assert_eq!(batch[k], mandelbrot_iter_fast(c, max_iter), "c = {}", c);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn de_fast_matches_escape_distance() {
+        for (re, im) in [
+            (-0.75, 0.1),
+            (0.3, 0.5),
+            (-1.25, 0.02),
+            (0.0, 0.0),
+            (2.0, 2.0),
+        ] {
+            let c = Complex::new(re, im);
+            let (n, de) = mandelbrot_de_fast(c, 500);
+            match escape_distance_fast(FractalType::Mandelbrot, c, None, 500) {
+                Some(distance) => {
+                    assert!(n < 500, "c = {}", c);
+                    assert_eq!(de, distance, "c = {}", c);
+                }
+                None => assert_eq!((n, de), (500, 0.0), "c = {}", c),
+            }
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! 集合全体の縮小図に現在の表示範囲を示すミニマップ
+//!
+//! 深いズームでも現在地が分かるよう、初期表示の範囲を低解像度で計算した全体図を描画領域の右上に重ね、
+//! 表示範囲を枠で示します。枠が小さくなりすぎた場合は表示範囲の中心に十字のマーカーを描きます。
+
+use super::animation::{INITIAL_HEIGHT, INITIAL_WIDTH};
+use super::colors::{ColoringMode, Colorizer, Palette};
+use super::constants::{MINIMAP_HEIGHT, MINIMAP_WIDTH};
+use super::draw::{draw_line, draw_rect};
+use super::mandelbrot::{escape_iter_fast, FractalType, Plane};
+use num_complex::Complex;
+use rayon::prelude::*;
+
+/// 描画領域の端からの余白
+const MARGIN: usize = 10;
+
+/// 枠線の色
+const BORDER_COLOR: u32 = 0xFFFFFF;
+
+/// 表示範囲の枠とマーカーの色
+const VIEWPORT_COLOR: u32 = 0xFFFF00;
+
+/// この大きさ（ピクセル）より枠が小さくなったらマーカーで示す
+const MIN_VIEWPORT_SIZE: f64 = 4.0;
+
+/// マーカーの腕の長さ（ピクセル）
+const MARKER_SIZE: f64 = 6.0;
+
+/// ミニマップ
+pub struct Minimap {
+    /// 計算済みの全体図の平面・式の種類・最大反復回数（未計算なら None）
+    shown: Option<(Plane, FractalType, u32)>,
+    iters: Vec<u32>,
+    /// 全体図の左上の座標
+    origin: (f64, f64),
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Minimap {
+    /// 全体図を未計算の状態で作成
+    pub fn new() -> Self {
+        Self {
+            shown: None,
+            iters: vec![0; MINIMAP_WIDTH * MINIMAP_HEIGHT],
+            origin: (0.0, 0.0),
+        }
+    }
+
+    /// 平面・式の種類・最大反復回数が変わっていれば全体図を計算し直す
+    pub fn update(&mut self, plane: &Plane, fractal: FractalType, max_iter: u32) {
+        if let Some((shown_plane, shown_fractal, shown_max_iter)) = &self.shown {
+            if shown_plane == plane && *shown_fractal == fractal && *shown_max_iter == max_iter {
+                return;
+            }
+        }
+
+        // ビューアの初期表示と同じ範囲を同じ縦横比で計算
+        let (cx, cy) = plane.initial_center(fractal);
+        let (x_min, y_max) = (cx - INITIAL_WIDTH / 2.0, cy + INITIAL_HEIGHT / 2.0);
+        let x_scale = INITIAL_WIDTH / MINIMAP_WIDTH as f64;
+        let y_scale = INITIAL_HEIGHT / MINIMAP_HEIGHT as f64;
+        let julia_c = plane.julia_c();
+
+        self.iters
+            .par_chunks_mut(MINIMAP_WIDTH)
+            .enumerate()
+            .for_each(|(y, row)| {
+                let py = y_max - y as f64 * y_scale;
+                for (x, iter) in row.iter_mut().enumerate() {
+                    let px = x_min + x as f64 * x_scale;
+                    *iter = escape_iter_fast(fractal, Complex::new(px, py), julia_c, max_iter);
+                }
+            });
+        self.origin = (x_min, y_max);
+        self.shown = Some((plane.clone(), fractal, max_iter));
+    }
+
+    /// 全体図に表示範囲（`x_min`, `x_max`, `y_min`, `y_max`）を重ねてウィンドウのバッファ（幅 `stride`）の
+    /// 描画領域（幅 `area_width`）の右上に描く
+    pub fn draw(
+        &self,
+        buffer: &mut [u32],
+        stride: usize,
+        area_width: usize,
+        (x_min, x_max, y_min, y_max): (f64, f64, f64, f64),
+        palette: &Palette,
+        mode: ColoringMode,
+    ) {
+        let Some((_, _, max_iter)) = self.shown else {
+            return;
+        };
+        let left = area_width - MARGIN - MINIMAP_WIDTH - 2;
+        let top = MARGIN;
+        let colorizer = Colorizer::new(&self.iters, max_iter, mode);
+
+        for y in 0..MINIMAP_HEIGHT {
+            for x in 0..MINIMAP_WIDTH {
+                let iter = self.iters[y * MINIMAP_WIDTH + x];
+                buffer[(top + 1 + y) * stride + left + 1 + x] = colorizer.color(iter, palette);
+            }
+        }
+        draw_rect(
+            buffer,
+            stride,
+            (stride, buffer.len() / stride),
+            (left as f64, top as f64),
+            (
+                (left + MINIMAP_WIDTH + 1) as f64,
+                (top + MINIMAP_HEIGHT + 1) as f64,
+            ),
+            BORDER_COLOR,
+        );
+
+        // 表示範囲を全体図のピクセル座標に変換
+        let (origin_x, origin_y) = self.origin;
+        let x_scale = INITIAL_WIDTH / MINIMAP_WIDTH as f64;
+        let y_scale = INITIAL_HEIGHT / MINIMAP_HEIGHT as f64;
+        let x0 = (x_min - origin_x) / x_scale;
+        let x1 = (x_max - origin_x) / x_scale;
+        let y0 = (origin_y - y_max) / y_scale;
+        let y1 = (origin_y - y_min) / y_scale;
+
+        // 全体図の内側にだけ描く（全体図より広い範囲は端で切り詰められる）
+        let inner = &mut buffer[(top + 1) * stride + left + 1..];
+        let clip = (MINIMAP_WIDTH, MINIMAP_HEIGHT);
+
+        if x1 - x0 < MIN_VIEWPORT_SIZE || y1 - y0 < MIN_VIEWPORT_SIZE {
+            // 中心が全体図の外にある場合は端に寄せて方向を示す
+            let cx = ((x0 + x1) / 2.0).clamp(0.0, MINIMAP_WIDTH as f64 - 1.0);
+            let cy = ((y0 + y1) / 2.0).clamp(0.0, MINIMAP_HEIGHT as f64 - 1.0);
+            for (from, to) in [
+                ((cx - MARKER_SIZE, cy), (cx + MARKER_SIZE, cy)),
+                ((cx, cy - MARKER_SIZE), (cx, cy + MARKER_SIZE)),
+            ] {
+                draw_line(inner, stride, clip, from, to, VIEWPORT_COLOR);
+            }
+            return;
+        }
+
+        draw_rect(inner, stride, clip, (x0, y0), (x1, y1), VIEWPORT_COLOR);
+    }
+}
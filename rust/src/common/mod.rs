@@ -1,6 +1,31 @@
 //! 共通モジュール
 
+pub mod animation;
+pub mod backend;
+pub mod cli;
+pub mod colorbar;
 pub mod colors;
+pub mod config;
 pub mod constants;
+pub mod display;
+pub mod draw;
+pub mod export;
+pub mod filename;
+pub mod fixed;
 pub mod font;
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+pub mod gesture;
+pub mod gradient;
+pub mod grid;
+pub mod input;
+pub mod julia_preview;
+pub mod location;
 pub mod mandelbrot;
+pub mod minimap;
+pub mod render;
+pub mod session;
+pub mod settings;
+pub mod snapshot;
+pub mod video;
+pub mod viewer;
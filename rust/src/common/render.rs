@@ -0,0 +1,433 @@
+//! バックグラウンド描画とキャンセル制御
+
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// タイルの一辺のピクセル数
+pub const TILE_SIZE: usize = 64;
+
+/// まだ計算されていないピクセルを表す反復回数
+pub const UNRENDERED: u32 = u32::MAX;
+
+/// 8×8 の Bayer 行列（値の小さい順に 8・4・2・1 ピクセルおきの格子を埋めていく）
+const BAYER: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// 描画開始からこの時間が経つまでは途中経過を表示しない（高速な描画のちらつき防止）
+const PREVIEW_DELAY: Duration = Duration::from_millis(100);
+
+/// 描画結果（着色前の反復回数）
+pub struct RenderOutput {
+    pub iters: Vec<u32>,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// 描画スレッドが書き込み、表示側が途中経過を読み出す反復回数バッファ
+pub struct SharedFrame {
+    iters: Vec<AtomicU32>,
+    width: usize,
+    height: usize,
+    dirty: AtomicBool,
+}
+
+impl SharedFrame {
+    /// すべて未計算の状態で作成
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            iters: (0..width * height)
+                .map(|_| AtomicU32::new(UNRENDERED))
+                .collect(),
+            width,
+            height,
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    /// `output` の縦横2倍の解像度で作成し、`output` のピクセルを偶数行・偶数列に置く（残りは未計算）
+    ///
+    /// ピクセルの座標を画像の中心からのずれで計算する描画では、元の解像度のピクセルと
+    /// 2倍の解像度の偶数行・偶数列のピクセルが同じ点になるため、計算し直さずに引き継げます。
+    pub fn refine(output: &RenderOutput) -> Self {
+        let frame = Self::new(output.width * 2, output.height * 2);
+        for (i, &iter) in output.iters.iter().enumerate() {
+            frame.set(i % output.width * 2, i / output.width * 2, iter);
+        }
+        frame
+    }
+
+    fn from_output(output: RenderOutput) -> Self {
+        Self {
+            iters: output.iters.into_iter().map(AtomicU32::new).collect(),
+            width: output.width,
+            height: output.height,
+            dirty: AtomicBool::new(true),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// 1ピクセル分の反復回数（未計算なら `UNRENDERED`）
+    pub fn get(&self, x: usize, y: usize) -> u32 {
+        self.iters[y * self.width + x].load(Ordering::Relaxed)
+    }
+
+    /// 1ピクセル分の反復回数を書き込む
+    pub fn set(&self, x: usize, y: usize, iter: u32) {
+        self.iters[y * self.width + x].store(iter, Ordering::Relaxed);
+    }
+
+    /// 新しく書き込んだ結果を表示側に通知
+    pub fn mark_dirty(&self) {
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    fn take_dirty(&self) -> bool {
+        self.dirty.swap(false, Ordering::Acquire)
+    }
+
+    /// 現在の内容をコピー（未計算のピクセルは `UNRENDERED`）
+    pub fn snapshot(&self) -> RenderOutput {
+        RenderOutput {
+            iters: self
+                .iters
+                .iter()
+                .map(|iter| iter.load(Ordering::Relaxed))
+                .collect(),
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+/// 実軸について対称な画像で、実軸より上の行だけを計算して下の行に写すための行の対応
+///
+/// 行 y と行 `sum - y` を、虚部の符号だけが異なる行として扱います。
+/// 実軸が行と行の間にある場合、写した行の虚部は本来の値から半ピクセル以内ずれます。
+#[derive(Clone, Copy, Debug)]
+pub struct Mirror {
+    sum: usize,
+}
+
+impl Mirror {
+    /// 上端の行の虚部が `top` で1行ごとに `y_scale` ずつ下がる `height` 行の画像が、実軸をまたぐ場合に作成
+    pub fn across_real_axis(top: f64, y_scale: f64, height: usize) -> Option<Self> {
+        let bottom = top - height.saturating_sub(1) as f64 * y_scale;
+        (top > 0.0 && bottom < 0.0).then(|| Self {
+            sum: (2.0 * top / y_scale).round() as usize,
+        })
+    }
+
+    /// 行 `y` を計算したときに同じ値を写す行（`height` 行の画像の中にある場合のみ）
+    pub fn target(self, y: usize, height: usize) -> Option<usize> {
+        (2 * y < self.sum && self.sum - y < height).then(|| self.sum - y)
+    }
+
+    /// 行 `y` が上の行から写される（計算しなくてよい）か
+    pub fn is_copied(self, y: usize) -> bool {
+        2 * y > self.sum && y <= self.sum
+    }
+}
+
+/// 1ピクセル分の反復回数を書き込み、`mirror` で写す行があればそこにも書き込む
+fn set_mirrored(frame: &SharedFrame, mirror: Option<Mirror>, x: usize, y: usize, iter: u32) {
+    frame.set(x, y, iter);
+    if let Some(target) = mirror.and_then(|m| m.target(y, frame.height)) {
+        frame.set(x, target, iter);
+    }
+}
+
+/// 画像を `TILE_SIZE` 四方のタイルに分割し、rayon で並列に計算する
+///
+/// 完成したタイルはすぐに `frame` に書き込まれるため、描画途中でも画面に反映できます。
+/// 境界付近の重いタイルと外側の軽いタイルが混在しても各スレッドに均等に分散されます。
+/// `mirror` を指定すると、実軸より下の行は計算せずに上の行の結果を写します。
+/// キャンセルされずに最後まで計算できた場合は true を返します。
+pub fn render_tiles<F>(
+    frame: &SharedFrame,
+    cancel: &CancelToken,
+    mirror: Option<Mirror>,
+    pixel: F,
+) -> bool
+where
+    F: Fn(usize, usize) -> u32 + Sync,
+{
+    let tiles: Vec<(usize, usize)> = (0..frame.height)
+        .step_by(TILE_SIZE)
+        .flat_map(|ty| (0..frame.width).step_by(TILE_SIZE).map(move |tx| (tx, ty)))
+        .collect();
+
+    tiles.par_iter().for_each(|&(tx, ty)| {
+        // キャンセル済みなら残りのタイルは計算しない
+        if cancel.is_cancelled() {
+            return;
+        }
+        for y in ty..(ty + TILE_SIZE).min(frame.height) {
+            if mirror.is_some_and(|m| m.is_copied(y)) {
+                continue;
+            }
+            for x in tx..(tx + TILE_SIZE).min(frame.width) {
+                set_mirrored(frame, mirror, x, y, pixel(x, y));
+            }
+        }
+        frame.mark_dirty();
+    });
+
+    !cancel.is_cancelled()
+}
+
+/// `render_tiles` と同じ順に、各行を `N` ピクセルずつまとめて計算する
+///
+/// `pixels(x, y)` は行 `y` の `x` から右に並ぶ `N` ピクセル分の反復回数を返します
+/// （タイルの右端からはみ出した分は捨てます）。SIMD で複数の点を同時に計算する描画用です。
+pub fn render_tiles_batched<F, const N: usize>(
+    frame: &SharedFrame,
+    cancel: &CancelToken,
+    mirror: Option<Mirror>,
+    pixels: F,
+) -> bool
+where
+    F: Fn(usize, usize) -> [u32; N] + Sync,
+{
+    let tiles: Vec<(usize, usize)> = (0..frame.height)
+        .step_by(TILE_SIZE)
+        .flat_map(|ty| (0..frame.width).step_by(TILE_SIZE).map(move |tx| (tx, ty)))
+        .collect();
+
+    tiles.par_iter().for_each(|&(tx, ty)| {
+        // キャンセル済みなら残りのタイルは計算しない
+        if cancel.is_cancelled() {
+            return;
+        }
+        let right = (tx + TILE_SIZE).min(frame.width);
+        for y in ty..(ty + TILE_SIZE).min(frame.height) {
+            if mirror.is_some_and(|m| m.is_copied(y)) {
+                continue;
+            }
+            for x in (tx..right).step_by(N) {
+                for (x, iter) in (x..right).zip(pixels(x, y)) {
+                    set_mirrored(frame, mirror, x, y, iter);
+                }
+            }
+        }
+        frame.mark_dirty();
+    });
+
+    !cancel.is_cancelled()
+}
+
+/// 画像全体に散らばったピクセルを 8×8 の Bayer 行列の値の順に 64 回に分けて、rayon で並列に計算する
+///
+/// 1回目で 8 ピクセルおき、4回目までで 4 ピクセルおき、16回目までで 2 ピクセルおきの格子が揃うため、
+/// 途中経過は画像全体が粗い状態から徐々に細かくなります（穴は `fill_unrendered` で埋めて表示）。
+/// 1ピクセルの計算が重い高精度モードで、計算量の数%の時点から全体の形が分かるようにするためのものです。
+/// 計算済みのピクセル（`SharedFrame::refine` で引き継いだもの）は計算し直しません。
+/// `mirror` は `render_tiles` と同じです。
+/// `init` はスレッド（rayon の分割単位）ごとに1回だけ呼ばれ、作った作業用の値を `pixel` に渡して使い回します
+/// （任意精度の `Float` をピクセルごとに確保し直さないため）。
+/// キャンセルされずに最後まで計算できた場合は true を返します。
+pub fn render_dithered<T, I, F>(
+    frame: &SharedFrame,
+    cancel: &CancelToken,
+    mirror: Option<Mirror>,
+    init: I,
+    pixel: F,
+) -> bool
+where
+    I: Fn() -> T + Sync + Send,
+    F: Fn(&mut T, usize, usize) -> u32 + Sync + Send,
+{
+    if let Some(mirror) = mirror {
+        // 引き継いだピクセルは、写す先と写す元のどちらか一方だけが計算済みなら他方にも写す
+        for y in 0..frame.height {
+            let Some(target) = mirror.target(y, frame.height) else {
+                continue;
+            };
+            for x in 0..frame.width {
+                let (iter, mirrored) = (frame.get(x, y), frame.get(x, target));
+                if iter == UNRENDERED {
+                    frame.set(x, y, mirrored);
+                } else if mirrored == UNRENDERED {
+                    frame.set(x, target, iter);
+                }
+            }
+        }
+    }
+
+    for step in 0..64 {
+        let points: Vec<(usize, usize)> = (0..frame.height)
+            .flat_map(|y| {
+                (0..frame.width)
+                    .filter(move |&x| BAYER[y % 8][x % 8] == step && frame.get(x, y) == UNRENDERED)
+                    .filter(move |_| !mirror.is_some_and(|m| m.is_copied(y)))
+                    .map(move |x| (x, y))
+            })
+            .collect();
+
+        points.par_iter().for_each_init(&init, |scratch, &(x, y)| {
+            // キャンセル済みなら残りのピクセルは計算しない
+            if !cancel.is_cancelled() {
+                set_mirrored(frame, mirror, x, y, pixel(scratch, x, y));
+            }
+        });
+        if cancel.is_cancelled() {
+            return false;
+        }
+        frame.mark_dirty();
+    }
+    true
+}
+
+/// 未計算のピクセルを、同じ 2・4・8 ピクセル四方のブロックの左上にある計算済みのピクセルの値で埋める
+///
+/// `render_dithered` の途中経過を粗いモザイクとして表示するためのものです。
+/// タイル単位の描画では未計算のタイル内に計算済みのピクセルがないため、何も変わりません。
+pub fn fill_unrendered(output: &mut RenderOutput) {
+    let width = output.width;
+    for y in 0..output.height {
+        for x in 0..width {
+            if output.iters[y * width + x] != UNRENDERED {
+                continue;
+            }
+            for mask in [!1, !3, !7] {
+                let iter = output.iters[(y & mask) * width + (x & mask)];
+                if iter != UNRENDERED {
+                    output.iters[y * width + x] = iter;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// 着色済みバッファのうち未計算のピクセルを背景色で塗る
+pub fn mask_unrendered(colors: &mut [u32], iters: &[u32], background: u32) {
+    for (color, &iter) in colors.iter_mut().zip(iters) {
+        if iter == UNRENDERED {
+            *color = background;
+        }
+    }
+}
+
+/// 描画のキャンセルフラグ（描画スレッドは行またはタイルごとに確認する）
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// 別スレッドで実行中の描画
+///
+/// 破棄またはキャンセルされると描画スレッドは次の確認時点で処理を打ち切り、
+/// 結果は返されません。
+pub struct BackgroundRender {
+    cancel: CancelToken,
+    frame: Arc<SharedFrame>,
+    receiver: Receiver<()>,
+    started: Instant,
+}
+
+impl BackgroundRender {
+    /// `width` x `height` の描画処理を別スレッドで開始
+    ///
+    /// 処理は `SharedFrame` に結果を書き込み、最後まで計算できた場合は true、
+    /// キャンセルされた場合は false を返します。
+    pub fn spawn<F>(width: usize, height: usize, job: F) -> Self
+    where
+        F: FnOnce(&SharedFrame, &CancelToken) -> bool + Send + 'static,
+    {
+        Self::spawn_with(SharedFrame::new(width, height), job)
+    }
+
+    /// 途中まで計算済みの `frame` に続きを書き込む描画処理を別スレッドで開始
+    pub fn spawn_with<F>(frame: SharedFrame, job: F) -> Self
+    where
+        F: FnOnce(&SharedFrame, &CancelToken) -> bool + Send + 'static,
+    {
+        let cancel = CancelToken::new();
+        let frame = Arc::new(frame);
+        let (sender, receiver) = mpsc::channel();
+
+        let token = cancel.clone();
+        let shared = Arc::clone(&frame);
+        std::thread::spawn(move || {
+            if job(&shared, &token) {
+                // 受信側が既に破棄されていれば結果は捨てる
+                let _ = sender.send(());
+            }
+        });
+
+        Self {
+            cancel,
+            frame,
+            receiver,
+            started: Instant::now(),
+        }
+    }
+
+    /// 計算済みの結果をそのまま保持（GPU など同期的に描画するモード用）
+    pub fn completed(output: RenderOutput) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let _ = sender.send(());
+        Self {
+            cancel: CancelToken::new(),
+            frame: Arc::new(SharedFrame::from_output(output)),
+            receiver,
+            started: Instant::now(),
+        }
+    }
+
+    /// 描画を中断
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// 描画が完了していれば結果を取り出す
+    pub fn try_take(&self) -> Option<RenderOutput> {
+        self.receiver.try_recv().ok().map(|_| self.frame.snapshot())
+    }
+
+    /// 前回から新しく計算されたピクセルがあれば途中経過を取り出す（未計算の穴は `fill_unrendered` で埋める）
+    pub fn preview(&self) -> Option<RenderOutput> {
+        if self.started.elapsed() < PREVIEW_DELAY || !self.frame.take_dirty() {
+            return None;
+        }
+        let mut output = self.frame.snapshot();
+        fill_unrendered(&mut output);
+        Some(output)
+    }
+}
+
+impl Drop for BackgroundRender {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
@@ -0,0 +1,124 @@
+//! ビューア状態のセッション保存・復元
+
+use super::colors::ColoringMode;
+use super::mandelbrot::FractalType;
+use rug::Float;
+use std::path::{Path, PathBuf};
+
+/// セッションファイルの既定のパス
+pub const DEFAULT_SESSION_FILE: &str = "mandelbrot_session.json";
+
+/// 保存・復元するビューアの状態
+///
+/// 表示範囲は精度を落とさないよう10進文字列で保存します:
+///
+/// ```json
+/// {
+///   "x_min": "-7.4364388703715e-1", "x_max": "...", "y_min": "...", "y_max": "...",
+///   "precision": 256, "compute_mode": "hp", "max_iter": 256,
+///   "palette": "/path/to/fire.map", "coloring_mode": "histogram",
+///   "fractal": "mandelbrot", "rotation": 30
+/// }
+/// ```
+pub struct Session {
+    pub x_min: Float,
+    pub x_max: Float,
+    pub y_min: Float,
+    pub y_max: Float,
+    pub precision: u32,
+    /// 保存時の計算モード（復元時はズーム倍率から選び直すため参考情報）
+    pub compute_mode: String,
+    pub max_iter: u32,
+    pub palette: Option<PathBuf>,
+    pub coloring_mode: ColoringMode,
+    pub fractal: FractalType,
+    /// 回転角（度。0 なら省略）
+    pub rotation: f64,
+}
+
+impl Session {
+    /// JSON ファイルから読み込む
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let value: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+
+        let number = |name: &str| {
+            value[name]
+                .as_u64()
+                .filter(|&n| n <= u32::MAX as u64)
+                .map(|n| n as u32)
+                .ok_or_else(|| format!("\"{}\" に整数が必要です", name))
+        };
+        let precision = number("precision")?;
+        if precision == 0 {
+            return Err("\"precision\" が不正です: 0".to_string());
+        }
+        let bound = |name: &str| {
+            let text = value[name]
+                .as_str()
+                .ok_or_else(|| format!("\"{}\" に文字列が必要です", name))?;
+            Float::parse(text)
+                .map(|p| Float::with_val(precision, p))
+                .map_err(|_| format!("\"{}\" が数値ではありません: {}", name, text))
+        };
+        let max_iter = number("max_iter")?;
+        if max_iter == 0 {
+            return Err("\"max_iter\" が不正です: 0".to_string());
+        }
+
+        Ok(Self {
+            x_min: bound("x_min")?,
+            x_max: bound("x_max")?,
+            y_min: bound("y_min")?,
+            y_max: bound("y_max")?,
+            precision,
+            compute_mode: value["compute_mode"].as_str().unwrap_or("").to_string(),
+            max_iter,
+            palette: value["palette"].as_str().map(PathBuf::from),
+            coloring_mode: match value["coloring_mode"].as_str() {
+                Some(name) => name.parse()?,
+                None => ColoringMode::Linear,
+            },
+            fractal: match value["fractal"].as_str() {
+                Some(name) => name.parse()?,
+                None => FractalType::Mandelbrot,
+            },
+            rotation: match &value["rotation"] {
+                serde_json::Value::Null => 0.0,
+                v => v
+                    .as_f64()
+                    .ok_or_else(|| format!("\"rotation\" が不正です: {}", v))?,
+            },
+        })
+    }
+
+    /// JSON ファイルに保存
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let decimal = |f: &Float| f.to_string_radix(10, None);
+        let mut value = serde_json::json!({
+            "x_min": decimal(&self.x_min),
+            "x_max": decimal(&self.x_max),
+            "y_min": decimal(&self.y_min),
+            "y_max": decimal(&self.y_max),
+            "precision": self.precision,
+            "compute_mode": self.compute_mode,
+            "max_iter": self.max_iter,
+            "coloring_mode": self.coloring_mode.name(),
+            "fractal": self.fractal.name(),
+        });
+        if let Some(palette) = &self.palette {
+            value["palette"] = palette.display().to_string().into();
+        }
+        if self.rotation != 0.0 {
+            value["rotation"] = self.rotation.into();
+        }
+
+        let text = serde_json::to_string_pretty(&value).map_err(|e| e.to_string())?;
+        std::fs::write(path, text + "\n").map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
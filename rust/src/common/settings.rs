@@ -0,0 +1,176 @@
+//! 実行時の設定値（描画領域の大きさ・最大反復回数・精度・切替閾値・ズームの倍率）
+//!
+//! 既定値は `constants.rs` の定数で、設定ファイル（`Config`）、コマンドライン引数の順に上書きします:
+//!
+//! - `--window-size <幅>x<高さ>`: 描画領域の大きさ（ウィンドウはカラーバーの分だけ広くなる）
+//! - `--max-iter <回数>`: 起動時の最大反復回数
+//! - `--scale <auto|1|2|3|4>`: HiDPI の拡大率（`common::display`）
+//!
+//! ビューア（`ViewerState::settings`）と GPU のバッファはこの値で作成します。
+//! 大きさは論理ピクセルで指定し、ウィンドウを開く前に `apply_scale_factor` で拡大率倍の物理ピクセルにします。
+
+use super::cli::{arg_value, parse_size};
+use super::config::Thresholds;
+use super::constants::{
+    COLORBAR_WIDTH, GPU_DF64_THRESHOLD, GPU_TO_CPU_THRESHOLD, HP_RENDER_DIVISOR, INITIAL_PRECISION,
+    MANDELBROT_HEIGHT, MANDELBROT_WIDTH, MAX_ITER, MAX_PRECISION, PRECISION_THRESHOLD,
+    ZOOM_FACTOR_IN, ZOOM_FACTOR_OUT,
+};
+use super::display::{ScaleFactor, WINDOW_IN_POINTS};
+
+/// 描画領域の最小の幅（ジュリア集合のプレビューとミニマップが並んで収まる大きさ）
+pub const MIN_WIDTH: usize = 400;
+
+/// 描画領域の最小の高さ
+pub const MIN_HEIGHT: usize = 300;
+
+/// 精度（ビット）の下限（f64 の仮数部より多くする）
+pub const MIN_PRECISION: u32 = 64;
+
+/// 実行時の設定値
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    /// 描画領域の幅（`apply_scale_factor` の後は物理ピクセル）
+    pub width: usize,
+    /// 描画領域の高さ
+    pub height: usize,
+    /// HiDPI の拡大率の指定
+    pub scale: ScaleFactor,
+    /// `apply_scale_factor` で決めた拡大率（それまでは 1）
+    pub scale_factor: usize,
+    /// 起動時の最大反復回数
+    pub max_iter: u32,
+    /// 高精度モードで使う精度（ビット）の初期値
+    pub initial_precision: u32,
+    /// 精度（ビット）の上限（ズーム倍率に応じて自動で上げる）
+    pub max_precision: u32,
+    /// 計算モードの切替閾値
+    pub thresholds: Thresholds,
+    /// ズームイン時の表示範囲の倍率
+    pub zoom_in: f64,
+    /// ズームアウト時の表示範囲の倍率
+    pub zoom_out: f64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            width: MANDELBROT_WIDTH,
+            height: MANDELBROT_HEIGHT,
+            scale: ScaleFactor::default(),
+            scale_factor: 1,
+            max_iter: MAX_ITER,
+            initial_precision: INITIAL_PRECISION,
+            max_precision: MAX_PRECISION,
+            thresholds: Thresholds {
+                gpu_df64: GPU_DF64_THRESHOLD,
+                gpu_to_cpu: GPU_TO_CPU_THRESHOLD,
+                high_precision: PRECISION_THRESHOLD,
+            },
+            zoom_in: ZOOM_FACTOR_IN,
+            zoom_out: ZOOM_FACTOR_OUT,
+        }
+    }
+}
+
+impl Settings {
+    /// カラーバーの幅（物理ピクセル）
+    pub fn colorbar_width(&self) -> usize {
+        COLORBAR_WIDTH * self.scale_factor
+    }
+
+    /// ウィンドウのバッファの幅（描画領域とカラーバー）
+    pub fn window_width(&self) -> usize {
+        self.width + self.colorbar_width()
+    }
+
+    /// ウィンドウのバッファの高さ
+    pub fn window_height(&self) -> usize {
+        self.height
+    }
+
+    /// ウィンドウを作成するときの大きさ（macOS では論理ピクセル、その他の環境ではバッファと同じ）
+    pub fn window_size(&self) -> (usize, usize) {
+        let divisor = if WINDOW_IN_POINTS {
+            self.scale_factor
+        } else {
+            1
+        };
+        (
+            self.window_width() / divisor,
+            self.window_height() / divisor,
+        )
+    }
+
+    /// minifb のマウス座標をバッファのピクセル座標にする倍率
+    pub fn mouse_scale(&self) -> f64 {
+        if WINDOW_IN_POINTS {
+            self.scale_factor as f64
+        } else {
+            1.0
+        }
+    }
+
+    /// 拡大率を決め（`scale` が auto なら検出）、描画領域の大きさを物理ピクセルにする（ウィンドウを開く前に一度だけ呼ぶ）
+    pub fn apply_scale_factor(&mut self) {
+        self.scale_factor = self.scale.resolve();
+        self.width *= self.scale_factor;
+        self.height *= self.scale_factor;
+    }
+
+    /// 高精度モードで最初に描画する解像度（描画領域の 1/`HP_RENDER_DIVISOR`）
+    pub fn hp_render_size(&self) -> (usize, usize) {
+        (
+            self.width / HP_RENDER_DIVISOR,
+            self.height / HP_RENDER_DIVISOR,
+        )
+    }
+
+    /// コマンドライン引数（`--window-size`、`--max-iter`、`--scale`）で上書きする
+    pub fn apply_args(&mut self) -> Result<(), String> {
+        if let Some(v) = arg_value("--window-size") {
+            (self.width, self.height) = parse_size(&v).ok_or_else(|| {
+                format!("描画領域の大きさは <幅>x<高さ> で指定してください: {}", v)
+            })?;
+        }
+        if let Some(v) = arg_value("--scale") {
+            self.scale = v.parse()?;
+        }
+        if let Some(v) = arg_value("--max-iter") {
+            self.max_iter = v
+                .parse()
+                .ok()
+                .filter(|&n| n >= 1)
+                .ok_or_else(|| format!("最大反復回数には正の整数が必要です: {}", v))?;
+        }
+        self.check()
+    }
+
+    /// 値の組み合わせが使えるか確認する
+    ///
+    /// 高精度モードは描画領域の 1/`HP_RENDER_DIVISOR` から縦横 2 倍ずつ解像度を上げるため、
+    /// 幅と高さは `HP_RENDER_DIVISOR` の倍数にします。
+    pub fn check(&self) -> Result<(), String> {
+        if self.width < MIN_WIDTH || self.height < MIN_HEIGHT {
+            return Err(format!(
+                "描画領域は {}x{} 以上にしてください: {}x{}",
+                MIN_WIDTH, MIN_HEIGHT, self.width, self.height
+            ));
+        }
+        if !self.width.is_multiple_of(HP_RENDER_DIVISOR)
+            || !self.height.is_multiple_of(HP_RENDER_DIVISOR)
+        {
+            return Err(format!(
+                "描画領域の幅と高さは {} の倍数にしてください: {}x{}",
+                HP_RENDER_DIVISOR, self.width, self.height
+            ));
+        }
+        if self.initial_precision < MIN_PRECISION || self.initial_precision > self.max_precision {
+            return Err(format!(
+                "精度の初期値は {}〜{}（上限）ビットにしてください: {}",
+                MIN_PRECISION, self.max_precision, self.initial_precision
+            ));
+        }
+        Ok(())
+    }
+}
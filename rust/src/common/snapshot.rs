@@ -0,0 +1,192 @@
+//! 表示パラメータを埋め込んだ PNG の保存・読み込み
+
+use super::animation::View;
+use super::location::{Location, LocationFormat};
+use super::mandelbrot::{FractalType, Plane};
+use rug::Float;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// テキストチャンクのキーワード
+const KEY_RE: &str = "Mandelbrot.re";
+const KEY_IM: &str = "Mandelbrot.im";
+const KEY_ZOOM: &str = "Mandelbrot.zoom";
+const KEY_MAX_ITER: &str = "Mandelbrot.max_iter";
+const KEY_PALETTE: &str = "Mandelbrot.palette";
+const KEY_JULIA_RE: &str = "Mandelbrot.julia_re";
+const KEY_JULIA_IM: &str = "Mandelbrot.julia_im";
+const KEY_FRACTAL: &str = "Mandelbrot.fractal";
+const KEY_ROTATION: &str = "Mandelbrot.rotation";
+
+/// PNG に埋め込む表示パラメータ
+///
+/// 中心座標・ズーム倍率・最大反復回数は tEXt チャンクに10進文字列で、
+/// パレットのパスは Latin-1 以外の文字を含みうるため iTXt チャンクに保存します。
+/// ジュリア集合の場合は定数 c も、マンデルブロ集合以外の式の場合はその名前も、
+/// 回転している場合は回転角（度）も tEXt チャンクに保存します。
+pub struct Snapshot {
+    pub view: View,
+    pub max_iter: u32,
+    pub palette: Option<PathBuf>,
+    pub plane: Plane,
+    pub fractal: FractalType,
+}
+
+impl Snapshot {
+    /// 着色済みのバッファ（0xRRGGBB）を表示パラメータ付きの PNG として保存
+    pub fn save_png(
+        &self,
+        path: &Path,
+        colors: &[u32],
+        width: usize,
+        height: usize,
+    ) -> Result<(), String> {
+        let png_error = |e: png::EncodingError| format!("{}: {}", path.display(), e);
+
+        let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let encoder = self
+            .png_encoder(BufWriter::new(file), width, height)
+            .map_err(png_error)?;
+
+        let rgb: Vec<u8> = colors
+            .iter()
+            .flat_map(|&pixel| {
+                [
+                    ((pixel >> 16) & 0xFF) as u8,
+                    ((pixel >> 8) & 0xFF) as u8,
+                    (pixel & 0xFF) as u8,
+                ]
+            })
+            .collect();
+        let mut writer = encoder.write_header().map_err(png_error)?;
+        writer.write_image_data(&rgb).map_err(png_error)?;
+        writer.finish().map_err(png_error)
+    }
+
+    /// 表示パラメータのテキストチャンクを設定した RGB 8bit の PNG エンコーダを作成
+    pub fn png_encoder<W: Write>(
+        &self,
+        writer: W,
+        width: usize,
+        height: usize,
+    ) -> Result<png::Encoder<'static, W>, png::EncodingError> {
+        let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let (re, im) = self.view.center_decimal();
+        encoder.add_text_chunk(KEY_RE.to_string(), re)?;
+        encoder.add_text_chunk(KEY_IM.to_string(), im)?;
+        encoder.add_text_chunk(KEY_ZOOM.to_string(), self.view.zoom().to_string())?;
+        encoder.add_text_chunk(KEY_MAX_ITER.to_string(), self.max_iter.to_string())?;
+        if let Plane::Julia { c_real, c_imag } = &self.plane {
+            encoder.add_text_chunk(KEY_JULIA_RE.to_string(), c_real.to_string_radix(10, None))?;
+            encoder.add_text_chunk(KEY_JULIA_IM.to_string(), c_imag.to_string_radix(10, None))?;
+        }
+        if self.fractal != FractalType::Mandelbrot {
+            encoder.add_text_chunk(KEY_FRACTAL.to_string(), self.fractal.name())?;
+        }
+        if self.view.rotation != 0.0 {
+            encoder.add_text_chunk(KEY_ROTATION.to_string(), self.view.rotation.to_string())?;
+        }
+        if let Some(palette) = &self.palette {
+            // 別のディレクトリから開いても読めるよう絶対パスで保存
+            let palette = palette.canonicalize().unwrap_or_else(|_| palette.clone());
+            encoder.add_itxt_chunk(KEY_PALETTE.to_string(), palette.display().to_string())?;
+        }
+        Ok(encoder)
+    }
+
+    /// PNG またはパラメータファイル（`.kfr` / `.par`）から表示パラメータを読み込む
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if LocationFormat::from_path(path).is_none() {
+            return Self::load_png(path);
+        }
+        let location = Location::load(path)?;
+        Ok(Self {
+            view: location.view,
+            max_iter: location.max_iter,
+            palette: None,
+            plane: Plane::Mandelbrot,
+            fractal: FractalType::Mandelbrot,
+        })
+    }
+
+    /// `save_png` で保存した PNG から表示パラメータを読み込む
+    pub fn load_png(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let reader = png::Decoder::new(BufReader::new(file))
+            .read_info()
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let info = reader.info();
+
+        let optional_text = |key: &str| {
+            info.uncompressed_latin1_text
+                .iter()
+                .find(|chunk| chunk.keyword == key)
+                .map(|chunk| chunk.text.as_str())
+        };
+        let text = |key: &str| {
+            optional_text(key).ok_or_else(|| {
+                format!(
+                    "{}: 表示パラメータ（{}）が埋め込まれていません",
+                    path.display(),
+                    key
+                )
+            })
+        };
+        let zoom: f64 = text(KEY_ZOOM)?
+            .parse()
+            .map_err(|_| format!("{}: ズーム倍率が不正です", path.display()))?;
+        let max_iter: u32 = text(KEY_MAX_ITER)?
+            .parse()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| format!("{}: 最大反復回数が不正です", path.display()))?;
+        let mut view = View::from_decimal(text(KEY_RE)?, text(KEY_IM)?, zoom)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        if let Some(degrees) = optional_text(KEY_ROTATION) {
+            view.rotation = degrees
+                .parse::<f64>()
+                .map_err(|_| format!("{}: 回転角が不正です", path.display()))?;
+        }
+        let palette = info
+            .utf8_text
+            .iter()
+            .find(|chunk| chunk.keyword == KEY_PALETTE)
+            .and_then(|chunk| chunk.get_text().ok())
+            .map(PathBuf::from);
+        let plane = match (optional_text(KEY_JULIA_RE), optional_text(KEY_JULIA_IM)) {
+            (Some(re), Some(im)) => {
+                let prec = view.center_x.prec();
+                let parse_float = |s: &str| {
+                    Float::parse(s)
+                        .map(|p| Float::with_val(prec, p))
+                        .map_err(|_| {
+                            format!("{}: ジュリア集合の定数が不正です: {}", path.display(), s)
+                        })
+                };
+                Plane::Julia {
+                    c_real: parse_float(re)?,
+                    c_imag: parse_float(im)?,
+                }
+            }
+            _ => Plane::Mandelbrot,
+        };
+        let fractal = match optional_text(KEY_FRACTAL) {
+            Some(name) => name
+                .parse()
+                .map_err(|e| format!("{}: {}", path.display(), e))?,
+            None => FractalType::Mandelbrot,
+        };
+
+        Ok(Self {
+            view,
+            max_iter,
+            palette,
+            plane,
+            fractal,
+        })
+    }
+}
@@ -0,0 +1,99 @@
+//! ffmpeg による動画出力
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+/// ffmpeg のサブプロセスに生のRGBフレームを渡して動画を作成するエンコーダ
+///
+/// コーデックは拡張子で選びます（`.webm`: VP9、それ以外: H.264）。
+pub struct VideoEncoder {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    path: PathBuf,
+    width: usize,
+    height: usize,
+}
+
+impl VideoEncoder {
+    /// ffmpeg を起動
+    pub fn spawn(path: &Path, width: usize, height: usize, fps: u32) -> Result<Self, String> {
+        let is_webm = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("webm"));
+        let codec: &[&str] = if is_webm {
+            &["-c:v", "libvpx-vp9", "-b:v", "0", "-crf", "30"]
+        } else {
+            &["-c:v", "libx264", "-crf", "18"]
+        };
+
+        let mut child = Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error"])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgb24"])
+            .args(["-s", &format!("{}x{}", width, height)])
+            .args(["-r", &fps.to_string()])
+            .args(["-i", "-"])
+            .args(codec)
+            .args(["-pix_fmt", "yuv420p"])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                format!(
+                    "ffmpeg を起動できませんでした（インストールされているか確認してください）: {}",
+                    e
+                )
+            })?;
+        let stdin = child.stdin.take();
+
+        Ok(Self {
+            child,
+            stdin,
+            path: path.to_path_buf(),
+            width,
+            height,
+        })
+    }
+
+    /// 着色済みのバッファ（0xRRGGBB）を1フレーム書き込む
+    pub fn write_frame(&mut self, colors: &[u32]) -> Result<(), String> {
+        debug_assert_eq!(colors.len(), self.width * self.height);
+        let rgb: Vec<u8> = colors
+            .iter()
+            .flat_map(|&pixel| {
+                [
+                    ((pixel >> 16) & 0xFF) as u8,
+                    ((pixel >> 8) & 0xFF) as u8,
+                    (pixel & 0xFF) as u8,
+                ]
+            })
+            .collect();
+
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or_else(|| "ffmpeg の入力が閉じられています".to_string())?;
+        stdin
+            .write_all(&rgb)
+            .map_err(|e| format!("ffmpeg への書き込みに失敗しました: {}", e))
+    }
+
+    /// 入力を閉じてエンコードの完了を待つ
+    pub fn finish(mut self) -> Result<(), String> {
+        // 入力を閉じると ffmpeg はエンコードを終えて終了する
+        self.stdin = None;
+        let status = self
+            .child
+            .wait()
+            .map_err(|e| format!("ffmpeg の終了を待てませんでした: {}", e))?;
+        if !status.success() {
+            return Err(format!(
+                "ffmpeg が異常終了しました（{}）: {}",
+                status,
+                self.path.display()
+            ));
+        }
+        Ok(())
+    }
+}
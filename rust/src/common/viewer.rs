@@ -0,0 +1,885 @@
+//! ビューアの状態（表示範囲・計算モード・画面バッファ）と表示範囲の操作
+//!
+//! 表示範囲は任意精度の座標で持ち、ズーム・パン・ジュリア集合への切替などの操作と、
+//! 描画結果の着色・カラーバーやオーバーレイの合成、セッション・画像・位置ファイルの保存を扱います。
+//! 描画そのもの（GPU / CPU の計算）は実行ファイル側で `render_job` に設定します。
+
+use super::animation::{
+    Keyframe, KeyframePath, View, DEFAULT_SEGMENT_FRAMES, INITIAL_HEIGHT, INITIAL_WIDTH,
+};
+use super::backend::{Backend, ComputeMode};
+use super::colorbar::draw_colorbar;
+use super::colors::{average_samples, colorize, ColoringMode, Colorizer, Palette, Tone};
+use super::config::{ensure_dir, Config};
+use super::draw::draw_line;
+use super::filename::output_path;
+use super::grid::draw_grid;
+use super::julia_preview::JuliaPreview;
+use super::location::{Location, LocationFormat};
+use super::mandelbrot::{detect_period, escape_orbit, FractalType, Plane};
+use super::minimap::Minimap;
+use super::render::{mask_unrendered, BackgroundRender, RenderOutput};
+use super::session::Session;
+use super::settings::Settings;
+use super::snapshot::Snapshot;
+use num_complex::Complex;
+use rug::Float;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// ビューアの状態
+pub struct ViewerState {
+    pub x_min: Float,
+    pub x_max: Float,
+    pub y_min: Float,
+    pub y_max: Float,
+    pub rotation: f64, // 表示範囲の回転角（度、反時計回り。画像は逆向きに回って見える）
+    pub precision: u32,
+    pub max_iter: u32,
+    pub plane: Plane,
+    pub fractal: FractalType,                  // 反復する式の種類
+    pub mandelbrot_bounds: Option<[Float; 4]>, // ジュリア集合の表示中に退避したマンデルブロ集合の表示範囲
+    pub julia_preview: JuliaPreview,           // カーソル位置のジュリア集合のプレビュー
+    pub minimap: Minimap,                      // 全体図と現在の表示範囲
+    pub show_orbit: bool,                      // カーソル位置の点の軌道を重ねて表示するか
+    pub orbit_points: Vec<(f64, f64)>,         // 表示中の軌道（描画領域内のピクセル座標）
+    pub show_grid: bool,                       // 座標の目盛り線とラベルを重ねて表示するか
+    pub backend: Backend, // 計算バックエンド（Auto は起動時に Gpu か Cpu に決まる）
+    pub compute_mode: ComputeMode,
+    pub buffer: Vec<u32>,            // ウィンドウ全体のバッファ
+    pub mandelbrot_buffer: Vec<u32>, // マンデルブロ部分のみ
+    pub iter_buffer: Vec<u32>,       // 着色前の反復回数
+    pub iter_width: usize,
+    pub iter_height: usize,
+    pub coloring_mode: ColoringMode,
+    pub samples: usize, // アンチエイリアスの1ピクセルあたりの縦横のサンプル数（1 / 2 / 3）
+    pub needs_redraw: bool,
+    pub palette: Palette,
+    pub palette_path: Option<PathBuf>,
+    pub tone: Tone,                           // パレットで着色した後の色調補正
+    pub render_job: Option<BackgroundRender>, // 描画中のジョブ
+    pub render_start: Instant,
+    pub settings: Settings, // 描画領域の大きさ・精度・ズームの倍率・切替閾値
+    pub screenshot_dir: PathBuf, // 画像・位置ファイルの保存先
+}
+
+impl ViewerState {
+    pub fn new(palette_path: Option<PathBuf>, config: &Config, backend: Backend) -> Self {
+        let settings = config.settings.clone();
+        let (width, height) = (settings.width, settings.height);
+        let prec = settings.initial_precision;
+        let mut state = Self {
+            x_min: Float::with_val(prec, -2.5),
+            x_max: Float::with_val(prec, 1.0),
+            y_min: Float::with_val(prec, -1.5),
+            y_max: Float::with_val(prec, 1.5),
+            rotation: 0.0,
+            precision: prec,
+            max_iter: settings.max_iter,
+            plane: Plane::Mandelbrot,
+            fractal: FractalType::Mandelbrot,
+            mandelbrot_bounds: None,
+            julia_preview: JuliaPreview::new(),
+            minimap: Minimap::new(),
+            show_orbit: false,
+            orbit_points: Vec::new(),
+            show_grid: false,
+            backend,
+            compute_mode: backend.mode(1.0, &settings.thresholds),
+            buffer: vec![0; settings.window_width() * settings.window_height()],
+            mandelbrot_buffer: vec![0; width * height],
+            iter_buffer: vec![0; width * height],
+            iter_width: width,
+            iter_height: height,
+            coloring_mode: ColoringMode::Linear,
+            samples: 1,
+            needs_redraw: true,
+            palette: Palette::default(),
+            palette_path,
+            tone: config.tone,
+            render_job: None,
+            render_start: Instant::now(),
+            settings,
+            screenshot_dir: config.screenshot_dir.clone(),
+        };
+        if state.palette_path.is_some() {
+            state.reload_palette();
+        }
+        state.draw_colorbar();
+        state
+    }
+
+    /// 表示中の平面の初期表示に戻す
+    pub fn reset(&mut self) {
+        let prec = self.settings.initial_precision;
+        let (cx, cy) = self.plane.initial_center(self.fractal);
+        self.x_min = Float::with_val(prec, cx - INITIAL_WIDTH / 2.0);
+        self.x_max = Float::with_val(prec, cx + INITIAL_WIDTH / 2.0);
+        self.y_min = Float::with_val(prec, cy - INITIAL_HEIGHT / 2.0);
+        self.y_max = Float::with_val(prec, cy + INITIAL_HEIGHT / 2.0);
+        self.rotation = 0.0;
+        self.precision = prec;
+        self.compute_mode = self.backend.mode(1.0, &self.settings.thresholds);
+        self.needs_redraw = true;
+    }
+
+    /// マウス位置の座標を c としたジュリア集合に切り替える（ジュリア集合の表示中ならマンデルブロ集合に戻る）
+    pub fn toggle_julia(&mut self, mouse: Option<(f64, f64)>) {
+        if let Some([x_min, x_max, y_min, y_max]) = self.mandelbrot_bounds.take() {
+            self.precision = x_min.prec();
+            self.x_min = x_min;
+            self.x_max = x_max;
+            self.y_min = y_min;
+            self.y_max = y_max;
+            self.plane = Plane::Mandelbrot;
+            println!("マンデルブロ集合に戻りました");
+            self.update_compute_mode();
+            self.needs_redraw = true;
+            return;
+        }
+
+        // カラーバー領域やウィンドウ外では無視
+        let Some((mouse_x, mouse_y)) = mouse.filter(|&(x, _)| x < self.settings.width as f64)
+        else {
+            return;
+        };
+        let (c_real, c_imag) = self.pixel_to_complex(mouse_x, mouse_y);
+        println!(
+            "ジュリア集合に切替: c = ({:.6}, {:.6}i)",
+            c_real.to_f64(),
+            c_imag.to_f64()
+        );
+        self.mandelbrot_bounds = Some([
+            self.x_min.clone(),
+            self.x_max.clone(),
+            self.y_min.clone(),
+            self.y_max.clone(),
+        ]);
+        self.plane = Plane::Julia { c_real, c_imag };
+        self.reset();
+    }
+
+    /// カーソル位置に合わせてジュリア集合のプレビューを更新（マンデルブロ集合の表示中のみ）
+    pub fn update_julia_preview(&mut self, mouse: Option<(f64, f64)>) {
+        let cursor = mouse
+            .filter(|&(x, y)| self.in_render_area(x, y))
+            .filter(|_| self.plane == Plane::Mandelbrot)
+            .map(|(x, y)| {
+                let (c_real, c_imag) = self.pixel_to_complex(x, y);
+                let (c_real, c_imag) = (c_real.to_f64(), c_imag.to_f64());
+                ((x, y), Complex::new(c_real, c_imag))
+            });
+        let area = (self.settings.width, self.settings.height);
+        if self
+            .julia_preview
+            .update(cursor, area, self.fractal, self.max_iter)
+        {
+            self.compose_buffer();
+        }
+    }
+
+    /// カーソル位置の点の軌道を更新
+    ///
+    /// 深いズームでも z_0（ジュリア集合）・z_1（パラメータ平面）がカーソル位置に重なるよう、
+    /// 各点はその点との差分からピクセル座標に変換します。
+    pub fn update_orbit(&mut self, mouse: Option<(f64, f64)>) {
+        let cursor = mouse
+            .filter(|_| self.show_orbit)
+            .filter(|&(x, y)| self.in_render_area(x, y));
+        let points: Vec<(f64, f64)> = match cursor {
+            Some((x, y)) => {
+                let (c_real, c_imag) = self.pixel_to_complex(x, y);
+                let point = Complex::new(c_real.to_f64(), c_imag.to_f64());
+                let x_scale = self.view_width() / self.settings.width as f64;
+                let y_scale = self.view_height() / self.settings.height as f64;
+                // 複素平面上のずれを画面の向きに戻す（回転の逆変換）
+                let (sin, cos) = self.rotation.to_radians().sin_cos();
+                escape_orbit(self.fractal, point, self.plane.julia_c(), self.max_iter)
+                    .iter()
+                    .map(|z| {
+                        let (dx, dy) = (z.re - point.re, z.im - point.im);
+                        (
+                            x + (dx * cos + dy * sin) / x_scale,
+                            y - (dy * cos - dx * sin) / y_scale,
+                        )
+                    })
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+        if points != self.orbit_points {
+            self.orbit_points = points;
+            self.compose_buffer();
+        }
+    }
+
+    /// クリック位置の点の周期と原子領域を表示（Ctrl+クリック）
+    pub fn inspect_point(&self, mouse_x: f64, mouse_y: f64) {
+        if mouse_x >= self.settings.width as f64 {
+            return;
+        }
+        let (c_real, c_imag) = self.pixel_to_complex(mouse_x, mouse_y);
+        let point = Complex::new(c_real.to_f64(), c_imag.to_f64());
+        let info = detect_period(self.fractal, point, self.plane.julia_c(), self.max_iter);
+        println!("({:.15}, {:.15}): {}", point.re, point.im, info);
+    }
+
+    /// 反復する式の種類を切り替えてパラメータ平面の初期表示に戻る（ジュリア集合の表示中なら抜ける）
+    pub fn set_fractal(&mut self, fractal: FractalType) {
+        self.fractal = fractal;
+        self.plane = Plane::Mandelbrot;
+        self.mandelbrot_bounds = None;
+        self.reset();
+        println!("フラクタルの種類: {}", fractal);
+    }
+
+    /// マンデルブロ集合の表示中か（ジュリア集合の表示中は `action` できない旨を表示）
+    pub fn check_mandelbrot_plane(&self, action: &str) -> bool {
+        if self.plane == Plane::Mandelbrot {
+            return true;
+        }
+        println!(
+            "ジュリア集合の表示中は{}できません（J キーでマンデルブロ集合に戻ります）",
+            action
+        );
+        false
+    }
+
+    pub fn current_zoom(&self) -> f64 {
+        3.5 / self.view_width()
+    }
+
+    /// 表示範囲の幅（差を任意精度で取ってから f64 に変換し、深いズームでも桁落ちしない）
+    pub fn view_width(&self) -> f64 {
+        Float::with_val(self.precision, &self.x_max - &self.x_min).to_f64()
+    }
+
+    /// 表示範囲の高さ
+    pub fn view_height(&self) -> f64 {
+        Float::with_val(self.precision, &self.y_max - &self.y_min).to_f64()
+    }
+
+    /// 表示範囲の中心（任意精度）
+    pub fn view_center(&self) -> (Float, Float) {
+        let prec = self.precision;
+        let cx = Float::with_val(prec, &self.x_min + &self.x_max) / 2u32;
+        let cy = Float::with_val(prec, &self.y_min + &self.y_max) / 2u32;
+        (cx, cy)
+    }
+
+    pub fn update_compute_mode(&mut self) {
+        let zoom = self.current_zoom();
+        let old_mode = self.compute_mode;
+
+        self.compute_mode = self.backend.mode(zoom, &self.settings.thresholds);
+        if matches!(
+            self.compute_mode,
+            ComputeMode::CpuFixed | ComputeMode::CpuHighPrecision | ComputeMode::FullHighPrecision
+        ) {
+            let required_precision = (zoom.log2() * 3.5) as u32 + 64;
+            let max_precision = self.settings.max_precision;
+            if required_precision > self.precision && self.precision < max_precision {
+                self.precision = (required_precision.next_power_of_two()).min(max_precision);
+                self.x_min.set_prec(self.precision);
+                self.x_max.set_prec(self.precision);
+                self.y_min.set_prec(self.precision);
+                self.y_max.set_prec(self.precision);
+            }
+        }
+
+        if old_mode != self.compute_mode {
+            println!("モード切替: {} → {}", old_mode, self.compute_mode);
+        }
+    }
+
+    /// 現在の表示範囲
+    pub fn view(&self) -> View {
+        let (center_x, center_y) = self.view_center();
+        View {
+            center_x,
+            center_y,
+            width: self.view_width(),
+            height: self.view_height(),
+            rotation: self.rotation,
+        }
+    }
+
+    /// ピクセル座標が描画領域（カラーバーを除く）の内側か
+    pub fn in_render_area(&self, x: f64, y: f64) -> bool {
+        x < self.settings.width as f64 && y < self.settings.height as f64
+    }
+
+    /// 画面上のピクセル座標を複素平面上の座標に変換
+    pub fn pixel_to_complex(&self, x: f64, y: f64) -> (Float, Float) {
+        let prec = self.precision;
+        let view = self.view();
+        let (dx, dy) = view.pixel_offset(x, y, self.settings.width, self.settings.height);
+
+        let cx = Float::with_val(prec, &view.center_x + dx);
+        let cy = Float::with_val(prec, &view.center_y + dy);
+        (cx, cy)
+    }
+
+    /// 指定された中心座標と現在のズーム倍率で範囲を更新
+    pub fn update_bounds(&mut self, center_x: Float, center_y: Float, width_scale: f64) {
+        let prec = self.precision;
+        let width_f = self.view_width();
+        let height_f = self.view_height();
+
+        let new_width = width_f * width_scale;
+        let new_height = height_f * width_scale;
+        let half_new_width = new_width / 2.0;
+        let half_new_height = new_height / 2.0;
+
+        self.x_min = Float::with_val(prec, &center_x - half_new_width);
+        self.x_max = Float::with_val(prec, &center_x + half_new_width);
+        self.y_min = Float::with_val(prec, &center_y - half_new_height);
+        self.y_max = Float::with_val(prec, &center_y + half_new_height);
+
+        self.update_compute_mode();
+        self.needs_redraw = true;
+    }
+
+    /// カーソル位置の点を画面上で動かさずにズーム（中心へは移動しないので、端の構造にも続けて寄っていける）
+    pub fn zoom(&mut self, mouse_x: f64, mouse_y: f64, factor: f64) {
+        // カラーバー領域では無視
+        if mouse_x >= self.settings.width as f64 {
+            return;
+        }
+
+        // 中心をカーソル位置の点に向かって (1 - factor) だけ寄せる
+        let prec = self.precision;
+        let (px, py) = self.pixel_to_complex(mouse_x, mouse_y);
+        let (cx, cy) = self.view_center();
+        let cx = Float::with_val(prec, &cx - &px) * factor + &px;
+        let cy = Float::with_val(prec, &cy - &py) * factor + &py;
+        self.update_bounds(cx, cy, factor);
+    }
+
+    /// 画像を (dx, dy) ピクセルだけずらすように表示範囲を移動（トラックパッドの 2 本指スクロール）
+    pub fn pan_by(&mut self, dx: f64, dy: f64) {
+        let (cx, cy) = self.pixel_to_complex(
+            self.settings.width as f64 / 2.0 - dx,
+            self.settings.height as f64 / 2.0 - dy,
+        );
+        self.update_bounds(cx, cy, 1.0);
+    }
+
+    /// クリック位置を画面中心に移動（パン）
+    pub fn pan_to(&mut self, mouse_x: f64, mouse_y: f64) {
+        // カラーバー領域では無視
+        if mouse_x >= self.settings.width as f64 {
+            return;
+        }
+
+        let (cx, cy) = self.pixel_to_complex(mouse_x, mouse_y);
+        self.update_bounds(cx, cy, 1.0); // 倍率は1.0（変えない）
+    }
+
+    /// 表示を画面中心のまわりに回転（`degrees` が正なら画像が時計回りに回る）
+    pub fn rotate(&mut self, degrees: f64) {
+        self.rotation = (self.rotation + degrees).rem_euclid(360.0);
+        println!("回転: {}°", self.rotation);
+        if self.show_grid && self.rotation != 0.0 {
+            println!("回転中は座標の目盛りを表示しません");
+        }
+        self.needs_redraw = true;
+    }
+
+    /// HSV パレットの色相の位相を `delta` 周ずらす
+    pub fn shift_palette_phase(&mut self, delta: f64) {
+        let Palette::Hsv { phase, .. } = &mut self.palette else {
+            println!("色相の位相は HSV パレットでのみ変更できます（--palette hsv）");
+            return;
+        };
+        *phase = (*phase + delta).rem_euclid(1.0);
+        self.apply_coloring();
+    }
+
+    /// 色調補正を変えて着色し直す
+    pub fn set_tone(&mut self, tone: Tone) {
+        self.tone = tone;
+        println!(
+            "色調補正: ガンマ {:.2} 明るさ {:+.2} コントラスト {:.2}",
+            tone.gamma, tone.brightness, tone.contrast
+        );
+        self.apply_coloring();
+    }
+
+    /// パレットファイルを（再）読み込み
+    pub fn reload_palette(&mut self) {
+        let Some(path) = &self.palette_path else {
+            println!("パレットファイルが指定されていません（--palette <file>）");
+            return;
+        };
+        match Palette::load(path) {
+            Ok(palette) => {
+                println!("パレットを読み込みました: {}", path.display());
+                self.palette = palette;
+                self.apply_coloring();
+            }
+            Err(e) => eprintln!("パレットの読み込みに失敗しました: {}", e),
+        }
+    }
+
+    /// カラーバーを現在のパレット・最大反復回数・着色モードで描画
+    ///
+    /// ヒストグラム平坦化では表示中の画像の反復回数の分布から色の対応を求めます。
+    pub fn draw_colorbar(&mut self) {
+        // 固定小数点・高精度モードは距離推定に対応せず線形モードと同じ色になるため、カラーバーも線形で描く
+        let mode = match self.coloring_mode {
+            ColoringMode::Distance
+                if matches!(
+                    self.compute_mode,
+                    ComputeMode::CpuFixed
+                        | ComputeMode::CpuHighPrecision
+                        | ComputeMode::FullHighPrecision
+                ) =>
+            {
+                ColoringMode::Linear
+            }
+            mode => mode,
+        };
+        let colorizer =
+            Colorizer::new(&self.iter_buffer, self.max_iter, mode).with_tone(&self.tone);
+        draw_colorbar(
+            &mut self.buffer,
+            (self.settings.width, self.settings.height),
+            self.settings.scale_factor,
+            &colorizer,
+            &self.palette,
+        );
+    }
+
+    /// 着色モードを切り替えて再着色（距離推定モードに出入りする場合のみ再計算）
+    pub fn toggle_coloring_mode(&mut self) {
+        let was_distance = self.coloring_mode == ColoringMode::Distance;
+        self.coloring_mode = self.coloring_mode.next();
+        println!("着色モード: {}", self.coloring_mode);
+        if was_distance != (self.coloring_mode == ColoringMode::Distance) {
+            self.needs_redraw = true;
+        }
+        if self.coloring_mode == ColoringMode::Distance
+            && matches!(
+                self.compute_mode,
+                ComputeMode::CpuFixed
+                    | ComputeMode::CpuHighPrecision
+                    | ComputeMode::FullHighPrecision
+            )
+        {
+            println!(
+                "固定小数点・高精度モードでは距離推定に対応していないため、線形モードで着色します"
+            );
+        }
+        self.apply_coloring();
+    }
+
+    /// アンチエイリアスを 1× → 4× → 9× → 1× の順に切り替えて描き直す
+    pub fn toggle_antialias(&mut self) {
+        self.samples = self.samples % 3 + 1;
+        if self.samples == 1 {
+            println!("アンチエイリアス: オフ");
+        } else {
+            println!(
+                "アンチエイリアス: {}× (1ピクセルあたり {}×{} 点)",
+                self.samples * self.samples,
+                self.samples,
+                self.samples
+            );
+        }
+        if self.compute_mode == ComputeMode::CpuHighPrecision {
+            println!("高精度（摂動法）モードではアンチエイリアスは使いません");
+        }
+        self.needs_redraw = true;
+    }
+
+    /// 描画結果（途中経過を含む）を表示
+    pub fn show_output(&mut self, output: RenderOutput) {
+        self.iter_buffer = output.iters;
+        self.iter_width = output.width;
+        self.iter_height = output.height;
+        self.apply_coloring();
+    }
+
+    /// 反復回数バッファを着色してマンデルブロ部分に配置
+    pub fn apply_coloring(&mut self) {
+        let mut colors = colorize(
+            &self.iter_buffer,
+            self.max_iter,
+            &self.palette,
+            self.coloring_mode,
+            &self.tone,
+        );
+        // 未計算のピクセルは背景色
+        mask_unrendered(&mut colors, &self.iter_buffer, 0x202020);
+
+        let (width, height) = (self.settings.width, self.settings.height);
+        if self.iter_width == width && self.iter_height == height {
+            self.mandelbrot_buffer = colors;
+        } else if self.iter_width > width {
+            // 描画領域より大きい結果はアンチエイリアスのサンプルを平均して縮小
+            let samples = self.iter_width / width;
+            self.mandelbrot_buffer = average_samples(&colors, width, samples);
+        } else {
+            // 低解像度の結果（高精度モードで解像度を上げていく途中）は拡大して表示
+            for y in 0..height {
+                let src = y * self.iter_height / height * self.iter_width;
+                for x in 0..width {
+                    self.mandelbrot_buffer[y * width + x] =
+                        colors[src + x * self.iter_width / width];
+                }
+            }
+        }
+        // 着色の対応（パレット・着色モード・ヒストグラム）が変わりうるのでカラーバーも描き直す
+        self.draw_colorbar();
+        self.compose_buffer();
+    }
+
+    /// 表示範囲（x_min, x_max, y_min, y_max）を f64 で取得
+    pub fn view_bounds_f64(&self) -> (f64, f64, f64, f64) {
+        (
+            self.x_min.to_f64(),
+            self.x_max.to_f64(),
+            self.y_min.to_f64(),
+            self.y_max.to_f64(),
+        )
+    }
+
+    /// マンデルブロ画像とカラーバー、目盛り、軌道とジュリア集合のプレビュー、ミニマップを合成
+    pub fn compose_buffer(&mut self) {
+        let bounds = self.view_bounds_f64();
+        let (width, height) = (self.settings.width, self.settings.height);
+        let stride = self.settings.window_width();
+        for y in 0..height {
+            self.buffer[y * stride..y * stride + width]
+                .copy_from_slice(&self.mandelbrot_buffer[y * width..(y + 1) * width]);
+        }
+        // 目盛り線は軸に平行な線として描くため、回転中は描かない
+        if self.show_grid && self.rotation == 0.0 {
+            draw_grid(
+                &mut self.buffer,
+                stride,
+                (width, height),
+                self.settings.scale_factor,
+                bounds,
+            );
+        }
+        for segment in self.orbit_points.windows(2) {
+            draw_line(
+                &mut self.buffer,
+                stride,
+                (width, height),
+                segment[0],
+                segment[1],
+                0xFFFFFF,
+            );
+        }
+        self.julia_preview
+            .draw(&mut self.buffer, stride, &self.palette, self.coloring_mode);
+        self.minimap
+            .update(&self.plane, self.fractal, self.max_iter);
+        self.minimap.draw(
+            &mut self.buffer,
+            stride,
+            width,
+            bounds,
+            &self.palette,
+            self.coloring_mode,
+        );
+    }
+
+    /// 現在の状態をセッションとして取り出す
+    ///
+    /// ジュリア集合の表示中は、退避しているマンデルブロ集合の表示範囲を保存します。
+    pub fn to_session(&self) -> Session {
+        let [x_min, x_max, y_min, y_max] = self.mandelbrot_bounds.clone().unwrap_or_else(|| {
+            [
+                self.x_min.clone(),
+                self.x_max.clone(),
+                self.y_min.clone(),
+                self.y_max.clone(),
+            ]
+        });
+        Session {
+            precision: x_min.prec(),
+            x_min,
+            x_max,
+            y_min,
+            y_max,
+            compute_mode: match self.compute_mode {
+                ComputeMode::Gpu => "gpu",
+                ComputeMode::GpuDf64 => "gpu-df64",
+                ComputeMode::CpuF64 => "cpu",
+                ComputeMode::CpuFixed => "fixed",
+                ComputeMode::CpuHighPrecision => "hp",
+                ComputeMode::FullHighPrecision => "hp-full",
+            }
+            .to_string(),
+            max_iter: self.max_iter,
+            palette: self.palette_path.clone(),
+            coloring_mode: self.coloring_mode,
+            fractal: self.fractal,
+            rotation: self.rotation,
+        }
+    }
+
+    /// 保存したセッションの状態に戻す（`--palette` の指定はセッションより優先）
+    pub fn restore_session(&mut self, session: Session) {
+        self.x_min = session.x_min;
+        self.x_max = session.x_max;
+        self.y_min = session.y_min;
+        self.y_max = session.y_max;
+        self.precision = session.precision;
+        self.plane = Plane::Mandelbrot;
+        self.fractal = session.fractal;
+        self.mandelbrot_bounds = None;
+        self.max_iter = session.max_iter;
+        self.coloring_mode = session.coloring_mode;
+        self.rotation = session.rotation;
+        if self.palette_path.is_none() && session.palette.is_some() {
+            self.palette_path = session.palette;
+            self.reload_palette();
+        }
+        self.update_compute_mode();
+        self.draw_colorbar();
+        self.needs_redraw = true;
+    }
+
+    /// 画像に埋め込む表示パラメータ
+    pub fn to_snapshot(&self) -> Snapshot {
+        Snapshot {
+            view: self.view(),
+            max_iter: self.max_iter,
+            palette: self.palette_path.clone(),
+            plane: self.plane.clone(),
+            fractal: self.fractal,
+        }
+    }
+
+    /// 画像に埋め込まれた表示パラメータの表示に戻す（`--palette` の指定は画像より優先）
+    ///
+    /// ジュリア集合の画像の場合は、マンデルブロ集合に戻ったときに初期表示になります。
+    pub fn restore_snapshot(&mut self, snapshot: Snapshot) {
+        self.fractal = snapshot.fractal;
+        if let Plane::Julia { .. } = snapshot.plane {
+            self.plane = Plane::Mandelbrot;
+            self.reset();
+            self.mandelbrot_bounds = Some([
+                self.x_min.clone(),
+                self.x_max.clone(),
+                self.y_min.clone(),
+                self.y_max.clone(),
+            ]);
+        } else {
+            self.mandelbrot_bounds = None;
+        }
+        self.plane = snapshot.plane;
+        let (x_min, x_max, y_min, y_max) = snapshot.view.bounds();
+        self.x_min = x_min;
+        self.x_max = x_max;
+        self.y_min = y_min;
+        self.y_max = y_max;
+        self.rotation = snapshot.view.rotation;
+        self.precision = snapshot.view.center_x.prec();
+        self.max_iter = snapshot.max_iter;
+        if self.palette_path.is_none() && snapshot.palette.is_some() {
+            self.palette_path = snapshot.palette;
+            self.reload_palette();
+        }
+        self.update_compute_mode();
+        self.draw_colorbar();
+        self.needs_redraw = true;
+    }
+
+    /// 現在の位置をパラメータファイルとして保存
+    pub fn save_location(&mut self, format: LocationFormat) {
+        if !self.check_mandelbrot_plane("位置ファイルを保存") {
+            return;
+        }
+        if self.fractal != FractalType::Mandelbrot {
+            println!("位置ファイルに保存できるのはマンデルブロ集合のみです（T キーで種類を切替）");
+            return;
+        }
+        let location = Location {
+            view: self.view(),
+            max_iter: self.max_iter,
+        };
+        let path = output_path(
+            &self.screenshot_dir,
+            "mandelbrot",
+            &location.view,
+            format.extension(),
+        );
+        match ensure_dir(&self.screenshot_dir).and_then(|()| location.save(&path, format)) {
+            Ok(()) => println!("位置を保存しました: {}", path.display()),
+            Err(e) => eprintln!("位置の保存に失敗しました: {}", e),
+        }
+    }
+
+    /// 現在の表示を画像として保存（ファイル名は日時と中心・ズーム倍率から決める）
+    pub fn save_image(&mut self) {
+        let snapshot = self.to_snapshot();
+        let path = output_path(&self.screenshot_dir, "mandelbrot", &snapshot.view, "png");
+
+        // 表示パラメータを埋め込んで、画像から同じ表示を開き直せるようにする
+        let result = ensure_dir(&self.screenshot_dir).and_then(|()| {
+            snapshot.save_png(
+                &path,
+                &self.buffer,
+                self.settings.window_width(),
+                self.settings.window_height(),
+            )
+        });
+        match result {
+            Ok(()) => println!("画像を保存しました: {}", path.display()),
+            Err(e) => eprintln!("画像の保存に失敗しました: {}", e),
+        }
+    }
+
+    /// 描画が完了していれば結果を反映（反映した場合は true）
+    pub fn finish_render(&mut self) -> bool {
+        let Some(output) = self.render_job.as_ref().and_then(|job| job.try_take()) else {
+            return false;
+        };
+        self.render_job = None;
+        self.show_output(output);
+        true
+    }
+
+    /// 描画途中で新しく計算されたタイルがあれば画面に反映
+    pub fn update_preview(&mut self) {
+        if let Some(output) = self.render_job.as_ref().and_then(|job| job.preview()) {
+            self.show_output(output);
+        }
+    }
+
+    /// 現在の表示をキーフレームファイルに追加
+    pub fn add_keyframe(&self, file: &Path) {
+        if !self.check_mandelbrot_plane("キーフレームを追加") {
+            return;
+        }
+        let keyframe = Keyframe {
+            view: self.view(),
+            max_iter: self.max_iter,
+            // キーフレームファイルの場所に依存しないよう絶対パスで記録
+            palette: self
+                .palette_path
+                .as_ref()
+                .and_then(|p| p.canonicalize().ok()),
+            frames: DEFAULT_SEGMENT_FRAMES,
+        };
+        match KeyframePath::append(file, keyframe) {
+            Ok(count) => println!(
+                "キーフレームを追加しました: {} ({}個目)",
+                file.display(),
+                count
+            ),
+            Err(e) => eprintln!("キーフレームの追加に失敗しました: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(rotation: f64) -> ViewerState {
+        let mut state = ViewerState::new(None, &Config::default(), Backend::Cpu);
+        state.rotation = rotation;
+        state
+    }
+
+    /// 複素平面上の点を画面上のピクセル座標に戻す（`update_orbit` と同じ逆変換）
+    fn complex_to_pixel(state: &ViewerState, (re, im): (&Float, &Float)) -> (f64, f64) {
+        let (cx, cy) = state.view_center();
+        let dx = Float::with_val(state.precision, re - &cx).to_f64();
+        let dy = Float::with_val(state.precision, im - &cy).to_f64();
+        let x_scale = state.view_width() / state.settings.width as f64;
+        let y_scale = state.view_height() / state.settings.height as f64;
+        let (sin, cos) = state.rotation.to_radians().sin_cos();
+        (
+            state.settings.width as f64 / 2.0 + (dx * cos + dy * sin) / x_scale,
+            state.settings.height as f64 / 2.0 - (dy * cos - dx * sin) / y_scale,
+        )
+    }
+
+    /// 画面上の点 (x, y) の複素平面上の座標が `expected` と表示範囲の幅の `1e-9` 倍以内で一致するか
+    fn assert_at_pixel(state: &ViewerState, (x, y): (f64, f64), expected: &(Float, Float)) {
+        let (re, im) = state.pixel_to_complex(x, y);
+        let tolerance = state.view_width() * 1e-9;
+        let dx = Float::with_val(state.precision, &re - &expected.0).to_f64();
+        let dy = Float::with_val(state.precision, &im - &expected.1).to_f64();
+        assert!(
+            dx.abs() < tolerance && dy.abs() < tolerance,
+            "({}, {}) のずれ: ({}, {})",
+            x,
+            y,
+            dx,
+            dy
+        );
+    }
+
+    #[test]
+    fn pixel_to_complex_round_trips() {
+        for rotation in [0.0, 45.0, 300.0] {
+            let mut state = state(rotation);
+            state.zoom(100.0, 80.0, 0.01);
+            let (w, h) = (state.settings.width as f64, state.settings.height as f64);
+            for (x, y) in [
+                (0.0, 0.0),
+                (w / 2.0, h / 2.0),
+                (w - 1.0, 13.0),
+                (7.5, h - 1.0),
+            ] {
+                let (re, im) = state.pixel_to_complex(x, y);
+                let (bx, by) = complex_to_pixel(&state, (&re, &im));
+                assert!(
+                    (bx - x).abs() < 1e-6 && (by - y).abs() < 1e-6,
+                    "回転 {}°: ({}, {}) → ({}, {})",
+                    rotation,
+                    x,
+                    y,
+                    bx,
+                    by
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn zoom_keeps_point_under_cursor() {
+        for rotation in [0.0, 30.0] {
+            let mut state = state(rotation);
+            let cursor = (123.0, 45.0);
+            let anchor = state.pixel_to_complex(cursor.0, cursor.1);
+            for factor in [0.5, 0.5, 0.1, 2.0] {
+                let width = state.view_width();
+                state.zoom(cursor.0, cursor.1, factor);
+                assert!((state.view_width() - width * factor).abs() < width * 1e-12);
+                assert_at_pixel(&state, cursor, &anchor);
+            }
+        }
+    }
+
+    #[test]
+    fn zoom_and_pan_ignore_colorbar_area() {
+        let mut state = state(0.0);
+        let bounds = state.view_bounds_f64();
+        let x = state.settings.width as f64 + 5.0;
+        state.zoom(x, 10.0, 0.5);
+        state.pan_to(x, 10.0);
+        assert_eq!(state.view_bounds_f64(), bounds);
+    }
+
+    #[test]
+    fn pan_to_centers_clicked_point() {
+        let mut state = state(60.0);
+        let clicked = state.pixel_to_complex(200.0, 100.0);
+        state.pan_to(200.0, 100.0);
+        let center = (
+            state.settings.width as f64 / 2.0,
+            state.settings.height as f64 / 2.0,
+        );
+        assert_at_pixel(&state, center, &clicked);
+    }
+}
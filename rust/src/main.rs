@@ -1,383 +1,1419 @@
-//! マンデルブロ集合インタラクティブビューア (ハイブリッド版)
-//! M1 Mac (Apple Silicon) 対応
+//! マンデルブロ集合インタラクティブビューア
+//! M1 Mac (Apple Silicon) の GPU (Metal) を使用して高速描画
 //!
-//! ズームレベルに応じて自動的に計算精度を切り替え:
-//!   - 浅いズーム（〜10^13倍）: f64 + Rayon並列処理（超高速）
-//!   - 深いズーム（10^13倍〜）: rug任意精度（無限ズーム）
+//! 計算バックエンドは `--backend gpu|cpu|hp|auto`（既定: auto）で選び、
+//! ズームレベルに応じて自動的に計算モードを切り替え:
+//!   - 浅いズーム（〜10^4倍）: GPU f32（超高速）
+//!   - やや深いズーム（10^4〜10^12倍）: GPU double-double（f32 の組で約48ビットの精度）
+//!   - 中程度のズーム（10^12〜10^13倍）: CPU f64 + Rayon並列処理
+//!   - 深いズーム（10^13〜10^30倍）: CPU 128ビット固定小数点（各ピクセルを整数演算で反復）
+//!   - さらに深いズーム（10^30倍〜）: CPU 摂動法（中心の参照軌道のみ rug任意精度、各ピクセルは f64 の差分）
+//!
+//! `cpu` は GPU を使わずに CPU f64・固定小数点・摂動法を、`hp` はズームに関係なく全ピクセルを任意精度で計算します
+//! （デバッグ用）。`auto` は GPU が使えれば `gpu`、使えなければ `cpu` になります。
+//! `gpu` を指定しても GPU アダプタがない環境（ヘッドレスのサーバーや一部の仮想マシン）では、
+//! 警告を表示して `cpu` で計算します。
 //!
 //! 操作方法:
-//!   - マウスホイール上下: 拡大/縮小
+//!   - マウスホイール上下・2本指スクロール: カーソル位置を固定して拡大/縮小（`[mouse] scroll = "pan"` では 2本指スクロールで移動）
 //!   - 左クリック+ドラッグ: 移動（パン）
-//!   - 右クリック: クリック位置を中心にズームイン
+//!   - 右クリック: クリック位置を固定してズームイン
 //!   - R キー: 初期表示にリセット
 //!   - S キー: 現在の表示を画像として保存
+//!   - P キー: 現在の位置をパラメータファイルとして保存
+//!   - L キー: パレットファイルを再読み込み（--palette <file> で指定。viridis / inferno / turbo などの組み込みの名前も可）
+//!   - H キー: 着色モード切替（線形 / ヒストグラム平坦化）
+//!   - T キー: フラクタルの種類を切替（マンデルブロ → バーニングシップ → トリコーン → マルチブロ d=3, 4）
+//!   - O キー: カーソル位置の点の軌道（z_0, z_1, … を結ぶ折れ線）を重ねて表示 / 非表示
+//!   - I キー: カーソル位置を c としたジュリア集合のプレビューを右下に表示 / 非表示
+//!   - J キー: マウス位置の座標を c としたジュリア集合に切替（もう一度押すとマンデルブロ集合に戻る）
+//!   - A キー: 初期表示から現在の表示までのズームアニメーションを出力
+//!   - K キー: 現在の表示をキーフレームとして追加（--keyframes <file>、既定: keyframes.json）
+//!   - E キー: 現在の表示を高解像度で書き出し（--export-size <幅>x<高さ>、--export-samples <数>）
+//!   - X キー: アンチエイリアスを切替（1ピクセルあたり 1 / 4 / 9 点を描画して色を平均）
+//!   - 矢印キー: 表示範囲の 1/20 ずつ移動、= / - キー: 画面中心でズームイン / アウト（押し続けると加速）
+//!   - [ / ] キー: 画像を画面中心のまわりに 5° ずつ左 / 右に回転（R キーで元に戻る）
+//!   - , / . キー: HSV パレット（--palette hsv）の色相をずらす（押し続けると色が流れる）
+//!   - 1 / 2 キー: ガンマ、3 / 4 キー: 明るさ、5 / 6 キー: コントラストを下げる / 上げる（0 キーで元に戻す。終了時に設定ファイルへ保存）
 //!   - Q / Escape キー: 終了
-
-use image::{ImageBuffer, Rgb};
+//!
+//! `--features gamepad` でビルドすると、ゲームパッド（左スティック: 移動、トリガー: ズーム、
+//! A: リセット、B: 保存、X: 着色モード切替、Y: パレット再読み込み）でも操作できます。
+//!
+//! キー割り当て・ズームの倍率・計算モードの切替閾値・最大反復回数・描画領域の大きさ・精度・パレット・画像の保存先は
+//! 設定ファイル（`--config <file>`、既定: ~/.config/flactal/config.toml）で変更できます。
+//! 描画領域の大きさと最大反復回数は `--window-size <幅>x<高さ>`、`--max-iter <回数>` でも指定できます。
+//! HiDPI ディスプレイでは描画領域を画面の拡大率倍の物理ピクセルで描画します（`--scale <auto|1|2|3|4>`、既定: auto）。
+//! 保存するファイルの名前には日時と中心・ズーム倍率が入り、保存先は `--output-dir <dir>` でも指定できます。
+//! GPU の f32 → df64・GPU → CPU・CPU → 固定小数点の切替閾値が設定ファイルになければ、初回起動時に各計算モードの描画時間を
+//! 計測して決め、設定ファイルに書き込みます（`--calibrate` で計測し直し）。
+//!
+//! CPU モードの描画はバックグラウンドスレッドで行い、描画中に操作すると古い描画は中断されます。
+//!
+//! `--record-zoom <実部>,<虚部>,<ズーム倍率>` を指定すると、ウィンドウを開かずに
+//! 指定した位置までのズームアニメーションを出力して終了します
+//! （`--frames <枚数>`、`--frames-dir <ディレクトリ>` で出力先を指定）。
+//! `--video <file.mp4|file.webm>` を指定すると連番画像の代わりに ffmpeg で動画を出力します
+//! （`--fps`、`--duration <秒>`、`--easing linear|ease-in|ease-out|ease-in-out`）。
+//! `--render-path <file.json>` を指定すると、キーフレームファイルに沿ったアニメーションを出力して終了します。
+//! `--export <実部>,<虚部>,<ズーム倍率>` を指定すると、その表示を高解像度で書き出して終了します
+//! （`--export-file <file.png>`、既定: mandelbrot_export.png）。
+//!
+//! 終了時に表示範囲などの状態をセッションファイル（`--session <file>`、既定: mandelbrot_session.json）へ
+//! 自動保存し、`--resume` を指定して起動すると前回の状態から再開します。
+//!
+//! S キーで保存する PNG には中心座標・ズーム倍率・最大反復回数・パレットが埋め込まれ、
+//! `--open <file.png>`（または先頭の引数、アプリへのドラッグ＆ドロップ）で同じ表示を開き直せます。
+//! Kalles Fraktaler / Fractint のパラメータファイル（.kfr / .par）も同じ方法で開け、
+//! P キーで現在の位置をパラメータファイルとして保存します（--location-format kfr|par、既定: kfr）。
+
+use bytemuck::{Pod, Zeroable};
+#[cfg(feature = "gamepad")]
+use mandelbrot::common::gamepad::GamepadInput;
 use mandelbrot::common::{
-    colors::iter_to_color_u32,
+    animation::{Keyframe, KeyframePath, RecordingOptions, View, DEFAULT_KEYFRAMES_FILE},
+    backend::{Backend, ComputeMode},
+    cli::{arg_value, file_arg, has_flag, is_subcommand},
+    colors::{colorize, encode_distance, ColoringMode, Colorizer},
+    config::{ensure_dir, key_name, store_thresholds, store_tone, Config, Thresholds},
     constants::*,
-    font::draw_text,
-    mandelbrot::{mandelbrot_iter_fast, mandelbrot_iter_hp},
+    export::{export_image, ExportOptions, RenderRequest, DEFAULT_EXPORT_FILE},
+    filename::output_path,
+    fixed::{self, iter_fixed, Fixed},
+    input::{Command, InputHandler},
+    location::LocationFormat,
+    mandelbrot::{
+        escape_distance_fast, escape_iter_fast, mandelbrot_iter_fast_x8,
+        perturbation_iter_with_series, FractalType, HpScratch, Plane, ReferenceOrbit,
+        SeriesApproximation, LANES,
+    },
+    render::{
+        render_dithered, render_tiles, render_tiles_batched, BackgroundRender, CancelToken, Mirror,
+        RenderOutput, SharedFrame,
+    },
+    session::{Session, DEFAULT_SESSION_FILE},
+    settings::Settings,
+    snapshot::Snapshot,
+    viewer::ViewerState,
 };
-use minifb::{Key, MouseButton, MouseMode, Window, WindowOptions};
+use minifb::{Key, Window, WindowOptions};
 use num_complex::Complex;
-use rayon::prelude::*;
-use rug::Float;
-use std::time::Instant;
-
-/// 計算モード
-#[derive(Clone, Copy, PartialEq)]
-enum ComputeMode {
-    Fast,
-    HighPrecision,
+use rug::{Assign, Float};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// GPU に渡すパラメータ構造体
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+///
+/// 座標は f32 の組 [hi, lo]（hi + lo の double-double）で渡します。
+/// WGSL の `vec2<f32>` は8バイト境界に置かれるため、全体の大きさを8の倍数にそろえています。
+struct GpuParams {
+    center_x: [f32; 2], // 中心の実部
+    center_y: [f32; 2], // 中心の虚部
+    c_real: [f32; 2],   // ジュリア集合の定数 c の実部
+    c_imag: [f32; 2],   // ジュリア集合の定数 c の虚部
+    x_scale: f32,       // 1ピクセルあたりの実部の幅
+    y_scale: f32,       // 1ピクセルあたりの虚部の幅
+    width: u32,
+    height: u32,
+    max_iter: u32,
+    julia: u32,        // 1 ならジュリア集合
+    fractal: u32,      // 0: マンデルブロ、1: バーニングシップ、2: トリコーン、3: マルチブロ
+    power: u32,        // マルチブロ集合の次数
+    distance: u32,     // 1 なら境界までの距離推定を出力
+    df64: u32,         // 1 なら double-double (df64) で反復
+    one: f32,          // 常に 1.0（シェーダーの最適化で df64 の誤差項が消えないようにする）
+    cos_rotation: f32, // 表示範囲の回転角の cos
+    sin_rotation: f32, // 表示範囲の回転角の sin
+    _padding: u32,
 }
 
-impl std::fmt::Display for ComputeMode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            ComputeMode::Fast => write!(f, "🚀 高速 (f64)"),
-            ComputeMode::HighPrecision => write!(f, "🔬 高精度 (任意精度)"),
-        }
-    }
+/// f64 を f32 の組 [hi, lo] に分ける（hi + lo で約48ビットの精度）
+fn split_df64(value: f64) -> [f32; 2] {
+    let hi = value as f32;
+    [hi, (value - hi as f64) as f32]
 }
 
-/// ビューアの状態
-struct ViewerState {
-    x_min: Float,
-    x_max: Float,
-    y_min: Float,
-    y_max: Float,
-    precision: u32,
-    compute_mode: ComputeMode,
-    buffer: Vec<u32>,            // ウィンドウ全体のバッファ
-    mandelbrot_buffer: Vec<u32>, // マンデルブロ部分のみ
-    needs_redraw: bool,
-    save_counter: u32,
+/// GPU コンテキスト
+struct GpuContext {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    params_buffer: wgpu::Buffer,
+    output_buffer: wgpu::Buffer,
+    staging_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    /// 出力バッファの幅（描画領域と同じ）
+    width: usize,
+    /// 出力バッファの高さ
+    height: usize,
 }
 
-impl ViewerState {
-    fn new() -> Self {
-        let prec = INITIAL_PRECISION;
-        let mut state = Self {
-            x_min: Float::with_val(prec, -2.5),
-            x_max: Float::with_val(prec, 1.0),
-            y_min: Float::with_val(prec, -1.5),
-            y_max: Float::with_val(prec, 1.5),
-            precision: prec,
-            compute_mode: ComputeMode::Fast,
-            buffer: vec![0; WINDOW_WIDTH * WINDOW_HEIGHT],
-            mandelbrot_buffer: vec![0; MANDELBROT_WIDTH * MANDELBROT_HEIGHT],
-            needs_redraw: true,
-            save_counter: 0,
-        };
-        state.draw_colorbar();
-        state
+impl GpuContext {
+    /// 幅 `width` × 高さ `height` の描画領域を計算できるよう GPU を初期化
+    /// （GPU アダプタやデバイスが得られない、シェーダーを準備できない場合はエラー）
+    fn new(width: usize, height: usize) -> Result<Self, String> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        }))
+        .ok_or("GPU アダプタが見つかりません")?;
+
+        println!("GPU: {}", adapter.get_info().name);
+
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("Mandelbrot Device"),
+                required_features: wgpu::Features::empty(),
+                required_limits: wgpu::Limits::default(),
+                memory_hints: wgpu::MemoryHints::Performance,
+            },
+            None,
+        ))
+        .map_err(|e| format!("GPU デバイスの取得に失敗しました: {}", e))?;
+
+        // シェーダーとパイプラインの作成で起きた検証エラーは、パニックさせずに初期化の失敗として返す
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        // シェーダーをロード
+        let shader_source = include_str!("mandelbrot.wgsl");
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Mandelbrot Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        });
+
+        // バインドグループレイアウト
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        // パイプラインレイアウト
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // コンピュートパイプライン
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Mandelbrot Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("main"),
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            cache: None,
+        });
+
+        if let Some(e) = pollster::block_on(device.pop_error_scope()) {
+            return Err(format!("GPU シェーダーの準備に失敗しました: {}", e));
+        }
+
+        // バッファ作成
+        let buffer_size = (width * height * std::mem::size_of::<u32>()) as u64;
+
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Params Buffer"),
+            size: std::mem::size_of::<GpuParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let output_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Output Buffer"),
+            size: buffer_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Staging Buffer"),
+            size: buffer_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // バインドグループ作成
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: output_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        Ok(Self {
+            device,
+            queue,
+            pipeline,
+            params_buffer,
+            output_buffer,
+            staging_buffer,
+            bind_group,
+            width,
+            height,
+        })
     }
 
-    fn reset(&mut self) {
-        let prec = INITIAL_PRECISION;
-        self.x_min = Float::with_val(prec, -2.5);
-        self.x_max = Float::with_val(prec, 1.0);
-        self.y_min = Float::with_val(prec, -1.5);
-        self.y_max = Float::with_val(prec, 1.5);
-        self.precision = prec;
-        self.compute_mode = ComputeMode::Fast;
-        self.needs_redraw = true;
+    fn compute(&self, params: &GpuParams) -> Vec<u32> {
+        // パラメータをGPUに送信
+        self.queue
+            .write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(params));
+
+        // コマンドエンコーダ作成
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Compute Encoder"),
+            });
+
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Mandelbrot Compute Pass"),
+                timestamp_writes: None,
+            });
+
+            compute_pass.set_pipeline(&self.pipeline);
+            compute_pass.set_bind_group(0, &self.bind_group, &[]);
+
+            // ワークグループ数を計算（8x8のワークグループサイズ）
+            let workgroups_x = (self.width as u32).div_ceil(8);
+            let workgroups_y = (self.height as u32).div_ceil(8);
+            compute_pass.dispatch_workgroups(workgroups_x, workgroups_y, 1);
+        }
+
+        // 結果をステージングバッファにコピー
+        encoder.copy_buffer_to_buffer(
+            &self.output_buffer,
+            0,
+            &self.staging_buffer,
+            0,
+            (self.width * self.height * std::mem::size_of::<u32>()) as u64,
+        );
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        // 結果を読み取り
+        let buffer_slice = self.staging_buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+            sender.send(result).unwrap();
+        });
+
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver.recv().unwrap().unwrap();
+
+        let data = buffer_slice.get_mapped_range();
+        let result: Vec<u32> = bytemuck::cast_slice(&data).to_vec();
+        drop(data);
+        self.staging_buffer.unmap();
+
+        result
     }
+}
 
-    fn current_zoom(&self) -> f64 {
-        let width = self.x_max.to_f64() - self.x_min.to_f64();
-        3.5 / width
+// ===== GPU版の計算 =====
+
+/// 描画領域全体を GPU で計算（`df64` なら f32 の代わりに double-double で反復）
+fn render_gpu(
+    gpu: &GpuContext,
+    view: &View,
+    fractal: FractalType,
+    julia_c: Option<Complex<f64>>,
+    max_iter: u32,
+    distance: bool,
+    df64: bool,
+) -> RenderOutput {
+    let c = julia_c.unwrap_or_default();
+    let (fractal, power) = match fractal {
+        FractalType::Mandelbrot => (0, 2),
+        FractalType::BurningShip => (1, 2),
+        FractalType::Tricorn => (2, 2),
+        FractalType::Multibrot(power) => (3, power),
+    };
+    let (sin, cos) = view.rotation.to_radians().sin_cos();
+    let params = GpuParams {
+        center_x: split_df64(view.center_x.to_f64()),
+        center_y: split_df64(view.center_y.to_f64()),
+        c_real: split_df64(c.re),
+        c_imag: split_df64(c.im),
+        x_scale: (view.width / gpu.width as f64) as f32,
+        y_scale: (view.height / gpu.height as f64) as f32,
+        width: gpu.width as u32,
+        height: gpu.height as u32,
+        max_iter,
+        julia: julia_c.is_some() as u32,
+        fractal,
+        power,
+        distance: distance as u32,
+        df64: df64 as u32,
+        one: 1.0,
+        cos_rotation: cos as f32,
+        sin_rotation: sin as f32,
+        _padding: 0,
+    };
+
+    // GPU で計算
+    RenderOutput {
+        iters: gpu.compute(&params),
+        width: gpu.width,
+        height: gpu.height,
     }
+}
 
-    fn update_compute_mode(&mut self) {
-        let zoom = self.current_zoom();
-        let old_mode = self.compute_mode;
+/// 描画領域の1ピクセルを `samples` × `samples` のサブピクセルに分け、サブピクセルの位置だけずらした表示範囲を
+/// `render` で描画して、`samples` 倍の解像度の1枚にまとめる（アンチエイリアス用）
+///
+/// `render` は描画領域（`area_width` × `area_height`）と同じ解像度で描画する関数
+/// （GPU の出力バッファの大きさは固定のため）。
+fn render_supersampled(
+    view: &View,
+    (area_width, area_height): (usize, usize),
+    samples: usize,
+    render: impl Fn(&View) -> RenderOutput,
+) -> RenderOutput {
+    if samples == 1 {
+        return render(view);
+    }
+    let width = area_width * samples;
+    let height = area_height * samples;
+    let x_step = view.width / width as f64;
+    let y_step = view.height / height as f64;
+    let prec = view.center_x.prec();
+
+    let mut iters = vec![0; width * height];
+    for sy in 0..samples {
+        for sx in 0..samples {
+            // サブピクセルの分だけ画面の右下へずらす
+            let (dx, dy) = view.rotate(sx as f64 * x_step, -(sy as f64) * y_step);
+            let shifted = View {
+                center_x: Float::with_val(prec, &view.center_x + dx),
+                center_y: Float::with_val(prec, &view.center_y + dy),
+                ..view.clone()
+            };
+            let pass = render(&shifted);
+            for (y, row) in pass.iters.chunks(area_width).enumerate() {
+                let line = (y * samples + sy) * width;
+                for (x, &iter) in row.iter().enumerate() {
+                    iters[line + x * samples + sx] = iter;
+                }
+            }
+        }
+    }
+    RenderOutput {
+        iters,
+        width,
+        height,
+    }
+}
 
-        if zoom > PRECISION_THRESHOLD {
-            self.compute_mode = ComputeMode::HighPrecision;
-            let required_precision = (zoom.log2() * 3.5) as u32 + 64;
-            if required_precision > self.precision && self.precision < MAX_PRECISION {
-                self.precision = (required_precision.next_power_of_two()).min(MAX_PRECISION);
-                self.x_min.set_prec(self.precision);
-                self.x_max.set_prec(self.precision);
-                self.y_min.set_prec(self.precision);
-                self.y_max.set_prec(self.precision);
+// ===== CPU f64版の計算 =====
+
+/// 各ピクセルを f64 で計算（`distance` なら反復回数の代わりに境界までの距離推定を格納）
+fn render_cpu_f64(
+    view: &View,
+    fractal: FractalType,
+    julia_c: Option<Complex<f64>>,
+    max_iter: u32,
+    distance: bool,
+    frame: &SharedFrame,
+    cancel: &CancelToken,
+) -> bool {
+    let (width, height) = (frame.width(), frame.height());
+    let (center_x, center_y) = (view.center_x.to_f64(), view.center_y.to_f64());
+    let x_scale = view.width / width as f64;
+    let y_scale = view.height / height as f64;
+    // 回転していると実軸が画面の横線にならないため、上下の折り返しは使えない
+    let mirror = (view.rotation == 0.0 && fractal.is_symmetric(julia_c))
+        .then(|| Mirror::across_real_axis(center_y + view.height / 2.0, y_scale, height))
+        .flatten();
+
+    // マンデルブロ集合の反復回数は 8 点ずつ SIMD で計算する
+    if !distance && fractal == FractalType::Mandelbrot && julia_c.is_none() {
+        return render_tiles_batched(frame, cancel, mirror, |x, y| {
+            let mut cx = [0.0; LANES];
+            let mut cy = [0.0; LANES];
+            for k in 0..LANES {
+                let (dx, dy) = view.pixel_offset((x + k) as f64, y as f64, width, height);
+                cx[k] = center_x + dx;
+                cy[k] = center_y + dy;
             }
+            mandelbrot_iter_fast_x8(cx, cy, max_iter)
+        });
+    }
+
+    render_tiles(frame, cancel, mirror, |x, y| {
+        let (dx, dy) = view.pixel_offset(x as f64, y as f64, width, height);
+        let point = Complex::new(center_x + dx, center_y + dy);
+        if distance {
+            escape_distance_fast(fractal, point, julia_c, max_iter)
+                .map_or(max_iter, |d| encode_distance(d, x_scale))
         } else {
-            self.compute_mode = ComputeMode::Fast;
+            escape_iter_fast(fractal, point, julia_c, max_iter)
         }
+    })
+}
 
-        if old_mode != self.compute_mode {
-            println!("モード切替: {} → {}", old_mode, self.compute_mode);
-        }
+// ===== CPU 高精度版の計算（摂動法） =====
+
+/// 中心点の参照軌道だけを任意精度で計算し、各ピクセルは参照点からの差分を f64 で反復する
+///
+/// 序盤の反復は参照軌道から求めた級数近似で省略します。ピクセルは画像全体に散らばった順に計算し、
+/// 途中経過は粗いモザイクから徐々に細かくなります。
+/// ジュリア集合とマンデルブロ集合以外の式は全ピクセルを任意精度で計算します。
+fn render_cpu_high_precision(
+    view: &View,
+    plane: &Plane,
+    fractal: FractalType,
+    max_iter: u32,
+    frame: &SharedFrame,
+    cancel: &CancelToken,
+) -> bool {
+    if *plane != Plane::Mandelbrot || fractal != FractalType::Mandelbrot {
+        return render_cpu_full_high_precision(view, plane, fractal, max_iter, frame, cancel);
     }
 
-    /// 画面上のピクセル座標を複素平面上の座標に変換
-    fn pixel_to_complex(&self, x: f64, y: f64) -> (f64, f64) {
-        let width_f = self.x_max.to_f64() - self.x_min.to_f64();
-        let height_f = self.y_max.to_f64() - self.y_min.to_f64();
-
-        let cx = self.x_min.to_f64() + width_f * (x / MANDELBROT_WIDTH as f64);
-        let cy = self.y_max.to_f64() - height_f * (y / MANDELBROT_HEIGHT as f64);
-        (cx, cy)
+    let prec = view.center_x.prec();
+    let orbit = ReferenceOrbit::new(&view.center_x, &view.center_y, max_iter, prec);
+    if cancel.is_cancelled() {
+        return false;
     }
 
-    /// 指定された中心座標と現在のズーム倍率で範囲を更新
-    fn update_bounds(&mut self, center_x: f64, center_y: f64, width_scale: f64) {
-        let prec = self.precision;
-        let width_f = self.x_max.to_f64() - self.x_min.to_f64();
-        let height_f = self.y_max.to_f64() - self.y_min.to_f64();
+    let (width, height) = (frame.width(), frame.height());
+    let y_scale = view.height / height as f64;
 
-        let new_width = width_f * width_scale;
-        let new_height = height_f * width_scale;
-        let half_new_width = new_width / 2.0;
-        let half_new_height = new_height / 2.0;
+    // 画面の角が参照点（中心）から最も遠い
+    let series = SeriesApproximation::new(&orbit, view.width.hypot(view.height) / 2.0);
+    println!("🔬 級数近似: {}回の反復を省略", series.skip());
+    let mirror = (view.rotation == 0.0)
+        .then(|| {
+            Mirror::across_real_axis(view.center_y.to_f64() + view.height / 2.0, y_scale, height)
+        })
+        .flatten();
+
+    render_dithered(
+        frame,
+        cancel,
+        mirror,
+        || (),
+        |_, x, y| {
+            let (dx, dy) = view.pixel_offset(x as f64, y as f64, width, height);
+            perturbation_iter_with_series(Complex::new(dx, dy), &orbit, &series, max_iter)
+        },
+    )
+}
 
-        self.x_min = Float::with_val(prec, center_x - half_new_width);
-        self.x_max = Float::with_val(prec, center_x + half_new_width);
-        self.y_min = Float::with_val(prec, center_y - half_new_height);
-        self.y_max = Float::with_val(prec, center_y + half_new_height);
+/// 全ピクセルを任意精度で計算
+///
+/// ジュリア集合は z_0 がピクセルごとに異なり、摂動法のリベース（参照軌道の先頭 Z_0 = 0 への乗り換え）が
+/// 使えません。バーニングシップなどの式も z² + c の差分の式が成り立たないため、
+/// 各ピクセルの軌道をそのまま任意精度で反復します。
+fn render_cpu_full_high_precision(
+    view: &View,
+    plane: &Plane,
+    fractal: FractalType,
+    max_iter: u32,
+    frame: &SharedFrame,
+    cancel: &CancelToken,
+) -> bool {
+    let prec = view.center_x.prec();
+    let (width, height) = (frame.width(), frame.height());
+    let y_scale = view.height / height as f64;
+    let mirror = (view.rotation == 0.0 && fractal.is_symmetric(plane.julia_c()))
+        .then(|| {
+            Mirror::across_real_axis(view.center_y.to_f64() + view.height / 2.0, y_scale, height)
+        })
+        .flatten();
+
+    // 作業用変数とピクセルの座標はスレッドごとに確保して使い回す
+    let init = || (HpScratch::new(prec), Float::new(prec), Float::new(prec));
+    render_dithered(frame, cancel, mirror, init, |(scratch, zx, zy), x, y| {
+        let (dx, dy) = view.pixel_offset(x as f64, y as f64, width, height);
+        zx.assign(&view.center_x + dx);
+        zy.assign(&view.center_y + dy);
+        plane.iter_hp_with(fractal, zx, zy, max_iter, scratch)
+    })
+}
 
-        self.update_compute_mode();
-        self.needs_redraw = true;
+// ===== CPU 固定小数点版の計算 =====
+
+/// 各ピクセルを小数部 120 ビットの固定小数点数で計算
+///
+/// f64 の精度が足りず、任意精度で計算するほどではない倍率（×10^13〜×10^30）で使います。
+/// ピクセルは `render_dithered` の順に計算し、途中経過は粗いモザイクから徐々に細かくなります。
+/// 固定小数点数で表せない式・座標（累乗の次数が大きい、中心や定数の絶対値が 4 以上）は全ピクセルを任意精度で計算します。
+fn render_cpu_fixed(
+    view: &View,
+    plane: &Plane,
+    fractal: FractalType,
+    max_iter: u32,
+    frame: &SharedFrame,
+    cancel: &CancelToken,
+) -> bool {
+    let params = fractal.params();
+    let center = (view.center_x.to_f64(), view.center_y.to_f64());
+    let in_range = |(re, im): (f64, f64)| re.abs() < 4.0 && im.abs() < 4.0;
+    if !fixed::supports(&params)
+        || !in_range(center)
+        || !plane.julia_c().is_none_or(|c| in_range((c.re, c.im)))
+    {
+        return render_cpu_full_high_precision(view, plane, fractal, max_iter, frame, cancel);
     }
 
-    fn zoom(&mut self, mouse_x: f64, mouse_y: f64, factor: f64) {
-        // カラーバー領域では無視
-        if mouse_x >= MANDELBROT_WIDTH as f64 {
-            return;
+    let (width, height) = (frame.width(), frame.height());
+    let (center_x, center_y) = (
+        Fixed::from_float(&view.center_x),
+        Fixed::from_float(&view.center_y),
+    );
+    let julia_c = match plane {
+        Plane::Mandelbrot => None,
+        Plane::Julia { c_real, c_imag } => {
+            Some((Fixed::from_float(c_real), Fixed::from_float(c_imag)))
         }
+    };
+    let y_scale = view.height / height as f64;
+    let mirror = (view.rotation == 0.0 && fractal.is_symmetric(plane.julia_c()))
+        .then(|| Mirror::across_real_axis(center.1 + view.height / 2.0, y_scale, height))
+        .flatten();
+
+    render_dithered(
+        frame,
+        cancel,
+        mirror,
+        || (),
+        |_, x, y| {
+            let (dx, dy) = view.pixel_offset(x as f64, y as f64, width, height);
+            let (re, im) = (
+                center_x + Fixed::from_f64(dx),
+                center_y + Fixed::from_f64(dy),
+            );
+            match julia_c {
+                Some((c_re, c_im)) => iter_fixed(&params, re, im, c_re, c_im, max_iter),
+                None => iter_fixed(&params, Fixed::ZERO, Fixed::ZERO, re, im, max_iter),
+            }
+        },
+    )
+}
 
-        let (cx, cy) = self.pixel_to_complex(mouse_x, mouse_y);
-        self.update_bounds(cx, cy, factor);
-    }
+// ===== メイン描画関数 =====
 
-    /// クリック位置を画面中心に移動（パン）
-    fn pan_to(&mut self, mouse_x: f64, mouse_y: f64) {
-        // カラーバー領域では無視
-        if mouse_x >= MANDELBROT_WIDTH as f64 {
-            return;
+/// 現在の表示範囲の描画を開始（描画中のジョブは中断）
+///
+/// GPU モードはその場で計算し、CPU モードはバックグラウンドスレッドで計算します。
+fn start_render(state: &mut ViewerState, gpu: Option<&GpuContext>) {
+    // 古いジョブは破棄時にキャンセルされる
+    state.render_job = None;
+    state.render_start = Instant::now();
+    state.needs_redraw = false;
+
+    let view = state.view();
+    let max_iter = state.max_iter;
+    let plane = state.plane.clone();
+    let fractal = state.fractal;
+    let julia_c = plane.julia_c();
+    let distance = state.coloring_mode == ColoringMode::Distance;
+    // アンチエイリアスは描画領域と同じ解像度で描画するモードで、サンプル数倍の解像度で描画して行う
+    let samples = state.samples;
+    let area = (state.settings.width, state.settings.height);
+    let (width, height) = (area.0 * samples, area.1 * samples);
+    let (hp_width, hp_height) = state.settings.hp_render_size();
+
+    let job = match (state.compute_mode, gpu) {
+        (ComputeMode::Gpu | ComputeMode::GpuDf64, Some(gpu)) => {
+            let df64 = state.compute_mode == ComputeMode::GpuDf64;
+            BackgroundRender::completed(render_supersampled(&view, area, samples, |view| {
+                render_gpu(gpu, view, fractal, julia_c, max_iter, distance, df64)
+            }))
+        }
+        // 高精度モードは低解像度から始めて、完了するごとに refine_render で解像度を上げる
+        (ComputeMode::CpuHighPrecision, _) => {
+            BackgroundRender::spawn(hp_width, hp_height, move |frame, cancel| {
+                render_cpu_high_precision(&view, &plane, fractal, max_iter, frame, cancel)
+            })
         }
+        (ComputeMode::CpuFixed, _) => {
+            BackgroundRender::spawn(width, height, move |frame, cancel| {
+                render_cpu_fixed(&view, &plane, fractal, max_iter, frame, cancel)
+            })
+        }
+        (ComputeMode::FullHighPrecision, _) => {
+            BackgroundRender::spawn(width, height, move |frame, cancel| {
+                render_cpu_full_high_precision(&view, &plane, fractal, max_iter, frame, cancel)
+            })
+        }
+        _ => BackgroundRender::spawn(width, height, move |frame, cancel| {
+            render_cpu_f64(&view, fractal, julia_c, max_iter, distance, frame, cancel)
+        }),
+    };
+    state.render_job = Some(job);
+}
 
-        let (cx, cy) = self.pixel_to_complex(mouse_x, mouse_y);
-        self.update_bounds(cx, cy, 1.0); // 倍率は1.0（変えない）
+/// 高精度（摂動法）モードの描画が完了したら、表示が変わっていない間は縦横2倍の解像度で描き直す
+///
+/// 描画領域の 1/`HP_RENDER_DIVISOR`（`Settings::hp_render_size`）から始めて描画領域と同じ解像度まで上げます。
+/// 前の解像度の結果は新しい解像度の偶数行・偶数列と同じ点なので引き継ぎ、残りのピクセルだけを計算します。
+/// 計算中は前の解像度の結果を拡大して表示します。
+fn refine_render(state: &mut ViewerState) {
+    if state.compute_mode != ComputeMode::CpuHighPrecision
+        || state.iter_width >= state.settings.width
+    {
+        return;
     }
+    let frame = SharedFrame::refine(&RenderOutput {
+        iters: state.iter_buffer.clone(),
+        width: state.iter_width,
+        height: state.iter_height,
+    });
+    println!("🔬 {}x{} で描き直し中...", frame.width(), frame.height());
+
+    let view = state.view();
+    let plane = state.plane.clone();
+    let (fractal, max_iter) = (state.fractal, state.max_iter);
+    state.render_start = Instant::now();
+    state.render_job = Some(BackgroundRender::spawn_with(frame, move |frame, cancel| {
+        render_cpu_high_precision(&view, &plane, fractal, max_iter, frame, cancel)
+    }));
+}
 
-    /// カラーバーを描画
-    fn draw_colorbar(&mut self) {
-        let bar_x_start = MANDELBROT_WIDTH + COLORBAR_MARGIN;
-        let bar_x_end = bar_x_start + COLORBAR_BAR_WIDTH;
-        let bar_y_start = 40;
-        let bar_y_end = MANDELBROT_HEIGHT - 40;
-        let bar_height = bar_y_end - bar_y_start;
+// ===== 切替閾値の計測 =====
 
-        // 背景をグレーに
-        for y in 0..WINDOW_HEIGHT {
-            for x in MANDELBROT_WIDTH..WINDOW_WIDTH {
-                self.buffer[y * WINDOW_WIDTH + x] = 0x404040;
-            }
-        }
+/// 閾値の計測に使う中心（境界付近で反復回数がばらつくシーホース谷）
+const CALIBRATION_CENTER: (&str, &str) = ("-0.743643887037151", "0.131825904205330");
 
-        // カラーバー本体を描画
-        for y in bar_y_start..bar_y_end {
-            let t = 1.0 - (y - bar_y_start) as f64 / bar_height as f64;
-            let iter = (t * MAX_ITER as f64) as u32;
-            let color = iter_to_color_u32(iter, MAX_ITER);
+/// 計測するズーム倍率（10^0〜10^13）
+const CALIBRATION_ZOOM_EXPONENTS: std::ops::RangeInclusive<i32> = 0..=13;
 
-            for x in bar_x_start..bar_x_end {
-                self.buffer[y * WINDOW_WIDTH + x] = color;
-            }
-        }
+/// 描画1回の1ピクセルあたりの時間（秒）
+fn time_per_pixel(render: impl FnOnce() -> RenderOutput) -> f64 {
+    let start = Instant::now();
+    let output = render();
+    start.elapsed().as_secs_f64() / (output.width * output.height) as f64
+}
 
-        // 枠線
-        let border_color = 0xFFFFFF;
-        for x in bar_x_start..bar_x_end {
-            self.buffer[(bar_y_start - 1) * WINDOW_WIDTH + x] = border_color;
-            self.buffer[bar_y_end * WINDOW_WIDTH + x] = border_color;
+/// ズーム倍率ごとの (倍率, 前の計算モードの時間, 次の計算モードの時間) から切替閾値を求める
+///
+/// 次の計算モードが初めて速くなった倍率とその1つ前の倍率の相乗平均を返します。
+/// 最初の倍率から次の計算モードの方が速ければその倍率、最後まで前の計算モードの方が速ければ `limit` です。
+fn crossover(timings: &[(f64, f64, f64)], limit: f64) -> f64 {
+    match timings
+        .iter()
+        .position(|&(_, before, after)| after < before)
+    {
+        Some(0) => timings[0].0,
+        Some(i) => (timings[i - 1].0 * timings[i].0).sqrt(),
+        None => limit,
+    }
+}
+
+/// GPU (f32)・GPU (df64)・CPU (f64)・固定小数点の各計算モードをいくつかのズーム倍率で描画して、
+/// 速い方に切り替える閾値を計測
+///
+/// 各計算モードは精度が足りる倍率（GPU の f32 は `GPU_DF64_THRESHOLD`、df64 は `GPU_TO_CPU_THRESHOLD`、
+/// f64 は `PRECISION_THRESHOLD`）までを比べ、閾値もその倍率を超えないようにします。
+/// f32 → df64 の閾値は GPU → CPU の閾値も超えないようにします（その先は df64 を使わないため）。固定小数点モードは低解像度で描画するため、1ピクセルあたりの時間で比べます。
+/// 描画領域の大きさ・最大反復回数は `settings` のものを使います。
+fn calibrate_thresholds(gpu: &GpuContext, settings: &Settings) -> Thresholds {
+    println!("切替閾値を計測中...");
+    let max_iter = settings.max_iter;
+    let (width, height) = (settings.width, settings.height);
+    let (hp_width, hp_height) = settings.hp_render_size();
+    let plane = Plane::Mandelbrot;
+    let fractal = FractalType::Mandelbrot;
+    // 初回の描画はシェーダーのコンパイルなどを含むため計測しない
+    let initial = View::initial(settings.initial_precision);
+    for df64 in [false, true] {
+        render_gpu(gpu, &initial, fractal, None, max_iter, false, df64);
+    }
+
+    let mut df64_timings = Vec::new();
+    let mut gpu_timings = Vec::new();
+    let mut fixed_timings = Vec::new();
+    for exponent in CALIBRATION_ZOOM_EXPONENTS {
+        let zoom = 10f64.powi(exponent);
+        let view = View::from_decimal(CALIBRATION_CENTER.0, CALIBRATION_CENTER.1, zoom)
+            .expect("計測に使う中心座標は数値");
+        let render_cpu = |width, height, mode| {
+            let frame = SharedFrame::new(width, height);
+            render_view_cpu(&view, &plane, fractal, mode, max_iter, false, &frame);
+            frame.snapshot()
+        };
+
+        let f64_time = time_per_pixel(|| render_cpu(width, height, ComputeMode::CpuF64));
+        let time_gpu =
+            |df64| time_per_pixel(|| render_gpu(gpu, &view, fractal, None, max_iter, false, df64));
+        let f32_time = (zoom <= GPU_DF64_THRESHOLD).then(|| time_gpu(false));
+        let df64_time = (zoom <= GPU_TO_CPU_THRESHOLD).then(|| time_gpu(true));
+        let fixed_time = (zoom <= PRECISION_THRESHOLD)
+            .then(|| time_per_pixel(|| render_cpu(hp_width, hp_height, ComputeMode::CpuFixed)));
+        if let (Some(f32_time), Some(df64_time)) = (f32_time, df64_time) {
+            df64_timings.push((zoom, f32_time, df64_time));
         }
-        for y in (bar_y_start - 1)..=bar_y_end {
-            self.buffer[y * WINDOW_WIDTH + bar_x_start - 1] = border_color;
-            self.buffer[y * WINDOW_WIDTH + bar_x_end] = border_color;
+        if let Some(df64_time) = df64_time {
+            gpu_timings.push((zoom, df64_time, f64_time));
+        }
+        if let Some(fixed_time) = fixed_time {
+            fixed_timings.push((zoom, f64_time, fixed_time));
         }
 
-        // 目盛りとラベルを描画
-        let tick_values = [0, 64, 128, 192, 256];
-        for &value in &tick_values {
-            let t = value as f64 / MAX_ITER as f64;
-            let y = bar_y_end - (t * bar_height as f64) as usize;
+        // 描画領域全体を描画する時間に換算して表示
+        let ms = |time: Option<f64>| {
+            time.map_or("-".to_string(), |t| {
+                format!("{:.1}ms", t * (width * height) as f64 * 1e3)
+            })
+        };
+        println!(
+            "  x{:.0e}: GPU (f32) {}, GPU (df64) {}, CPU (f64) {}, 固定小数点 {}",
+            zoom,
+            ms(f32_time),
+            ms(df64_time),
+            ms(Some(f64_time)),
+            ms(fixed_time)
+        );
+    }
 
-            // 目盛り線
-            for x in bar_x_end..(bar_x_end + 5) {
-                if y < WINDOW_HEIGHT {
-                    self.buffer[y * WINDOW_WIDTH + x] = 0xFFFFFF;
-                }
-            }
+    let gpu_to_cpu = crossover(&gpu_timings, GPU_TO_CPU_THRESHOLD);
+    Thresholds {
+        gpu_df64: crossover(&df64_timings, GPU_DF64_THRESHOLD).min(gpu_to_cpu),
+        gpu_to_cpu,
+        high_precision: crossover(&fixed_timings, PRECISION_THRESHOLD),
+    }
+}
 
-            // 数値ラベルを描画
-            let label = format!("{}", value);
-            let label_x = bar_x_end + 7;
-            let label_y = y.saturating_sub(3);
-            draw_text(
-                &mut self.buffer,
-                WINDOW_WIDTH,
-                WINDOW_HEIGHT,
-                label_x,
-                label_y,
-                &label,
-                0xCCCCCC,
-            );
-        }
+// ===== ベンチマーク =====
+
+/// ベンチマークで描画する位置（名前, 実部, 虚部, ズーム倍率）
+const BENCH_LOCATIONS: [(&str, &str, &str, f64); 3] = [
+    ("浅い", "-0.75", "0", 1.0),
+    (
+        "中程度",
+        "-0.743643887037158704752191506114774",
+        "0.131825904205311970493132056385139",
+        1e8,
+    ),
+    (
+        "深い",
+        "-0.743643887037158704752191506114774",
+        "0.131825904205311970493132056385139",
+        1e20,
+    ),
+];
+
+/// ベンチマークの最大反復回数
+const BENCH_MAX_ITER: u32 = 1000;
+
+/// 1つの計算モードを描画する回数
+const BENCH_RUNS: u32 = 3;
+
+/// ベンチマークする計算モード（表示名, 計算モード, 精度が足りるズーム倍率の上限）
+const BENCH_MODES: [(&str, ComputeMode, f64); 6] = [
+    ("GPU f32", ComputeMode::Gpu, GPU_DF64_THRESHOLD),
+    ("GPU df64", ComputeMode::GpuDf64, GPU_TO_CPU_THRESHOLD),
+    ("CPU f64", ComputeMode::CpuF64, PRECISION_THRESHOLD),
+    ("固定小数点", ComputeMode::CpuFixed, FIXED_POINT_THRESHOLD),
+    ("摂動法", ComputeMode::CpuHighPrecision, f64::INFINITY),
+    (
+        "全ピクセル任意精度",
+        ComputeMode::FullHighPrecision,
+        f64::INFINITY,
+    ),
+];
+
+/// 固定の位置・解像度・反復回数で各計算モードを描画し、フレーム時間と1秒あたりのピクセル数を表で表示
+///
+/// 位置ごとに精度が足りる計算モードだけを計測します（GPU がなければ GPU のモードは省略）。
+/// GPU と CPU (f64) は描画領域と同じ解像度（既定 800×600）、固定小数点と高精度の2モードはその縦横 1/4 で描画するため、
+/// モード間は1秒あたりのピクセル数で比べてください。計算カーネルを最適化したときの性能の確認にも使えます。
+fn run_bench(gpu: Option<&GpuContext>, settings: &Settings) {
+    let plane = Plane::Mandelbrot;
+    let fractal = FractalType::Mandelbrot;
+    println!(
+        "ベンチマーク中（最大反復回数 {}、各 {} 回描画）...",
+        BENCH_MAX_ITER, BENCH_RUNS
+    );
+    if let Some(gpu) = gpu {
+        // 初回の描画はシェーダーのコンパイルなどを含むため計測しない
+        render_gpu(
+            gpu,
+            &View::initial(settings.initial_precision),
+            fractal,
+            None,
+            BENCH_MAX_ITER,
+            false,
+            false,
+        );
     }
 
-    /// マンデルブロ画像とカラーバーを合成
-    fn compose_buffer(&mut self) {
-        for y in 0..MANDELBROT_HEIGHT {
-            for x in 0..MANDELBROT_WIDTH {
-                self.buffer[y * WINDOW_WIDTH + x] =
-                    self.mandelbrot_buffer[y * MANDELBROT_WIDTH + x];
+    // 摂動法は描画のたびに級数近似の情報を表示するため、表は計測がすべて終わってから表示
+    let mut rows = Vec::new();
+    for (location, re, im, zoom) in BENCH_LOCATIONS {
+        let view = View::from_decimal(re, im, zoom).expect("ベンチマークの中心座標は数値");
+        for (name, mode, limit) in BENCH_MODES {
+            let gpu_mode = matches!(mode, ComputeMode::Gpu | ComputeMode::GpuDf64);
+            if zoom > limit || (gpu_mode && gpu.is_none()) {
+                continue;
             }
+            let (width, height) = match mode {
+                ComputeMode::CpuFixed
+                | ComputeMode::CpuHighPrecision
+                | ComputeMode::FullHighPrecision => settings.hp_render_size(),
+                _ => (settings.width, settings.height),
+            };
+
+            let times: Vec<Duration> = (0..BENCH_RUNS)
+                .map(|_| {
+                    let start = Instant::now();
+                    if let Some(gpu) = gpu.filter(|_| gpu_mode) {
+                        let df64 = mode == ComputeMode::GpuDf64;
+                        render_gpu(gpu, &view, fractal, None, BENCH_MAX_ITER, false, df64);
+                    } else {
+                        let frame = SharedFrame::new(width, height);
+                        render_view_cpu(
+                            &view,
+                            &plane,
+                            fractal,
+                            mode,
+                            BENCH_MAX_ITER,
+                            false,
+                            &frame,
+                        );
+                    }
+                    start.elapsed()
+                })
+                .collect();
+            let mean = times.iter().sum::<Duration>() / BENCH_RUNS;
+            let best = times.iter().min().copied().unwrap_or_default();
+            let pixels_per_sec = (width * height) as f64 / mean.as_secs_f64();
+            rows.push(format!(
+                "| {} | x{:.0e} | {} | {}x{} | {:.2?} | {:.2?} | {:.2} |",
+                location,
+                zoom,
+                name,
+                width,
+                height,
+                mean,
+                best,
+                pixels_per_sec / 1e6
+            ));
         }
     }
 
-    fn save_image(&mut self) {
-        self.save_counter += 1;
-        let filename = format!("mandelbrot_{:03}.png", self.save_counter);
+    println!();
+    println!("| 位置 | ズーム | 計算モード | 解像度 | 平均 | 最速 | 百万ピクセル/秒 |");
+    println!("|------|--------|-----------|--------|------|------|----------------|");
+    for row in rows {
+        println!("{}", row);
+    }
+}
+
+// ===== ズームアニメーション =====
+
+/// 指定した表示範囲をビューアの描画領域と同じ解像度で描画（完了まで待つ）
+///
+/// 式の種類・着色モードは `state` のものを使います。計算モードはビューアと同じ閾値で選び、
+/// 高精度モードも縮小せずに計算します。
+fn render_view(
+    state: &ViewerState,
+    gpu: Option<&GpuContext>,
+    view: &View,
+    plane: &Plane,
+    max_iter: u32,
+) -> RenderOutput {
+    let mode = state.backend.mode(view.zoom(), &state.settings.thresholds);
+    let distance = state.coloring_mode == ColoringMode::Distance;
+    if let (ComputeMode::Gpu | ComputeMode::GpuDf64, Some(gpu)) = (mode, gpu) {
+        return render_gpu(
+            gpu,
+            view,
+            state.fractal,
+            plane.julia_c(),
+            max_iter,
+            distance,
+            mode == ComputeMode::GpuDf64,
+        );
+    }
 
-        let img: ImageBuffer<Rgb<u8>, Vec<u8>> =
-            ImageBuffer::from_fn(WINDOW_WIDTH as u32, WINDOW_HEIGHT as u32, |x, y| {
-                let pixel = self.buffer[(y as usize) * WINDOW_WIDTH + (x as usize)];
-                let r = ((pixel >> 16) & 0xFF) as u8;
-                let g = ((pixel >> 8) & 0xFF) as u8;
-                let b = (pixel & 0xFF) as u8;
-                Rgb([r, g, b])
-            });
+    let frame = SharedFrame::new(state.settings.width, state.settings.height);
+    render_view_cpu(view, plane, state.fractal, mode, max_iter, distance, &frame);
+    frame.snapshot()
+}
 
-        img.save(&filename).expect("画像の保存に失敗しました");
-        println!("画像を保存しました: {}", filename);
+/// 指定した表示範囲をフレームの解像度で CPU 描画（完了まで待つ）
+///
+/// GPU の計算モードは CPU (f64) で計算します。距離推定（`distance`）は f64 で計算する場合のみ行います。
+fn render_view_cpu(
+    view: &View,
+    plane: &Plane,
+    fractal: FractalType,
+    mode: ComputeMode,
+    max_iter: u32,
+    distance: bool,
+    frame: &SharedFrame,
+) {
+    let cancel = CancelToken::new();
+    if mode == ComputeMode::CpuFixed {
+        render_cpu_fixed(view, plane, fractal, max_iter, frame, &cancel);
+    } else if mode == ComputeMode::CpuHighPrecision {
+        render_cpu_high_precision(view, plane, fractal, max_iter, frame, &cancel);
+    } else if mode == ComputeMode::FullHighPrecision {
+        render_cpu_full_high_precision(view, plane, fractal, max_iter, frame, &cancel);
+    } else {
+        render_cpu_f64(
+            view,
+            fractal,
+            plane.julia_c(),
+            max_iter,
+            distance,
+            frame,
+            &cancel,
+        );
     }
 }
 
-// ===== f64高速版の計算 =====
-
-fn render_fast(state: &mut ViewerState) {
-    let x_min = state.x_min.to_f64();
-    let x_max = state.x_max.to_f64();
-    let y_min = state.y_min.to_f64();
-    let y_max = state.y_max.to_f64();
-
-    let x_scale = (x_max - x_min) / MANDELBROT_WIDTH as f64;
-    let y_scale = (y_max - y_min) / MANDELBROT_HEIGHT as f64;
-
-    let pixels: Vec<u32> = (0..MANDELBROT_HEIGHT)
-        .into_par_iter()
-        .flat_map(|y| {
-            (0..MANDELBROT_WIDTH)
-                .map(|x| {
-                    let cx = x_min + x as f64 * x_scale;
-                    let cy = y_max - y as f64 * y_scale;
-                    let c = Complex::new(cx, cy);
-                    let iter = mandelbrot_iter_fast(c, MAX_ITER);
-                    iter_to_color_u32(iter, MAX_ITER)
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect();
+/// 初期表示から `target` まで指数的にズームするアニメーションを連番画像または動画で出力
+fn record_zoom(
+    state: &ViewerState,
+    gpu: Option<&GpuContext>,
+    target: &View,
+    options: &RecordingOptions,
+) -> Result<(), String> {
+    // 初期表示と目標の2つのキーフレームからなるパスとして出力
+    let start = Keyframe {
+        view: View::initial(target.center_x.prec()),
+        max_iter: state.max_iter,
+        palette: None,
+        frames: options.frames - 1,
+    };
+    let end = Keyframe {
+        view: target.clone(),
+        max_iter: state.max_iter,
+        palette: None,
+        frames: 0,
+    };
+    let path = KeyframePath {
+        keyframes: vec![start, end],
+    };
+    record_path(state, gpu, &path, options)
+}
+
+/// キーフレームパスに沿ったアニメーションを連番画像または動画で出力（表示中の平面で描画）
+fn record_path(
+    state: &ViewerState,
+    gpu: Option<&GpuContext>,
+    path: &KeyframePath,
+    options: &RecordingOptions,
+) -> Result<(), String> {
+    let palettes = path.palettes(&state.palette)?;
+    let frames = path.frames(options.easing);
+    let mut sink = options.open_sink(state.settings.width, state.settings.height)?;
+    let started = Instant::now();
+
+    for (index, frame) in frames.iter().enumerate() {
+        let output = render_view(state, gpu, &frame.view, &state.plane, frame.max_iter);
+        let colors = colorize(
+            &output.iters,
+            frame.max_iter,
+            &palettes[frame.keyframe],
+            state.coloring_mode,
+            &state.tone,
+        );
+        sink.write(&colors)?;
+        println!(
+            "🎬 録画中: {}/{} ズーム: x{:.2e} 最大反復: {}",
+            index + 1,
+            frames.len(),
+            frame.view.zoom(),
+            frame.max_iter
+        );
+    }
+    sink.finish()?;
+    println!(
+        "アニメーションを出力しました: {} ({}枚, {:.2?})",
+        options.destination().display(),
+        frames.len(),
+        started.elapsed()
+    );
+    Ok(())
+}
 
-    state.mandelbrot_buffer = pixels;
+/// 指定した表示を高解像度の PNG に書き出す（ウィンドウの表示は変えない）
+///
+/// GPU の解像度はマンデルブロ描画領域に固定のため、浅いズームでも CPU (f64) で描画します。
+fn export_view(
+    state: &ViewerState,
+    gpu: Option<&GpuContext>,
+    view: &View,
+    file: &Path,
+    options: &ExportOptions,
+) -> Result<(), String> {
+    // 帯ごとに計算モードが変わらないよう、モードは表示全体のズーム倍率で決める
+    let mode = state.backend.mode(view.zoom(), &state.settings.thresholds);
+    // ヒストグラム着色の累積分布は画面解像度の描画から求め、全ての帯で共通にする
+    let distance = state.coloring_mode == ColoringMode::Distance;
+    let preview = render_view(state, gpu, view, &state.plane, state.max_iter);
+    let colorizer =
+        Colorizer::new(&preview.iters, state.max_iter, state.coloring_mode).with_tone(&state.tone);
+    let snapshot = Snapshot {
+        view: view.clone(),
+        max_iter: state.max_iter,
+        palette: state.palette_path.clone(),
+        plane: state.plane.clone(),
+        fractal: state.fractal,
+    };
+    let started = Instant::now();
+
+    export_image(
+        file,
+        &snapshot,
+        options,
+        &state.palette,
+        &colorizer,
+        |band, frame| {
+            render_view_cpu(
+                band,
+                &state.plane,
+                state.fractal,
+                mode,
+                state.max_iter,
+                distance,
+                frame,
+            )
+        },
+    )?;
+    println!(
+        "高解像度画像を書き出しました: {} ({}x{}, 1ピクセルあたり {} 点, {:.2?})",
+        file.display(),
+        options.width,
+        options.height,
+        options.samples * options.samples,
+        started.elapsed()
+    );
+    Ok(())
 }
 
-// ===== 高精度版の計算 =====
-
-fn render_high_precision(state: &mut ViewerState) {
-    let prec = state.precision;
-    let x_min_f = state.x_min.to_f64();
-    let x_max_f = state.x_max.to_f64();
-    let y_min_f = state.y_min.to_f64();
-    let y_max_f = state.y_max.to_f64();
-
-    // 低解像度で計算
-    let x_scale = (x_max_f - x_min_f) / HP_RENDER_WIDTH as f64;
-    let y_scale = (y_max_f - y_min_f) / HP_RENDER_HEIGHT as f64;
-
-    let mut low_res_pixels = vec![0u32; HP_RENDER_WIDTH * HP_RENDER_HEIGHT];
-
-    // 背景を初期化
-    let offset_x = (MANDELBROT_WIDTH - HP_RENDER_WIDTH) / 2;
-    let offset_y = (MANDELBROT_HEIGHT - HP_RENDER_HEIGHT) / 2;
-    state.mandelbrot_buffer = vec![0x202020u32; MANDELBROT_WIDTH * MANDELBROT_HEIGHT];
-
-    // プログレスバー更新頻度調整: 全体の1%ごとに更新 (ただし最低1回)
-    let update_interval = std::cmp::max(1, HP_RENDER_HEIGHT / 100);
-
-    for py in 0..HP_RENDER_HEIGHT {
-        // 計算
-        for px in 0..HP_RENDER_WIDTH {
-            let cx_f = x_min_f + x_scale * px as f64;
-            let cy_f = y_max_f - y_scale * py as f64;
-            let cx = Float::with_val(prec, cx_f);
-            let cy = Float::with_val(prec, cy_f);
-            let iter = mandelbrot_iter_hp(&cx, &cy, MAX_ITER, prec);
-            low_res_pixels[py * HP_RENDER_WIDTH + px] = iter_to_color_u32(iter, MAX_ITER);
-
-            // 現在の行を即座に描画
-            let dest_x = offset_x + px;
-            let dest_y = offset_y + py;
-            state.mandelbrot_buffer[dest_y * MANDELBROT_WIDTH + dest_x] =
-                low_res_pixels[py * HP_RENDER_WIDTH + px];
-        }
+/// `--backend` の指定（省略時は Auto）
+fn requested_backend() -> Result<Backend, String> {
+    arg_value("--backend").map_or(Ok(Backend::Auto), |v| v.parse())
+}
 
-        // コンソールにプログレスバーを表示 (間引いて更新)
-        if py % update_interval == 0 || py == HP_RENDER_HEIGHT - 1 {
-            let progress = (py + 1) as f64 / HP_RENDER_HEIGHT as f64;
-            let bar_width = 30;
-            let filled = (progress * bar_width as f64) as usize;
-            let empty = bar_width - filled;
-            print!(
-                "\r🔬 計算中: [{}{}] {:>3}%",
-                "█".repeat(filled),
-                "░".repeat(empty),
-                ((py + 1) * 100 / HP_RENDER_HEIGHT)
-            );
-            use std::io::Write;
-            std::io::stdout().flush().ok();
-        }
+/// `render` サブコマンド: ウィンドウを開かずに指定した表示を描画して PNG に保存
+///
+/// minifb のウィンドウを作らないため、ディスプレイのないサーバーでも動きます。
+/// 計算モードはビューアと同じくバックエンドとズーム倍率から決めます（閾値の計測はしない）。
+fn run_render(config: &Config) -> Result<(), String> {
+    let request = RenderRequest::from_args()?;
+    let fractal: Option<FractalType> = arg_value("--fractal").map(|v| v.parse()).transpose()?;
+    let (backend, gpu) = select_backend(requested_backend()?, &config.settings);
+    let palette_path = arg_value("--palette")
+        .map(PathBuf::from)
+        .or_else(|| config.palette.clone());
+
+    let mut state = ViewerState::new(palette_path, config, backend);
+    if let Some(fractal) = fractal {
+        state.set_fractal(fractal);
+    }
+    if let Some(max_iter) = request.max_iter {
+        state.max_iter = max_iter;
     }
-    println!(" 完了!");
+    println!(
+        "計算バックエンド: {} | 計算モード: {} | 最大反復回数: {}",
+        backend,
+        backend.mode(request.view.zoom(), &state.settings.thresholds),
+        state.max_iter
+    );
+    export_view(
+        &state,
+        gpu.as_ref(),
+        &request.view,
+        &request.file,
+        &request.options,
+    )
 }
 
-fn render_mandelbrot(state: &mut ViewerState) {
-    match state.compute_mode {
-        ComputeMode::Fast => render_fast(state),
-        ComputeMode::HighPrecision => render_high_precision(state),
+/// `--backend` の指定に従って GPU を `settings` の描画領域の大きさで初期化し、使うバックエンドを決める
+/// （GPU を初期化できなければ Cpu になる）
+fn select_backend(requested: Backend, settings: &Settings) -> (Backend, Option<GpuContext>) {
+    if !matches!(requested, Backend::Gpu | Backend::Auto) {
+        return (requested, None);
+    }
+    println!("GPU を初期化中...");
+    match GpuContext::new(settings.width, settings.height) {
+        Ok(gpu) => {
+            println!("GPU 初期化完了");
+            (Backend::Gpu, Some(gpu))
+        }
+        Err(e) if requested == Backend::Auto => {
+            println!("{}。CPU で計算します", e);
+            (Backend::Cpu, None)
+        }
+        Err(e) => {
+            eprintln!("警告: GPU の初期化に失敗しました: {}", e);
+            eprintln!("警告: --backend cpu に切り替えて CPU (f64) で計算します");
+            (Backend::Cpu, None)
+        }
     }
-    state.compose_buffer();
-    state.needs_redraw = false;
 }
 
 fn main() {
+    let mut config = Config::from_args().unwrap_or_else(|e| {
+        eprintln!("設定ファイルの読み込みに失敗しました: {}", e);
+        std::process::exit(1);
+    });
+    let keys = &config.keys;
+
+    // ウィンドウを開かずに静止画を描画するだけ
+    if is_subcommand("render") {
+        if let Err(e) = run_render(&config) {
+            eprintln!("静止画の描画に失敗しました: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // 各計算モードの描画時間を計測して表示するだけ
+    if has_flag("--bench") {
+        match requested_backend() {
+            Ok(requested) => {
+                let gpu = select_backend(requested, &config.settings).1;
+                run_bench(gpu.as_ref(), &config.settings);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     println!("╔══════════════════════════════════════════════════════════════╗");
-    println!("║  マンデルブロ集合ビューア (ハイブリッド版)                   ║");
+    println!("║  マンデルブロ集合ビューア                                    ║");
     println!("╠══════════════════════════════════════════════════════════════╣");
-    println!("║  🚀 浅いズーム: f64 + 並列処理（超高速）                     ║");
-    println!("║  🔬 深いズーム: 任意精度（自動切替、無限ズーム可能）         ║");
-    println!("║  切替閾値: 10^13倍                                           ║");
+    println!("║  🎮 浅いズーム: GPU f32 / df64（超高速、--backend gpu）      ║");
+    println!("║  🚀 中程度: CPU f64 + 並列処理（高速）                       ║");
+    println!("║  🔬 深いズーム: CPU 摂動法（自動切替、無限ズーム可能）       ║");
     println!("╚══════════════════════════════════════════════════════════════╝");
     println!();
     println!("操作方法:");
-    println!("  - マウスホイール: 拡大/縮小");
+    println!("  - マウスホイール・2本指スクロール: 拡大/縮小");
     println!("  - 左クリック+ドラッグ: 移動（パン）");
-    println!("  - 右クリック: クリック位置を中心にズームイン");
-    println!("  - R キー: 初期表示にリセット");
-    println!("  - S キー: 現在の表示を画像として保存");
-    println!("  - Q / Escape キー: 終了");
+    println!("  - 右クリック: クリック位置を固定してズームイン");
+    let help = [
+        (keys.reset, "初期表示にリセット"),
+        (keys.save_image, "現在の表示を画像として保存"),
+        (
+            keys.save_location,
+            "現在の位置をパラメータファイル（.kfr / .par）として保存",
+        ),
+        (keys.reload_palette, "パレットファイルを再読み込み"),
+        (
+            keys.coloring_mode,
+            "着色モード切替（線形 / ヒストグラム平坦化 / 距離推定）",
+        ),
+        (
+            keys.fractal,
+            "フラクタルの種類を切替（--fractal <種類> で起動時に指定）",
+        ),
+        (keys.orbit, "カーソル位置の点の軌道を表示 / 非表示"),
+        (keys.grid, "座標の目盛り線とラベルを表示 / 非表示"),
+        (
+            keys.julia_preview,
+            "カーソル位置のジュリア集合のプレビューを表示 / 非表示",
+        ),
+        (
+            keys.julia,
+            "マウス位置を c とするジュリア集合に切替 / マンデルブロ集合に戻る",
+        ),
+        (
+            keys.record,
+            "ズームアニメーションを連番画像（--video 指定時は動画）で出力",
+        ),
+        (keys.keyframe, "現在の表示をキーフレームとして追加"),
+        (keys.antialias, "アンチエイリアスを切替（1× → 4× → 9×）"),
+        (keys.pan_left, "左へ移動（押し続けると加速）"),
+        (keys.pan_right, "右へ移動（押し続けると加速）"),
+        (keys.pan_up, "上へ移動（押し続けると加速）"),
+        (keys.pan_down, "下へ移動（押し続けると加速）"),
+        (keys.zoom_in, "画面中心でズームイン（押し続けると加速）"),
+        (keys.zoom_out, "画面中心でズームアウト（押し続けると加速）"),
+        (keys.rotate_left, "画像を左回りに 5° 回転"),
+        (keys.rotate_right, "画像を右回りに 5° 回転"),
+        (
+            keys.phase_down,
+            "HSV パレットの色相を戻す（押し続けると色が流れる）",
+        ),
+        (
+            keys.phase_up,
+            "HSV パレットの色相を進める（押し続けると色が流れる）",
+        ),
+        (keys.gamma_down, "ガンマを下げる（中間調を暗く）"),
+        (keys.gamma_up, "ガンマを上げる（中間調を明るく）"),
+        (keys.brightness_down, "明るさを下げる"),
+        (keys.brightness_up, "明るさを上げる"),
+        (keys.contrast_down, "コントラストを下げる"),
+        (keys.contrast_up, "コントラストを上げる"),
+        (keys.tone_reset, "色調補正を元に戻す"),
+        (
+            keys.export,
+            "現在の表示を高解像度で書き出し（既定: 7680x4320）",
+        ),
+    ];
+    for (key, description) in help {
+        println!("  - {} キー: {}", key_name(key), description);
+    }
+    println!("  - {} / Escape キー: 終了", key_name(keys.quit));
+    println!();
+
+    let requested = requested_backend().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    // HiDPI ディスプレイでは描画領域を物理ピクセルの大きさにしてから GPU とウィンドウを用意する
+    config.settings.apply_scale_factor();
+    if config.settings.scale_factor > 1 {
+        println!(
+            "画面の拡大率: {}（{}x{} で描画）",
+            config.settings.scale_factor, config.settings.width, config.settings.height
+        );
+    }
+    let (backend, gpu) = select_backend(requested, &config.settings);
+    let gpu = gpu.as_ref();
+    println!("計算バックエンド: {}", backend);
+
+    // GPU を使う場合、閾値が設定ファイルにない初回起動時（または --calibrate 指定時）は計測して設定ファイルに保存
+    if let Some(gpu) = gpu.filter(|_| !config.thresholds_set || has_flag("--calibrate")) {
+        config.settings.thresholds = calibrate_thresholds(gpu, &config.settings);
+        match Config::path_from_args() {
+            Some(path) => match store_thresholds(&path, &config.settings.thresholds) {
+                Ok(()) => println!("切替閾値を保存しました: {}", path.display()),
+                Err(e) => eprintln!("切替閾値の保存に失敗しました: {}", e),
+            },
+            None => eprintln!("設定ファイルの場所が分からないため、切替閾値を保存できません"),
+        }
+    }
+    match backend {
+        Backend::Gpu => println!(
+            "切替閾値: {:e}倍 (f32→df64), {:e}倍 (GPU→CPU), {:e}倍 (CPU→固定小数点)",
+            config.settings.thresholds.gpu_df64,
+            config.settings.thresholds.gpu_to_cpu,
+            config.settings.thresholds.high_precision
+        ),
+        Backend::Cpu => println!(
+            "切替閾値: {:e}倍 (CPU→固定小数点)",
+            config.settings.thresholds.high_precision
+        ),
+        _ => {}
+    }
     println!();
 
+    let palette_path = arg_value("--palette")
+        .map(PathBuf::from)
+        .or_else(|| config.palette.clone());
+    let fractal: Option<FractalType> = arg_value("--fractal").map(|v| {
+        v.parse().unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })
+    });
+    let new_state = |palette_path| {
+        let mut state = ViewerState::new(palette_path, &config, backend);
+        if let Some(fractal) = fractal {
+            state.set_fractal(fractal);
+        }
+        state
+    };
+    let recording = RecordingOptions::from_args().unwrap_or_else(|e| {
+        eprintln!("アニメーション出力の設定が不正です: {}", e);
+        std::process::exit(1);
+    });
+    let export_options = ExportOptions::from_args().unwrap_or_else(|e| {
+        eprintln!("高解像度書き出しの設定が不正です: {}", e);
+        std::process::exit(1);
+    });
+
+    // ウィンドウを開かずにズームアニメーションだけを出力
+    if let Some(spec) = arg_value("--record-zoom") {
+        let result = View::parse(&spec).and_then(|target| {
+            let state = new_state(palette_path);
+            record_zoom(&state, gpu, &target, &recording)
+        });
+        if let Err(e) = result {
+            eprintln!("ズームアニメーションの出力に失敗しました: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // ウィンドウを開かずにキーフレームパスのアニメーションだけを出力
+    if let Some(file) = arg_value("--render-path") {
+        let result = KeyframePath::load(Path::new(&file)).and_then(|path| {
+            let state = new_state(palette_path);
+            record_path(&state, gpu, &path, &recording)
+        });
+        if let Err(e) = result {
+            eprintln!("キーフレームパスの出力に失敗しました: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // ウィンドウを開かずに指定した表示を高解像度で書き出すだけ
+    if let Some(spec) = arg_value("--export") {
+        let file =
+            PathBuf::from(arg_value("--export-file").unwrap_or_else(|| DEFAULT_EXPORT_FILE.into()));
+        let result = View::parse(&spec).and_then(|view| {
+            let state = new_state(palette_path);
+            export_view(&state, gpu, &view, &file, &export_options)
+        });
+        if let Err(e) = result {
+            eprintln!("高解像度画像の書き出しに失敗しました: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let keyframes_file =
+        PathBuf::from(arg_value("--keyframes").unwrap_or_else(|| DEFAULT_KEYFRAMES_FILE.into()));
+    let session_file =
+        PathBuf::from(arg_value("--session").unwrap_or_else(|| DEFAULT_SESSION_FILE.into()));
+
+    let (window_width, window_height) = config.settings.window_size();
     let mut window = Window::new(
-        "マンデルブロ集合 (ハイブリッド版 - 自動精度切替)",
-        WINDOW_WIDTH,
-        WINDOW_HEIGHT,
+        &format!("マンデルブロ集合 ({}版)", backend),
+        window_width,
+        window_height,
         WindowOptions {
             resize: false,
             ..WindowOptions::default()
@@ -387,88 +1423,149 @@ fn main() {
 
     window.set_target_fps(60);
 
-    let mut state = ViewerState::new();
-    let mut prev_scroll: Option<(f32, f32)> = None;
-
-    // 初期描画
-    let start = Instant::now();
-    render_mandelbrot(&mut state);
-    println!(
-        "初期描画完了: {:.2?} [{}]",
-        start.elapsed(),
-        state.compute_mode
-    );
-
-    let mut prev_left_down = false;
-
-    while window.is_open() && !window.is_key_down(Key::Escape) && !window.is_key_down(Key::Q) {
-        if window.is_key_pressed(Key::R, minifb::KeyRepeat::No) {
-            state.reset();
-            println!("リセット");
+    let location_format = match arg_value("--location-format") {
+        Some(v) => v.parse().unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }),
+        None => LocationFormat::KallesFraktaler,
+    };
+    let mut state = new_state(palette_path);
+    if has_flag("--resume") {
+        match Session::load(&session_file) {
+            Ok(session) => {
+                state.restore_session(session);
+                println!("セッションを復元しました: {}", session_file.display());
+            }
+            Err(e) => eprintln!("セッションの復元に失敗しました: {}", e),
         }
-
-        if window.is_key_pressed(Key::S, minifb::KeyRepeat::No) {
-            state.save_image();
+    }
+    if let Some(file) = arg_value("--open").map(PathBuf::from).or_else(file_arg) {
+        match Snapshot::open(&file) {
+            Ok(snapshot) => {
+                state.restore_snapshot(snapshot);
+                println!("表示を開きました: {}", file.display());
+            }
+            Err(e) => eprintln!("表示を開けませんでした: {}", e),
         }
-
-        if let Some((mx, my)) = window.get_mouse_pos(MouseMode::Discard) {
-            if let Some(scroll) = window.get_scroll_wheel() {
-                if prev_scroll != Some(scroll) {
-                    let factor = if scroll.1 > 0.0 { ZOOM_FACTOR_IN } else { ZOOM_FACTOR_OUT };
-                    state.zoom(mx as f64, my as f64, factor);
-                    prev_scroll = Some(scroll);
+    }
+    let mut input = InputHandler::new();
+    #[cfg(feature = "gamepad")]
+    let mut gamepad = match GamepadInput::new() {
+        Ok(gamepad) => Some(gamepad),
+        Err(e) => {
+            eprintln!("警告: {}", e);
+            None
+        }
+    };
+
+    while window.is_open() && !window.is_key_down(Key::Escape) && !window.is_key_down(keys.quit) {
+        for command in input.handle(&window, &mut state, &config, location_format) {
+            match command {
+                Command::Record => {
+                    // 描画中のジョブは止めてからアニメーションを出力
+                    state.render_job = None;
+                    state.needs_redraw = true;
+                    if let Err(e) = record_zoom(&state, gpu, &state.view(), &recording) {
+                        eprintln!("ズームアニメーションの出力に失敗しました: {}", e);
+                    }
+                }
+                Command::AddKeyframe => state.add_keyframe(&keyframes_file),
+                Command::Export => {
+                    // 描画中のジョブは止めてから書き出す
+                    state.render_job = None;
+                    state.needs_redraw = true;
+                    let view = state.view();
+                    let file =
+                        output_path(&config.screenshot_dir, "mandelbrot_export", &view, "png");
+                    let result = ensure_dir(&config.screenshot_dir)
+                        .and_then(|()| export_view(&state, gpu, &view, &file, &export_options));
+                    if let Err(e) = result {
+                        eprintln!("高解像度画像の書き出しに失敗しました: {}", e);
+                    }
                 }
-            } else {
-                prev_scroll = None;
-            }
-
-            // 左クリックでパン移動（押した瞬間のみ）
-            let left_down = window.get_mouse_down(MouseButton::Left);
-            if left_down && !prev_left_down {
-                state.pan_to(mx as f64, my as f64);
             }
-            prev_left_down = left_down;
+        }
 
-            if window.get_mouse_down(MouseButton::Right) {
-                state.zoom(mx as f64, my as f64, ZOOM_FACTOR_IN);
-            }
+        #[cfg(feature = "gamepad")]
+        if let Some(gamepad) = &mut gamepad {
+            gamepad.handle(&mut state);
         }
 
+        // 操作で表示範囲が変わったら、描画中のジョブを中断して描き直す
         if state.needs_redraw {
-            let start = Instant::now();
-            render_mandelbrot(&mut state);
+            start_render(&mut state, gpu);
+        }
 
+        state.update_preview();
+
+        if state.finish_render() {
             let zoom = state.current_zoom();
-            let center_x = (state.x_min.to_f64() + state.x_max.to_f64()) / 2.0;
-            let center_y = (state.y_min.to_f64() + state.y_max.to_f64()) / 2.0;
+            let (center_x, center_y) = state.view_center();
 
             let mode_info = match state.compute_mode {
-                ComputeMode::Fast => "🚀".to_string(),
-                ComputeMode::HighPrecision => format!("🔬 {}bit", state.precision),
+                ComputeMode::Gpu | ComputeMode::GpuDf64 => "🎮".to_string(),
+                ComputeMode::CpuF64 => "🚀".to_string(),
+                ComputeMode::CpuFixed => "🧮".to_string(),
+                ComputeMode::CpuHighPrecision | ComputeMode::FullHighPrecision => {
+                    format!("🔬 {}bit", state.precision)
+                }
             };
 
             // ウィンドウタイトルを更新してモードを表示（テキストのみ）
             let title_mode = match state.compute_mode {
-                ComputeMode::Fast => "CPU".to_string(),
-                ComputeMode::HighPrecision => format!("HP {}bit", state.precision),
+                ComputeMode::Gpu => "GPU".to_string(),
+                ComputeMode::GpuDf64 => "GPU df64".to_string(),
+                ComputeMode::CpuF64 => "CPU".to_string(),
+                ComputeMode::CpuFixed => "CPU fixed".to_string(),
+                ComputeMode::CpuHighPrecision => format!("HP {}bit", state.precision),
+                ComputeMode::FullHighPrecision => format!("HP full {}bit", state.precision),
             };
-            let title = format!("マンデルブロ集合 [{}] x{:.2e}", title_mode, zoom);
+            let title = format!(
+                "{} [{}] x{:.2e}",
+                state.plane.label(state.fractal),
+                title_mode,
+                zoom
+            );
             window.set_title(&title);
 
             println!(
                 "再描画: {:.2?} {} | 中心: ({:.6}, {:.6}i) | ズーム: x{:.2e}",
-                start.elapsed(),
+                state.render_start.elapsed(),
                 mode_info,
-                center_x,
-                center_y,
+                center_x.to_f64(),
+                center_y.to_f64(),
                 zoom
             );
+
+            // 高精度モードは表示が変わらない間、解像度を上げて描き直す
+            refine_render(&mut state);
         }
 
         window
-            .update_with_buffer(&state.buffer, WINDOW_WIDTH, WINDOW_HEIGHT)
+            .update_with_buffer(
+                &state.buffer,
+                state.settings.window_width(),
+                state.settings.window_height(),
+            )
             .expect("バッファの更新に失敗しました");
     }
 
+    // 次回 --resume で再開できるよう状態を保存
+    match state.to_session().save(&session_file) {
+        Ok(()) => println!("セッションを保存しました: {}", session_file.display()),
+        Err(e) => eprintln!("セッションの保存に失敗しました: {}", e),
+    }
+    // キーで変えた色調補正は設定ファイルに保存し、次回の起動でも使う
+    if state.tone != config.tone {
+        match Config::path_from_args() {
+            Some(path) => match store_tone(&path, &state.tone) {
+                Ok(()) => println!("色調補正を保存しました: {}", path.display()),
+                Err(e) => eprintln!("色調補正の保存に失敗しました: {}", e),
+            },
+            None => eprintln!("設定ファイルの場所が分からないため、色調補正を保存できません"),
+        }
+    }
+
     println!("終了しました");
-}
\ No newline at end of file
+}
@@ -1,55 +1,300 @@
 // マンデルブロ集合計算シェーダー (WGSL)
 // 各ピクセルの反復回数をGPUで並列計算する
+//
+// 座標は f32 の組 (hi, lo) で表し、hi + lo の値を持つ double-double (df64) として受け取る。
+// df64 が 0 なら hi だけを使って f32 で、1 なら df64 の加算・乗算で反復する。
 
 struct Params {
-    x_min: f32,
-    x_max: f32,
-    y_min: f32,
-    y_max: f32,
+    center_x: vec2<f32>, // 中心の実部 (hi, lo)
+    center_y: vec2<f32>, // 中心の虚部 (hi, lo)
+    c_real: vec2<f32>,  // ジュリア集合の定数 c の実部 (hi, lo)
+    c_imag: vec2<f32>,  // ジュリア集合の定数 c の虚部 (hi, lo)
+    x_scale: f32,       // 1ピクセルあたりの実部の幅
+    y_scale: f32,       // 1ピクセルあたりの虚部の幅
     width: u32,
     height: u32,
     max_iter: u32,
+    julia: u32,      // 1 ならジュリア集合（z_0 = ピクセル座標、c = (c_real, c_imag)）
+    fractal: u32,    // 0: マンデルブロ、1: バーニングシップ、2: トリコーン、3: マルチブロ
+    power: u32,      // マルチブロ集合の次数
+    distance: u32,   // 1 なら反復回数の代わりに境界までの距離推定を出力
+    df64: u32,       // 1 なら df64 で反復
+    one: f32,        // 常に 1.0（df64 の誤差項が最適化で消されないよう演算に挟む）
+    cos_rotation: f32,  // 表示範囲の回転角の cos
+    sin_rotation: f32,  // 表示範囲の回転角の sin
     _padding: u32,
 }
 
+// 距離推定の値を格納したピクセルに立てるビット（colors.rs の DISTANCE_FLAG と同じ）
+const DISTANCE_FLAG: u32 = 0x80000000u;
+// 距離の固定小数点表現の1ピクセルあたりの値（colors.rs の DISTANCE_SCALE と同じ）
+const DISTANCE_SCALE: f32 = 256.0;
+// Dekker の分割に使う定数（2^12 + 1、f32 の仮数部24ビットを上下12ビットに分ける）
+const SPLITTER: f32 = 4097.0;
+
 @group(0) @binding(0) var<uniform> params: Params;
 @group(0) @binding(1) var<storage, read_write> output: array<u32>;
 
+// 反復の結果
+struct Escape {
+    iter: u32,
+    escaped: bool,
+    z: vec2<f32>,   // 最後の z（df64 では hi のみ）
+    dz: vec2<f32>,  // 距離推定用の微分
+}
+
+// ===== f32 版 =====
+
+// 反復式の c を足す前の部分 f(z) を計算
+fn step(z: vec2<f32>) -> vec2<f32> {
+    switch params.fractal {
+        // バーニングシップ: (|Re z| + i|Im z|)²
+        case 1u: {
+            let w = abs(z);
+            return vec2<f32>(w.x * w.x - w.y * w.y, 2.0 * w.x * w.y);
+        }
+        // トリコーン: conj(z)²
+        case 2u: {
+            return vec2<f32>(z.x * z.x - z.y * z.y, -2.0 * z.x * z.y);
+        }
+        // マルチブロ: z^power
+        case 3u: {
+            var w = z;
+            for (var k: u32 = 1u; k < params.power; k = k + 1u) {
+                w = vec2<f32>(w.x * z.x - w.y * z.y, w.x * z.y + w.y * z.x);
+            }
+            return w;
+        }
+        // マンデルブロ: z²
+        default: {
+            return vec2<f32>(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y);
+        }
+    }
+}
+
+// 複素数の積
+fn cmul(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+// step の z についての微分に dz を掛けたもの
+// バーニングシップとトリコーンは絶対値の等しい z² の微分で近似する
+fn step_derivative(z: vec2<f32>, dz: vec2<f32>) -> vec2<f32> {
+    if (params.fractal == 3u) {
+        var w = dz * f32(params.power);
+        for (var k: u32 = 1u; k < params.power; k = k + 1u) {
+            w = cmul(w, z);
+        }
+        return w;
+    }
+    return 2.0 * cmul(z, dz);
+}
+
+// 距離推定用の微分の初期値 (dz_0, dc)
+// マンデルブロ集合は dz/dc、ジュリア集合は dz/dz_0 を追跡する
+fn initial_derivative() -> array<vec2<f32>, 2> {
+    if (params.julia != 0u) {
+        return array<vec2<f32>, 2>(vec2<f32>(1.0, 0.0), vec2<f32>(0.0, 0.0));
+    }
+    return array<vec2<f32>, 2>(vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 0.0));
+}
+
+// 発散判定の半径の2乗（距離推定では距離の式の誤差を抑えるため大きくする）
+fn bailout() -> f32 {
+    return select(4.0, 1.0e4, params.distance != 0u);
+}
+
+// ピクセル座標 p を f32 で反復
+fn iterate_f32(p: vec2<f32>) -> Escape {
+    // マンデルブロ集合: z_0 = 0、c = ピクセル座標
+    // ジュリア集合: z_0 = ピクセル座標、c = 固定の定数
+    var z = vec2<f32>(0.0, 0.0);
+    var c = p;
+    if (params.julia != 0u) {
+        z = p;
+        c = vec2<f32>(params.c_real.x, params.c_imag.x);
+    }
+    let init = initial_derivative();
+    var dz = init[0];
+    let dc = init[1];
+    let radius = bailout();
+
+    var result = Escape(0u, false, z, dz);
+    for (var i: u32 = 0u; i < params.max_iter; i = i + 1u) {
+        if (dot(z, z) > radius) {
+            result.escaped = true;
+            break;
+        }
+        if (params.distance != 0u) {
+            dz = step_derivative(z, dz) + dc;
+        }
+        z = step(z) + c;
+        result.iter = i + 1u;
+    }
+    result.z = z;
+    result.dz = dz;
+    return result;
+}
+
+// ===== df64 版 =====
+// 値は vec2<f32>(hi, lo) で hi + lo を表し、|lo| ≤ ulp(hi) / 2 を保つ。
+// params.one を掛けるのは、高速な浮動小数点最適化で (a + b) - a を b に簡約されるのを防ぐため。
+
+// a + b を誤差なしで (和, 誤差) に分ける
+fn two_sum(a: f32, b: f32) -> vec2<f32> {
+    let s = a + b;
+    let v = s * params.one - a;
+    let e = (a - (s - v) * params.one) + (b - v);
+    return vec2<f32>(s, e);
+}
+
+// |a| ≥ |b| の場合の two_sum
+fn quick_two_sum(a: f32, b: f32) -> vec2<f32> {
+    let s = a + b;
+    let e = b - (s * params.one - a);
+    return vec2<f32>(s, e);
+}
+
+// a を仮数部12ビットずつの上位・下位に分ける
+fn split(a: f32) -> vec2<f32> {
+    let t = SPLITTER * a;
+    let hi = t * params.one - (t - a);
+    return vec2<f32>(hi, a - hi);
+}
+
+// a × b を誤差なしで (積, 誤差) に分ける（FMA のない GPU でも正しいよう Dekker の方法で計算）
+fn two_prod(a: f32, b: f32) -> vec2<f32> {
+    let p = a * b;
+    let sa = split(a);
+    let sb = split(b);
+    let e = ((sa.x * sb.x - p) + sa.x * sb.y + sa.y * sb.x) + sa.y * sb.y;
+    return vec2<f32>(p, e);
+}
+
+fn dd_add(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    let s = two_sum(a.x, b.x);
+    let t = two_sum(a.y, b.y);
+    let u = quick_two_sum(s.x, s.y + t.x);
+    return quick_two_sum(u.x, u.y + t.y);
+}
+
+fn dd_sub(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    return dd_add(a, -b);
+}
+
+fn dd_mul(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    let p = two_prod(a.x, b.x);
+    return quick_two_sum(p.x, p.y + (a.x * b.y + a.y * b.x));
+}
+
+fn dd_abs(a: vec2<f32>) -> vec2<f32> {
+    return select(a, -a, a.x < 0.0);
+}
+
+// df64 の複素数
+struct DdComplex {
+    re: vec2<f32>,
+    im: vec2<f32>,
+}
+
+fn dd_cmul(a: DdComplex, b: DdComplex) -> DdComplex {
+    return DdComplex(
+        dd_sub(dd_mul(a.re, b.re), dd_mul(a.im, b.im)),
+        dd_add(dd_mul(a.re, b.im), dd_mul(a.im, b.re)),
+    );
+}
+
+// step の df64 版
+fn dd_step(z: DdComplex) -> DdComplex {
+    switch params.fractal {
+        case 1u: {
+            let w = DdComplex(dd_abs(z.re), dd_abs(z.im));
+            let re_im = dd_mul(w.re, w.im);
+            return DdComplex(dd_sub(dd_mul(w.re, w.re), dd_mul(w.im, w.im)), dd_add(re_im, re_im));
+        }
+        case 2u: {
+            let re_im = dd_mul(z.re, z.im);
+            return DdComplex(dd_sub(dd_mul(z.re, z.re), dd_mul(z.im, z.im)), -dd_add(re_im, re_im));
+        }
+        case 3u: {
+            var w = z;
+            for (var k: u32 = 1u; k < params.power; k = k + 1u) {
+                w = dd_cmul(w, z);
+            }
+            return w;
+        }
+        default: {
+            let re_im = dd_mul(z.re, z.im);
+            return DdComplex(dd_sub(dd_mul(z.re, z.re), dd_mul(z.im, z.im)), dd_add(re_im, re_im));
+        }
+    }
+}
+
+// ピクセル座標 p を df64 で反復（発散判定と距離推定の微分は hi の f32 で十分）
+fn iterate_df64(p: DdComplex) -> Escape {
+    var z = DdComplex(vec2<f32>(0.0, 0.0), vec2<f32>(0.0, 0.0));
+    var c = p;
+    if (params.julia != 0u) {
+        z = p;
+        c = DdComplex(params.c_real, params.c_imag);
+    }
+    let init = initial_derivative();
+    var dz = init[0];
+    let dc = init[1];
+    let radius = bailout();
+
+    var result = Escape(0u, false, vec2<f32>(z.re.x, z.im.x), dz);
+    for (var i: u32 = 0u; i < params.max_iter; i = i + 1u) {
+        let hi = vec2<f32>(z.re.x, z.im.x);
+        if (dot(hi, hi) > radius) {
+            result.escaped = true;
+            break;
+        }
+        if (params.distance != 0u) {
+            dz = step_derivative(hi, dz) + dc;
+        }
+        let next = dd_step(z);
+        z = DdComplex(dd_add(next.re, c.re), dd_add(next.im, c.im));
+        result.iter = i + 1u;
+    }
+    result.z = vec2<f32>(z.re.x, z.im.x);
+    result.dz = dz;
+    return result;
+}
+
 @compute @workgroup_size(8, 8)
 fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let x = global_id.x;
     let y = global_id.y;
-    
+
     if (x >= params.width || y >= params.height) {
         return;
     }
-    
+
     // ピクセル座標を複素数座標に変換
-    let x_scale = (params.x_max - params.x_min) / f32(params.width);
-    let y_scale = (params.y_max - params.y_min) / f32(params.height);
-    
-    let c_real = params.x_min + f32(x) * x_scale;
-    let c_imag = params.y_max - f32(y) * y_scale;
-    
-    // マンデルブロ反復計算
-    var z_real: f32 = 0.0;
-    var z_imag: f32 = 0.0;
-    var iter: u32 = 0u;
-    
-    for (var i: u32 = 0u; i < params.max_iter; i = i + 1u) {
-        let zr2 = z_real * z_real;
-        let zi2 = z_imag * z_imag;
-        
-        if (zr2 + zi2 > 4.0) {
-            break;
-        }
-        
-        z_imag = 2.0 * z_real * z_imag + c_imag;
-        z_real = zr2 - zi2 + c_real;
-        iter = i + 1u;
+    // 画面中心からのずれ（右・上が正）を表示範囲の回転角だけ回して中心に足す
+    // 中心からのずれは f32 でも相対誤差が十分小さいので、df64 では中心の座標にだけ下位を持たせる
+    let u = (f32(x) - 0.5 * f32(params.width)) * params.x_scale;
+    let v = (0.5 * f32(params.height) - f32(y)) * params.y_scale;
+    let dx = u * params.cos_rotation - v * params.sin_rotation;
+    let dy = u * params.sin_rotation + v * params.cos_rotation;
+    var result: Escape;
+    if (params.df64 != 0u) {
+        let px = dd_add(params.center_x, vec2<f32>(dx, 0.0));
+        let py = dd_add(params.center_y, vec2<f32>(dy, 0.0));
+        result = iterate_df64(DdComplex(px, py));
+    } else {
+        result = iterate_f32(vec2<f32>(params.center_x.x + dx, params.center_y.x + dy));
     }
-    
+
     // 結果を出力バッファに書き込み
     let idx = y * params.width + x;
-    output[idx] = iter;
+    if (params.distance != 0u && result.escaped) {
+        // 0.5·|z|·ln|z| / |dz| をピクセル単位の固定小数点数で格納
+        let norm = length(result.z);
+        let pixels = 0.5 * norm * log(norm) / length(result.dz) / params.x_scale;
+        output[idx] = DISTANCE_FLAG | u32(clamp(pixels * DISTANCE_SCALE, 0.0, 2147483520.0));
+    } else {
+        output[idx] = result.iter;
+    }
 }